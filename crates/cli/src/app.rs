@@ -1,29 +1,44 @@
 use std::{
+    borrow::Cow,
     cell::RefCell,
     convert::TryFrom,
+    fs,
     io::{self, Write},
+    path::{Path, PathBuf},
     rc::Rc,
+    sync::Arc,
     time::Instant,
 };
 
-use anyhow::{Context, Result};
-use candle_core::{DType, Tensor};
+use anyhow::{Context, Result, ensure};
 use deepseek_ocr_config::{AppConfig, LocalFileSystem};
 use deepseek_ocr_core::{
-    inference::{
-        build_prompt_tokens, compute_image_embeddings, normalize_text, prepare_vision_inputs,
-        render_prompt,
+    engine::{EngineSettings, OcrEngine, OcrRequest},
+    export::{
+        OutputFormat, PageLayout, PageOutput,
+        document::Document,
+        pdf::{self, ImageSource, PdfOptions, PdfPage},
+        table::{Table, TableFormat},
     },
-    model::{DeepseekOcrModel, GenerateOptions},
+    figures::{FigureOptions, extract_figures, insert_figures},
+    grounding::{BoundingBox, strip_grounding_tokens},
+    postprocess::postprocess,
+    redact::{Redactor, redact_image},
     runtime::{default_dtype_for_device, prepare_device_and_dtype},
+    segment::{SegmentOptions, segment_ocr},
+    task::{TaskPreset, resolve_task},
+    vision::{
+        CleanupReport, DecodeOptions, DecodedImage, decode_pages, page_runs, record_orientations,
+    },
+    zoom::{ZoomOptions, zoom_ocr},
 };
-use image::DynamicImage;
-use tokenizers::Tokenizer;
+use image::{DynamicImage, metadata::Orientation};
+use serde_json::json;
 use tracing::info;
 
 use crate::{
-    args::Args,
-    bench,
+    args::{Args, Command},
+    baseline, bench, benchmark, estimate, eval, extract, locate,
     prompt::load_prompt,
     resources::{ensure_config_file, ensure_tokenizer_file, prepare_weights_path},
 };
@@ -32,23 +47,66 @@ pub fn run(args: Args) -> Result<()> {
     let bench_enabled = args.bench || args.bench_output.is_some();
     let bench_session = bench::maybe_start(bench_enabled, args.bench_output.clone())?;
 
-    let prompt_raw = load_prompt(&args)?;
+    match &args.command {
+        Some(Command::Locate(locate_args)) => locate::run(&args, locate_args)?,
+        Some(Command::Extract(extract_args)) => extract::run(&args, extract_args)?,
+        Some(Command::Eval(eval_args)) => eval::run(&args, eval_args)?,
+        Some(Command::Baseline(baseline_args)) => baseline::run(&args, baseline_args)?,
+        Some(Command::Benchmark(benchmark_args)) => benchmark::run(&args, benchmark_args)?,
+        None => run_generate(&args)?,
+    }
 
+    if let Some(session) = bench_session {
+        let report = session.finalize()?;
+        bench::print_summary(&report);
+    }
+
+    Ok(())
+}
+
+/// Configuration resolved from the config file, the optional task preset and CLI overrides.
+pub(crate) struct LoadedConfig {
+    pub fs: LocalFileSystem,
+    pub app_config: AppConfig,
+    pub task: Option<TaskPreset>,
+}
+
+/// Load configuration, register `[tasks]` and layer `task_name` plus the CLI flags on top.
+pub(crate) fn load_config(args: &Args, task_name: Option<&str>) -> Result<LoadedConfig> {
     let fs = LocalFileSystem::new("deepseek-ocr");
     let (mut app_config, descriptor) = AppConfig::load_or_init(&fs, args.config.as_deref())?;
-    app_config += &args;
+    app_config.register_tasks()?;
+    let task = task_name.map(resolve_task).transpose()?;
+    if let Some(task) = &task {
+        app_config.inference.apply_task(task);
+    }
+    app_config += args;
     app_config.normalise(&fs)?;
-    let resources = app_config.active_model_resources(&fs)?;
 
     info!(
         "Using configuration {} (active model `{}`)",
         descriptor.location.display_with(&fs)?,
         app_config.models.active
     );
+    if let Some(name) = task_name {
+        info!("Using task preset `{name}`");
+    }
+
+    Ok(LoadedConfig {
+        fs,
+        app_config,
+        task,
+    })
+}
 
-    let config_path = ensure_config_file(&fs, &resources.config)?;
-    let tokenizer_path = ensure_tokenizer_file(&fs, &resources.tokenizer)?;
-    let weights_path = prepare_weights_path(&fs, &resources.weights)?;
+/// Resolve model resources (downloading when needed) and load the engine.
+pub(crate) fn load_engine(loaded: &LoadedConfig) -> Result<OcrEngine> {
+    let fs = &loaded.fs;
+    let app_config = &loaded.app_config;
+    let resources = app_config.active_model_resources(fs)?;
+    let config_path = ensure_config_file(fs, &resources.config)?;
+    let tokenizer_path = ensure_tokenizer_file(fs, &resources.tokenizer)?;
+    let weights_path = prepare_weights_path(fs, &resources.weights)?;
 
     let (device, maybe_precision) =
         prepare_device_and_dtype(app_config.inference.device, app_config.inference.precision)?;
@@ -63,86 +121,436 @@ pub fn run(args: Args) -> Result<()> {
     );
 
     let load_start = Instant::now();
-    let model = DeepseekOcrModel::load(
-        Some(&config_path),
-        Some(&weights_path),
-        device.clone(),
+    let engine = OcrEngine::load(
+        &config_path,
+        &weights_path,
+        &tokenizer_path,
+        device,
         dtype,
-    )
-    .context("failed to load DeepSeek-OCR model")?;
+        EngineSettings::from(&app_config.inference),
+    )?;
     info!(
         "Model ready in {:.2?} (flash-attn: {}, weights={})",
         load_start.elapsed(),
-        model.flash_attention_enabled(),
+        engine.model().flash_attention_enabled(),
         weights_path.display()
     );
+    Ok(engine)
+}
+
+/// Read and decode every page of `paths` to RGB, honouring EXIF orientation and flattening
+/// transparency as configured. One entry per path; multi-page TIFFs yield several pages.
+pub(crate) fn open_pages(
+    paths: &[PathBuf],
+    options: &DecodeOptions,
+) -> Result<Vec<Vec<DynamicImage>>> {
+    Ok(read_inputs(paths, options)?
+        .into_iter()
+        .map(|(_, pages)| pages.into_iter().map(|decoded| decoded.image).collect())
+        .collect())
+}
 
-    let tokenizer = Tokenizer::from_file(&tokenizer_path).map_err(|err| {
-        anyhow::anyhow!(
-            "failed to load tokenizer from {}: {err}",
-            tokenizer_path.display()
-        )
-    })?;
+/// Like [`open_pages`], keeping each file's bytes and how its pages were decoded.
+fn read_inputs(
+    paths: &[PathBuf],
+    options: &DecodeOptions,
+) -> Result<Vec<(Vec<u8>, Vec<DecodedImage>)>> {
+    paths
+        .iter()
+        .map(|path| {
+            let bytes = fs::read(path)
+                .with_context(|| format!("failed to read image at {}", path.display()))?;
+            let pages = decode_pages(&bytes, options)
+                .with_context(|| format!("failed to open image at {}", path.display()))?;
+            if pages.len() > 1 {
+                info!("Read {} pages from {}", pages.len(), path.display());
+            }
+            for decoded in &pages {
+                if decoded.orientation != Orientation::NoTransforms {
+                    info!(
+                        "Applied EXIF orientation {:?} to {}",
+                        decoded.orientation,
+                        path.display()
+                    );
+                }
+                if decoded.flattened_alpha() {
+                    info!(
+                        "Composited transparent pixels of {} onto {}",
+                        path.display(),
+                        options.background
+                    );
+                }
+            }
+            Ok((bytes, pages))
+        })
+        .collect()
+}
+
+/// Like [`open_pages`] for commands that take exactly one single-page image per path.
+pub(crate) fn open_images(paths: &[PathBuf], options: &DecodeOptions) -> Result<Vec<DynamicImage>> {
+    open_pages(paths, options)?
+        .into_iter()
+        .zip(paths)
+        .map(|(mut pages, path)| {
+            ensure!(
+                pages.len() == 1,
+                "{} has {} pages; only generation accepts multi-page images",
+                path.display(),
+                pages.len()
+            );
+            Ok(pages.remove(0))
+        })
+        .collect()
+}
 
-    let prompt_with_template = render_prompt(&app_config.inference.template, "", &prompt_raw)?;
+fn run_generate(args: &Args) -> Result<()> {
+    let loaded = load_config(args, args.task.as_deref())?;
+    let prompt_raw = load_prompt(args, loaded.task.as_ref())?;
+    if args.dry_run {
+        return estimate::run(args, &loaded, &prompt_raw);
+    }
+    let engine = load_engine(&loaded)?;
+    let app_config = &loaded.app_config;
+
+    let prompt_with_template = engine.render_prompt(&prompt_raw)?;
     let image_slots = prompt_with_template.matches("<image>").count();
-    anyhow::ensure!(
+    ensure!(
         image_slots == args.images.len(),
         "prompt includes {image_slots} <image> tokens but {} image paths were provided",
         args.images.len()
     );
 
-    let images: Vec<DynamicImage> = args
-        .images
+    ensure!(
+        !args.format.is_binary() || args.output.is_some(),
+        "--format {} writes a binary file; pass --output PATH",
+        args.format.name()
+    );
+    let postprocess_options = &app_config.inference.postprocess;
+    let redactor = (app_config.inference.redaction.enabled || args.redacted_images.is_some())
+        .then(|| Redactor::new(&app_config.inference.redaction))
+        .transpose()?;
+    let (files, pages): (Vec<_>, Vec<_>) = read_inputs(&args.images, &app_config.inference.decode)?
+        .into_iter()
+        .unzip();
+    let decoded = page_runs(pages)?;
+    // PDF pages keep the encoding of a file holding just that page; redaction changes pixels.
+    let sources: Vec<ImageSource> = decoded
         .iter()
-        .map(|path| {
-            image::open(path).with_context(|| format!("failed to open image at {}", path.display()))
+        .map(|run| match (run.as_slice(), files.as_slice()) {
+            ([page], [file]) => ImageSource::new(file, page, redactor.is_some()),
+            _ => ImageSource::Lossless,
         })
-        .collect::<Result<Vec<_>>>()?;
-
-    let owned_inputs = prepare_vision_inputs(
-        &model,
-        &images,
-        app_config.inference.base_size,
-        app_config.inference.image_size,
-        app_config.inference.crop_mode,
-    )?;
-    let embeddings = compute_image_embeddings(&model, &owned_inputs)?;
-
-    let (input_ids_vec, mask_vec) = build_prompt_tokens(
-        &tokenizer,
-        &prompt_with_template,
-        &embeddings,
-        &owned_inputs,
-        app_config.inference.base_size,
-        app_config.inference.image_size,
-        app_config.inference.crop_mode,
-    )?;
+        .collect();
+    let orientations: Vec<Vec<Orientation>> = decoded
+        .iter()
+        .map(|run| run.iter().map(|page| page.orientation).collect())
+        .collect();
+    let runs: Vec<Vec<DynamicImage>> = decoded
+        .into_iter()
+        .map(|run| run.into_iter().map(|page| page.image).collect())
+        .collect();
+    ensure!(
+        args.redacted_images.is_none() || runs.iter().all(|images| images.len() == 1),
+        "--redacted-images needs exactly one image per page"
+    );
+    ensure!(
+        args.figures.is_none() || runs.iter().all(|images| images.len() == 1),
+        "--figures needs exactly one image per page"
+    );
+    let figure_options = FigureOptions {
+        describe_task: Some(args.figure_task.clone()),
+        ..FigureOptions::default()
+    };
+    // Text goes to stdout as it is produced unless a document is written at the end, or the
+    // text still has to be rewritten with figures, post-processed or redacted.
+    let echo = args.format == OutputFormat::Text
+        && args.output.is_none()
+        && args.figures.is_none()
+        && !postprocess_options.any_enabled()
+        && redactor.is_none();
+    let mut pages = Vec::with_capacity(runs.len());
+    let mut redacted_runs = Vec::new();
+    let mut cleanup = Vec::new();
+    for (idx, images) in runs.iter().enumerate() {
+        if runs.len() > 1 {
+            if idx > 0 && echo {
+                println!();
+            }
+            info!("--- Page {}/{} ---", idx + 1, runs.len());
+        }
+        let (text, mut reports) = run_page(
+            &engine,
+            args,
+            &prompt_with_template,
+            images,
+            app_config.inference.max_new_tokens,
+            echo,
+        )?;
+        record_orientations(&mut reports, images, &orientations[idx]);
+        cleanup.push(reports);
+        let text = match &args.figures {
+            Some(dir) => {
+                // Figure crops of a redacted page must not show what the text hides.
+                let image = match &redactor {
+                    Some(redactor) => Cow::Owned(redactor.redact_page(&text, &images[0])),
+                    None => Cow::Borrowed(&images[0]),
+                };
+                add_figures(
+                    &engine,
+                    &text,
+                    &image,
+                    idx + 1,
+                    dir,
+                    args.output.as_deref(),
+                    &figure_options,
+                )?
+            }
+            None => text,
+        };
+        let text = postprocess(&text, postprocess_options);
+        pages.push(match &redactor {
+            Some(redactor) => {
+                redacted_runs.push(redact_run(redactor, &text, images));
+                redactor.redact_text(&text)
+            }
+            None => text,
+        });
+    }
+    // Documents embedding the page images (PDF) must not show what the text hides.
+    let runs = if redactor.is_some() {
+        redacted_runs
+    } else {
+        runs
+    };
+    if let Some(dir) = &args.redacted_images {
+        write_redacted_images(dir, &runs)?;
+    }
+    if !echo {
+        let document = render_document(args.format, &pages, &runs, &sources)?;
+        write_output(&document, args.output.as_deref())?;
+    }
+    if let Some(dir) = &args.tables {
+        write_tables(dir, args.tables_format, &page_outputs(&pages, &runs))?;
+    }
+    if let Some(path) = &args.cleanup_report {
+        write_cleanup_report(path, &cleanup)?;
+    }
+    Ok(())
+}
+
+/// Render page texts in `format`; layout formats need one image per page for the geometry,
+/// and PDF embeds those images, encoded as `sources` allow, under the invisible text.
+fn render_document(
+    format: OutputFormat,
+    pages: &[String],
+    runs: &[Vec<DynamicImage>],
+    sources: &[ImageSource],
+) -> Result<Vec<u8>> {
+    if let Some(images) = runs.iter().find(|images| images.len() != 1) {
+        ensure!(
+            !format.is_layout(),
+            "--format {} needs exactly one image per page (got {})",
+            format.name(),
+            images.len()
+        );
+    }
+    if format == OutputFormat::Pdf {
+        let layouts: Vec<PageLayout> = pages
+            .iter()
+            .zip(runs)
+            .map(|(text, images)| {
+                PageLayout::from_output(text, images[0].width(), images[0].height())
+            })
+            .collect();
+        let pages: Vec<PdfPage> = runs
+            .iter()
+            .zip(&layouts)
+            .zip(sources)
+            .map(|((images, layout), &source)| PdfPage {
+                image: &images[0],
+                layout,
+                source,
+            })
+            .collect();
+        return pdf::render(&pages, &PdfOptions::default());
+    }
+    Ok(format.render(&page_outputs(pages, runs))?.into_bytes())
+}
 
+/// Pair page texts with their image size when the page has a single image.
+fn page_outputs<'a>(pages: &'a [String], runs: &[Vec<DynamicImage>]) -> Vec<PageOutput<'a>> {
+    pages
+        .iter()
+        .zip(runs)
+        .map(|(text, images)| PageOutput {
+            text,
+            size: match images.as_slice() {
+                [image] => Some((image.width(), image.height())),
+                _ => None,
+            },
+        })
+        .collect()
+}
+
+/// Crop the figures of one page into `dir`, describe them and return `text` with each figure's
+/// link and description inserted.
+fn add_figures(
+    engine: &OcrEngine,
+    text: &str,
+    image: &DynamicImage,
+    page: usize,
+    dir: &Path,
+    output: Option<&Path>,
+    options: &FigureOptions,
+) -> Result<String> {
+    let result = extract_figures(engine, text, image, options)?;
+    if result.figures.is_empty() {
+        info!("No figures found on page {page}");
+        return Ok(text.to_string());
+    }
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let mut links = Vec::with_capacity(result.figures.len());
+    for (idx, figure) in result.figures.iter().enumerate() {
+        let path = dir.join(format!("page{page}_figure{}.png", idx + 1));
+        figure
+            .image
+            .save(&path)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        links.push(figure_link(&path, output));
+    }
     info!(
-        "Prompt prepared: {} tokens ({} image tokens)",
-        input_ids_vec.len(),
-        mask_vec.iter().filter(|&&b| b != 0).count()
+        "Wrote {} figure(s) of page {page} to {} (generated {} description tokens)",
+        result.figures.len(),
+        dir.display(),
+        result.completion_tokens
     );
+    Ok(insert_figures(text, &result.figures, &links))
+}
+
+/// Link to a saved figure, relative to the output file's directory when it lies below it.
+fn figure_link(path: &Path, output: Option<&Path>) -> String {
+    let relative = output
+        .and_then(Path::parent)
+        .filter(|base| !base.as_os_str().is_empty())
+        .and_then(|base| path.strip_prefix(base).ok())
+        .unwrap_or(path);
+    relative.to_string_lossy().replace('\\', "/")
+}
 
-    let input_ids = Tensor::from_vec(
-        input_ids_vec.clone(),
-        (1, input_ids_vec.len()),
-        model.device(),
-    )?
-    .to_dtype(DType::I64)?;
-    let mask_tensor = Tensor::from_vec(mask_vec.clone(), (1, mask_vec.len()), model.device())?
-        .to_dtype(DType::U8)?;
+/// Paint over the PII found in `text` on the page image; pages made of several images have no
+/// single frame for the grounding boxes and are returned unchanged.
+fn redact_run(redactor: &Redactor, text: &str, images: &[DynamicImage]) -> Vec<DynamicImage> {
+    match images {
+        [image] => {
+            let layout = PageLayout::from_output(text, image.width(), image.height());
+            let regions = redactor.regions(&layout);
+            info!("Redacted {} image region(s)", regions.len());
+            vec![redact_image(image, &regions, redactor.style)]
+        }
+        _ => images.to_vec(),
+    }
+}
+
+/// Write the redacted page images to `dir` as `page{p}.png`.
+fn write_redacted_images(dir: &Path, runs: &[Vec<DynamicImage>]) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    for (idx, image) in runs.iter().flatten().enumerate() {
+        let path = dir.join(format!("page{}.png", idx + 1));
+        image
+            .save(&path)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    info!(
+        "Wrote {} redacted image(s) to {}",
+        runs.len(),
+        dir.display()
+    );
+    Ok(())
+}
 
-    let mut options = GenerateOptions::new(app_config.inference.max_new_tokens);
-    options.images_seq_mask = Some(&mask_tensor);
-    if !embeddings.is_empty() {
-        options.image_embeddings = Some(embeddings.as_slice());
+/// Write each table of the output to `dir` as `page{p}_table{n}.{csv,json}`.
+fn write_tables(dir: &Path, format: TableFormat, outputs: &[PageOutput]) -> Result<()> {
+    let tables = Table::extract(&Document::from_pages(outputs));
+    if tables.is_empty() {
+        info!("No tables found in the output");
+        return Ok(());
+    }
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let mut per_page = 0;
+    for (idx, table) in tables.iter().enumerate() {
+        per_page = if idx > 0 && tables[idx - 1].page == table.page {
+            per_page + 1
+        } else {
+            1
+        };
+        let path = dir.join(format!(
+            "page{}_table{per_page}.{}",
+            table.page,
+            format.extension()
+        ));
+        fs::write(&path, table.render(format)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    info!("Wrote {} table(s) to {}", tables.len(), dir.display());
+    Ok(())
+}
+
+/// Write the cleanup reports of each page to `path` as `[{"page": 1, "images": [...]}, ...]`;
+/// pages without cleanup have no images listed.
+fn write_cleanup_report(path: &Path, pages: &[Vec<CleanupReport>]) -> Result<()> {
+    let report: Vec<_> = pages
+        .iter()
+        .enumerate()
+        .map(|(idx, images)| json!({ "page": idx + 1, "images": images }))
+        .collect();
+    fs::write(path, serde_json::to_string_pretty(&report)?)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    info!("Wrote cleanup report to {}", path.display());
+    Ok(())
+}
+
+fn write_output(document: &[u8], output: Option<&Path>) -> Result<()> {
+    match output {
+        Some(path) => {
+            fs::write(path, document)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            info!("Wrote output to {}", path.display());
+        }
+        None => io::stdout()
+            .write_all(document)
+            .context("failed to write output")?,
+    }
+    Ok(())
+}
+
+/// Run the selected pipeline (regions, zoom, segment or streaming generation) on one set of
+/// images and return its text with the grounding markup kept, along with what cleanup changed
+/// in the images. With `echo` the text is also written to stdout the way it streams.
+fn run_page(
+    engine: &OcrEngine,
+    args: &Args,
+    prompt: &str,
+    images: &[DynamicImage],
+    max_new_tokens: usize,
+    echo: bool,
+) -> Result<(String, Vec<CleanupReport>)> {
+    let output = if !args.regions.is_empty() {
+        Some(run_regions(engine, prompt, images, &args.regions)?)
+    } else if args.zoom {
+        Some(run_zoom(engine, prompt, images)?)
+    } else if args.segment {
+        Some(run_segmented(engine, prompt, images)?)
+    } else {
+        None
+    };
+    if let Some((text, cleanup)) = output {
+        if echo {
+            println!("{}", strip_grounding_tokens(&text));
+        }
+        return Ok((text, cleanup));
     }
-    options.eos_token_id = model.language_model().config().eos_token_id;
-    options.use_cache = app_config.inference.use_cache;
 
-    let tokenizer_for_stream = tokenizer.clone();
+    let tokenizer_for_stream = Arc::clone(engine.tokenizer());
     let progress_state = Rc::new(RefCell::new(0usize));
     let stream_state = Rc::clone(&progress_state);
     let stdout = Rc::new(RefCell::new(io::stdout()));
@@ -167,39 +575,134 @@ pub fn run(args: Args) -> Result<()> {
         }
         *last = count;
     };
-    options.progress_callback = Some(&progress_callback);
 
-    info!(
-        "Starting generation with requested budget {} tokens",
-        app_config.inference.max_new_tokens
-    );
+    let mut request = page_request(engine, prompt, images);
+    if echo {
+        request.progress_callback = Some(&progress_callback);
+    }
+
+    info!("Starting generation with requested budget {max_new_tokens} tokens");
     info!("--- Generation start ---");
     let gen_start = Instant::now();
-    let generated = model.generate(&input_ids, options)?;
+    let result = engine.run(&request)?;
+    if echo {
+        writeln!(stdout.borrow_mut())?;
+    }
     let elapsed = gen_start.elapsed();
     info!("--- Generation done in {:.2?} ---", elapsed);
+    info!(
+        "Prompt: {} tokens ({} image tokens), generated {} tokens, finish reason: {}",
+        result.prompt_tokens,
+        result.image_tokens,
+        result.completion_tokens(),
+        result.finish_reason.name()
+    );
 
-    let generated_tokens = generated
-        .to_vec2::<i64>()?
+    info!("Final output:\n{}", result.text);
+
+    Ok((result.grounded_text(), result.cleanup))
+}
+
+/// Request for one page carrying the engine's vision settings (sizes, tiling, resolution and
+/// cleanup) and token budget, so every pipeline runs a page the same way.
+pub(crate) fn page_request<'a>(
+    engine: &OcrEngine,
+    prompt: &'a str,
+    images: &'a [DynamicImage],
+) -> OcrRequest<'a> {
+    let settings = engine.settings();
+    let mut request = OcrRequest::new(prompt, images);
+    request.vision = Some(settings.vision);
+    request.max_new_tokens = Some(settings.max_new_tokens);
+    request
+}
+
+fn run_regions(
+    engine: &OcrEngine,
+    prompt: &str,
+    images: &[DynamicImage],
+    regions: &[BoundingBox],
+) -> Result<(String, Vec<CleanupReport>)> {
+    info!("Running OCR on {} region(s)", regions.len());
+    let gen_start = Instant::now();
+    let results = engine.run_regions(&page_request(engine, prompt, images), regions)?;
+    info!("--- Generation done in {:.2?} ---", gen_start.elapsed());
+
+    for (idx, result) in results.iter().enumerate() {
+        let region = result.region;
+        info!(
+            "Region {idx} ({}, {}, {}, {}): generated {} tokens",
+            region.x1,
+            region.y1,
+            region.x2,
+            region.y2,
+            result.ocr.completion_tokens()
+        );
+    }
+    let text = results
+        .iter()
+        .map(|result| result.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let cleanup = results
         .into_iter()
-        .next()
-        .unwrap_or_default();
-    let decoded = tokenizer
-        .decode(
-            &generated_tokens
-                .iter()
-                .filter_map(|&id| u32::try_from(id).ok())
-                .collect::<Vec<_>>(),
-            true,
-        )
-        .unwrap_or_default();
-    let normalized = normalize_text(&decoded);
-    info!("Final output:\n{normalized}");
+        .flat_map(|result| result.ocr.cleanup)
+        .collect();
+    Ok((text, cleanup))
+}
 
-    if let Some(session) = bench_session {
-        let report = session.finalize()?;
-        bench::print_summary(&report);
+fn run_zoom(
+    engine: &OcrEngine,
+    prompt: &str,
+    images: &[DynamicImage],
+) -> Result<(String, Vec<CleanupReport>)> {
+    info!("Running two-pass zoom OCR");
+    let gen_start = Instant::now();
+    let result = zoom_ocr(
+        engine,
+        &page_request(engine, prompt, images),
+        &ZoomOptions::default(),
+    )?;
+    info!("--- Generation done in {:.2?} ---", gen_start.elapsed());
+    for zoom in &result.refinements {
+        info!(
+            "Refined `{}` region ({}, {}, {}, {})",
+            zoom.label, zoom.region.x1, zoom.region.y1, zoom.region.x2, zoom.region.y2
+        );
     }
+    info!(
+        "Prompt: {} tokens, generated {} tokens across {} refined region(s)",
+        result.prompt_tokens,
+        result.completion_tokens,
+        result.refinements.len()
+    );
+    Ok((result.text, result.first_pass.cleanup))
+}
 
-    Ok(())
+fn run_segmented(
+    engine: &OcrEngine,
+    prompt: &str,
+    images: &[DynamicImage],
+) -> Result<(String, Vec<CleanupReport>)> {
+    let gen_start = Instant::now();
+    let result = segment_ocr(
+        engine,
+        &page_request(engine, prompt, images),
+        &SegmentOptions::default(),
+    )?;
+    info!(
+        "--- Generation done in {:.2?} over {} segment(s) ---",
+        gen_start.elapsed(),
+        result.segments.len()
+    );
+    info!(
+        "Prompt: {} tokens, generated {} tokens",
+        result.prompt_tokens, result.completion_tokens
+    );
+    let cleanup = result
+        .segments
+        .into_iter()
+        .flat_map(|segment| segment.ocr.cleanup)
+        .collect();
+    Ok((result.text, cleanup))
 }
@@ -1,25 +1,40 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use deepseek_ocr_config::{AppConfig, ConfigOverride, ConfigOverrides};
-use deepseek_ocr_core::runtime::{DeviceKind, Precision};
+use deepseek_ocr_core::{
+    eval::ReportFormat,
+    export::{OutputFormat, table::TableFormat},
+    grounding::BoundingBox,
+    postprocess::{PostprocessOptions, PostprocessStep},
+    redact::{PiiKind, RedactRegion, RedactStyle, parse_pattern},
+    repetition::RepetitionAction,
+    resolution::ResolutionMode,
+    roi::parse_region,
+    runtime::{DeviceKind, Precision},
+    vision::{BackgroundColor, CleanupOptions, CleanupStep},
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "DeepSeek-OCR CLI", long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Optional path to a configuration file (defaults to platform config dir).
-    #[arg(long, value_name = "PATH", help_heading = "Application")]
+    #[arg(long, global = true, value_name = "PATH", help_heading = "Application")]
     pub config: Option<PathBuf>,
 
     /// Select which model entry to load from the configuration.
-    #[arg(long, value_name = "ID", help_heading = "Application")]
+    #[arg(long, global = true, value_name = "ID", help_heading = "Application")]
     pub model: Option<String>,
 
     /// Override the model configuration JSON path.
-    #[arg(long, value_name = "PATH", help_heading = "Application")]
+    #[arg(long, global = true, value_name = "PATH", help_heading = "Application")]
     pub model_config: Option<PathBuf>,
 
-    /// Prompt text. Use `<image>` tokens to denote image slots.
+    /// Prompt text. Use `<image>` tokens to denote image slots. With `--task`, fills the
+    /// task's `{query}` placeholder instead.
     #[arg(long, conflicts_with = "prompt_file")]
     pub prompt: Option<String>,
 
@@ -27,59 +42,346 @@ pub struct Args {
     #[arg(long, value_name = "PATH", conflicts_with = "prompt")]
     pub prompt_file: Option<PathBuf>,
 
+    /// Named task preset (markdown/free-ocr/grounding/figure/describe/extract/locate or a
+    /// `[tasks]` entry from the configuration file).
+    #[arg(long, value_name = "NAME", help_heading = "Inference")]
+    pub task: Option<String>,
+
     /// Conversation template name (plain/deepseek/deepseekv2/alignment).
-    #[arg(long, help_heading = "Inference")]
+    #[arg(long, global = true, help_heading = "Inference")]
     pub template: Option<String>,
 
     /// Image files corresponding to `<image>` placeholders, in order.
-    #[arg(long = "image", value_name = "PATH")]
+    #[arg(long = "image", global = true, value_name = "PATH")]
     pub images: Vec<PathBuf>,
 
+    /// Only OCR this pixel rectangle (`x1,y1,x2,y2`) of the single `--image`. Repeat for several
+    /// regions; grounding coordinates are mapped back to the full image.
+    #[arg(long = "region", value_name = "X1,Y1,X2,Y2", value_parser = parse_region)]
+    pub regions: Vec<BoundingBox>,
+
+    /// Two-pass zoom: after a grounding pass, re-read small or unreliable regions as upscaled
+    /// crops and splice the refined text back. Use with a grounding prompt such as
+    /// `--task markdown`.
+    #[arg(long, conflicts_with = "regions", help_heading = "Inference")]
+    pub zoom: bool,
+
+    /// Split very tall or wide images (receipts, page screenshots) into overlapping strips, OCR
+    /// each and merge the outputs, dropping text repeated in the overlaps.
+    #[arg(long, conflicts_with_all = ["regions", "zoom"], help_heading = "Inference")]
+    pub segment: bool,
+
+    /// Output format: the model's text, the structured document as plain text/HTML/JSON, an
+    /// hOCR/ALTO document built from the grounding boxes, or a searchable PDF of the page images
+    /// (use a grounding prompt such as `--task markdown` for the layout formats; `pdf` requires
+    /// `--output`).
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, help_heading = "Inference")]
+    pub format: OutputFormat,
+
+    /// Write the output to a file instead of stdout.
+    #[arg(long, value_name = "PATH", help_heading = "Inference")]
+    pub output: Option<PathBuf>,
+
+    /// Also write every table in the output to DIR as a normalised cell grid, one file per
+    /// table (`page1_table1.csv`, ...).
+    #[arg(long, value_name = "DIR", help_heading = "Inference")]
+    pub tables: Option<PathBuf>,
+
+    /// File format of the tables written by `--tables`.
+    #[arg(long, value_enum, default_value_t = TableFormat::Csv, help_heading = "Inference")]
+    pub tables_format: TableFormat,
+
+    /// Also write what document cleanup changed in each image to PATH as JSON, one entry per
+    /// page image.
+    #[arg(long, value_name = "PATH", help_heading = "Inference")]
+    pub cleanup_report: Option<PathBuf>,
+
+    /// Crop every figure of the grounded output to DIR (`page1_figure1.png`, ...), describe each
+    /// crop with `--figure-task` and insert the image link and description into the text. Use
+    /// with a grounding prompt such as `--task markdown`.
+    #[arg(long, value_name = "DIR", help_heading = "Inference")]
+    pub figures: Option<PathBuf>,
+
+    /// Task preset run on each figure crop (e.g. `figure` to parse charts instead).
+    #[arg(
+        long,
+        value_name = "NAME",
+        default_value = "describe",
+        help_heading = "Inference"
+    )]
+    pub figure_task: String,
+
+    /// Write each page image with its redacted regions painted over to DIR (`page1.png`, ...);
+    /// implies `--redact`. Needs grounded output, e.g. `--task markdown`.
+    #[arg(long, value_name = "DIR", help_heading = "Redaction")]
+    pub redacted_images: Option<PathBuf>,
+
+    /// Redact personal data (e-mail addresses, phone, SSN, card and IBAN numbers by default) in
+    /// the output text.
+    #[arg(long, help_heading = "Redaction")]
+    pub redact: bool,
+
+    /// Built-in PII patterns to redact, comma separated (email,phone,ssn,card,iban,date).
+    #[arg(long, value_delimiter = ',', help_heading = "Redaction")]
+    pub redact_kinds: Option<Vec<PiiKind>>,
+
+    /// Extra named redaction pattern; repeat for several.
+    #[arg(long = "redact-pattern", value_name = "NAME=REGEX", value_parser = parse_pattern, help_heading = "Redaction")]
+    pub redact_patterns: Vec<(String, String)>,
+
+    /// How redacted image regions are painted over (black/blur).
+    #[arg(long, help_heading = "Redaction")]
+    pub redact_style: Option<RedactStyle>,
+
+    /// Granularity of image redaction (word/line/block, default block); word and line boxes are
+    /// estimated within each grounded block, and ungrounded output redacts the whole page.
+    #[arg(long, help_heading = "Redaction")]
+    pub redact_region: Option<RedactRegion>,
+
+    /// Print the prompt token estimate (text plus vision placeholders) as JSON and exit
+    /// without loading the model weights.
+    #[arg(long, conflicts_with_all = ["regions", "zoom", "segment"], help_heading = "Inference")]
+    pub dry_run: bool,
+
     /// Override the default tokenizer path.
-    #[arg(long, value_name = "PATH", help_heading = "Application")]
+    #[arg(long, global = true, value_name = "PATH", help_heading = "Application")]
     pub tokenizer: Option<PathBuf>,
 
     /// Override the weights path (defaults to DeepSeek-OCR/model-*.safetensors).
-    #[arg(long, value_name = "PATH", help_heading = "Application")]
+    #[arg(long, global = true, value_name = "PATH", help_heading = "Application")]
     pub weights: Option<PathBuf>,
 
     /// Device backend to execute on (cpu/metal/cuda).
-    #[arg(long, help_heading = "Inference")]
+    #[arg(long, global = true, help_heading = "Inference")]
     pub device: Option<DeviceKind>,
 
     /// Numeric precision. Defaults to f32 on CPU and f16 on Metal/CUDA.
-    #[arg(long, help_heading = "Inference")]
+    #[arg(long, global = true, help_heading = "Inference")]
     pub dtype: Option<Precision>,
 
     /// Global view resolution (defaults to 1024).
-    #[arg(long, help_heading = "Inference")]
+    #[arg(long, global = true, help_heading = "Inference")]
     pub base_size: Option<u32>,
 
     /// Local crop resolution (defaults to 640).
-    #[arg(long, help_heading = "Inference")]
+    #[arg(long, global = true, help_heading = "Inference")]
     pub image_size: Option<u32>,
 
     /// Enable/disable dynamic crop mode (true/false).
-    #[arg(long, help_heading = "Inference")]
+    #[arg(long, global = true, help_heading = "Inference")]
     pub crop_mode: Option<bool>,
 
+    /// Minimum number of local tiles in crop mode (defaults to 2).
+    #[arg(long, global = true, help_heading = "Inference")]
+    pub min_tiles: Option<u32>,
+
+    /// Maximum number of local tiles in crop mode (defaults to 9).
+    #[arg(long, global = true, help_heading = "Inference")]
+    pub max_tiles: Option<u32>,
+
+    /// Append a whole-image thumbnail tile in crop mode (true/false).
+    #[arg(long, global = true, help_heading = "Inference")]
+    pub thumbnail: Option<bool>,
+
+    /// Resolution mode (tiny/small/base/large/gundam/auto); overrides the size flags.
+    #[arg(long, global = true, help_heading = "Inference")]
+    pub resolution: Option<ResolutionMode>,
+
+    /// Per-image vision token cap used by `--resolution auto`.
+    #[arg(long, global = true, help_heading = "Inference")]
+    pub max_vision_tokens: Option<usize>,
+
+    /// Apply the EXIF orientation tag when decoding images (true/false, defaults to true).
+    #[arg(long, global = true, help_heading = "Inference")]
+    pub exif_orientation: Option<bool>,
+
+    /// Colour transparent pixels are composited onto (#rrggbb, defaults to white).
+    #[arg(long, global = true, value_name = "COLOR", help_heading = "Inference")]
+    pub background: Option<BackgroundColor>,
+
+    /// Document cleanup steps to run before preprocessing, comma separated
+    /// (rotate,deskew,contrast,shadows,denoise,binarize or all).
+    #[arg(long, global = true, value_delimiter = ',', help_heading = "Inference")]
+    pub cleanup: Option<Vec<CleanupStep>>,
+
+    /// Text post-processing passes to run on the decoded output, comma separated
+    /// (repeats,tables,hyphens,latex or all).
+    #[arg(long, global = true, value_delimiter = ',', help_heading = "Inference")]
+    pub postprocess: Option<Vec<PostprocessStep>>,
+
+    /// What to do when decoding falls into a repetition loop: off, stop (finish reason
+    /// `repetition`) or penalize (steer away from the loop and keep going).
+    #[arg(long, global = true, value_name = "ACTION", help_heading = "Inference")]
+    pub repetition: Option<RepetitionAction>,
+
+    /// Longest repeated unit, in tokens, checked by repetition detection.
+    #[arg(long, global = true, value_name = "TOKENS", help_heading = "Inference")]
+    pub repetition_window: Option<usize>,
+
+    /// Back-to-back copies of a unit that count as a repetition loop.
+    #[arg(long, global = true, value_name = "COUNT", help_heading = "Inference")]
+    pub repetition_threshold: Option<usize>,
+
     /// Maximum number of tokens to generate.
-    #[arg(long, help_heading = "Inference")]
+    #[arg(long, global = true, help_heading = "Inference")]
     pub max_new_tokens: Option<usize>,
 
     /// Disable KV-cache usage during decoding.
-    #[arg(long, help_heading = "Inference")]
+    #[arg(long, global = true, help_heading = "Inference")]
     pub no_cache: bool,
 
     /// Enable benchmark instrumentation (requires `bench-metrics` feature).
-    #[arg(long, help_heading = "Benchmark")]
+    #[arg(long, global = true, help_heading = "Benchmark")]
     pub bench: bool,
 
     /// Write benchmark events to a JSON file.
-    #[arg(long, value_name = "PATH", help_heading = "Benchmark")]
+    #[arg(long, global = true, value_name = "PATH", help_heading = "Benchmark")]
     pub bench_output: Option<PathBuf>,
 }
 
+/// Specialised operations. Without a subcommand the CLI runs free-form generation.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Find the given text in a single `--image` and print its pixel-space boxes as JSON.
+    Locate(LocateArgs),
+    /// Extract form or invoice fields from a single `--image` and print them as JSON.
+    Extract(ExtractArgs),
+    /// Score OCR output against ground-truth text for a directory of labelled images.
+    Eval(EvalArgs),
+    /// Capture parity tensors in the `baselines/` layout, or compare them with a stored baseline.
+    Baseline(BaselineArgs),
+    /// Time repeated runs over the `--image` files and report per-stage latency percentiles,
+    /// or compare stored benchmark reports.
+    Benchmark(BenchmarkArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct LocateArgs {
+    /// Text to search for.
+    #[arg(long)]
+    pub query: String,
+
+    /// Write the JSON result to a file instead of stdout.
+    #[arg(long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ExtractArgs {
+    /// Field to extract as `name` or `name:type` (string/number/integer/boolean/date); repeat
+    /// for several.
+    #[arg(
+        long = "field",
+        value_name = "NAME[:TYPE]",
+        required_unless_present = "schema"
+    )]
+    pub fields: Vec<String>,
+
+    /// JSON Schema object (or array of `{"name", "type", "description"}` fields) describing
+    /// the fields to extract.
+    #[arg(long, value_name = "PATH", conflicts_with = "fields")]
+    pub schema: Option<PathBuf>,
+
+    /// Skip the grounding pass that finds the supporting box of each value.
+    #[arg(long)]
+    pub no_boxes: bool,
+
+    /// Write the JSON result to a file instead of stdout.
+    #[arg(long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct EvalArgs {
+    /// Directory of images, each with its ground truth in a `.md` or `.txt` file of the same
+    /// name (`page1.png` + `page1.md`).
+    #[arg(long, value_name = "DIR")]
+    pub dataset: PathBuf,
+
+    /// Task preset used to transcribe every image.
+    #[arg(long, value_name = "NAME", default_value = "markdown")]
+    pub task: String,
+
+    /// Report format.
+    #[arg(long, value_enum, default_value_t = ReportFormat::Json)]
+    pub format: ReportFormat,
+
+    /// Earlier JSON report to compare against; the markdown report then shows deltas.
+    #[arg(long, value_name = "PATH")]
+    pub baseline: Option<PathBuf>,
+
+    /// Compare texts case-insensitively.
+    #[arg(long)]
+    pub ignore_case: bool,
+
+    /// Write the report to a file instead of stdout.
+    #[arg(long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct BaselineArgs {
+    /// Prompt to capture (include `<image>`); defaults to the prompt of the `--compare` baseline.
+    #[arg(long)]
+    pub prompt: Option<String>,
+
+    /// Write the Rust capture to this directory.
+    #[arg(long, value_name = "DIR", required_unless_present = "compare")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Stored baseline to compare against. Its image, prompt, vision sizes and generated tokens
+    /// are reused so every stage covers the same positions.
+    #[arg(long, value_name = "DIR")]
+    pub compare: Option<PathBuf>,
+
+    /// Compare this existing capture instead of running the model.
+    #[arg(
+        long,
+        value_name = "DIR",
+        requires = "compare",
+        conflicts_with = "output_dir"
+    )]
+    pub candidate: Option<PathBuf>,
+
+    /// Fail when the max abs error of any compared array exceeds this value.
+    #[arg(long, value_name = "ERROR")]
+    pub tolerance: Option<f64>,
+
+    /// Write the parity report as JSON.
+    #[arg(long, value_name = "PATH", requires = "compare")]
+    pub report: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct BenchmarkArgs {
+    /// Untimed passes over all images before measuring.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub warmup: usize,
+
+    /// Measured passes over all images.
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    pub iterations: usize,
+
+    /// Write the JSON report to this file.
+    #[arg(long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// Reference report (`benchmark --output` or `--bench-output` JSON) to compare stage totals
+    /// against.
+    #[arg(long, value_name = "PATH")]
+    pub compare: Option<PathBuf>,
+
+    /// Compare this stored report with `--compare` instead of running the model; repeat for
+    /// several.
+    #[arg(
+        long = "candidate",
+        value_name = "PATH",
+        requires = "compare",
+        conflicts_with = "output"
+    )]
+    pub candidates: Vec<PathBuf>,
+}
+
 impl From<&Args> for ConfigOverrides {
     fn from(args: &Args) -> Self {
         let mut overrides = ConfigOverrides::default();
@@ -94,7 +396,27 @@ impl From<&Args> for ConfigOverrides {
         overrides.inference.base_size = args.base_size;
         overrides.inference.image_size = args.image_size;
         overrides.inference.crop_mode = args.crop_mode;
+        overrides.inference.min_tiles = args.min_tiles;
+        overrides.inference.max_tiles = args.max_tiles;
+        overrides.inference.use_thumbnail = args.thumbnail;
+        overrides.inference.resolution = args.resolution;
+        overrides.inference.max_vision_tokens = args.max_vision_tokens;
+        overrides.inference.exif_orientation = args.exif_orientation;
+        overrides.inference.background = args.background;
+        overrides.inference.cleanup = args.cleanup.as_deref().map(CleanupOptions::from_steps);
+        overrides.inference.postprocess = args
+            .postprocess
+            .as_deref()
+            .map(PostprocessOptions::from_steps);
         overrides.inference.max_new_tokens = args.max_new_tokens;
+        overrides.inference.redact = args.redact.then_some(true);
+        overrides.inference.redact_kinds = args.redact_kinds.clone();
+        overrides.inference.redact_patterns = args.redact_patterns.clone();
+        overrides.inference.redact_style = args.redact_style;
+        overrides.inference.redact_region = args.redact_region;
+        overrides.inference.repetition = args.repetition;
+        overrides.inference.repetition_window = args.repetition_window;
+        overrides.inference.repetition_threshold = args.repetition_threshold;
         if args.no_cache {
             overrides.inference.use_cache = Some(false);
         }
@@ -0,0 +1,127 @@
+use std::{fs, path::Path, time::Instant};
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use deepseek_ocr_core::baseline::{
+    Baseline, CaptureOptions, capture_baseline, compare_baselines, resolve_recorded_path,
+};
+use tracing::info;
+
+use crate::{
+    app::{load_config, load_engine, open_images},
+    args::{Args, BaselineArgs},
+};
+
+pub fn run(args: &Args, baseline: &BaselineArgs) -> Result<()> {
+    let reference = baseline
+        .compare
+        .as_deref()
+        .map(|dir| {
+            info!("Loading reference baseline from {}", dir.display());
+            Baseline::load(dir)
+        })
+        .transpose()?;
+
+    let candidate = match &baseline.candidate {
+        Some(dir) => Baseline::load(dir)?,
+        None => {
+            let capture = capture(args, baseline, reference.as_ref())?;
+            if let Some(dir) = &baseline.output_dir {
+                capture.write(dir)?;
+                info!("Wrote baseline capture to {}", dir.display());
+            }
+            capture
+        }
+    };
+
+    let Some(reference) = reference else {
+        return Ok(());
+    };
+    let report = compare_baselines(&reference, &candidate)?;
+    print!("{}", report.to_text());
+    if let Some(path) = &baseline.report {
+        fs::write(path, serde_json::to_string_pretty(&report)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        info!("Wrote parity report to {}", path.display());
+    }
+    if let Some(tolerance) = baseline.tolerance {
+        let failures = report.failures(tolerance);
+        if !failures.is_empty() {
+            let names: Vec<String> = failures
+                .iter()
+                .map(|diff| format!("{}/{}", diff.stage, diff.name))
+                .collect();
+            bail!(
+                "{} array(s) exceed max abs error {tolerance} or could not be compared: {}",
+                failures.len(),
+                names.join(", ")
+            );
+        }
+        info!(
+            "All stages within max abs error {tolerance} (largest {:.3e})",
+            report.max_abs()
+        );
+    }
+    Ok(())
+}
+
+/// Run the Rust pipeline. Settings missing from the command line are taken from `reference`.
+fn capture(args: &Args, baseline: &BaselineArgs, reference: Option<&Baseline>) -> Result<Baseline> {
+    let image = match (args.images.as_slice(), reference) {
+        ([image], _) => image.clone(),
+        ([], Some(reference)) => resolve_recorded_path(
+            baseline.compare.as_deref().unwrap_or(Path::new(".")),
+            &reference.metadata.image,
+        ),
+        (images, _) => bail!(
+            "baseline expects exactly one --image (got {})",
+            images.len()
+        ),
+    };
+    let prompt = baseline
+        .prompt
+        .clone()
+        .or_else(|| reference.map(|reference| reference.metadata.prompt.clone()))
+        .ok_or_else(|| anyhow!("--prompt is required without --compare"))?;
+    ensure!(
+        prompt.matches("<image>").count() == 1,
+        "the baseline prompt needs exactly one <image> slot"
+    );
+
+    let loaded = load_config(args, None)?;
+    let mut engine = load_engine(&loaded)?;
+    if let Some(reference) = reference {
+        let vision = &mut engine.settings_mut().vision;
+        let metadata = &reference.metadata;
+        vision.base_size = metadata.base_size.unwrap_or(vision.base_size);
+        vision.image_size = metadata.image_size.unwrap_or(vision.image_size);
+        vision.crop_mode = metadata.crop_mode.unwrap_or(vision.crop_mode);
+    }
+    let images = open_images(
+        std::slice::from_ref(&image),
+        &loaded.app_config.inference.decode,
+    )?;
+
+    let options = CaptureOptions {
+        image_paths: vec![image.display().to_string()],
+        max_new_tokens: loaded.app_config.inference.max_new_tokens,
+        teacher_tokens: reference.map(|reference| {
+            let output = &reference.output;
+            output.tokens[output.prefill_len.min(output.tokens.len())..].to_vec()
+        }),
+    };
+    let start = Instant::now();
+    let mut capture = capture_baseline(&engine, &images, &prompt, &options)?;
+    capture.metadata.model = loaded.app_config.models.active.clone();
+    info!(
+        "Captured {} prompt and {} generated token(s) in {:.2?}{}",
+        capture.output.prefill_len,
+        capture.output.generated_len,
+        start.elapsed(),
+        if options.teacher_tokens.is_some() {
+            " (teacher-forced on the reference tokens)"
+        } else {
+            ""
+        }
+    );
+    Ok(capture)
+}
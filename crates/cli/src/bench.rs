@@ -99,6 +99,21 @@ impl Session {
     }
 }
 
+/// Run `op` and return the events it emitted; always empty without `bench-metrics`.
+#[cfg(feature = "bench-metrics")]
+pub fn record_events<T>(op: impl FnOnce() -> Result<T>) -> Result<(T, Vec<BenchEvent>)> {
+    let collector = Arc::new(Collector::default());
+    set_recorder(Some(collector.clone()));
+    let result = op();
+    set_recorder(None);
+    Ok((result?, collector.snapshot()))
+}
+
+#[cfg(not(feature = "bench-metrics"))]
+pub fn record_events<T>(op: impl FnOnce() -> Result<T>) -> Result<(T, Vec<BenchEvent>)> {
+    Ok((op()?, Vec::new()))
+}
+
 pub fn maybe_start(enabled: bool, output: Option<PathBuf>) -> Result<Option<Session>> {
     if !enabled {
         return Ok(None);
@@ -0,0 +1,162 @@
+use std::{collections::BTreeMap, fs, path::Path, time::Instant};
+
+use anyhow::{Context, Result, ensure};
+use deepseek_ocr_config::AppConfig;
+use deepseek_ocr_core::{
+    benchmark::{
+        BenchmarkReport, RequestSample, compare_stage_totals, load_stage_totals, peak_memory_bytes,
+        reset_peak_memory,
+    },
+    engine::{EngineSettings, OcrRequest},
+};
+use tracing::info;
+
+use crate::{
+    app::{load_config, load_engine, open_images},
+    args::{Args, BenchmarkArgs},
+    bench::record_events,
+    eval::insert_engine_settings,
+    prompt::load_prompt,
+};
+
+pub fn run(args: &Args, benchmark: &BenchmarkArgs) -> Result<()> {
+    ensure!(
+        !args.bench && args.bench_output.is_none(),
+        "the benchmark subcommand records its own events; use --output instead of --bench/--bench-output"
+    );
+    if !benchmark.candidates.is_empty() {
+        let reference = benchmark
+            .compare
+            .as_deref()
+            .context("--candidate requires --compare")?;
+        let targets = benchmark
+            .candidates
+            .iter()
+            .map(|path| Ok((label(path), load_stage_totals(path)?)))
+            .collect::<Result<Vec<_>>>()?;
+        print!(
+            "{}",
+            compare_stage_totals(&load_stage_totals(reference)?, &targets)
+        );
+        return Ok(());
+    }
+    ensure!(benchmark.iterations > 0, "--iterations must be at least 1");
+    ensure!(
+        !args.images.is_empty(),
+        "benchmark needs at least one --image"
+    );
+
+    let loaded = load_config(args, args.task.as_deref())?;
+    let prompt = load_prompt(args, loaded.task.as_ref())?;
+    let engine = load_engine(&loaded)?;
+    let prompt = engine.render_prompt(&prompt)?;
+    ensure!(
+        prompt.matches("<image>").count() == 1,
+        "benchmark needs a prompt with exactly one <image> slot"
+    );
+    let images = open_images(&args.images, &loaded.app_config.inference.decode)?;
+    if !cfg!(feature = "bench-metrics") {
+        info!(
+            "Built without the bench-metrics feature: only request latency is reported, no stages or token rates"
+        );
+    }
+
+    for iteration in 0..benchmark.warmup {
+        for image in &images {
+            engine.run(&OcrRequest::new(&prompt, std::slice::from_ref(image)))?;
+        }
+        info!("Warmup {}/{} done", iteration + 1, benchmark.warmup);
+    }
+    if !reset_peak_memory() {
+        info!("Could not reset the peak memory mark: the reported peak covers the whole process");
+    }
+
+    let mut samples = Vec::with_capacity(benchmark.iterations * images.len());
+    for iteration in 0..benchmark.iterations {
+        for (image, path) in images.iter().zip(&args.images) {
+            let start = Instant::now();
+            let (result, events) = record_events(|| {
+                engine.run(&OcrRequest::new(&prompt, std::slice::from_ref(image)))
+            })?;
+            let duration = start.elapsed();
+            info!(
+                "[{}/{}] {}: {duration:.2?} ({} prompt, {} completion tokens)",
+                iteration + 1,
+                benchmark.iterations,
+                path.display(),
+                result.prompt_tokens,
+                result.completion_tokens()
+            );
+            samples.push(RequestSample { duration, events });
+        }
+    }
+
+    let report = BenchmarkReport::from_samples(
+        settings(args, &loaded.app_config, engine.settings()),
+        args.images
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect(),
+        benchmark.warmup,
+        benchmark.iterations,
+        &samples,
+        peak_memory_bytes(),
+    );
+    print!("{}", report.to_text());
+    if let Some(path) = &benchmark.output {
+        fs::write(path, serde_json::to_string_pretty(&report)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        info!("Wrote benchmark report to {}", path.display());
+    }
+    if let Some(reference) = &benchmark.compare {
+        let current = report
+            .stage_totals
+            .iter()
+            .map(|stats| (stats.stage.clone(), stats.clone()))
+            .collect();
+        print!(
+            "{}",
+            compare_stage_totals(
+                &load_stage_totals(reference)?,
+                &[("current".to_string(), current)]
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Column label of a compared report: its file stem.
+fn label(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Settings recorded in the report so two runs can be told apart.
+fn settings(
+    args: &Args,
+    app_config: &AppConfig,
+    engine: &EngineSettings,
+) -> BTreeMap<String, String> {
+    let inference = &app_config.inference;
+    let mut settings = BTreeMap::new();
+    settings.insert("model".to_string(), app_config.models.active.clone());
+    if let Some(task) = &args.task {
+        settings.insert("task".to_string(), task.clone());
+    }
+    settings.insert(
+        "device".to_string(),
+        format!("{:?}", inference.device).to_lowercase(),
+    );
+    settings.insert(
+        "dtype".to_string(),
+        inference
+            .precision
+            .map_or("default".to_string(), |precision| {
+                format!("{precision:?}").to_lowercase()
+            }),
+    );
+    insert_engine_settings(&mut settings, app_config, engine);
+    settings.insert("use_cache".to_string(), inference.use_cache.to_string());
+    settings
+}
@@ -0,0 +1,81 @@
+use anyhow::{Context, Result, anyhow};
+use deepseek_ocr_core::{
+    engine::{EngineSettings, OcrRequest, TokenEstimate, estimate_tokens},
+    inference::render_prompt,
+    resolution::ResolutionMode,
+    vision::page_runs,
+};
+use serde::Serialize;
+use tokenizers::Tokenizer;
+use tracing::info;
+
+use crate::{
+    app::{LoadedConfig, open_pages},
+    args::Args,
+    resources::ensure_tokenizer_file,
+};
+
+#[derive(Debug, Serialize)]
+struct EstimateOutput<'a> {
+    prompt_tokens: usize,
+    /// Number of separate runs (pages of a multi-page input).
+    pages: usize,
+    text_tokens: usize,
+    image_tokens: &'a [usize],
+    base_size: u32,
+    image_size: u32,
+    crop_mode: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resolution: Option<ResolutionMode>,
+}
+
+/// `--dry-run`: report the prompt token budget without loading the model weights. Only the
+/// tokenizer is loaded, but every page is fully decoded and cleaned up, since cleanup and auto
+/// resolution depend on the pixels.
+pub fn run(args: &Args, loaded: &LoadedConfig, prompt_raw: &str) -> Result<()> {
+    let app_config = &loaded.app_config;
+    let resources = app_config.active_model_resources(&loaded.fs)?;
+    let tokenizer_path = ensure_tokenizer_file(&loaded.fs, &resources.tokenizer)?;
+    let tokenizer = Tokenizer::from_file(&tokenizer_path).map_err(|err| {
+        anyhow!(
+            "failed to load tokenizer from {}: {err}",
+            tokenizer_path.display()
+        )
+    })?;
+
+    let settings = EngineSettings::from(&app_config.inference);
+    let prompt = render_prompt(&settings.template, "", prompt_raw)?;
+    let runs = page_runs(open_pages(&args.images, &app_config.inference.decode)?)?;
+    let mut estimate = None::<TokenEstimate>;
+    for images in &runs {
+        let page = estimate_tokens(
+            &tokenizer,
+            &OcrRequest::new(&prompt, images),
+            settings.vision,
+        )?;
+        match estimate.as_mut() {
+            Some(total) => total.add_page(page),
+            None => estimate = Some(page),
+        }
+    }
+    let estimate = estimate.context("nothing to estimate")?;
+    info!(
+        "Estimated {} prompt tokens ({} text, {} image)",
+        estimate.prompt.prompt_tokens(),
+        estimate.prompt.text_tokens,
+        estimate.prompt.total_image_tokens()
+    );
+
+    let output = EstimateOutput {
+        prompt_tokens: estimate.prompt.prompt_tokens(),
+        pages: runs.len(),
+        text_tokens: estimate.prompt.text_tokens,
+        image_tokens: &estimate.prompt.image_tokens,
+        base_size: estimate.vision.base_size,
+        image_size: estimate.vision.image_size,
+        crop_mode: estimate.vision.crop_mode,
+        resolution: estimate.resolution.map(|choice| choice.mode),
+    };
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
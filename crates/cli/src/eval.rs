@@ -0,0 +1,261 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::{Context, Result, ensure};
+use deepseek_ocr_config::AppConfig;
+use deepseek_ocr_core::{
+    engine::EngineSettings,
+    eval::{EvalReport, SampleResult, TableScores, TextScores, extract_tables, normalize_for_eval},
+    model::FinishReason,
+    postprocess::postprocess,
+    vision::CleanupOptions,
+};
+use tracing::info;
+
+use crate::{
+    app::{load_config, load_engine, open_pages, page_request},
+    args::{Args, EvalArgs},
+    prompt::load_prompt,
+};
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "bmp", "gif", "tif", "tiff"];
+const TRUTH_EXTENSIONS: &[&str] = &["md", "txt"];
+
+/// One labelled image of the dataset.
+struct Sample {
+    name: String,
+    image: PathBuf,
+    truth: PathBuf,
+}
+
+pub fn run(args: &Args, eval: &EvalArgs) -> Result<()> {
+    let samples = discover_samples(&eval.dataset)?;
+    ensure!(
+        !samples.is_empty(),
+        "no labelled images found in {} (expected `name.png` next to `name.md` or `name.txt`)",
+        eval.dataset.display()
+    );
+    let baseline = eval
+        .baseline
+        .as_deref()
+        .map(|path| {
+            let raw = fs::read_to_string(path)
+                .with_context(|| format!("failed to read baseline {}", path.display()))?;
+            serde_json::from_str::<EvalReport>(&raw)
+                .with_context(|| format!("failed to parse baseline report {}", path.display()))
+        })
+        .transpose()?;
+
+    let loaded = load_config(args, Some(&eval.task))?;
+    let prompt = load_prompt(args, loaded.task.as_ref())?;
+    let engine = load_engine(&loaded)?;
+    let app_config = &loaded.app_config;
+    let prompt = engine.render_prompt(&prompt)?;
+    ensure!(
+        prompt.matches("<image>").count() == 1,
+        "eval needs a prompt with exactly one <image> slot"
+    );
+
+    info!(
+        "Evaluating {} sample(s) with task `{}`",
+        samples.len(),
+        eval.task
+    );
+    let mut results = Vec::with_capacity(samples.len());
+    for (idx, sample) in samples.iter().enumerate() {
+        let reference = fs::read_to_string(&sample.truth)
+            .with_context(|| format!("failed to read {}", sample.truth.display()))?;
+        let pages = open_pages(
+            std::slice::from_ref(&sample.image),
+            &app_config.inference.decode,
+        )?
+        .remove(0);
+
+        let start = Instant::now();
+        let mut texts = Vec::with_capacity(pages.len());
+        let mut prompt_tokens = 0;
+        let mut completion_tokens = 0;
+        let mut finish_reason = FinishReason::Stop;
+        for page in &pages {
+            let result = engine.run(&page_request(&engine, &prompt, std::slice::from_ref(page)))?;
+            prompt_tokens += result.prompt_tokens;
+            completion_tokens += result.completion_tokens();
+            if result.finish_reason != FinishReason::Stop {
+                finish_reason = result.finish_reason;
+            }
+            texts.push(postprocess(&result.text, &app_config.inference.postprocess));
+        }
+        let seconds = start.elapsed().as_secs_f64();
+        let prediction = texts.join("\n\n");
+
+        let text = TextScores::compare(
+            &normalize_for_eval(&prediction, eval.ignore_case),
+            &normalize_for_eval(&reference, eval.ignore_case),
+        );
+        let tables =
+            TableScores::compare(&extract_tables(&prediction), &extract_tables(&reference));
+        info!(
+            "[{}/{}] {}: CER {:.4}, WER {:.4} ({completion_tokens} tokens in {seconds:.2}s, finish reason: {})",
+            idx + 1,
+            samples.len(),
+            sample.name,
+            text.cer(),
+            text.wer(),
+            finish_reason.name()
+        );
+        results.push(SampleResult {
+            name: sample.name.clone(),
+            text,
+            tables,
+            prompt_tokens,
+            completion_tokens,
+            finish_reason,
+            seconds,
+        });
+    }
+
+    let report = EvalReport::new(
+        settings(args, eval, &loaded.app_config, engine.settings()),
+        results,
+    );
+    info!(
+        "CER {:.4}, WER {:.4}, normalised edit distance {:.4} over {} sample(s)",
+        report.summary.cer,
+        report.summary.wer,
+        report.summary.normalized_edit_distance,
+        report.summary.samples
+    );
+    let rendered = report.render(eval.format, baseline.as_ref())?;
+    match &eval.output {
+        Some(path) => {
+            fs::write(path, rendered)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            info!("Wrote evaluation report to {}", path.display());
+        }
+        None => println!("{rendered}"),
+    }
+    Ok(())
+}
+
+/// Images of `dir` that have a ground-truth file beside them, sorted by file name.
+fn discover_samples(dir: &Path) -> Result<Vec<Sample>> {
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("failed to read dataset {}", dir.display()))?;
+    let mut samples = Vec::new();
+    for entry in entries {
+        let image = entry?.path();
+        let is_image = image
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+        if !is_image {
+            continue;
+        }
+        let truth = TRUTH_EXTENSIONS
+            .iter()
+            .map(|ext| image.with_extension(ext))
+            .find(|path| path.is_file());
+        let name = image
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match truth {
+            Some(truth) => samples.push(Sample { name, image, truth }),
+            None => info!("Skipping {name}: no ground-truth .md or .txt file"),
+        }
+    }
+    samples.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(samples)
+}
+
+/// Settings recorded in the report so two runs can be told apart.
+fn settings(
+    args: &Args,
+    eval: &EvalArgs,
+    app_config: &AppConfig,
+    engine: &EngineSettings,
+) -> BTreeMap<String, String> {
+    let inference = &app_config.inference;
+    let mut settings = BTreeMap::new();
+    settings.insert("model".to_string(), app_config.models.active.clone());
+    settings.insert("task".to_string(), eval.task.clone());
+    if let Some(prompt) = &args.prompt {
+        settings.insert("query".to_string(), prompt.clone());
+    }
+    settings.insert(
+        "device".to_string(),
+        format!("{:?}", inference.device).to_lowercase(),
+    );
+    settings.insert(
+        "dtype".to_string(),
+        inference
+            .precision
+            .map_or("default".to_string(), |precision| {
+                format!("{precision:?}").to_lowercase()
+            }),
+    );
+    insert_engine_settings(&mut settings, app_config, engine);
+    settings.insert("ignore_case".to_string(), eval.ignore_case.to_string());
+    settings
+}
+
+/// Record the vision, resolution, cleanup and token budget `engine` actually runs with, after
+/// the CLI flags and presets were applied to the configuration.
+pub(crate) fn insert_engine_settings(
+    settings: &mut BTreeMap<String, String>,
+    app_config: &AppConfig,
+    engine: &EngineSettings,
+) {
+    let inference = &app_config.inference;
+    let vision = &engine.vision;
+    settings.insert(
+        "vision".to_string(),
+        format!(
+            "base {} / image {} / crop {} / tiles {}-{} / thumbnail {}",
+            vision.base_size,
+            vision.image_size,
+            vision.crop_mode,
+            vision.tiling.min_tiles,
+            vision.tiling.max_tiles,
+            vision.tiling.use_thumbnail
+        ),
+    );
+    let resolution = match (inference.resolution, vision.auto_resolution) {
+        (_, Some(auto)) => match auto.max_vision_tokens {
+            Some(limit) => format!("auto (max {limit} vision tokens)"),
+            None => "auto".to_string(),
+        },
+        (Some(mode), None) => mode.name().to_string(),
+        (None, None) => "default".to_string(),
+    };
+    settings.insert("resolution".to_string(), resolution);
+    settings.insert("cleanup".to_string(), cleanup_steps(&vision.cleanup));
+    settings.insert(
+        "max_new_tokens".to_string(),
+        engine.max_new_tokens.to_string(),
+    );
+}
+
+/// Enabled cleanup steps as a comma-separated list, or `none`.
+fn cleanup_steps(options: &CleanupOptions) -> String {
+    let steps: Vec<&str> = [
+        (options.auto_rotate, "rotate"),
+        (options.deskew, "deskew"),
+        (options.remove_shadows, "shadows"),
+        (options.normalize_contrast, "contrast"),
+        (options.denoise, "denoise"),
+        (options.binarize, "binarize"),
+    ]
+    .into_iter()
+    .filter_map(|(enabled, name)| enabled.then_some(name))
+    .collect();
+    if steps.is_empty() {
+        "none".to_string()
+    } else {
+        steps.join(",")
+    }
+}
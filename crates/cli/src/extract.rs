@@ -0,0 +1,87 @@
+use std::{fs, time::Instant};
+
+use anyhow::{Context, Result, ensure};
+use deepseek_ocr_core::{
+    extract::{ExtractOptions, ExtractedField, ExtractionSchema, extract_fields},
+    redact::Redactor,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tracing::info;
+
+use crate::{
+    app::{load_config, load_engine, open_images},
+    args::{Args, ExtractArgs},
+};
+
+#[derive(Debug, Serialize)]
+struct ExtractOutput<'a> {
+    data: Map<String, Value>,
+    fields: &'a [ExtractedField],
+    width: u32,
+    height: u32,
+    text: &'a str,
+}
+
+pub fn run(args: &Args, extract: &ExtractArgs) -> Result<()> {
+    ensure!(
+        args.images.len() == 1,
+        "extract expects exactly one --image (got {})",
+        args.images.len()
+    );
+    let schema = match &extract.schema {
+        Some(path) => {
+            let raw = fs::read_to_string(path)
+                .with_context(|| format!("failed to read schema {}", path.display()))?;
+            let json: Value = serde_json::from_str(&raw)
+                .with_context(|| format!("failed to parse schema {}", path.display()))?;
+            ExtractionSchema::from_json(&json)?
+        }
+        None => ExtractionSchema::from_fields(&extract.fields)?,
+    };
+
+    let loaded = load_config(args, Some("extract"))?;
+    let engine = load_engine(&loaded)?;
+    let images = open_images(&args.images, &loaded.app_config.inference.decode)?;
+
+    let options = ExtractOptions {
+        boxes: !extract.no_boxes,
+        max_new_tokens: args.max_new_tokens,
+    };
+    let start = Instant::now();
+    let mut result = extract_fields(&engine, &images[0], &schema, &options)?;
+    let found = result
+        .fields
+        .iter()
+        .filter(|field| !field.value.is_null())
+        .count();
+    info!(
+        "Extracted {found}/{} field(s) in {:.2?} (generated {} tokens, finish reason: {})",
+        result.fields.len(),
+        start.elapsed(),
+        result.completion_tokens,
+        result.finish_reason.name()
+    );
+
+    let redaction = &loaded.app_config.inference.redaction;
+    if redaction.enabled {
+        result.redact(&Redactor::new(redaction)?);
+    }
+
+    let output = ExtractOutput {
+        data: result.data(),
+        fields: &result.fields,
+        width: result.width,
+        height: result.height,
+        text: &result.answer,
+    };
+    let json = serde_json::to_string_pretty(&output)?;
+    match &extract.output {
+        Some(path) => {
+            fs::write(path, json).with_context(|| format!("failed to write {}", path.display()))?;
+            info!("Wrote extraction result to {}", path.display());
+        }
+        None => println!("{json}"),
+    }
+    Ok(())
+}
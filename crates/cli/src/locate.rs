@@ -0,0 +1,64 @@
+use std::{fs, time::Instant};
+
+use anyhow::{Context, Result, ensure};
+use deepseek_ocr_core::{grounding::GroundedBox, redact::Redactor};
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    app::{load_config, load_engine, open_images},
+    args::{Args, LocateArgs},
+};
+
+#[derive(Debug, Serialize)]
+struct LocateOutput<'a> {
+    query: &'a str,
+    width: u32,
+    height: u32,
+    boxes: &'a [GroundedBox],
+    text: &'a str,
+}
+
+pub fn run(args: &Args, locate: &LocateArgs) -> Result<()> {
+    ensure!(
+        args.images.len() == 1,
+        "locate expects exactly one --image (got {})",
+        args.images.len()
+    );
+    ensure!(!locate.query.trim().is_empty(), "--query must not be empty");
+
+    let loaded = load_config(args, Some("locate"))?;
+    let engine = load_engine(&loaded)?;
+    let images = open_images(&args.images, &loaded.app_config.inference.decode)?;
+
+    let start = Instant::now();
+    let mut result = engine.locate(&images[0], &locate.query)?;
+    info!(
+        "Located {} box(es) for `{}` in {:.2?}",
+        result.boxes.len(),
+        result.query,
+        start.elapsed()
+    );
+
+    let redaction = &loaded.app_config.inference.redaction;
+    if redaction.enabled {
+        result.redact(&Redactor::new(redaction)?);
+    }
+
+    let output = LocateOutput {
+        query: &result.query,
+        width: result.width,
+        height: result.height,
+        boxes: &result.boxes,
+        text: &result.ocr.text,
+    };
+    let json = serde_json::to_string_pretty(&output)?;
+    match &locate.output {
+        Some(path) => {
+            fs::write(path, json).with_context(|| format!("failed to write {}", path.display()))?;
+            info!("Wrote locate result to {}", path.display());
+        }
+        None => println!("{json}"),
+    }
+    Ok(())
+}
@@ -1,6 +1,12 @@
 mod app;
 mod args;
+mod baseline;
 mod bench;
+mod benchmark;
+mod estimate;
+mod eval;
+mod extract;
+mod locate;
 mod logging;
 mod prompt;
 mod resources;
@@ -1,19 +1,23 @@
 use std::fs;
 
 use anyhow::{Context, Result, anyhow};
+use deepseek_ocr_core::task::TaskPreset;
 
 use crate::args::Args;
 
-pub fn load_prompt(args: &Args) -> Result<String> {
+pub fn load_prompt(args: &Args, task: Option<&TaskPreset>) -> Result<String> {
+    let text = read_prompt_text(args)?;
+    if let Some(task) = task {
+        return task.render(text.as_deref());
+    }
+    text.ok_or_else(|| anyhow!("prompt is required (use --prompt, --prompt-file or --task)"))
+}
+
+fn read_prompt_text(args: &Args) -> Result<Option<String>> {
     if let Some(path) = &args.prompt_file {
         return fs::read_to_string(path)
             .with_context(|| format!("failed to read prompt file {}", path.display()))
-            .map(|s| s.trim_end().to_owned());
-    }
-    if let Some(prompt) = &args.prompt {
-        return Ok(prompt.clone());
+            .map(|s| Some(s.trim_end().to_owned()));
     }
-    Err(anyhow!(
-        "prompt is required (use --prompt or --prompt-file)"
-    ))
+    Ok(args.prompt.clone())
 }
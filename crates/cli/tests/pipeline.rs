@@ -0,0 +1,146 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+use deepseek_ocr_core::fixture::{TinyModel, write_tiny_model};
+use image::{Rgb, RgbImage};
+use serde_json::Value;
+
+/// Scratch directory holding the tiny model, the input page and the outputs of one test.
+fn workspace(name: &str) -> (PathBuf, TinyModel) {
+    let dir = env::temp_dir().join(format!("deepseek-ocr-cli-{name}-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let files = write_tiny_model(&dir.join("model"), 0).unwrap();
+    RgbImage::from_fn(300, 200, |x, y| {
+        if (x / 16 + y / 8) % 3 == 0 {
+            Rgb([20, 20, 20])
+        } else {
+            Rgb([240, 240, 235])
+        }
+    })
+    .save(dir.join("page.png"))
+    .unwrap();
+    (dir, files)
+}
+
+/// CLI invocation on the tiny model with a small, fixed vision setup and token budget.
+fn cli(dir: &Path, files: &TinyModel) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_deepseek-ocr-cli"));
+    command
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--model-config")
+        .arg(&files.config)
+        .arg("--weights")
+        .arg(&files.weights)
+        .arg("--tokenizer")
+        .arg(&files.tokenizer)
+        .args(["--device", "cpu", "--resolution", "tiny"])
+        .args(["--max-new-tokens", "4"]);
+    command
+}
+
+#[test]
+fn generate_writes_output_and_cleanup_report() {
+    let (dir, files) = workspace("generate");
+    let output = dir.join("out.md");
+    let report = dir.join("cleanup.json");
+    let run = cli(&dir, &files)
+        .arg("--image")
+        .arg(dir.join("page.png"))
+        .args(["--prompt", "<image>\nFree OCR.", "--cleanup", "contrast"])
+        .arg("--output")
+        .arg(&output)
+        .arg("--cleanup-report")
+        .arg(&report)
+        .output()
+        .unwrap();
+    assert!(
+        run.status.success(),
+        "{}",
+        String::from_utf8_lossy(&run.stderr)
+    );
+    assert!(output.is_file());
+
+    let report: Value = serde_json::from_str(&fs::read_to_string(&report).unwrap()).unwrap();
+    let pages = report.as_array().unwrap();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0]["page"], 1);
+    assert_eq!(pages[0]["images"].as_array().unwrap().len(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn eval_records_the_effective_settings() {
+    let (dir, files) = workspace("eval");
+    let dataset = dir.join("dataset");
+    fs::create_dir_all(&dataset).unwrap();
+    fs::rename(dir.join("page.png"), dataset.join("page.png")).unwrap();
+    fs::write(dataset.join("page.md"), "Hello").unwrap();
+    let output = dir.join("report.json");
+    let run = cli(&dir, &files)
+        .args(["--cleanup", "deskew,denoise"])
+        .arg("eval")
+        .arg("--dataset")
+        .arg(&dataset)
+        .args(["--task", "free-ocr"])
+        .arg("--output")
+        .arg(&output)
+        .output()
+        .unwrap();
+    assert!(
+        run.status.success(),
+        "{}",
+        String::from_utf8_lossy(&run.stderr)
+    );
+
+    let report: Value = serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+    let settings = &report["settings"];
+    assert_eq!(settings["resolution"], "tiny");
+    assert_eq!(settings["cleanup"], "deskew,denoise");
+    assert_eq!(settings["max_new_tokens"], "4");
+    assert!(
+        settings["vision"]
+            .as_str()
+            .unwrap()
+            .starts_with("base 512 / image 512 / crop false")
+    );
+    assert_eq!(report["samples"].as_array().unwrap().len(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn benchmark_records_the_effective_settings() {
+    let (dir, files) = workspace("benchmark");
+    let output = dir.join("benchmark.json");
+    let run = cli(&dir, &files)
+        .arg("--image")
+        .arg(dir.join("page.png"))
+        .args(["--prompt", "<image>\nFree OCR.", "--cleanup", "contrast"])
+        .arg("benchmark")
+        .args(["--warmup", "0", "--iterations", "1"])
+        .arg("--output")
+        .arg(&output)
+        .output()
+        .unwrap();
+    assert!(
+        run.status.success(),
+        "{}",
+        String::from_utf8_lossy(&run.stderr)
+    );
+
+    let report: Value = serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+    let settings = &report["settings"];
+    assert_eq!(settings["resolution"], "tiny");
+    assert_eq!(settings["cleanup"], "contrast");
+    assert_eq!(settings["max_new_tokens"], "4");
+    assert!(
+        settings["vision"]
+            .as_str()
+            .unwrap()
+            .starts_with("base 512 / image 512 / crop false")
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -6,7 +6,16 @@ use std::{
 };
 
 use anyhow::{Context, Result, anyhow};
-use deepseek_ocr_core::runtime::{DeviceKind, Precision};
+use deepseek_ocr_core::{
+    engine::{EngineSettings, VisionSettings},
+    postprocess::PostprocessOptions,
+    redact::{PiiKind, RedactOptions, RedactRegion, RedactStyle},
+    repetition::{RepetitionAction, RepetitionOptions},
+    resolution::ResolutionMode,
+    runtime::{DeviceKind, Precision},
+    task::{TaskPreset, register_task},
+    vision::{BackgroundColor, CleanupOptions, DecodeOptions, TilingOptions},
+};
 use serde::{Deserialize, Serialize};
 
 use crate::fs::{VirtualFileSystem, VirtualPath};
@@ -19,6 +28,8 @@ pub struct AppConfig {
     pub models: ModelRegistry,
     pub inference: InferenceSettings,
     pub server: ServerSettings,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tasks: BTreeMap<String, TaskPreset>,
 }
 
 impl Default for AppConfig {
@@ -27,6 +38,7 @@ impl Default for AppConfig {
             models: ModelRegistry::default(),
             inference: InferenceSettings::default(),
             server: ServerSettings::default(),
+            tasks: BTreeMap::new(),
         }
     }
 }
@@ -76,12 +88,30 @@ pub struct InferenceSettings {
     pub base_size: u32,
     pub image_size: u32,
     pub crop_mode: bool,
+    pub min_tiles: u32,
+    pub max_tiles: u32,
+    pub use_thumbnail: bool,
+    /// Named resolution mode; when set it replaces `base_size`/`image_size`/`crop_mode`.
+    pub resolution: Option<ResolutionMode>,
+    /// Per-image vision token cap honoured by `resolution = "auto"`.
+    pub max_vision_tokens: Option<usize>,
     pub max_new_tokens: usize,
     pub use_cache: bool,
+    /// Repetition-loop detection during decoding (`[inference.repetition]`); off by default.
+    pub repetition: RepetitionOptions,
+    /// Image decoding (`[inference.decode]`): EXIF orientation and the alpha background.
+    pub decode: DecodeOptions,
+    /// Document cleanup steps (`[inference.cleanup]`); all disabled by default.
+    pub cleanup: CleanupOptions,
+    /// Text post-processing passes (`[inference.postprocess]`); all disabled by default.
+    pub postprocess: PostprocessOptions,
+    /// PII redaction of the output text and page images (`[inference.redaction]`); off by default.
+    pub redaction: RedactOptions,
 }
 
 impl Default for InferenceSettings {
     fn default() -> Self {
+        let tiling = TilingOptions::default();
         Self {
             device: DeviceKind::Cpu,
             precision: None,
@@ -89,8 +119,63 @@ impl Default for InferenceSettings {
             base_size: 1024,
             image_size: 640,
             crop_mode: true,
+            min_tiles: tiling.min_tiles,
+            max_tiles: tiling.max_tiles,
+            use_thumbnail: tiling.use_thumbnail,
+            resolution: None,
+            max_vision_tokens: None,
             max_new_tokens: 512,
             use_cache: true,
+            repetition: RepetitionOptions::default(),
+            decode: DecodeOptions::default(),
+            cleanup: CleanupOptions::default(),
+            postprocess: PostprocessOptions::default(),
+            redaction: RedactOptions::default(),
+        }
+    }
+}
+
+impl InferenceSettings {
+    /// Apply a task preset's recommended resolution and token budget.
+    pub fn apply_task(&mut self, task: &TaskPreset) {
+        if let Some(base_size) = task.base_size {
+            self.base_size = base_size;
+        }
+        if let Some(image_size) = task.image_size {
+            self.image_size = image_size;
+        }
+        if let Some(crop_mode) = task.crop_mode {
+            self.crop_mode = crop_mode;
+        }
+        if let Some(max_new_tokens) = task.max_new_tokens {
+            self.max_new_tokens = max_new_tokens;
+        }
+    }
+}
+
+impl From<&InferenceSettings> for EngineSettings {
+    fn from(settings: &InferenceSettings) -> Self {
+        let mut vision = VisionSettings {
+            base_size: settings.base_size,
+            image_size: settings.image_size,
+            crop_mode: settings.crop_mode,
+            tiling: TilingOptions {
+                min_tiles: settings.min_tiles,
+                max_tiles: settings.max_tiles,
+                use_thumbnail: settings.use_thumbnail,
+            },
+            auto_resolution: None,
+            cleanup: settings.cleanup,
+        };
+        if let Some(mode) = settings.resolution {
+            mode.apply(&mut vision, settings.max_vision_tokens);
+        }
+        Self {
+            template: settings.template.clone(),
+            vision,
+            max_new_tokens: settings.max_new_tokens,
+            use_cache: settings.use_cache,
+            repetition: settings.repetition,
         }
     }
 }
@@ -182,6 +267,16 @@ impl AppConfig {
         Ok(())
     }
 
+    /// Register the `[tasks]` table with the shared task registry, extending or overriding the
+    /// built-in presets.
+    pub fn register_tasks(&self) -> Result<()> {
+        for (name, preset) in &self.tasks {
+            register_task(name, preset.clone())
+                .with_context(|| format!("invalid task `{name}` in configuration"))?;
+        }
+        Ok(())
+    }
+
     pub fn active_model_resources(&self, fs: &impl VirtualFileSystem) -> Result<ModelResources> {
         self.model_resources(fs, &self.models.active)
     }
@@ -238,12 +333,64 @@ impl AppConfig {
         if let Some(crop_mode) = overrides.inference.crop_mode {
             self.inference.crop_mode = crop_mode;
         }
+        if let Some(min_tiles) = overrides.inference.min_tiles {
+            self.inference.min_tiles = min_tiles;
+        }
+        if let Some(max_tiles) = overrides.inference.max_tiles {
+            self.inference.max_tiles = max_tiles;
+        }
+        if let Some(use_thumbnail) = overrides.inference.use_thumbnail {
+            self.inference.use_thumbnail = use_thumbnail;
+        }
+        if overrides.inference.resolution.is_some() {
+            self.inference.resolution = overrides.inference.resolution;
+        }
+        if overrides.inference.max_vision_tokens.is_some() {
+            self.inference.max_vision_tokens = overrides.inference.max_vision_tokens;
+        }
         if let Some(max_new_tokens) = overrides.inference.max_new_tokens {
             self.inference.max_new_tokens = max_new_tokens;
         }
         if let Some(use_cache) = overrides.inference.use_cache {
             self.inference.use_cache = use_cache;
         }
+        if let Some(action) = overrides.inference.repetition {
+            self.inference.repetition.action = action;
+        }
+        if let Some(window) = overrides.inference.repetition_window {
+            self.inference.repetition.window = window;
+        }
+        if let Some(threshold) = overrides.inference.repetition_threshold {
+            self.inference.repetition.threshold = threshold;
+        }
+        if let Some(exif_orientation) = overrides.inference.exif_orientation {
+            self.inference.decode.exif_orientation = exif_orientation;
+        }
+        if let Some(background) = overrides.inference.background {
+            self.inference.decode.background = background;
+        }
+        if let Some(cleanup) = overrides.inference.cleanup {
+            self.inference.cleanup = cleanup;
+        }
+        if let Some(postprocess) = overrides.inference.postprocess {
+            self.inference.postprocess = postprocess;
+        }
+        let redaction = &mut self.inference.redaction;
+        if let Some(enabled) = overrides.inference.redact {
+            redaction.enabled = enabled;
+        }
+        if let Some(kinds) = overrides.inference.redact_kinds.as_ref() {
+            redaction.kinds = kinds.clone();
+        }
+        redaction
+            .patterns
+            .extend(overrides.inference.redact_patterns.iter().cloned());
+        if let Some(style) = overrides.inference.redact_style {
+            redaction.style = style;
+        }
+        if let Some(region) = overrides.inference.redact_region {
+            redaction.region = region;
+        }
         if let Some(host) = overrides.server.host.as_ref() {
             self.server.host = host.clone();
         }
@@ -370,8 +517,26 @@ pub struct InferenceOverride {
     pub base_size: Option<u32>,
     pub image_size: Option<u32>,
     pub crop_mode: Option<bool>,
+    pub min_tiles: Option<u32>,
+    pub max_tiles: Option<u32>,
+    pub use_thumbnail: Option<bool>,
+    pub resolution: Option<ResolutionMode>,
+    pub max_vision_tokens: Option<usize>,
     pub max_new_tokens: Option<usize>,
     pub use_cache: Option<bool>,
+    pub repetition: Option<RepetitionAction>,
+    pub repetition_window: Option<usize>,
+    pub repetition_threshold: Option<usize>,
+    pub exif_orientation: Option<bool>,
+    pub background: Option<BackgroundColor>,
+    pub cleanup: Option<CleanupOptions>,
+    pub postprocess: Option<PostprocessOptions>,
+    pub redact: Option<bool>,
+    pub redact_kinds: Option<Vec<PiiKind>>,
+    /// Named patterns added to the configured ones.
+    pub redact_patterns: Vec<(String, String)>,
+    pub redact_style: Option<RedactStyle>,
+    pub redact_region: Option<RedactRegion>,
 }
 
 #[derive(Debug, Default, Clone)]
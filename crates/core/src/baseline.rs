@@ -0,0 +1,728 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, ensure};
+use candle_core::{DType, Device, Tensor};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engine::OcrEngine,
+    inference::{build_prompt_tokens, normalize_text, prepare_vision_inputs},
+    model::{GenerateOptions, VisionProjectionOutputs},
+};
+
+/// `prompt.json` layout version written by captures and expected when loading.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Stages stored as `.npz` archives, in pipeline order.
+pub const STAGES: [&str; 3] = ["vision_embeddings", "projector_outputs", "logits"];
+
+/// `baseline.json`: capture settings and pointers to the other files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaselineMetadata {
+    pub model: String,
+    pub prompt: String,
+    pub image: String,
+    #[serde(default)]
+    pub base_size: Option<u32>,
+    #[serde(default)]
+    pub image_size: Option<u32>,
+    #[serde(default)]
+    pub crop_mode: Option<bool>,
+    pub dtype: String,
+    pub device: String,
+    #[serde(default)]
+    pub max_new_tokens: Option<usize>,
+    /// Producer of the capture (`rust` for captures written by this module).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub markdown_path: Option<String>,
+    #[serde(default)]
+    pub prompt_assets_path: Option<String>,
+    #[serde(default)]
+    pub output_tokens_path: Option<String>,
+    #[serde(default)]
+    pub vision_embeddings_path: Option<String>,
+    #[serde(default)]
+    pub projector_outputs_path: Option<String>,
+    #[serde(default)]
+    pub logits_path: Option<String>,
+    #[serde(default)]
+    pub vision_token_total: Option<usize>,
+    pub markdown: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenRange {
+    pub start: usize,
+    pub length: usize,
+}
+
+/// `prompt.json`: prompt tensorisation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptAssets {
+    pub schema_version: u32,
+    pub prompt: String,
+    pub rendered_prompt: String,
+    pub input_ids: Vec<i64>,
+    pub bos_token_id: i64,
+    pub image_token_id: i64,
+    pub prefill_len: usize,
+    /// `1` at `<image>` positions.
+    pub images_seq_mask: Vec<u8>,
+    pub image_token_ranges: Vec<TokenRange>,
+    pub image_token_counts: Vec<usize>,
+    /// `[width_tiles, height_tiles]` per image.
+    pub images_spatial_crop: Vec<[usize; 2]>,
+    pub vision_token_counts: Vec<usize>,
+    pub vision_token_total: usize,
+    pub image_paths: Vec<String>,
+    pub per_image_patch_counts: Vec<usize>,
+}
+
+/// `output_tokens.json`: the full token sequence used for teacher forcing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputTokens {
+    /// Prefill followed by generated tokens.
+    pub tokens: Vec<i64>,
+    pub prefill_len: usize,
+    pub generated_len: usize,
+    #[serde(default)]
+    pub eos_token_id: Option<i64>,
+    #[serde(default)]
+    pub decoded_markdown: Option<String>,
+}
+
+/// Named arrays of one `.npz` stage, in archive order.
+pub type Arrays = Vec<(String, Tensor)>;
+
+/// A baseline directory held in memory.
+#[derive(Debug, Clone)]
+pub struct Baseline {
+    pub metadata: BaselineMetadata,
+    pub prompt: PromptAssets,
+    pub output: OutputTokens,
+    pub vision_embeddings: Arrays,
+    pub projector_outputs: Arrays,
+    pub logits: Arrays,
+}
+
+impl Baseline {
+    /// Load `baseline.json`, `prompt.json`, `output_tokens.json` and the stage archives from
+    /// `dir`. Missing archives load as empty stages.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let read_json = |name: &str| -> Result<String> {
+            let path = dir.join(name);
+            fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))
+        };
+        let metadata: BaselineMetadata = serde_json::from_str(&read_json("baseline.json")?)
+            .context("failed to parse baseline.json")?;
+        let prompt: PromptAssets = serde_json::from_str(&read_json("prompt.json")?)
+            .context("failed to parse prompt.json")?;
+        ensure!(
+            prompt.schema_version == SCHEMA_VERSION,
+            "prompt.json has schema version {} (expected {SCHEMA_VERSION})",
+            prompt.schema_version
+        );
+        let output: OutputTokens = serde_json::from_str(&read_json("output_tokens.json")?)
+            .context("failed to parse output_tokens.json")?;
+        let read_stage = |stage: &str| -> Result<Arrays> {
+            let path = dir.join(format!("{stage}.npz"));
+            if !path.exists() {
+                return Ok(Vec::new());
+            }
+            Tensor::read_npz(&path).with_context(|| format!("failed to read {}", path.display()))
+        };
+        Ok(Self {
+            metadata,
+            prompt,
+            output,
+            vision_embeddings: read_stage("vision_embeddings")?,
+            projector_outputs: read_stage("projector_outputs")?,
+            logits: read_stage("logits")?,
+        })
+    }
+
+    /// Write the baseline into `dir` (created when missing), replacing files of the same name.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        let path_of = |name: &str| dir.join(name).display().to_string();
+        let mut metadata = self.metadata.clone();
+        metadata.markdown_path = Some(path_of("result.mmd"));
+        metadata.prompt_assets_path = Some(path_of("prompt.json"));
+        metadata.output_tokens_path = Some(path_of("output_tokens.json"));
+        metadata.vision_embeddings_path = Some(path_of("vision_embeddings.npz"));
+        metadata.projector_outputs_path = Some(path_of("projector_outputs.npz"));
+        metadata.logits_path = Some(path_of("logits.npz"));
+
+        let write = |name: &str, contents: String| -> Result<()> {
+            let path = dir.join(name);
+            fs::write(&path, contents)
+                .with_context(|| format!("failed to write {}", path.display()))
+        };
+        write("baseline.json", serde_json::to_string_pretty(&metadata)?)?;
+        write("prompt.json", serde_json::to_string_pretty(&self.prompt)?)?;
+        write(
+            "output_tokens.json",
+            serde_json::to_string_pretty(&self.output)?,
+        )?;
+        write("result.mmd", self.metadata.markdown.clone())?;
+        for (stage, arrays) in STAGES.iter().zip(self.stages()) {
+            let path = dir.join(format!("{stage}.npz"));
+            Tensor::write_npz(arrays, &path)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Stage archives in [`STAGES`] order.
+    pub fn stages(&self) -> [&Arrays; 3] {
+        [
+            &self.vision_embeddings,
+            &self.projector_outputs,
+            &self.logits,
+        ]
+    }
+}
+
+/// Inputs of [`capture_baseline`] besides the engine settings.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureOptions {
+    /// Recorded as `image_paths`/`image` in the capture.
+    pub image_paths: Vec<String>,
+    /// Generation budget when `teacher_tokens` is not set.
+    pub max_new_tokens: usize,
+    /// Generated tokens of a reference capture to teacher-force instead of decoding, so the
+    /// logits of both captures cover the same positions.
+    pub teacher_tokens: Option<Vec<i64>>,
+}
+
+/// Run the pipeline on `images` with the raw `prompt` (rendered with the engine's template) and
+/// record every stage. Vision settings come from the engine; automatic resolution and cleanup
+/// are not applied.
+pub fn capture_baseline(
+    engine: &OcrEngine,
+    images: &[DynamicImage],
+    prompt: &str,
+    options: &CaptureOptions,
+) -> Result<Baseline> {
+    let model = engine.model();
+    let tokenizer = engine.tokenizer();
+    let vision = engine.settings().vision;
+    let rendered_prompt = engine.render_prompt(prompt)?;
+
+    let owned_inputs = prepare_vision_inputs(
+        model,
+        images,
+        vision.base_size,
+        vision.image_size,
+        vision.crop_mode,
+        vision.tiling,
+    )?;
+    let mut vision_embeddings = Vec::new();
+    let mut projector_outputs = Vec::new();
+    let mut fused = Vec::with_capacity(owned_inputs.len());
+    let mut spatial_crops = Vec::with_capacity(owned_inputs.len());
+    let mut patch_counts = Vec::with_capacity(owned_inputs.len());
+    for (idx, input) in owned_inputs.iter().enumerate() {
+        let VisionProjectionOutputs {
+            global_pre,
+            local_pre,
+            global_post,
+            local_post,
+            global_tokens,
+            local_tokens,
+            fused_tokens,
+        } = model.compute_vision_projection(&input.as_ref())?;
+        let (_, pre_hidden) = global_pre.dims2()?;
+        let (_, hidden) = global_post.dims2()?;
+        let local_pre = local_or_empty(local_pre, pre_hidden)?;
+        // Projector inputs are CLIP features followed by SAM features.
+        let half = pre_hidden / 2;
+        for (view, pre) in [("global", &global_pre), ("local", &local_pre)] {
+            vision_embeddings.push((format!("{view}_pre_image{idx}"), to_f32(pre)?));
+            vision_embeddings.push((
+                format!("{view}_clip_tokens_image{idx}"),
+                to_f32(&pre.narrow(1, 0, half)?)?,
+            ));
+            vision_embeddings.push((
+                format!("{view}_sam_tokens_image{idx}"),
+                to_f32(&pre.narrow(1, half, pre_hidden - half)?)?,
+            ));
+        }
+        let arrays = [
+            ("global_post", global_post),
+            ("local_post", local_or_empty(local_post, hidden)?),
+            ("global_tokens", global_tokens),
+            ("local_tokens", local_or_empty(local_tokens, hidden)?),
+            ("fused_tokens", fused_tokens.clone()),
+        ];
+        for (name, tensor) in arrays {
+            projector_outputs.push((format!("{name}_image{idx}"), to_f32(&tensor)?));
+        }
+        fused.push(fused_tokens);
+        let (width, height) = input.crop_shape.unwrap_or((1, 1));
+        spatial_crops.push([width, height]);
+        patch_counts.push(match &input.patches {
+            Some(patches) => patches.dim(0)?,
+            None => 0,
+        });
+    }
+    if !fused.is_empty() {
+        let refs: Vec<&Tensor> = fused.iter().collect();
+        projector_outputs.push(("fused_concat".to_string(), to_f32(&Tensor::cat(&refs, 0)?)?));
+    }
+    let (image_newline, view_separator) = model.projector_separators();
+    projector_outputs.push(("image_newline".to_string(), to_f32(image_newline)?));
+    projector_outputs.push(("view_separator".to_string(), to_f32(view_separator)?));
+
+    let (input_ids, images_seq_mask) = build_prompt_tokens(
+        tokenizer,
+        &rendered_prompt,
+        &fused,
+        &owned_inputs,
+        vision.base_size,
+        vision.image_size,
+        vision.crop_mode,
+    )?;
+    let prefill_len = input_ids.len();
+    let device = model.device();
+    let eos_token_id = model.language_model().config().eos_token_id;
+    let mask = Tensor::from_vec(images_seq_mask.clone(), (1, prefill_len), device)?;
+
+    let generated = match &options.teacher_tokens {
+        Some(tokens) => tokens.clone(),
+        None => {
+            let ids = Tensor::from_vec(input_ids.clone(), (1, prefill_len), device)?;
+            let mut generate = GenerateOptions::new(options.max_new_tokens);
+            generate.images_seq_mask = Some(&mask);
+            if !fused.is_empty() {
+                generate.image_embeddings = Some(fused.as_slice());
+            }
+            generate.eos_token_id = eos_token_id;
+            generate.use_cache = engine.settings().use_cache;
+            model
+                .generate_with_reason(&ids, generate)?
+                .tokens
+                .to_vec2::<i64>()?
+                .into_iter()
+                .next()
+                .unwrap_or_default()
+        }
+    };
+    let markdown = normalize_text(&engine.decode_tokens(&generated, false)?);
+
+    let mut tokens = input_ids.clone();
+    tokens.extend(&generated);
+    let seq_len = tokens.len();
+    let mut full_mask = images_seq_mask.clone();
+    full_mask.resize(seq_len, 0);
+    let ids = Tensor::from_vec(tokens.clone(), (1, seq_len), device)?;
+    let mask = Tensor::from_vec(full_mask, (1, seq_len), device)?;
+    let attention_mask = Tensor::ones((1, seq_len), DType::I64, device)?;
+    let forward = model.forward(
+        Some(&ids),
+        None,
+        Some(&attention_mask),
+        None,
+        Some(&mask),
+        None,
+        (!fused.is_empty()).then_some(fused.as_slice()),
+        None,
+        false,
+    )?;
+    let lengths = |len: usize| Tensor::new(&[len as i64], &Device::Cpu);
+    let logits = vec![
+        ("logits".to_string(), to_f32(&forward.logits.get(0)?)?),
+        ("prefill_len".to_string(), lengths(prefill_len)?),
+        ("generated_len".to_string(), lengths(generated.len())?),
+    ];
+
+    let image_token_ranges = token_ranges(&images_seq_mask);
+    let vision_token_counts: Vec<usize> = fused
+        .iter()
+        .map(|tensor| tensor.dim(0))
+        .collect::<candle_core::Result<_>>()?;
+    let vision_token_total = vision_token_counts.iter().sum();
+    let image_token_id = tokenizer
+        .token_to_id("<image>")
+        .context("tokenizer missing <image> token")? as i64;
+    let prompt_assets = PromptAssets {
+        schema_version: SCHEMA_VERSION,
+        prompt: prompt.to_string(),
+        rendered_prompt,
+        bos_token_id: input_ids.first().copied().unwrap_or_default(),
+        input_ids,
+        image_token_id,
+        prefill_len,
+        image_token_counts: image_token_ranges
+            .iter()
+            .map(|range| range.length)
+            .collect(),
+        images_seq_mask,
+        image_token_ranges,
+        images_spatial_crop: spatial_crops,
+        vision_token_counts,
+        vision_token_total,
+        image_paths: options.image_paths.clone(),
+        per_image_patch_counts: patch_counts,
+    };
+    let metadata = BaselineMetadata {
+        model: String::new(),
+        prompt: prompt.to_string(),
+        image: options.image_paths.first().cloned().unwrap_or_default(),
+        base_size: Some(vision.base_size),
+        image_size: Some(vision.image_size),
+        crop_mode: Some(vision.crop_mode),
+        dtype: dtype_name(model.dtype()).to_string(),
+        device: device_name(device).to_string(),
+        max_new_tokens: Some(options.max_new_tokens),
+        source: Some("rust".to_string()),
+        markdown_path: None,
+        prompt_assets_path: None,
+        output_tokens_path: None,
+        vision_embeddings_path: None,
+        projector_outputs_path: None,
+        logits_path: None,
+        vision_token_total: Some(vision_token_total),
+        markdown: markdown.clone(),
+    };
+    Ok(Baseline {
+        metadata,
+        output: OutputTokens {
+            tokens,
+            prefill_len,
+            generated_len: generated.len(),
+            eos_token_id,
+            decoded_markdown: Some(markdown),
+        },
+        prompt: prompt_assets,
+        vision_embeddings,
+        projector_outputs,
+        logits,
+    })
+}
+
+fn to_f32(tensor: &Tensor) -> Result<Tensor> {
+    Ok(tensor
+        .to_dtype(DType::F32)?
+        .to_device(&Device::Cpu)?
+        .contiguous()?)
+}
+
+/// Missing local tensors are stored as empty `(0, hidden)` arrays, as in Python captures.
+fn local_or_empty(tensor: Option<Tensor>, hidden: usize) -> Result<Tensor> {
+    match tensor {
+        Some(tensor) => Ok(tensor),
+        None => Ok(Tensor::zeros((0, hidden), DType::F32, &Device::Cpu)?),
+    }
+}
+
+/// Contiguous runs of `1` in an `images_seq_mask`.
+pub fn token_ranges(mask: &[u8]) -> Vec<TokenRange> {
+    let mut ranges: Vec<TokenRange> = Vec::new();
+    for (idx, &flag) in mask.iter().enumerate() {
+        if flag == 0 {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(range) if range.start + range.length == idx => range.length += 1,
+            _ => ranges.push(TokenRange {
+                start: idx,
+                length: 1,
+            }),
+        }
+    }
+    ranges
+}
+
+fn dtype_name(dtype: DType) -> &'static str {
+    match dtype {
+        DType::F32 => "fp32",
+        DType::F16 => "fp16",
+        other => other.as_str(),
+    }
+}
+
+fn device_name(device: &Device) -> &'static str {
+    if device.is_cuda() {
+        "cuda"
+    } else if device.is_metal() {
+        "metal"
+    } else {
+        "cpu"
+    }
+}
+
+/// Error statistics of one array of a candidate against the reference.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArrayDiff {
+    pub stage: String,
+    pub name: String,
+    pub reference_shape: Vec<usize>,
+    /// `None` when the candidate lacks the array.
+    pub candidate_shape: Option<Vec<usize>>,
+    pub elements: usize,
+    /// `None` when the array is missing or its shape differs.
+    pub max_abs: Option<f64>,
+    pub mean_abs: Option<f64>,
+}
+
+/// Aggregate of one stage: the largest error and the element-weighted mean error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageSummary {
+    pub stage: String,
+    pub arrays: usize,
+    /// Arrays missing from the candidate or with a different shape.
+    pub mismatched: usize,
+    pub max_abs: Option<f64>,
+    pub mean_abs: Option<f64>,
+}
+
+/// How the token sequences of two captures line up.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenComparison {
+    pub reference_prompt_tokens: usize,
+    pub candidate_prompt_tokens: usize,
+    /// Differing prompt positions, plus the length difference.
+    pub prompt_mismatches: usize,
+    pub reference_generated: usize,
+    pub candidate_generated: usize,
+    /// First generated position where the tokens differ.
+    pub first_divergence: Option<usize>,
+}
+
+/// Result of [`compare_baselines`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParityReport {
+    pub tokens: TokenComparison,
+    pub stages: Vec<StageSummary>,
+    pub arrays: Vec<ArrayDiff>,
+}
+
+/// Compare every floating-point array of `reference` with the same-named array of `candidate`.
+/// Integer arrays (lengths) are skipped; arrays only present in the candidate are ignored.
+pub fn compare_baselines(reference: &Baseline, candidate: &Baseline) -> Result<ParityReport> {
+    let mut arrays = Vec::new();
+    let mut stages = Vec::new();
+    for ((stage, expected), actual) in STAGES
+        .iter()
+        .zip(reference.stages())
+        .zip(candidate.stages())
+    {
+        let diffs = compare_arrays(stage, expected, actual)?;
+        stages.push(summarize_stage(stage, &diffs));
+        arrays.extend(diffs);
+    }
+    Ok(ParityReport {
+        tokens: compare_tokens(reference, candidate),
+        stages,
+        arrays,
+    })
+}
+
+/// Per-array statistics of one stage.
+pub fn compare_arrays(
+    stage: &str,
+    reference: &Arrays,
+    candidate: &Arrays,
+) -> Result<Vec<ArrayDiff>> {
+    let mut diffs = Vec::new();
+    for (name, expected) in reference {
+        if !expected.dtype().is_float() {
+            continue;
+        }
+        let actual = candidate
+            .iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, tensor)| tensor);
+        let (max_abs, mean_abs) = match actual {
+            Some(actual) if actual.dims() == expected.dims() => {
+                let (max_abs, mean_abs) = abs_error(expected, actual)?;
+                (Some(max_abs), Some(mean_abs))
+            }
+            _ => (None, None),
+        };
+        diffs.push(ArrayDiff {
+            stage: stage.to_string(),
+            name: name.clone(),
+            reference_shape: expected.dims().to_vec(),
+            candidate_shape: actual.map(|tensor| tensor.dims().to_vec()),
+            elements: expected.elem_count(),
+            max_abs,
+            mean_abs,
+        });
+    }
+    Ok(diffs)
+}
+
+/// Maximum and mean absolute difference of two tensors of the same shape. Empty tensors have
+/// no error.
+pub fn abs_error(reference: &Tensor, candidate: &Tensor) -> Result<(f64, f64)> {
+    ensure!(
+        reference.dims() == candidate.dims(),
+        "shapes differ ({:?} vs {:?})",
+        reference.dims(),
+        candidate.dims()
+    );
+    if reference.elem_count() == 0 {
+        return Ok((0.0, 0.0));
+    }
+    let flat = |tensor: &Tensor| -> Result<Tensor> {
+        Ok(tensor
+            .to_device(&Device::Cpu)?
+            .to_dtype(DType::F64)?
+            .flatten_all()?)
+    };
+    let diff = (flat(reference)? - flat(candidate)?)?.abs()?;
+    Ok((
+        diff.max(0)?.to_scalar::<f64>()?,
+        diff.mean_all()?.to_scalar::<f64>()?,
+    ))
+}
+
+fn summarize_stage(stage: &str, diffs: &[ArrayDiff]) -> StageSummary {
+    let compared: Vec<&ArrayDiff> = diffs.iter().filter(|diff| diff.max_abs.is_some()).collect();
+    let elements: usize = compared.iter().map(|diff| diff.elements).sum();
+    let max_abs = compared
+        .iter()
+        .filter_map(|diff| diff.max_abs)
+        .reduce(f64::max);
+    let mean_abs = (elements > 0).then(|| {
+        compared
+            .iter()
+            .map(|diff| diff.mean_abs.unwrap_or(0.0) * diff.elements as f64)
+            .sum::<f64>()
+            / elements as f64
+    });
+    StageSummary {
+        stage: stage.to_string(),
+        arrays: diffs.len(),
+        mismatched: diffs.len() - compared.len(),
+        max_abs: max_abs.or((!compared.is_empty()).then_some(0.0)),
+        mean_abs: mean_abs.or((!compared.is_empty()).then_some(0.0)),
+    }
+}
+
+fn compare_tokens(reference: &Baseline, candidate: &Baseline) -> TokenComparison {
+    let expected = &reference.prompt.input_ids;
+    let actual = &candidate.prompt.input_ids;
+    let prompt_mismatches = expected
+        .iter()
+        .zip(actual)
+        .filter(|(lhs, rhs)| lhs != rhs)
+        .count()
+        + expected.len().abs_diff(actual.len());
+    let generated = |baseline: &Baseline| -> Vec<i64> {
+        let output = &baseline.output;
+        output
+            .tokens
+            .get(output.prefill_len..)
+            .unwrap_or_default()
+            .to_vec()
+    };
+    let (expected, actual) = (generated(reference), generated(candidate));
+    let first_divergence = expected
+        .iter()
+        .zip(&actual)
+        .position(|(lhs, rhs)| lhs != rhs)
+        .or_else(|| (expected.len() != actual.len()).then(|| expected.len().min(actual.len())));
+    TokenComparison {
+        reference_prompt_tokens: reference.prompt.input_ids.len(),
+        candidate_prompt_tokens: candidate.prompt.input_ids.len(),
+        prompt_mismatches,
+        reference_generated: expected.len(),
+        candidate_generated: actual.len(),
+        first_divergence,
+    }
+}
+
+impl ParityReport {
+    /// Largest absolute error over all compared arrays.
+    pub fn max_abs(&self) -> f64 {
+        self.arrays
+            .iter()
+            .filter_map(|diff| diff.max_abs)
+            .fold(0.0, f64::max)
+    }
+
+    /// Arrays whose maximum error exceeds `tolerance`, or that could not be compared.
+    pub fn failures(&self, tolerance: f64) -> Vec<&ArrayDiff> {
+        self.arrays
+            .iter()
+            .filter(|diff| diff.max_abs.is_none_or(|max_abs| max_abs > tolerance))
+            .collect()
+    }
+
+    /// Human-readable summary: token alignment, then per-stage and per-array errors.
+    pub fn to_text(&self) -> String {
+        let tokens = &self.tokens;
+        let mut out = format!(
+            "prompt tokens: {} reference / {} candidate, {} mismatched\n\
+             generated tokens: {} reference / {} candidate, first divergence: {}\n\n",
+            tokens.reference_prompt_tokens,
+            tokens.candidate_prompt_tokens,
+            tokens.prompt_mismatches,
+            tokens.reference_generated,
+            tokens.candidate_generated,
+            tokens
+                .first_divergence
+                .map_or("none".to_string(), |idx| idx.to_string())
+        );
+        let _ = writeln!(
+            out,
+            "{:<40} {:>12} {:>12}",
+            "stage / array", "max abs", "mean abs"
+        );
+        for stage in &self.stages {
+            let _ = writeln!(
+                out,
+                "{:<40} {:>12} {:>12}",
+                format!("{} ({} arrays)", stage.stage, stage.arrays),
+                format_error(stage.max_abs),
+                format_error(stage.mean_abs)
+            );
+            for diff in self.arrays.iter().filter(|diff| diff.stage == stage.stage) {
+                let note = match &diff.candidate_shape {
+                    None => " (missing)".to_string(),
+                    Some(shape) if diff.max_abs.is_none() => {
+                        format!(" (shape {shape:?} vs {:?})", diff.reference_shape)
+                    }
+                    Some(_) => String::new(),
+                };
+                let _ = writeln!(
+                    out,
+                    "  {:<38} {:>12} {:>12}{note}",
+                    diff.name,
+                    format_error(diff.max_abs),
+                    format_error(diff.mean_abs)
+                );
+            }
+        }
+        out
+    }
+}
+
+fn format_error(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |value| format!("{value:.3e}"))
+}
+
+/// Resolve a path recorded in `baseline.json`: as given when it exists (Python captures record
+/// workspace-relative paths), otherwise by file name inside the baseline directory.
+pub fn resolve_recorded_path(dir: &Path, recorded: &str) -> PathBuf {
+    let path = PathBuf::from(recorded);
+    if path.exists() {
+        return path;
+    }
+    match path.file_name() {
+        Some(name) if dir.join(name).exists() => dir.join(name),
+        _ => dir
+            .join("images")
+            .join(path.file_name().unwrap_or_default()),
+    }
+}
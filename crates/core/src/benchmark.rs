@@ -1,4 +1,7 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, fmt::Write as _, fs, path::Path, time::Duration};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 
 /// Value recorded for a benchmark field.
 #[derive(Debug, Clone)]
@@ -206,3 +209,396 @@ mod imp {
 }
 
 pub use imp::{BenchRecorder, Timer, record_instant, set_recorder};
+
+/// Decoder stages whose `prompt_tokens` field measures prefill throughput.
+const PREFILL_STAGES: &[&str] = &["decode.prefill", "decode.prefill_no_cache"];
+/// Decoder stage whose `steps` field measures per-token decode throughput.
+const DECODE_STAGE: &str = "decode.iterative";
+
+/// Display names used when comparing reports, matching `scripts/compare_bench.py`.
+const FRIENDLY_STAGE_NAMES: &[(&str, &str)] = &[
+    ("decode.generate", "Decode – Overall"),
+    ("decode.iterative", "Decode – Token Loop"),
+    ("decode.prefill", "Decode – Prompt Prefill"),
+    ("decode.generate_no_cache", "Decode – No Cache"),
+    ("prompt.build_tokens", "Prompt – Build Tokens"),
+    ("prompt.render", "Prompt – Render Template"),
+    ("vision.compute_embeddings", "Vision – Embed Images"),
+    ("vision.prepare_inputs", "Vision – Prepare Inputs"),
+    ("vision.compute_projection", "Vision – Project Features"),
+    ("vision.prepare_masks", "Vision – Prepare Masks"),
+];
+
+/// Linearly interpolated quantile (`0.0..=1.0`) of ascending `sorted` values; 0 when empty.
+pub fn percentile(sorted: &[f64], quantile: f64) -> f64 {
+    let Some(last) = sorted.len().checked_sub(1) else {
+        return 0.0;
+    };
+    let rank = quantile.clamp(0.0, 1.0) * last as f64;
+    let low = rank.floor() as usize;
+    let high = rank.ceil() as usize;
+    sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64)
+}
+
+/// Timing summary of one stage. In benchmark reports every sample is one request, with the
+/// durations of repeated events inside that request summed; `--bench-output` files count single
+/// events instead. The percentiles are absent from those older files.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageStats {
+    pub stage: String,
+    #[serde(default)]
+    pub count: usize,
+    #[serde(default)]
+    pub total_ms: f64,
+    #[serde(default)]
+    pub avg_ms: f64,
+    #[serde(default)]
+    pub min_ms: f64,
+    #[serde(default)]
+    pub max_ms: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p50_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p90_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p99_ms: Option<f64>,
+}
+
+impl StageStats {
+    pub fn from_samples(stage: &str, samples_ms: &[f64]) -> Self {
+        let mut sorted = samples_ms.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let total_ms: f64 = sorted.iter().sum();
+        Self {
+            stage: stage.to_string(),
+            count: sorted.len(),
+            total_ms,
+            avg_ms: if sorted.is_empty() {
+                0.0
+            } else {
+                total_ms / sorted.len() as f64
+            },
+            min_ms: sorted.first().copied().unwrap_or_default(),
+            max_ms: sorted.last().copied().unwrap_or_default(),
+            p50_ms: Some(percentile(&sorted, 0.5)),
+            p90_ms: Some(percentile(&sorted, 0.9)),
+            p99_ms: Some(percentile(&sorted, 0.99)),
+        }
+    }
+}
+
+/// Tokens processed by a decoder phase across all measured requests.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Throughput {
+    pub tokens: u64,
+    pub seconds: f64,
+    pub tokens_per_sec: f64,
+}
+
+impl Throughput {
+    fn new(tokens: u64, seconds: f64) -> Option<Self> {
+        (seconds > 0.0).then(|| Self {
+            tokens,
+            seconds,
+            tokens_per_sec: tokens as f64 / seconds,
+        })
+    }
+}
+
+/// One measured request: its wall-clock time and the events recorded while it ran.
+#[derive(Debug, Clone)]
+pub struct RequestSample {
+    pub duration: Duration,
+    pub events: Vec<BenchEvent>,
+}
+
+/// Result of `deepseek-ocr-cli benchmark`. `stage_totals` keeps the name and fields of
+/// `--bench-output` files so either kind of file can be compared.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub settings: BTreeMap<String, String>,
+    pub images: Vec<String>,
+    pub warmup: usize,
+    pub iterations: usize,
+    /// End-to-end latency per request.
+    pub requests: StageStats,
+    pub stage_totals: Vec<StageStats>,
+    pub prefill: Option<Throughput>,
+    pub decode: Option<Throughput>,
+    /// Peak resident set size of the process over the timed iterations (including the loaded
+    /// model), where the platform reports it.
+    pub peak_memory_bytes: Option<u64>,
+}
+
+impl BenchmarkReport {
+    pub fn from_samples(
+        settings: BTreeMap<String, String>,
+        images: Vec<String>,
+        warmup: usize,
+        iterations: usize,
+        samples: &[RequestSample],
+        peak_memory_bytes: Option<u64>,
+    ) -> Self {
+        let latencies: Vec<f64> = samples
+            .iter()
+            .map(|sample| sample.duration.as_secs_f64() * 1e3)
+            .collect();
+
+        let mut per_stage: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+        let (mut prefill_tokens, mut prefill_seconds) = (0, 0.0);
+        let (mut decode_tokens, mut decode_seconds) = (0, 0.0);
+        for sample in samples {
+            let mut request: BTreeMap<&str, f64> = BTreeMap::new();
+            for event in &sample.events {
+                *request.entry(event.stage).or_default() += event.duration.as_secs_f64() * 1e3;
+                if PREFILL_STAGES.contains(&event.stage) {
+                    prefill_tokens += event_u64(event, "prompt_tokens");
+                    prefill_seconds += event.duration.as_secs_f64();
+                } else if event.stage == DECODE_STAGE {
+                    decode_tokens += event_u64(event, "steps");
+                    decode_seconds += event.duration.as_secs_f64();
+                }
+            }
+            for (stage, ms) in request {
+                per_stage.entry(stage).or_default().push(ms);
+            }
+        }
+
+        Self {
+            settings,
+            images,
+            warmup,
+            iterations,
+            requests: StageStats::from_samples("request", &latencies),
+            stage_totals: per_stage
+                .into_iter()
+                .map(|(stage, samples)| StageStats::from_samples(stage, &samples))
+                .collect(),
+            prefill: Throughput::new(prefill_tokens, prefill_seconds),
+            decode: Throughput::new(decode_tokens, decode_seconds),
+            peak_memory_bytes,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{} measured iteration(s) over {} image(s) after {} warmup iteration(s)",
+            self.iterations,
+            self.images.len(),
+            self.warmup
+        );
+        let header = [
+            "stage", "count", "p50 (ms)", "p90 (ms)", "p99 (ms)", "avg (ms)",
+        ];
+        let rows: Vec<Vec<String>> = std::iter::once(&self.requests)
+            .chain(&self.stage_totals)
+            .map(|stats| {
+                let ms = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{v:.3}"));
+                vec![
+                    stats.stage.clone(),
+                    stats.count.to_string(),
+                    ms(stats.p50_ms),
+                    ms(stats.p90_ms),
+                    ms(stats.p99_ms),
+                    ms(Some(stats.avg_ms)),
+                ]
+            })
+            .collect();
+        out.push_str(&render_table(&header, &rows));
+        for (phase, throughput) in [("prefill", self.prefill), ("decode", self.decode)] {
+            match throughput {
+                Some(t) => {
+                    let _ = writeln!(
+                        out,
+                        "{phase}: {:.2} tokens/s ({} tokens in {:.3}s)",
+                        t.tokens_per_sec, t.tokens, t.seconds
+                    );
+                }
+                None => {
+                    let _ = writeln!(out, "{phase}: not recorded");
+                }
+            }
+        }
+        let _ = writeln!(
+            out,
+            "peak memory: {}",
+            self.peak_memory_bytes
+                .map_or("unavailable".to_string(), |bytes| {
+                    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+                })
+        );
+        out
+    }
+}
+
+fn event_u64(event: &BenchEvent, key: &str) -> u64 {
+    event
+        .fields
+        .iter()
+        .find(|field| field.key == key)
+        .and_then(|field| match field.value {
+            BenchValue::U64(value) => Some(value),
+            BenchValue::I64(value) => u64::try_from(value).ok(),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Peak resident set size of this process (`VmHWM`) since it started, or since the last
+/// [`reset_peak_memory`]; `None` off Linux.
+#[cfg(target_os = "linux")]
+pub fn peak_memory_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn peak_memory_bytes() -> Option<u64> {
+    None
+}
+
+/// Restart the `VmHWM` high-water mark from the current resident size by writing `5` to
+/// `/proc/self/clear_refs`. Returns `false` when the kernel refuses and off Linux, in which
+/// case [`peak_memory_bytes`] keeps reporting the whole-process peak.
+#[cfg(target_os = "linux")]
+pub fn reset_peak_memory() -> bool {
+    fs::write("/proc/self/clear_refs", "5").is_ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn reset_peak_memory() -> bool {
+    false
+}
+
+/// Read the `stage_totals` of a benchmark report, a `--bench-output` file or a Python
+/// benchmark dump, keyed by stage.
+pub fn load_stage_totals(path: &Path) -> Result<BTreeMap<String, StageStats>> {
+    #[derive(Deserialize)]
+    struct Totals {
+        #[serde(default)]
+        stage_totals: Vec<StageStats>,
+    }
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("failed to read benchmark {}", path.display()))?;
+    let totals: Totals = serde_json::from_str(&raw)
+        .with_context(|| format!("failed to parse benchmark {}", path.display()))?;
+    Ok(totals
+        .stage_totals
+        .into_iter()
+        .filter(|stats| !stats.stage.is_empty())
+        .map(|stats| (stats.stage.clone(), stats))
+        .collect())
+}
+
+/// `"Decode – Prompt Prefill (decode.prefill)"`; unknown stages are title-cased.
+pub fn friendly_stage_name(stage: &str) -> String {
+    if let Some((_, name)) = FRIENDLY_STAGE_NAMES.iter().find(|(key, _)| *key == stage) {
+        return format!("{name} ({stage})");
+    }
+    let pretty: Vec<String> = stage
+        .split(['.', '_'])
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect()
+            })
+        })
+        .collect();
+    if pretty.is_empty() {
+        return stage.to_string();
+    }
+    format!("{} ({stage})", pretty.join(" "))
+}
+
+/// Stage-by-stage table of total time against `reference`, one total/ratio column pair per
+/// labelled target.
+pub fn compare_stage_totals(
+    reference: &BTreeMap<String, StageStats>,
+    targets: &[(String, BTreeMap<String, StageStats>)],
+) -> String {
+    let mut header = vec![
+        "stage".to_string(),
+        "ref total (ms)".to_string(),
+        "ref avg (ms)".to_string(),
+    ];
+    for (label, _) in targets {
+        header.push(format!("{label} total"));
+        header.push(format!("{label}/ref"));
+    }
+
+    let mut stages: Vec<&String> = reference
+        .keys()
+        .chain(targets.iter().flat_map(|(_, totals)| totals.keys()))
+        .collect();
+    stages.sort();
+    stages.dedup();
+    let rows: Vec<Vec<String>> = stages
+        .into_iter()
+        .map(|stage| {
+            let reference = reference.get(stage);
+            let mut row = vec![friendly_stage_name(stage)];
+            match reference {
+                Some(stats) => {
+                    row.push(format!("{:.3}", stats.total_ms));
+                    row.push(format!("{:.3}", stats.avg_ms));
+                }
+                None => row.extend(["-".to_string(), "-".to_string()]),
+            }
+            for (_, totals) in targets {
+                match totals.get(stage) {
+                    Some(target) => {
+                        row.push(format!("{:.3}", target.total_ms));
+                        row.push(match reference {
+                            Some(stats) if stats.total_ms > 0.0 => {
+                                format!("{:.2}x", target.total_ms / stats.total_ms)
+                            }
+                            _ => "-".to_string(),
+                        });
+                    }
+                    None => row.extend(["-".to_string(), "-".to_string()]),
+                }
+            }
+            row
+        })
+        .collect();
+    let header: Vec<&str> = header.iter().map(String::as_str).collect();
+    render_table(&header, &rows)
+}
+
+/// Left-aligned columns separated by ` | `, with a `-+-` rule under the header.
+fn render_table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let widths: Vec<usize> = (0..header.len())
+        .map(|idx| {
+            rows.iter()
+                .map(|row| row[idx].chars().count())
+                .chain([header[idx].chars().count()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let line = |cells: &mut dyn Iterator<Item = &str>| {
+        let cells: Vec<String> = cells
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{cell:<width$}"))
+            .collect();
+        cells.join(" | ").trim_end().to_string()
+    };
+    let mut out = line(&mut header.iter().copied());
+    out.push('\n');
+    let rule: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+    out.push_str(&rule.join("-+-"));
+    out.push('\n');
+    for row in rows {
+        out.push_str(&line(&mut row.iter().map(String::as_str)));
+        out.push('\n');
+    }
+    out
+}
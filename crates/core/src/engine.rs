@@ -0,0 +1,487 @@
+use std::{borrow::Cow, convert::TryFrom, path::Path, sync::Arc};
+
+use anyhow::{Context, Result, anyhow, ensure};
+use candle_core::{DType, Device, Tensor};
+use image::DynamicImage;
+use tokenizers::Tokenizer;
+use tracing::info;
+
+use crate::{
+    grounding::{BoundingBox, GroundedBox, pixel_boxes, remap_grounding, strip_grounding_tokens},
+    inference::{
+        PromptTokenEstimate, build_prompt_tokens, compute_image_embeddings, estimate_prompt_tokens,
+        normalize_text, prepare_vision_inputs, render_prompt,
+    },
+    model::{DeepseekOcrModel, FinishReason, GenerateOptions},
+    redact::Redactor,
+    repetition::RepetitionOptions,
+    resolution::{AutoResolution, ResolutionChoice, resolve_vision},
+    roi::{RegionFrame, crop_region},
+    task::{TaskPreset, resolve_task},
+    vision::{CleanupOptions, CleanupReport, TilingOptions, clean_image},
+};
+
+/// Resolution knobs forwarded to the vision preprocessing stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VisionSettings {
+    pub base_size: u32,
+    pub image_size: u32,
+    pub crop_mode: bool,
+    /// Tile-count limits and thumbnail toggle used when `crop_mode` is enabled.
+    pub tiling: TilingOptions,
+    /// When set, the sizes above are replaced per request by an automatically chosen
+    /// resolution mode (see [`choose_resolution`]).
+    pub auto_resolution: Option<AutoResolution>,
+    /// Document cleanup applied to each image before preprocessing.
+    pub cleanup: CleanupOptions,
+}
+
+impl Default for VisionSettings {
+    fn default() -> Self {
+        Self {
+            base_size: 1024,
+            image_size: 640,
+            crop_mode: true,
+            tiling: TilingOptions::default(),
+            auto_resolution: None,
+            cleanup: CleanupOptions::default(),
+        }
+    }
+}
+
+/// Defaults applied to every request served by an [`OcrEngine`].
+#[derive(Debug, Clone)]
+pub struct EngineSettings {
+    pub template: String,
+    pub vision: VisionSettings,
+    pub max_new_tokens: usize,
+    pub use_cache: bool,
+    pub repetition: RepetitionOptions,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        Self {
+            template: "plain".to_string(),
+            vision: VisionSettings::default(),
+            max_new_tokens: 512,
+            use_cache: true,
+            repetition: RepetitionOptions::default(),
+        }
+    }
+}
+
+/// Called during decoding with the number of generated tokens and the ids so far.
+pub type ProgressCallback<'a> = &'a dyn Fn(usize, &[i64]);
+
+/// A single pipeline invocation. `prompt` must already be rendered (see [`render_prompt`]) and
+/// contain one `<image>` slot per entry in `images`.
+#[derive(Clone, Copy)]
+pub struct OcrRequest<'a> {
+    pub prompt: &'a str,
+    pub images: &'a [DynamicImage],
+    pub vision: Option<VisionSettings>,
+    pub max_new_tokens: Option<usize>,
+    /// Overrides [`EngineSettings::repetition`] for this request.
+    pub repetition: Option<RepetitionOptions>,
+    pub progress_callback: Option<ProgressCallback<'a>>,
+}
+
+impl<'a> OcrRequest<'a> {
+    pub fn new(prompt: &'a str, images: &'a [DynamicImage]) -> Self {
+        Self {
+            prompt,
+            images,
+            vision: None,
+            max_new_tokens: None,
+            repetition: None,
+            progress_callback: None,
+        }
+    }
+}
+
+/// Decoded output of an [`OcrEngine`] run.
+#[derive(Debug, Clone)]
+pub struct OcrResult {
+    /// Normalised text decoded without special tokens, as streamed by the progress callback.
+    pub text: String,
+    /// Tokenizer output with special tokens kept, so the `<|ref|>`/`<|det|>` grounding markup
+    /// survives; not normalised. When cleanup turned or deskewed a single input image, the boxes
+    /// are mapped back to that image's frame (in `text` too).
+    pub raw_text: String,
+    /// Generated token ids, excluding the prompt.
+    pub tokens: Vec<i64>,
+    pub prompt_tokens: usize,
+    pub image_tokens: usize,
+    pub finish_reason: FinishReason,
+    pub vision: VisionSettings,
+    /// Automatic resolution decision, when auto selection was enabled.
+    pub resolution: Option<ResolutionChoice>,
+    /// Per-image cleanup report; empty when no cleanup step was enabled.
+    pub cleanup: Vec<CleanupReport>,
+}
+
+impl OcrResult {
+    pub fn completion_tokens(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Normalised text with the grounding markup kept, for consumers that parse boxes.
+    pub fn grounded_text(&self) -> String {
+        normalize_text(&self.raw_text)
+    }
+}
+
+/// Output of [`OcrEngine::locate`]; boxes are in pixels of the queried image.
+#[derive(Debug, Clone)]
+pub struct LocateResult {
+    pub query: String,
+    pub width: u32,
+    pub height: u32,
+    pub boxes: Vec<GroundedBox>,
+    pub ocr: OcrResult,
+}
+
+impl LocateResult {
+    /// Replace personal data in the transcription and the box labels.
+    pub fn redact(&mut self, redactor: &Redactor) {
+        self.ocr.text = redactor.redact_text(&self.ocr.text);
+        self.ocr.raw_text = redactor.redact_text(&self.ocr.raw_text);
+        for found in &mut self.boxes {
+            found.label = redactor.redact_text(&found.label);
+        }
+    }
+}
+
+/// Output of one crop processed by [`OcrEngine::run_regions`].
+#[derive(Debug, Clone)]
+pub struct RegionResult {
+    /// Crop rectangle in original-image pixels, clamped to the image bounds.
+    pub region: BoundingBox,
+    /// Generated text with grounding coordinates remapped to the original image frame.
+    pub text: String,
+    /// Grounded boxes in original-image pixels.
+    pub boxes: Vec<GroundedBox>,
+    pub ocr: OcrResult,
+}
+
+/// Prompt size of a request computed without running the model (see [`estimate_tokens`]).
+#[derive(Debug, Clone)]
+pub struct TokenEstimate {
+    pub prompt: PromptTokenEstimate,
+    /// Settings the estimate was computed for, after automatic resolution selection.
+    pub vision: VisionSettings,
+    pub resolution: Option<ResolutionChoice>,
+}
+
+impl TokenEstimate {
+    /// Add the estimate of another page run of the same prompt (multi-page inputs run once per
+    /// page). The vision settings and resolution choice of the first page are kept.
+    pub fn add_page(&mut self, page: TokenEstimate) {
+        self.prompt.text_tokens += page.prompt.text_tokens;
+        self.prompt.image_tokens.extend(page.prompt.image_tokens);
+    }
+}
+
+/// Estimate the prompt length of `request`. Only the tokenizer and image dimensions are used
+/// (plus pixel statistics when auto resolution is enabled), so no model needs to be loaded.
+pub fn estimate_tokens(
+    tokenizer: &Tokenizer,
+    request: &OcrRequest<'_>,
+    defaults: VisionSettings,
+) -> Result<TokenEstimate> {
+    let requested = request.vision.unwrap_or(defaults);
+    let (images, _) = clean_images(request.images, &requested.cleanup);
+    let (vision, resolution) = resolve_vision(requested, &images);
+    let sizes: Vec<(u32, u32)> = images
+        .iter()
+        .map(|image| (image.width(), image.height()))
+        .collect();
+    let prompt = estimate_prompt_tokens(
+        tokenizer,
+        request.prompt,
+        &sizes,
+        vision.base_size,
+        vision.image_size,
+        vision.crop_mode,
+        vision.tiling,
+    )?;
+    Ok(TokenEstimate {
+        prompt,
+        vision,
+        resolution,
+    })
+}
+
+/// Run the enabled cleanup steps on every image, borrowing the originals when none apply.
+fn clean_images<'a>(
+    images: &'a [DynamicImage],
+    options: &CleanupOptions,
+) -> (Cow<'a, [DynamicImage]>, Vec<CleanupReport>) {
+    if !options.any_enabled() {
+        return (Cow::Borrowed(images), Vec::new());
+    }
+    let (cleaned, reports) = images
+        .iter()
+        .map(|image| clean_image(image, options))
+        .unzip();
+    (Cow::Owned(cleaned), reports)
+}
+
+/// Owns the model, tokenizer and default settings so callers can run the full OCR pipeline
+/// (prompt rendering, vision preprocessing, embedding, decoding) through a single entry point.
+pub struct OcrEngine {
+    model: DeepseekOcrModel,
+    tokenizer: Arc<Tokenizer>,
+    settings: EngineSettings,
+}
+
+impl OcrEngine {
+    pub fn new(model: DeepseekOcrModel, tokenizer: Tokenizer, settings: EngineSettings) -> Self {
+        Self {
+            model,
+            tokenizer: Arc::new(tokenizer),
+            settings,
+        }
+    }
+
+    /// Load model weights and tokenizer from disk and wrap them in an engine.
+    pub fn load(
+        config_path: &Path,
+        weights_path: &Path,
+        tokenizer_path: &Path,
+        device: Device,
+        dtype: DType,
+        settings: EngineSettings,
+    ) -> Result<Self> {
+        let model = DeepseekOcrModel::load(Some(config_path), Some(weights_path), device, dtype)
+            .context("failed to load DeepSeek-OCR model")?;
+        let tokenizer = Tokenizer::from_file(tokenizer_path).map_err(|err| {
+            anyhow!(
+                "failed to load tokenizer from {}: {err}",
+                tokenizer_path.display()
+            )
+        })?;
+        Ok(Self::new(model, tokenizer, settings))
+    }
+
+    pub fn model(&self) -> &DeepseekOcrModel {
+        &self.model
+    }
+
+    pub fn tokenizer(&self) -> &Arc<Tokenizer> {
+        &self.tokenizer
+    }
+
+    pub fn settings(&self) -> &EngineSettings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut EngineSettings {
+        &mut self.settings
+    }
+
+    /// Render `prompt` with the configured conversation template.
+    pub fn render_prompt(&self, prompt: &str) -> Result<String> {
+        render_prompt(&self.settings.template, "", prompt)
+    }
+
+    /// OCR a single image. `prompt` is the raw user prompt and must contain exactly one
+    /// `<image>` slot.
+    pub fn ocr(&self, image: &DynamicImage, prompt: &str) -> Result<OcrResult> {
+        let rendered = self.render_prompt(prompt)?;
+        let images = std::slice::from_ref(image);
+        self.run(&OcrRequest::new(&rendered, images))
+    }
+
+    /// OCR a single image using a named task preset; the preset's resolution and token budget
+    /// take precedence over the engine defaults.
+    pub fn ocr_task(
+        &self,
+        image: &DynamicImage,
+        task: &TaskPreset,
+        query: Option<&str>,
+    ) -> Result<OcrResult> {
+        let rendered = self.render_prompt(&task.render(query)?)?;
+        let images = std::slice::from_ref(image);
+        let mut request = OcrRequest::new(&rendered, images);
+        request.vision = Some(task.vision_settings(self.settings.vision));
+        request.max_new_tokens = task.max_new_tokens;
+        self.run(&request)
+    }
+
+    /// Find `query` in `image` using the `locate` task and return its boxes in pixel space.
+    pub fn locate(&self, image: &DynamicImage, query: &str) -> Result<LocateResult> {
+        let task = resolve_task("locate")?;
+        let ocr = self.ocr_task(image, &task, Some(query))?;
+        let (width, height) = (image.width(), image.height());
+        let boxes = pixel_boxes(&ocr.raw_text, width, height);
+        Ok(LocateResult {
+            query: query.trim().to_owned(),
+            width,
+            height,
+            boxes,
+            ocr,
+        })
+    }
+
+    /// Run `request` once per region, each time on the corresponding crop of its single image.
+    /// Grounding coordinates in the outputs are mapped back to the original image frame.
+    pub fn run_regions(
+        &self,
+        request: &OcrRequest<'_>,
+        regions: &[BoundingBox],
+    ) -> Result<Vec<RegionResult>> {
+        ensure!(
+            request.images.len() == 1,
+            "region OCR expects exactly one image (got {})",
+            request.images.len()
+        );
+        let image = &request.images[0];
+        let (width, height) = (image.width(), image.height());
+        regions
+            .iter()
+            .map(|region| {
+                let (crop, clamped) = crop_region(image, region)?;
+                let crop_request = OcrRequest {
+                    images: std::slice::from_ref(&crop),
+                    ..*request
+                };
+                let ocr = self.run(&crop_request)?;
+                let frame = RegionFrame::new(clamped, width, height);
+                let grounded = ocr.grounded_text();
+                let boxes = frame.remap_boxes(&pixel_boxes(&grounded, crop.width(), crop.height()));
+                Ok(RegionResult {
+                    region: clamped,
+                    text: frame.remap_text(&grounded),
+                    boxes,
+                    ocr,
+                })
+            })
+            .collect()
+    }
+
+    /// Estimate the prompt length of `request` without running the model.
+    pub fn estimate_tokens(&self, request: &OcrRequest<'_>) -> Result<TokenEstimate> {
+        estimate_tokens(&self.tokenizer, request, self.settings.vision)
+    }
+
+    /// Execute the full pipeline for an already rendered prompt.
+    pub fn run(&self, request: &OcrRequest<'_>) -> Result<OcrResult> {
+        let requested = request.vision.unwrap_or(self.settings.vision);
+        let (images, cleanup) = clean_images(request.images, &requested.cleanup);
+        for (idx, report) in cleanup.iter().enumerate() {
+            info!(
+                "Cleanup image {idx}: rotated {}°, deskewed {:.1}°, shadows removed: {}, contrast normalised: {}, denoised: {}, binarized: {}",
+                report.rotation_degrees,
+                report.skew_degrees,
+                report.shadows_removed,
+                report.contrast_normalized,
+                report.denoised,
+                report.binarized
+            );
+        }
+        let (vision, resolution) = resolve_vision(requested, &images);
+        if let Some(choice) = &resolution {
+            info!(
+                "Auto resolution picked `{}` (base_size={}, image_size={}, crop_mode={}, ~{} image tokens, text density {:.3})",
+                choice.mode.name(),
+                choice.vision.base_size,
+                choice.vision.image_size,
+                choice.vision.crop_mode,
+                choice.image_tokens,
+                choice.text_density
+            );
+        }
+        let max_new_tokens = request
+            .max_new_tokens
+            .unwrap_or(self.settings.max_new_tokens);
+        let image_slots = request.prompt.matches("<image>").count();
+        ensure!(
+            image_slots == request.images.len(),
+            "prompt includes {image_slots} <image> tokens but {} images were provided",
+            request.images.len()
+        );
+
+        let model = &self.model;
+        let owned_inputs = prepare_vision_inputs(
+            model,
+            &images,
+            vision.base_size,
+            vision.image_size,
+            vision.crop_mode,
+            vision.tiling,
+        )?;
+        let embeddings = compute_image_embeddings(model, &owned_inputs)?;
+        let (input_ids_vec, mask_vec) = build_prompt_tokens(
+            &self.tokenizer,
+            request.prompt,
+            &embeddings,
+            &owned_inputs,
+            vision.base_size,
+            vision.image_size,
+            vision.crop_mode,
+        )?;
+        let prompt_tokens = input_ids_vec.len();
+        let image_tokens = mask_vec.iter().filter(|&&flag| flag != 0).count();
+
+        let input_ids = Tensor::from_vec(input_ids_vec, (1, prompt_tokens), model.device())?
+            .to_dtype(DType::I64)?;
+        let mask_tensor =
+            Tensor::from_vec(mask_vec, (1, prompt_tokens), model.device())?.to_dtype(DType::U8)?;
+
+        let mut options = GenerateOptions::new(max_new_tokens);
+        options.images_seq_mask = Some(&mask_tensor);
+        if !embeddings.is_empty() {
+            options.image_embeddings = Some(embeddings.as_slice());
+        }
+        options.eos_token_id = model.language_model().config().eos_token_id;
+        options.use_cache = self.settings.use_cache;
+        options.progress_callback = request.progress_callback;
+        options.repetition = request.repetition.unwrap_or(self.settings.repetition);
+
+        let generation = model.generate_with_reason(&input_ids, options)?;
+        if generation.finish_reason == FinishReason::Repetition {
+            info!("Decoding stopped on a repetition loop");
+        }
+        let tokens = generation
+            .tokens
+            .to_vec2::<i64>()?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        let mut raw_text = self.decode_tokens(&tokens, false)?;
+        let mut text = normalize_text(&self.decode_tokens(&tokens, true)?);
+        // The model saw the cleaned image; report its boxes against the pixels the caller has.
+        if let [report] = cleanup.as_slice()
+            && report.changes_geometry()
+        {
+            raw_text = remap_grounding(&raw_text, |bbox| report.to_input_frame(bbox));
+            text = normalize_text(&strip_grounding_tokens(&raw_text));
+        }
+
+        Ok(OcrResult {
+            text,
+            raw_text,
+            tokens,
+            prompt_tokens,
+            image_tokens,
+            finish_reason: generation.finish_reason,
+            vision,
+            resolution,
+            cleanup,
+        })
+    }
+
+    /// Decode generated token ids. With `skip_special_tokens` the text matches what streaming
+    /// shows; without it the `<|ref|>`/`<|det|>` grounding markers survive.
+    pub fn decode_tokens(&self, tokens: &[i64], skip_special_tokens: bool) -> Result<String> {
+        let ids: Vec<u32> = tokens
+            .iter()
+            .filter_map(|&id| u32::try_from(id).ok())
+            .collect();
+        self.tokenizer
+            .decode(&ids, skip_special_tokens)
+            .map_err(|err| anyhow!("failed to decode generated tokens: {err}"))
+    }
+}
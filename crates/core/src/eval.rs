@@ -0,0 +1,356 @@
+use std::{collections::BTreeMap, fmt::Write as _};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    export::{PageOutput, document::Document, table::Table},
+    model::FinishReason,
+    text::{collapse_whitespace, edit_distance},
+};
+
+/// File format of an [`EvalReport`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    /// Machine-readable report; also what `--baseline` reads back.
+    #[default]
+    Json,
+    /// Summary and per-sample tables for review.
+    Markdown,
+}
+
+/// Reduce OCR output or a ground-truth file to the text that is scored: grounding markup and
+/// markdown/HTML syntax removed (tables become tab-separated rows), whitespace collapsed and,
+/// with `ignore_case`, lower-cased.
+pub fn normalize_for_eval(text: &str, ignore_case: bool) -> String {
+    let document = Document::from_pages(&[PageOutput { text, size: None }]);
+    let text = collapse_whitespace(&document.plain_text());
+    if ignore_case {
+        text.to_lowercase()
+    } else {
+        text
+    }
+}
+
+/// Tables of OCR output or a ground-truth file.
+pub fn extract_tables(text: &str) -> Vec<Table> {
+    Table::extract(&Document::from_pages(&[PageOutput { text, size: None }]))
+}
+
+/// Character and word edit distances of a prediction against its reference.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TextScores {
+    pub char_errors: usize,
+    /// Reference length in characters.
+    pub chars: usize,
+    pub word_errors: usize,
+    /// Reference length in words.
+    pub words: usize,
+    /// Character edit distance divided by the longer of the two texts, in `0.0..=1.0`.
+    pub normalized_edit_distance: f64,
+}
+
+impl TextScores {
+    /// Compare two normalised texts (see [`normalize_for_eval`]).
+    pub fn compare(prediction: &str, reference: &str) -> Self {
+        let predicted: Vec<char> = prediction.chars().collect();
+        let expected: Vec<char> = reference.chars().collect();
+        let char_errors = edit_distance(&predicted, &expected);
+        let predicted_words: Vec<&str> = prediction.split_whitespace().collect();
+        let expected_words: Vec<&str> = reference.split_whitespace().collect();
+        let longest = predicted.len().max(expected.len());
+        Self {
+            char_errors,
+            chars: expected.len(),
+            word_errors: edit_distance(&predicted_words, &expected_words),
+            words: expected_words.len(),
+            normalized_edit_distance: if longest == 0 {
+                0.0
+            } else {
+                char_errors as f64 / longest as f64
+            },
+        }
+    }
+
+    /// Character error rate: errors per reference character (can exceed 1).
+    pub fn cer(&self) -> f64 {
+        error_rate(self.char_errors, self.chars)
+    }
+
+    /// Word error rate: errors per reference word (can exceed 1).
+    pub fn wer(&self) -> f64 {
+        error_rate(self.word_errors, self.words)
+    }
+}
+
+fn error_rate(errors: usize, total: usize) -> f64 {
+    match (errors, total) {
+        (0, _) => 0.0,
+        (_, 0) => 1.0,
+        _ => errors as f64 / total as f64,
+    }
+}
+
+/// Similarity of two table grids in `0.0..=1.0`: one minus the normalised edit distance between
+/// their row/cell token sequences, a lightweight stand-in for tree-edit-distance (TEDS) scores.
+/// With `content` each cell token also carries its whitespace-collapsed text; without it only
+/// the structure (rows and cells per row) is compared.
+pub fn table_similarity(
+    prediction: &[Vec<String>],
+    reference: &[Vec<String>],
+    content: bool,
+) -> f64 {
+    let predicted = table_tokens(prediction, content);
+    let expected = table_tokens(reference, content);
+    let longest = predicted.len().max(expected.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(&predicted, &expected) as f64 / longest as f64
+}
+
+fn table_tokens(rows: &[Vec<String>], content: bool) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for row in rows {
+        tokens.push("<tr>".to_string());
+        for cell in row {
+            tokens.push(if content {
+                format!("<td>{}", collapse_whitespace(cell))
+            } else {
+                "<td>".to_string()
+            });
+        }
+    }
+    tokens
+}
+
+/// Table similarity of one sample, averaged over the tables of prediction and reference paired
+/// in reading order; a table without a counterpart scores 0.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TableScores {
+    pub predicted: usize,
+    pub expected: usize,
+    pub structure: f64,
+    pub content: f64,
+}
+
+impl TableScores {
+    /// `None` when neither side has a table.
+    pub fn compare(prediction: &[Table], reference: &[Table]) -> Option<Self> {
+        let count = prediction.len().max(reference.len());
+        if count == 0 {
+            return None;
+        }
+        let mean = |content: bool| {
+            prediction
+                .iter()
+                .zip(reference)
+                .map(|(predicted, expected)| {
+                    table_similarity(&predicted.rows, &expected.rows, content)
+                })
+                .sum::<f64>()
+                / count as f64
+        };
+        Some(Self {
+            predicted: prediction.len(),
+            expected: reference.len(),
+            structure: mean(false),
+            content: mean(true),
+        })
+    }
+}
+
+/// Scores of one labelled image.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SampleResult {
+    /// Image file name relative to the dataset directory.
+    pub name: String,
+    pub text: TextScores,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tables: Option<TableScores>,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub finish_reason: FinishReason,
+    pub seconds: f64,
+}
+
+/// Dataset-level scores. CER and WER are micro averages (total errors over total reference
+/// length); the other metrics are means over samples.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalSummary {
+    pub samples: usize,
+    pub cer: f64,
+    pub wer: f64,
+    pub normalized_edit_distance: f64,
+    /// Mean over samples that have tables on either side.
+    pub table_structure: Option<f64>,
+    pub table_content: Option<f64>,
+    /// Samples whose decoding hit the token budget or a repetition loop.
+    pub truncated: usize,
+    pub completion_tokens: usize,
+    pub seconds: f64,
+}
+
+impl EvalSummary {
+    pub fn from_samples(samples: &[SampleResult]) -> Self {
+        let sum = |f: fn(&TextScores) -> usize| samples.iter().map(|s| f(&s.text)).sum::<usize>();
+        let mean = |values: Vec<f64>| {
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        };
+        let tables: Vec<TableScores> = samples.iter().filter_map(|s| s.tables).collect();
+        Self {
+            samples: samples.len(),
+            cer: error_rate(sum(|t| t.char_errors), sum(|t| t.chars)),
+            wer: error_rate(sum(|t| t.word_errors), sum(|t| t.words)),
+            normalized_edit_distance: mean(
+                samples
+                    .iter()
+                    .map(|s| s.text.normalized_edit_distance)
+                    .collect(),
+            )
+            .unwrap_or(0.0),
+            table_structure: mean(tables.iter().map(|t| t.structure).collect()),
+            table_content: mean(tables.iter().map(|t| t.content).collect()),
+            truncated: samples
+                .iter()
+                .filter(|s| s.finish_reason != FinishReason::Stop)
+                .count(),
+            completion_tokens: samples.iter().map(|s| s.completion_tokens).sum(),
+            seconds: samples.iter().map(|s| s.seconds).sum(),
+        }
+    }
+}
+
+/// Result of evaluating a dataset, sorted by sample name so reports of two runs diff cleanly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalReport {
+    /// Run settings (model, task, device, precision, ...) recorded for comparison.
+    pub settings: BTreeMap<String, String>,
+    pub summary: EvalSummary,
+    pub samples: Vec<SampleResult>,
+}
+
+impl EvalReport {
+    pub fn new(settings: BTreeMap<String, String>, mut samples: Vec<SampleResult>) -> Self {
+        samples.sort_by(|a, b| a.name.cmp(&b.name));
+        Self {
+            settings,
+            summary: EvalSummary::from_samples(&samples),
+            samples,
+        }
+    }
+
+    /// Render the report; the markdown form adds deltas against `baseline` when given.
+    pub fn render(&self, format: ReportFormat, baseline: Option<&EvalReport>) -> Result<String> {
+        match format {
+            ReportFormat::Json => {
+                serde_json::to_string_pretty(self).context("failed to serialise report")
+            }
+            ReportFormat::Markdown => Ok(self.to_markdown(baseline)),
+        }
+    }
+
+    pub fn to_markdown(&self, baseline: Option<&EvalReport>) -> String {
+        let mut out = String::from("# OCR evaluation\n\n| Setting | Value |\n| --- | --- |\n");
+        for (key, value) in &self.settings {
+            let _ = writeln!(out, "| {key} | {value} |");
+        }
+
+        out.push_str("\n## Summary\n\n");
+        let summary = &self.summary;
+        let base = baseline.map(|report| &report.summary);
+        let rows: [(&str, Option<f64>, Option<f64>); 5] = [
+            ("CER", Some(summary.cer), base.map(|b| b.cer)),
+            ("WER", Some(summary.wer), base.map(|b| b.wer)),
+            (
+                "Normalised edit distance",
+                Some(summary.normalized_edit_distance),
+                base.map(|b| b.normalized_edit_distance),
+            ),
+            (
+                "Table structure similarity",
+                summary.table_structure,
+                base.and_then(|b| b.table_structure),
+            ),
+            (
+                "Table content similarity",
+                summary.table_content,
+                base.and_then(|b| b.table_content),
+            ),
+        ];
+        if baseline.is_some() {
+            out.push_str("| Metric | Value | Baseline | Δ |\n| --- | --- | --- | --- |\n");
+        } else {
+            out.push_str("| Metric | Value |\n| --- | --- |\n");
+        }
+        for (name, value, previous) in rows {
+            let _ = write!(out, "| {name} | {} |", format_score(value));
+            if baseline.is_some() {
+                let _ = write!(
+                    out,
+                    " {} | {} |",
+                    format_score(previous),
+                    format_delta(value, previous)
+                );
+            }
+            out.push('\n');
+        }
+        let _ = writeln!(
+            out,
+            "\n{} sample(s), {} truncated, {} tokens generated in {:.1}s.",
+            summary.samples, summary.truncated, summary.completion_tokens, summary.seconds
+        );
+
+        out.push_str("\n## Samples\n\n| Sample | CER | WER | NED | Tables (structure / content) | Tokens | Finish |");
+        if baseline.is_some() {
+            out.push_str(" Δ CER |");
+        }
+        out.push_str("\n| --- | --- | --- | --- | --- | --- | --- |");
+        if baseline.is_some() {
+            out.push_str(" --- |");
+        }
+        out.push('\n');
+        for sample in &self.samples {
+            let tables = sample.tables.map_or("–".to_string(), |tables| {
+                format!("{:.4} / {:.4}", tables.structure, tables.content)
+            });
+            let _ = write!(
+                out,
+                "| {} | {:.4} | {:.4} | {:.4} | {tables} | {} | {} |",
+                sample.name,
+                sample.text.cer(),
+                sample.text.wer(),
+                sample.text.normalized_edit_distance,
+                sample.completion_tokens,
+                sample.finish_reason.name()
+            );
+            if let Some(baseline) = baseline {
+                let previous = baseline
+                    .samples
+                    .iter()
+                    .find(|other| other.name == sample.name)
+                    .map(|other| other.text.cer());
+                let _ = write!(
+                    out,
+                    " {} |",
+                    format_delta(Some(sample.text.cer()), previous)
+                );
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn format_score(value: Option<f64>) -> String {
+    value.map_or("–".to_string(), |value| format!("{value:.4}"))
+}
+
+fn format_delta(value: Option<f64>, previous: Option<f64>) -> String {
+    match (value, previous) {
+        (Some(value), Some(previous)) => format!("{:+.4}", value - previous),
+        _ => "–".to_string(),
+    }
+}
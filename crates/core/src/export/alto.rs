@@ -0,0 +1,99 @@
+use std::fmt::Write;
+
+use super::{LayoutBlock, PageLayout, SOFTWARE_NAME, escape_xml, pixel_rect};
+use crate::grounding::BoundingBox;
+
+/// Render `pages` as an ALTO 4 XML document with pixel measurements.
+pub fn render(pages: &[PageLayout]) -> String {
+    let mut xml = String::new();
+    xml.push_str(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<alto xmlns=\"http://www.loc.gov/standards/alto/ns-v4#\" ",
+        "xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" ",
+        "xsi:schemaLocation=\"http://www.loc.gov/standards/alto/ns-v4# ",
+        "http://www.loc.gov/alto/v4/alto-4-2.xsd\">\n",
+        "  <Description>\n",
+        "    <MeasurementUnit>pixel</MeasurementUnit>\n",
+        "    <OCRProcessing ID=\"OCR_0\">\n",
+        "      <ocrProcessingStep>\n",
+        "        <processingSoftware>\n",
+    ));
+    let _ = writeln!(
+        xml,
+        "          <softwareName>{SOFTWARE_NAME}</softwareName>\n          <softwareVersion>{}</softwareVersion>",
+        env!("CARGO_PKG_VERSION")
+    );
+    xml.push_str(concat!(
+        "        </processingSoftware>\n",
+        "      </ocrProcessingStep>\n",
+        "    </OCRProcessing>\n",
+        "  </Description>\n",
+        "  <Layout>\n",
+    ));
+    for (page_idx, page) in pages.iter().enumerate() {
+        let page_no = page_idx + 1;
+        let _ = writeln!(
+            xml,
+            "    <Page ID=\"P{page_no}\" PHYSICAL_IMG_NR=\"{page_no}\" WIDTH=\"{}\" HEIGHT=\"{}\">",
+            page.width, page.height
+        );
+        let _ = writeln!(
+            xml,
+            "      <PrintSpace HPOS=\"0\" VPOS=\"0\" WIDTH=\"{}\" HEIGHT=\"{}\">",
+            page.width, page.height
+        );
+        for (block_idx, block) in page.blocks.iter().enumerate() {
+            write_block(&mut xml, block, &format!("P{page_no}_B{}", block_idx + 1));
+        }
+        xml.push_str("      </PrintSpace>\n    </Page>\n");
+    }
+    xml.push_str("  </Layout>\n</alto>\n");
+    xml
+}
+
+fn write_block(xml: &mut String, block: &LayoutBlock, id: &str) {
+    if block.is_picture() {
+        let _ = writeln!(
+            xml,
+            "        <Illustration ID=\"{id}\" {}/>",
+            position(&block.bbox)
+        );
+        return;
+    }
+    let _ = writeln!(
+        xml,
+        "        <TextBlock ID=\"{id}\" {}>",
+        position(&block.bbox)
+    );
+    for (line_idx, line) in block.lines().iter().enumerate() {
+        let line_id = format!("{id}_L{}", line_idx + 1);
+        let _ = writeln!(
+            xml,
+            "          <TextLine ID=\"{line_id}\" {}>",
+            position(&line.bbox)
+        );
+        for (word_idx, word) in line.words.iter().enumerate() {
+            if word_idx > 0 {
+                xml.push_str("            <SP/>\n");
+            }
+            let _ = writeln!(
+                xml,
+                "            <String ID=\"{line_id}_S{}\" {} CONTENT=\"{}\"/>",
+                word_idx + 1,
+                position(&word.bbox),
+                escape_xml(&word.text)
+            );
+        }
+        xml.push_str("          </TextLine>\n");
+    }
+    xml.push_str("        </TextBlock>\n");
+}
+
+fn position(bbox: &BoundingBox) -> String {
+    let (x1, y1, x2, y2) = pixel_rect(bbox);
+    format!(
+        "HPOS=\"{x1}\" VPOS=\"{y1}\" WIDTH=\"{}\" HEIGHT=\"{}\"",
+        x2 - x1,
+        y2 - y1
+    )
+}
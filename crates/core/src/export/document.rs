@@ -0,0 +1,333 @@
+use std::{iter::Peekable, str::Lines};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    PICTURE_LABELS, PageOutput, plain_text,
+    table::{parse_html_table, parse_pipe_table},
+    tag_at,
+};
+use crate::grounding::{BoundingBox, parse_grounding, strip_grounding};
+
+/// Grounding labels of display formulas.
+const FORMULA_LABELS: [&str; 3] = ["equation", "formula", "isolate_formula"];
+
+/// Typed view of decoded OCR output, so callers do not have to re-parse markdown.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    pub pages: Vec<DocumentPage>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DocumentPage {
+    /// Pixel size of the page image, known when the page has exactly one image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Blocks in reading order.
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Block {
+    #[serde(flatten)]
+    pub kind: BlockKind,
+    /// Grounded region in page pixels (the union when a reference has several boxes). Absent
+    /// for ungrounded output or when the page size is unknown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<BoundingBox>,
+}
+
+/// Block content; text fields hold plain text with markdown/HTML markup removed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BlockKind {
+    Heading {
+        level: u8,
+        text: String,
+    },
+    Paragraph {
+        text: String,
+    },
+    /// Cell texts row by row, normalised to a rectangular grid (see [`Table`](super::table::Table)).
+    Table {
+        rows: Vec<Vec<String>>,
+    },
+    /// Display formula source without its `\[ \]` / `$$` delimiters.
+    Formula {
+        latex: String,
+    },
+    Figure {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        caption: Option<String>,
+    },
+    List {
+        ordered: bool,
+        items: Vec<String>,
+    },
+}
+
+impl Document {
+    pub fn from_pages(pages: &[PageOutput<'_>]) -> Self {
+        Self {
+            pages: pages
+                .iter()
+                .map(|page| DocumentPage::from_output(page.text, page.size))
+                .collect(),
+        }
+    }
+
+    /// Plain-text rendering: one paragraph per block, tables tab separated, lists with their
+    /// markers and figures reduced to their captions.
+    pub fn plain_text(&self) -> String {
+        self.pages
+            .iter()
+            .flat_map(|page| &page.blocks)
+            .filter_map(|block| block.kind.plain_text())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+impl DocumentPage {
+    /// Build a page from decoded model output. Grounded output is split per reference and the
+    /// label refines the block type (`title`, `sub_title`, `image`, `image_caption`,
+    /// `equation`); the markdown inside each reference, or the whole output when there is no
+    /// grounding, is parsed with [`parse_blocks`]. `size` is the page image size in pixels.
+    pub fn from_output(text: &str, size: Option<(u32, u32)>) -> Self {
+        let spans = parse_grounding(text);
+        let mut blocks = Vec::new();
+        match spans.first() {
+            None => push_blocks(&mut blocks, parse_blocks(&strip_grounding(text)), None),
+            Some(first) => {
+                push_blocks(&mut blocks, parse_blocks(&text[..first.range.start]), None);
+                for span in &spans {
+                    let bbox = size.and_then(|(width, height)| {
+                        Some(union(&span.boxes)?.to_pixels(width, height))
+                    });
+                    push_span(&mut blocks, &span.label, &span.content, bbox);
+                }
+            }
+        }
+        Self {
+            width: size.map(|(width, _)| width),
+            height: size.map(|(_, height)| height),
+            blocks,
+        }
+    }
+}
+
+impl BlockKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockKind::Heading { .. } => "heading",
+            BlockKind::Paragraph { .. } => "paragraph",
+            BlockKind::Table { .. } => "table",
+            BlockKind::Formula { .. } => "formula",
+            BlockKind::Figure { .. } => "figure",
+            BlockKind::List { .. } => "list",
+        }
+    }
+
+    fn plain_text(&self) -> Option<String> {
+        let text = match self {
+            BlockKind::Heading { text, .. } | BlockKind::Paragraph { text } => text.clone(),
+            BlockKind::Table { rows } => rows
+                .iter()
+                .map(|row| row.join("\t"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            BlockKind::Formula { latex } => latex.clone(),
+            BlockKind::Figure { caption } => caption.clone()?,
+            BlockKind::List { ordered, items } => items
+                .iter()
+                .enumerate()
+                .map(|(idx, item)| {
+                    if *ordered {
+                        format!("{}. {item}", idx + 1)
+                    } else {
+                        format!("- {item}")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        Some(text).filter(|text| !text.is_empty())
+    }
+}
+
+fn push_blocks(blocks: &mut Vec<Block>, kinds: Vec<BlockKind>, bbox: Option<BoundingBox>) {
+    blocks.extend(kinds.into_iter().map(|kind| Block { kind, bbox }));
+}
+
+fn push_span(blocks: &mut Vec<Block>, label: &str, content: &str, bbox: Option<BoundingBox>) {
+    let label = label.to_ascii_lowercase();
+    if PICTURE_LABELS.contains(&label.as_str()) {
+        blocks.push(Block {
+            kind: BlockKind::Figure { caption: None },
+            bbox,
+        });
+    } else if label == "image_caption" {
+        // Attach the caption to the figure it follows; otherwise keep it as a paragraph.
+        if let Some(Block {
+            kind: BlockKind::Figure { caption },
+            ..
+        }) = blocks.last_mut()
+        {
+            let text = plain_text(content);
+            if caption.is_none() && !text.is_empty() {
+                *caption = Some(text);
+                return;
+            }
+        }
+    }
+
+    let mut kinds = parse_blocks(content);
+    if let [BlockKind::Paragraph { text }] = kinds.as_slice() {
+        let refined = match label.as_str() {
+            "title" => Some(BlockKind::Heading {
+                level: 1,
+                text: text.clone(),
+            }),
+            "sub_title" => Some(BlockKind::Heading {
+                level: 2,
+                text: text.clone(),
+            }),
+            label if FORMULA_LABELS.contains(&label) => Some(BlockKind::Formula {
+                latex: content.trim().to_string(),
+            }),
+            _ => None,
+        };
+        if let Some(refined) = refined {
+            kinds = vec![refined];
+        }
+    }
+    push_blocks(blocks, kinds, bbox);
+}
+
+/// Smallest box covering `boxes`.
+fn union(boxes: &[BoundingBox]) -> Option<BoundingBox> {
+    boxes.iter().copied().reduce(|acc, bbox| {
+        BoundingBox::new(
+            acc.x1.min(bbox.x1),
+            acc.y1.min(bbox.y1),
+            acc.x2.max(bbox.x2),
+            acc.y2.max(bbox.y2),
+        )
+    })
+}
+
+/// Split markdown (as produced by the model: ATX headings, HTML or pipe tables, `\[ \]` / `$$`
+/// formulas, `![](...)` images, `-`/`1.` lists) into typed blocks. Other lines are grouped into
+/// paragraphs separated by blank lines.
+pub fn parse_blocks(markdown: &str) -> Vec<BlockKind> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut lines = markdown.lines().peekable();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        let block = if line.is_empty() {
+            None
+        } else if tag_at(line, 0, "table") {
+            Some(BlockKind::Table {
+                rows: parse_html_table(&collect_until(line, &mut lines, "</table>")),
+            })
+        } else if line.starts_with('|') {
+            let mut table = vec![line];
+            while let Some(next) = lines.next_if(|next| next.trim().starts_with('|')) {
+                table.push(next);
+            }
+            Some(BlockKind::Table {
+                rows: parse_pipe_table(&table),
+            })
+        } else if let Some((open, close)) = ["\\[", "$$"]
+            .iter()
+            .zip(["\\]", "$$"])
+            .find(|(open, _)| line.starts_with(**open))
+        {
+            let source = collect_until(&line[open.len()..], &mut lines, close);
+            let latex = source
+                .rsplit_once(close)
+                .map_or(source.as_str(), |(latex, _)| latex);
+            Some(BlockKind::Formula {
+                latex: latex.trim().to_string(),
+            })
+        } else if let Some((level, text)) = heading(line) {
+            Some(BlockKind::Heading {
+                level,
+                text: plain_text(text),
+            })
+        } else if let Some(alt) = image_alt(line) {
+            Some(BlockKind::Figure {
+                caption: Some(plain_text(alt)).filter(|alt| !alt.is_empty()),
+            })
+        } else if let Some((ordered, item)) = list_item(line) {
+            let mut items = vec![plain_text(item)];
+            while let Some(next) = lines.next_if(|next| {
+                list_item(next.trim()).is_some_and(|(next_ordered, _)| next_ordered == ordered)
+            }) {
+                items.extend(list_item(next.trim()).map(|(_, item)| plain_text(item)));
+            }
+            Some(BlockKind::List { ordered, items })
+        } else {
+            paragraph.push(line);
+            continue;
+        };
+        if !paragraph.is_empty() {
+            let text = plain_text(&paragraph.join("\n"));
+            paragraph.clear();
+            if !text.is_empty() {
+                blocks.push(BlockKind::Paragraph { text });
+            }
+        }
+        blocks.extend(block);
+    }
+    let text = plain_text(&paragraph.join("\n"));
+    if !text.is_empty() {
+        blocks.push(BlockKind::Paragraph { text });
+    }
+    blocks
+}
+
+/// `first` plus following lines up to and including the first one containing `close`.
+fn collect_until(first: &str, lines: &mut Peekable<Lines<'_>>, close: &str) -> String {
+    let mut source = first.to_string();
+    while !source.to_ascii_lowercase().contains(close) {
+        let Some(next) = lines.next() else { break };
+        source.push('\n');
+        source.push_str(next);
+    }
+    source
+}
+
+fn heading(line: &str) -> Option<(u8, &str)> {
+    let level = line.bytes().take_while(|&b| b == b'#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    Some((level as u8, rest.trim()))
+}
+
+fn image_alt(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("![")?;
+    let (alt, target) = rest.split_once("](")?;
+    target.ends_with(')').then_some(alt)
+}
+
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(marker) {
+            return Some((false, item.trim()));
+        }
+    }
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    let rest = &line[digits..];
+    if digits == 0 || digits > 3 {
+        return None;
+    }
+    rest.strip_prefix(". ")
+        .or_else(|| rest.strip_prefix(") "))
+        .map(|item| (true, item.trim()))
+}
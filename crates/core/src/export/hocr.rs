@@ -0,0 +1,97 @@
+use std::fmt::Write;
+
+use super::{LayoutBlock, PageLayout, SOFTWARE_NAME, escape_xml, pixel_rect};
+use crate::grounding::BoundingBox;
+
+/// Render `pages` as an hOCR 1.2 document (XHTML).
+pub fn render(pages: &[PageLayout]) -> String {
+    let mut html = String::new();
+    html.push_str(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Transitional//EN\" ",
+        "\"http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd\">\n",
+        "<html xmlns=\"http://www.w3.org/1999/xhtml\" xml:lang=\"en\" lang=\"en\">\n",
+        "<head>\n",
+        "<title></title>\n",
+        "<meta http-equiv=\"Content-Type\" content=\"text/html;charset=utf-8\" />\n",
+    ));
+    let _ = writeln!(
+        html,
+        "<meta name=\"ocr-system\" content=\"{SOFTWARE_NAME} {}\" />",
+        env!("CARGO_PKG_VERSION")
+    );
+    html.push_str(concat!(
+        "<meta name=\"ocr-capabilities\" ",
+        "content=\"ocr_page ocr_carea ocr_par ocr_line ocrx_word ocr_photo ocr_table\" />\n",
+        "</head>\n",
+        "<body>\n",
+    ));
+    for (page_idx, page) in pages.iter().enumerate() {
+        let page_no = page_idx + 1;
+        let _ = writeln!(
+            html,
+            "<div class=\"ocr_page\" id=\"page_{page_no}\" title=\"bbox 0 0 {} {}; ppageno {page_idx}\">",
+            page.width, page.height
+        );
+        for (block_idx, block) in page.blocks.iter().enumerate() {
+            write_block(&mut html, block, page_no, block_idx + 1);
+        }
+        html.push_str("</div>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn write_block(html: &mut String, block: &LayoutBlock, page_no: usize, block_no: usize) {
+    let id = format!("{page_no}_{block_no}");
+    if block.is_picture() {
+        let _ = writeln!(
+            html,
+            "<div class=\"ocr_photo\" id=\"photo_{id}\" title=\"{}\"></div>",
+            title(&block.bbox)
+        );
+        return;
+    }
+    let class = if block.label == "table" {
+        "ocr_table"
+    } else {
+        "ocr_carea"
+    };
+    let _ = writeln!(
+        html,
+        "<div class=\"{class}\" id=\"block_{id}\" title=\"{}\">",
+        title(&block.bbox)
+    );
+    let _ = writeln!(
+        html,
+        "<p class=\"ocr_par\" id=\"par_{id}\" title=\"{}\">",
+        title(&block.bbox)
+    );
+    for (line_idx, line) in block.lines().iter().enumerate() {
+        let line_id = format!("{id}_{}", line_idx + 1);
+        let _ = write!(
+            html,
+            "<span class=\"ocr_line\" id=\"line_{line_id}\" title=\"{}\">",
+            title(&line.bbox)
+        );
+        for (word_idx, word) in line.words.iter().enumerate() {
+            if word_idx > 0 {
+                html.push(' ');
+            }
+            let _ = write!(
+                html,
+                "<span class=\"ocrx_word\" id=\"word_{line_id}_{}\" title=\"{}\">{}</span>",
+                word_idx + 1,
+                title(&word.bbox),
+                escape_xml(&word.text)
+            );
+        }
+        html.push_str("</span>\n");
+    }
+    html.push_str("</p>\n</div>\n");
+}
+
+fn title(bbox: &BoundingBox) -> String {
+    let (x1, y1, x2, y2) = pixel_rect(bbox);
+    format!("bbox {x1} {y1} {x2} {y2}")
+}
@@ -0,0 +1,92 @@
+use std::fmt::Write;
+
+use super::{
+    SOFTWARE_NAME,
+    document::{Block, BlockKind, Document},
+    escape_xml, pixel_rect,
+};
+
+/// Render `document` as a standalone HTML5 page: one `<section class="page">` per page and one
+/// element per block, with grounded boxes in `data-bbox="x1 y1 x2 y2"` (pixels). Formulas keep
+/// their LaTeX between `\[ \]` for MathJax/KaTeX.
+pub fn render(document: &Document) -> String {
+    let mut html = String::new();
+    html.push_str(concat!(
+        "<!DOCTYPE html>\n",
+        "<html>\n",
+        "<head>\n",
+        "<meta charset=\"utf-8\" />\n",
+    ));
+    let _ = writeln!(
+        html,
+        "<meta name=\"generator\" content=\"{SOFTWARE_NAME} {}\" />",
+        env!("CARGO_PKG_VERSION")
+    );
+    html.push_str("<title></title>\n</head>\n<body>\n");
+    for (page_idx, page) in document.pages.iter().enumerate() {
+        let _ = write!(html, "<section class=\"page\" id=\"page_{}\"", page_idx + 1);
+        if let (Some(width), Some(height)) = (page.width, page.height) {
+            let _ = write!(html, " data-width=\"{width}\" data-height=\"{height}\"");
+        }
+        html.push_str(">\n");
+        for block in &page.blocks {
+            write_block(&mut html, block);
+        }
+        html.push_str("</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn write_block(html: &mut String, block: &Block) {
+    let attrs = block
+        .bbox
+        .map(|bbox| {
+            let (x1, y1, x2, y2) = pixel_rect(&bbox);
+            format!(" data-bbox=\"{x1} {y1} {x2} {y2}\"")
+        })
+        .unwrap_or_default();
+    match &block.kind {
+        BlockKind::Heading { level, text } => {
+            let level = (*level).clamp(1, 6);
+            let _ = writeln!(html, "<h{level}{attrs}>{}</h{level}>", escape_xml(text));
+        }
+        BlockKind::Paragraph { text } => {
+            let text = escape_xml(text).replace('\n', "<br />\n");
+            let _ = writeln!(html, "<p{attrs}>{text}</p>");
+        }
+        BlockKind::Table { rows } => {
+            let _ = writeln!(html, "<table{attrs}>");
+            for row in rows {
+                html.push_str("<tr>");
+                for cell in row {
+                    let _ = write!(html, "<td>{}</td>", escape_xml(cell));
+                }
+                html.push_str("</tr>\n");
+            }
+            html.push_str("</table>\n");
+        }
+        BlockKind::Formula { latex } => {
+            let _ = writeln!(
+                html,
+                "<div class=\"formula\"{attrs}>\\[{}\\]</div>",
+                escape_xml(latex)
+            );
+        }
+        BlockKind::Figure { caption } => {
+            let _ = write!(html, "<figure{attrs}>");
+            if let Some(caption) = caption {
+                let _ = write!(html, "<figcaption>{}</figcaption>", escape_xml(caption));
+            }
+            html.push_str("</figure>\n");
+        }
+        BlockKind::List { ordered, items } => {
+            let tag = if *ordered { "ol" } else { "ul" };
+            let _ = writeln!(html, "<{tag}{attrs}>");
+            for item in items {
+                let _ = writeln!(html, "<li>{}</li>", escape_xml(item));
+            }
+            let _ = writeln!(html, "</{tag}>");
+        }
+    }
+}
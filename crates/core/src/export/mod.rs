@@ -0,0 +1,382 @@
+pub mod alto;
+pub mod document;
+pub mod hocr;
+pub mod html;
+pub mod pdf;
+pub mod table;
+
+use anyhow::{Context, Result, bail, ensure};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::grounding::{BoundingBox, parse_grounding, strip_grounding, strip_grounding_tokens};
+
+/// Name written into the producer fields of exported documents.
+pub const SOFTWARE_NAME: &str = "deepseek-ocr.rs";
+
+/// Grounding labels that mark pictures rather than text.
+const PICTURE_LABELS: [&str; 2] = ["image", "figure"];
+
+/// How OCR output is written: the model's own text, a structured document, an archival layout
+/// document or a searchable PDF.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Decoded text as streamed: grounding special tokens dropped, labels and boxes kept.
+    #[default]
+    Text,
+    /// Plain text of the structured document (markup removed).
+    Plain,
+    /// HTML rendering of the structured document.
+    Html,
+    /// The structured document as JSON (see [`document::Document`]).
+    Json,
+    /// hOCR 1.2 HTML.
+    Hocr,
+    /// ALTO 4 XML.
+    Alto,
+    /// Page images with an invisible text layer (binary; see [`pdf::render`]).
+    Pdf,
+}
+
+impl OutputFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Plain => "plain",
+            OutputFormat::Html => "html",
+            OutputFormat::Json => "json",
+            OutputFormat::Hocr => "hocr",
+            OutputFormat::Alto => "alto",
+            OutputFormat::Pdf => "pdf",
+        }
+    }
+
+    /// Whether this format needs page geometry (and therefore a single image per page).
+    pub fn is_layout(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Hocr | OutputFormat::Alto | OutputFormat::Pdf
+        )
+    }
+
+    /// Whether this format is binary and also needs the page images.
+    pub fn is_binary(self) -> bool {
+        self == OutputFormat::Pdf
+    }
+
+    /// Render decoded `pages` (grounding markup kept) in this format; `Text` joins the page
+    /// outputs with blank lines. Layout formats require every page size; binary formats are rejected.
+    pub fn render(self, pages: &[PageOutput<'_>]) -> Result<String> {
+        if self.is_layout() {
+            ensure!(
+                pages.iter().all(|page| page.size.is_some()),
+                "format `{}` needs exactly one image per page",
+                self.name()
+            );
+        }
+        let layouts = || -> Vec<PageLayout> {
+            pages
+                .iter()
+                .filter_map(|page| {
+                    let (width, height) = page.size?;
+                    Some(PageLayout::from_output(page.text, width, height))
+                })
+                .collect()
+        };
+        Ok(match self {
+            OutputFormat::Text => pages
+                .iter()
+                .map(|page| strip_grounding_tokens(page.text))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            OutputFormat::Plain => document::Document::from_pages(pages).plain_text(),
+            OutputFormat::Html => html::render(&document::Document::from_pages(pages)),
+            OutputFormat::Json => {
+                serde_json::to_string_pretty(&document::Document::from_pages(pages))
+                    .context("failed to serialise document")?
+            }
+            OutputFormat::Hocr => hocr::render(&layouts()),
+            OutputFormat::Alto => alto::render(&layouts()),
+            OutputFormat::Pdf => bail!("format `pdf` is binary and needs the page images"),
+        })
+    }
+}
+
+/// Decoded output of one page and the pixel size of its image, when the page has exactly one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageOutput<'a> {
+    pub text: &'a str,
+    pub size: Option<(u32, u32)>,
+}
+
+/// One page of grounded OCR output in the pixel frame of its source image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLayout {
+    pub width: u32,
+    pub height: u32,
+    /// Whether the blocks come from grounding boxes; ungrounded output is one page-sized block.
+    pub grounded: bool,
+    /// Blocks in reading order.
+    pub blocks: Vec<LayoutBlock>,
+}
+
+/// A grounded region and its text with markdown/HTML markup removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutBlock {
+    /// Grounding label (`text`, `title`, `table`, `image`, ...).
+    pub label: String,
+    /// Region in source-image pixels.
+    pub bbox: BoundingBox,
+    pub text: String,
+}
+
+/// A text line of a block with its estimated box and words.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutLine {
+    pub bbox: BoundingBox,
+    pub words: Vec<LayoutWord>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutWord {
+    pub bbox: BoundingBox,
+    pub text: String,
+}
+
+impl PageLayout {
+    /// Build a page from decoded model output for a `width`×`height` image. Each grounded box
+    /// becomes a block (a reference with several boxes repeats its text in the first only).
+    /// Output without grounding becomes a single block covering the whole page.
+    pub fn from_output(text: &str, width: u32, height: u32) -> Self {
+        let spans = parse_grounding(text);
+        let grounded = !spans.is_empty();
+        let blocks = if !grounded {
+            let text = plain_text(&strip_grounding(text));
+            if text.is_empty() {
+                Vec::new()
+            } else {
+                vec![LayoutBlock {
+                    label: "text".to_string(),
+                    bbox: BoundingBox::new(0.0, 0.0, width as f32, height as f32),
+                    text,
+                }]
+            }
+        } else {
+            spans
+                .into_iter()
+                .flat_map(|span| {
+                    let text = plain_text(&span.content);
+                    let label = span.label;
+                    span.boxes
+                        .into_iter()
+                        .enumerate()
+                        .map(move |(idx, bbox)| LayoutBlock {
+                            label: label.clone(),
+                            bbox: bbox.to_pixels(width, height),
+                            text: if idx == 0 {
+                                text.clone()
+                            } else {
+                                String::new()
+                            },
+                        })
+                })
+                .filter(|block| block.bbox.area() > 0.0)
+                .collect()
+        };
+        Self {
+            width,
+            height,
+            grounded,
+            blocks,
+        }
+    }
+
+    /// Text of every block, one block per paragraph.
+    pub fn plain_text(&self) -> String {
+        self.blocks
+            .iter()
+            .filter(|block| !block.text.is_empty())
+            .map(|block| block.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+impl LayoutBlock {
+    pub fn is_picture(&self) -> bool {
+        PICTURE_LABELS.contains(&self.label.as_str())
+    }
+
+    /// Split the block into lines and words. The model only grounds whole blocks, so line boxes
+    /// divide the block height evenly and word boxes divide each line by character count.
+    pub fn lines(&self) -> Vec<LayoutLine> {
+        let lines: Vec<&str> = self
+            .text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let line_height = self.bbox.height() / lines.len().max(1) as f32;
+        lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| {
+                let y1 = self.bbox.y1 + idx as f32 * line_height;
+                let bbox = BoundingBox::new(self.bbox.x1, y1, self.bbox.x2, y1 + line_height);
+                LayoutLine {
+                    bbox,
+                    words: split_words(line, &bbox),
+                }
+            })
+            .collect()
+    }
+}
+
+fn split_words(line: &str, bbox: &BoundingBox) -> Vec<LayoutWord> {
+    let total = line.chars().count().max(1) as f32;
+    let advance = bbox.width() / total;
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut start = 0usize;
+    for (idx, ch) in line.chars().chain(std::iter::once(' ')).enumerate() {
+        if !ch.is_whitespace() {
+            current.push(ch);
+            continue;
+        }
+        if !current.is_empty() {
+            let x1 = bbox.x1 + start as f32 * advance;
+            let x2 = bbox.x1 + idx as f32 * advance;
+            words.push(LayoutWord {
+                bbox: BoundingBox::new(x1, bbox.y1, x2, bbox.y2),
+                text: std::mem::take(&mut current),
+            });
+        }
+        start = idx + 1;
+    }
+    words
+}
+
+/// Reduce block content (markdown, HTML tables, LaTeX) to plain text lines: tags are dropped
+/// (table cells become space separated, rows and `<br>` line breaks), ATX heading markers and
+/// paired `**`/`__` emphasis removed and common entities decoded.
+pub fn plain_text(content: &str) -> String {
+    let mut text = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(open) = rest.find('<') {
+        text.push_str(&rest[..open]);
+        let is_tag = rest[open + 1..]
+            .chars()
+            .next()
+            .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '/');
+        let close = rest[open..].find('>');
+        let Some(close) = close.filter(|_| is_tag) else {
+            text.push('<');
+            rest = &rest[open + 1..];
+            continue;
+        };
+        let tag = rest[open + 1..open + close].trim().to_ascii_lowercase();
+        if tag.starts_with("/tr") || tag.starts_with("br") || tag.starts_with("/p") {
+            text.push('\n');
+        } else if tag.starts_with("/td") || tag.starts_with("/th") {
+            text.push(' ');
+        }
+        rest = &rest[open + close + 1..];
+    }
+    text.push_str(rest);
+
+    text.lines()
+        .map(|line| {
+            let line = line.trim_start();
+            let hashes = line.len() - line.trim_start_matches('#').len();
+            let line = match line[hashes..].strip_prefix([' ', '\t']) {
+                Some(heading) if (1..=6).contains(&hashes) => heading,
+                _ => line,
+            };
+            let line = strip_emphasis(line, "**", |_| false);
+            // `__init__` and the like are identifiers, not bold text.
+            let line = strip_emphasis(&line, "__", |inner| {
+                inner.chars().all(|ch| ch.is_alphanumeric() || ch == '_')
+            });
+            let line = line
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&#39;", "'")
+                .replace("&nbsp;", " ")
+                .replace("&amp;", "&");
+            line.split_whitespace().collect::<Vec<_>>().join(" ")
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Drop `delimiter` pairs wrapped around text (`**bold**`), leaving unpaired delimiters, pairs
+/// with whitespace just inside them and pairs whose content `keep` accepts untouched.
+fn strip_emphasis(line: &str, delimiter: &str, keep: impl Fn(&str) -> bool) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(open) = rest.find(delimiter) {
+        let after = &rest[open + delimiter.len()..];
+        let close = after.find(delimiter).filter(|&close| {
+            let inner = &after[..close];
+            !inner.is_empty()
+                && !inner.starts_with(char::is_whitespace)
+                && !inner.ends_with(char::is_whitespace)
+                && !keep(inner)
+        });
+        match close {
+            Some(close) => {
+                stripped.push_str(&rest[..open]);
+                stripped.push_str(&after[..close]);
+                rest = &after[close + delimiter.len()..];
+            }
+            None => {
+                stripped.push_str(&rest[..open + delimiter.len()]);
+                rest = after;
+            }
+        }
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+/// Escape `&`, `<`, `>`, `"` and `'` for XML text and attribute values.
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than tab/newline are not allowed in XML 1.0.
+            ch if ch.is_control() && ch != '\t' && ch != '\n' => {}
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Integer pixel coordinates `(x1, y1, x2, y2)` of `bbox`.
+fn pixel_rect(bbox: &BoundingBox) -> (i64, i64, i64, i64) {
+    (
+        bbox.x1.floor() as i64,
+        bbox.y1.floor() as i64,
+        bbox.x2.ceil() as i64,
+        bbox.y2.ceil() as i64,
+    )
+}
+
+/// Whether `text` has an opening `<name` (or closing, with `name` = `/name`) tag at byte `at`.
+pub(crate) fn tag_at(text: &str, at: usize, name: &str) -> bool {
+    let rest = &text.as_bytes()[at..];
+    rest.first() == Some(&b'<')
+        && rest.len() > name.len()
+        && rest[1..=name.len()].eq_ignore_ascii_case(name.as_bytes())
+        && rest
+            .get(name.len() + 1)
+            .is_none_or(|&b| b == b'>' || b == b'/' || b.is_ascii_whitespace())
+}
@@ -0,0 +1,451 @@
+use std::{
+    fmt::Write as _,
+    io::{Cursor, Write as _},
+};
+
+use anyhow::{Context, Result, ensure};
+use flate2::{Compression, write::ZlibEncoder};
+use image::{
+    DynamicImage, ImageFormat, RgbImage, codecs::jpeg::JpegEncoder, metadata::Orientation,
+};
+
+use super::{PageLayout, SOFTWARE_NAME};
+use crate::vision::DecodedImage;
+
+/// Advance width of every glyph of the invisible text font, in 1/1000 em.
+const GLYPH_WIDTH: f32 = 500.0;
+
+/// Page sizing and image encoding for [`render`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PdfOptions {
+    /// Resolution the page images are assumed to have; sets the physical page size.
+    pub dpi: f32,
+    /// JPEG quality (1-100) of the embedded page images.
+    pub jpeg_quality: u8,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            dpi: 300.0,
+            jpeg_quality: 92,
+        }
+    }
+}
+
+/// How the page image was stored, which decides how [`render`] embeds it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageSource<'a> {
+    /// The JPEG file the page image was decoded from, unchanged: embedded as is.
+    Jpeg(&'a [u8]),
+    /// Lossy source whose pixels changed: re-encoded as JPEG at [`PdfOptions::jpeg_quality`].
+    Lossy,
+    /// Lossless source (PNG, TIFF, ...): Flate-compressed without loss.
+    Lossless,
+}
+
+impl<'a> ImageSource<'a> {
+    /// Source of `page`, decoded from the file `bytes`. A JPEG is passed through unless
+    /// `modified` or its EXIF orientation was applied, since the file then shows other pixels.
+    pub fn new(bytes: &'a [u8], page: &DecodedImage, modified: bool) -> Self {
+        match image::guess_format(bytes) {
+            Ok(ImageFormat::Jpeg) if !modified && page.orientation == Orientation::NoTransforms => {
+                Self::Jpeg(bytes)
+            }
+            Ok(ImageFormat::Jpeg | ImageFormat::WebP) => Self::Lossy,
+            _ => Self::Lossless,
+        }
+    }
+}
+
+/// One page of [`render`]: the image, the layout drawn over it and how the image is embedded.
+#[derive(Debug, Clone, Copy)]
+pub struct PdfPage<'a> {
+    pub image: &'a DynamicImage,
+    pub layout: &'a PageLayout,
+    pub source: ImageSource<'a>,
+}
+
+/// Write a PDF with one page per image: the image fills the page and the layout's words are
+/// drawn over it in invisible text (render mode 3), so the page looks like the scan but can be
+/// searched and copied. Like Tesseract, the text uses an embedded glyph-less TrueType font with
+/// Identity-H encoding and a ToUnicode map, which works for any script.
+pub fn render(pages: &[PdfPage<'_>], options: &PdfOptions) -> Result<Vec<u8>> {
+    ensure!(options.dpi > 0.0, "PDF resolution must be positive");
+    let mut writer = PdfWriter::default();
+    // Fixed objects: 1 catalog, 2 page tree, 3-8 font, 9 document info.
+    let first_page = 10;
+    let page_ids: Vec<usize> = (0..pages.len()).map(|idx| first_page + idx * 3).collect();
+
+    writer.object(1, "<< /Type /Catalog /Pages 2 0 R >>");
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{id} 0 R")).collect();
+    writer.object(
+        2,
+        &format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        ),
+    );
+    writer.object(
+        3,
+        "<< /Type /Font /Subtype /Type0 /BaseFont /GlyphLessFont /Encoding /Identity-H \
+         /DescendantFonts [4 0 R] /ToUnicode 5 0 R >>",
+    );
+    writer.object(
+        4,
+        &format!(
+            "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /GlyphLessFont \
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+             /FontDescriptor 6 0 R /DW {GLYPH_WIDTH} /CIDToGIDMap 8 0 R >>"
+        ),
+    );
+    writer.stream(5, "", to_unicode_cmap().as_bytes());
+    writer.object(
+        6,
+        "<< /Type /FontDescriptor /FontName /GlyphLessFont /Flags 5 /FontBBox [0 0 500 1000] \
+         /ItalicAngle 0 /Ascent 1000 /Descent 0 /CapHeight 1000 /StemV 80 /FontFile2 7 0 R >>",
+    );
+    let font = glyphless_font();
+    writer.stream(
+        7,
+        &format!("/Length1 {} /Filter /FlateDecode", font.len()),
+        &deflate(&font)?,
+    );
+    // Every CID is drawn with glyph 1.
+    let cid_to_gid = [0u8, 1].repeat(1 << 16);
+    writer.stream(8, "/Filter /FlateDecode", &deflate(&cid_to_gid)?);
+    writer.object(9, &format!("<< /Producer ({SOFTWARE_NAME}) >>"));
+
+    let scale = 72.0 / options.dpi;
+    for (&page_id, page) in page_ids.iter().zip(pages) {
+        let PdfPage { image, layout, .. } = *page;
+        let (dictionary, data) = encode_image(page, options)?;
+        let (width, height) = (image.width() as f32 * scale, image.height() as f32 * scale);
+        // Layout boxes are in the pixel frame of the layout, which may differ from the image.
+        let sx = width / layout.width.max(1) as f32;
+        let sy = height / layout.height.max(1) as f32;
+        let content = page_content(layout, width, height, sx, sy);
+
+        writer.object(
+            page_id,
+            &format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /XObject << /Im0 {} 0 R >> /Font << /F0 3 0 R >> >> \
+                 /Contents {} 0 R >>",
+                number(width),
+                number(height),
+                page_id + 1,
+                page_id + 2
+            ),
+        );
+        writer.stream(
+            page_id + 1,
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} {dictionary}",
+                image.width(),
+                image.height()
+            ),
+            &data,
+        );
+        writer.stream(page_id + 2, "", content.as_bytes());
+    }
+    Ok(writer.finish(1, 9))
+}
+
+/// Image XObject entries (colour space, depth, filter) and data for the page image. A JPEG
+/// source is only passed through when its frame matches the decoded image.
+fn encode_image(page: &PdfPage<'_>, options: &PdfOptions) -> Result<(String, Vec<u8>)> {
+    let image = page.image;
+    if let ImageSource::Jpeg(bytes) = page.source {
+        let size = (image.width(), image.height());
+        let colour_space = match jpeg_frame(bytes) {
+            Some((width, height, 1)) if (width, height) == size => Some("DeviceGray"),
+            Some((width, height, 3)) if (width, height) == size => Some("DeviceRGB"),
+            _ => None,
+        };
+        if let Some(colour_space) = colour_space {
+            return Ok((
+                format!("/ColorSpace /{colour_space} /BitsPerComponent 8 /Filter /DCTDecode"),
+                bytes.to_vec(),
+            ));
+        }
+    }
+    let rgb = image.to_rgb8();
+    if page.source == ImageSource::Lossless {
+        let (colour_space, samples) = match gray_samples(&rgb) {
+            Some(gray) => ("DeviceGray", gray),
+            None => ("DeviceRGB", rgb.into_raw()),
+        };
+        return Ok((
+            format!("/ColorSpace /{colour_space} /BitsPerComponent 8 /Filter /FlateDecode"),
+            deflate(&samples)?,
+        ));
+    }
+    let mut jpeg = Cursor::new(Vec::new());
+    JpegEncoder::new_with_quality(&mut jpeg, options.jpeg_quality.clamp(1, 100))
+        .encode_image(&rgb)
+        .context("failed to encode page image")?;
+    Ok((
+        "/ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode".to_string(),
+        jpeg.into_inner(),
+    ))
+}
+
+/// Single-channel samples when every pixel is gray, which is a third of the data for scans.
+fn gray_samples(rgb: &RgbImage) -> Option<Vec<u8>> {
+    rgb.pixels()
+        .map(|pixel| {
+            let [r, g, b] = pixel.0;
+            (r == g && g == b).then_some(r)
+        })
+        .collect()
+}
+
+/// Width, height and component count of an 8-bit baseline, extended or progressive JPEG
+/// (the Huffman-coded frames every PDF reader decodes).
+fn jpeg_frame(bytes: &[u8]) -> Option<(u32, u32, u8)> {
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut pos = 2;
+    loop {
+        if *bytes.get(pos)? != 0xFF {
+            return None;
+        }
+        while *bytes.get(pos)? == 0xFF {
+            pos += 1;
+        }
+        let marker = bytes[pos];
+        pos += 1;
+        if matches!(marker, 0x01 | 0xD0..=0xD7) {
+            continue;
+        }
+        let length = u16::from_be_bytes([*bytes.get(pos)?, *bytes.get(pos + 1)?]) as usize;
+        match marker {
+            0xC0..=0xC2 => {
+                let frame = bytes.get(pos + 2..pos + 8)?;
+                let height = u16::from_be_bytes([frame[1], frame[2]]);
+                let width = u16::from_be_bytes([frame[3], frame[4]]);
+                return (frame[0] == 8).then_some((width.into(), height.into(), frame[5]));
+            }
+            // Any other frame type, or scan data before a frame header.
+            0xC3..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => return None,
+            0xD9 | 0xDA => return None,
+            _ => pos += length,
+        }
+    }
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish().context("failed to compress PDF stream")
+}
+
+/// TrueType program with an empty `.notdef` and one empty glyph that every CID maps to, like
+/// Tesseract's `GlyphLessFont`: viewers can lay out and select the text without drawing it.
+/// Only the tables PDF requires of an embedded TrueType font are written.
+fn glyphless_font() -> Vec<u8> {
+    let shorts = |values: &[i16]| -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    };
+    let glyph_width = GLYPH_WIDTH as i16;
+    // Version and font revision 1.0, checksum adjustment (patched below), magic number.
+    let mut head: Vec<u8> = [0x0001_0000u32, 0x0001_0000, 0, 0x5F0F_3CF5]
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect();
+    head.extend(shorts(&[0, 1000])); // flags, units per em
+    head.extend([0; 16]); // created, modified
+    // Bounding box, mac style, smallest readable size, direction hint, short loca, glyf format.
+    head.extend(shorts(&[0, 0, glyph_width, 1000, 0, 8, 2, 0, 0]));
+    let mut hhea = 0x0001_0000u32.to_be_bytes().to_vec();
+    // Ascender, descender, line gap, widest advance, side bearings, extent, caret slope and
+    // offset, four reserved fields, metric format, horizontal metric count.
+    hhea.extend(shorts(&[
+        1000,
+        0,
+        0,
+        glyph_width,
+        0,
+        0,
+        glyph_width,
+        1,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        2,
+    ]));
+    let mut maxp = 0x0001_0000u32.to_be_bytes().to_vec();
+    // Two glyphs without points, contours or instructions; one zone besides the twilight zone.
+    maxp.extend(shorts(&[2, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0]));
+    let tables: [(&[u8; 4], Vec<u8>); 6] = [
+        (b"glyf", Vec::new()),
+        (b"head", head),
+        (b"hhea", hhea),
+        (b"hmtx", shorts(&[glyph_width, 0, glyph_width, 0])),
+        (b"loca", shorts(&[0, 0, 0])),
+        (b"maxp", maxp),
+    ];
+
+    let directory_len = 12 + 16 * tables.len();
+    let mut font = 0x0001_0000u32.to_be_bytes().to_vec();
+    // Table count, search range, entry selector, range shift.
+    font.extend(shorts(&[6, 64, 2, 32]));
+    let mut data = Vec::new();
+    let mut head_offset = 0;
+    for (tag, table) in &tables {
+        let offset = directory_len + data.len();
+        if *tag == b"head" {
+            head_offset = offset;
+        }
+        font.extend(*tag);
+        font.extend(checksum(table).to_be_bytes());
+        font.extend((offset as u32).to_be_bytes());
+        font.extend((table.len() as u32).to_be_bytes());
+        data.extend(table);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+    font.extend(data);
+    let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&font));
+    font[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    font
+}
+
+/// TrueType checksum: the wrapping sum of the big-endian 32-bit words, zero-padded.
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// Page image followed by one invisible text object per word.
+fn page_content(layout: &PageLayout, width: f32, height: f32, sx: f32, sy: f32) -> String {
+    let mut content = format!(
+        "q {} 0 0 {} 0 0 cm /Im0 Do Q\n",
+        number(width),
+        number(height)
+    );
+    content.push_str("BT 3 Tr\n");
+    for block in layout.blocks.iter().filter(|block| !block.is_picture()) {
+        for line in block.lines() {
+            let size = line.bbox.height() * sy;
+            if size <= 0.0 {
+                continue;
+            }
+            for word in &line.words {
+                let units: Vec<u16> = word.text.encode_utf16().collect();
+                let natural = units.len() as f32 * GLYPH_WIDTH / 1000.0 * size;
+                let target = word.bbox.width() * sx;
+                if units.is_empty() || natural <= 0.0 || target <= 0.0 {
+                    continue;
+                }
+                let hex: String = units.iter().map(|unit| format!("{unit:04X}")).collect();
+                let _ = writeln!(
+                    content,
+                    "/F0 {} Tf {} Tz 1 0 0 1 {} {} Tm <{hex}> Tj",
+                    number(size),
+                    number(100.0 * target / natural),
+                    number(word.bbox.x1 * sx),
+                    number(height - word.bbox.y2 * sy)
+                );
+            }
+        }
+    }
+    content.push_str("ET\n");
+    content
+}
+
+/// Identity map from 2-byte codes (UTF-16 code units) to Unicode.
+fn to_unicode_cmap() -> String {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    // bfrange entries may not cross a change in the high byte, so map in 256 ranges of 256.
+    for chunk in (0..=255u16).collect::<Vec<_>>().chunks(100) {
+        let _ = writeln!(cmap, "{} beginbfrange", chunk.len());
+        for high in chunk {
+            let _ = writeln!(cmap, "<{high:02X}00> <{high:02X}FF> <{high:02X}00>");
+        }
+        cmap.push_str("endbfrange\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
+
+/// Compact decimal for content streams (PDF has no exponent notation).
+fn number(value: f32) -> String {
+    let text = format!("{value:.3}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Minimal PDF 1.4 serialiser: numbered objects written in any order plus the xref table.
+#[derive(Default)]
+struct PdfWriter {
+    body: Vec<u8>,
+    offsets: Vec<(usize, usize)>,
+}
+
+impl PdfWriter {
+    fn object(&mut self, id: usize, dictionary: &str) {
+        self.begin(id);
+        self.body.extend_from_slice(dictionary.as_bytes());
+        self.body.extend_from_slice(b"\nendobj\n");
+    }
+
+    fn stream(&mut self, id: usize, entries: &str, data: &[u8]) {
+        self.begin(id);
+        let separator = if entries.is_empty() { "" } else { " " };
+        self.body.extend_from_slice(
+            format!("<< {entries}{separator}/Length {} >>\nstream\n", data.len()).as_bytes(),
+        );
+        self.body.extend_from_slice(data);
+        self.body.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    fn begin(&mut self, id: usize) {
+        self.offsets.push((id, HEADER.len() + self.body.len()));
+        self.body
+            .extend_from_slice(format!("{id} 0 obj\n").as_bytes());
+    }
+
+    fn finish(mut self, root: usize, info: usize) -> Vec<u8> {
+        self.offsets.sort_unstable();
+        let size = self.offsets.last().map_or(0, |(id, _)| id + 1);
+        let mut output = HEADER.to_vec();
+        output.append(&mut self.body);
+        let xref = output.len();
+        let mut table = format!("xref\n0 {size}\n0000000000 65535 f \n");
+        let mut entries = self.offsets.iter().peekable();
+        for id in 1..size {
+            match entries.next_if(|(entry, _)| *entry == id) {
+                Some((_, offset)) => {
+                    let _ = writeln!(table, "{offset:010} 00000 n ");
+                }
+                None => table.push_str("0000000000 65535 f \n"),
+            }
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {size} /Root {root} 0 R /Info {info} 0 R >>\n\
+             startxref\n{xref}\n%%EOF\n"
+        );
+        output.extend_from_slice(table.as_bytes());
+        output
+    }
+}
+
+const HEADER: &[u8] = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n";
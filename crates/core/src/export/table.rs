@@ -0,0 +1,231 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    document::{BlockKind, Document},
+    plain_text, tag_at,
+};
+use crate::grounding::BoundingBox;
+
+/// Upper bound on a single `rowspan`/`colspan`, so a malformed attribute cannot blow up the grid.
+pub const MAX_SPAN: usize = 100;
+
+/// File format of exported tables.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableFormat {
+    /// RFC 4180 CSV, one line per row.
+    #[default]
+    Csv,
+    /// JSON array of rows, each an array of cell texts.
+    Json,
+}
+
+impl TableFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            TableFormat::Csv => "csv",
+            TableFormat::Json => "json",
+        }
+    }
+}
+
+/// A table found in OCR output, normalised to a rectangular grid: every row has the same number
+/// of cells, and a cell spanning several rows or columns repeats its text in each position.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Table {
+    /// 1-based page the table was found on.
+    pub page: usize,
+    pub rows: Vec<Vec<String>>,
+    /// Grounded region in page pixels, when known (see [`Block::bbox`](super::document::Block)).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<BoundingBox>,
+}
+
+impl Table {
+    /// Every non-empty table of `document`, in reading order.
+    pub fn extract(document: &Document) -> Vec<Table> {
+        document
+            .pages
+            .iter()
+            .enumerate()
+            .flat_map(|(page_idx, page)| {
+                page.blocks
+                    .iter()
+                    .filter_map(move |block| match &block.kind {
+                        BlockKind::Table { rows } if !rows.is_empty() => Some(Table {
+                            page: page_idx + 1,
+                            rows: rows.clone(),
+                            bbox: block.bbox,
+                        }),
+                        _ => None,
+                    })
+            })
+            .collect()
+    }
+
+    pub fn columns(&self) -> usize {
+        self.rows.first().map_or(0, Vec::len)
+    }
+
+    pub fn render(&self, format: TableFormat) -> Result<String> {
+        match format {
+            TableFormat::Csv => Ok(self.to_csv()),
+            TableFormat::Json => {
+                serde_json::to_string_pretty(&self.rows).context("failed to serialise table")
+            }
+        }
+    }
+
+    /// CSV with CRLF line endings; fields containing commas, quotes or line breaks are quoted.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        for row in &self.rows {
+            let fields: Vec<String> = row.iter().map(|cell| csv_field(cell)).collect();
+            csv.push_str(&fields.join(","));
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+}
+
+fn csv_field(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Cell grid of an HTML table, with `rowspan`/`colspan` expanded and short rows padded.
+pub fn parse_html_table(html: &str) -> Vec<Vec<String>> {
+    let mut rows: Vec<Vec<HtmlCell>> = Vec::new();
+    // Content start, rowspan and colspan of the cell being read.
+    let mut open: Option<(usize, usize, usize)> = None;
+    for (at, _) in html.match_indices('<') {
+        let is_row = tag_at(html, at, "tr");
+        let is_cell = tag_at(html, at, "td") || tag_at(html, at, "th");
+        let ends_cell = ["/td", "/th", "/tr", "/table"]
+            .iter()
+            .any(|name| tag_at(html, at, name));
+        if is_row || is_cell || ends_cell {
+            close_cell(&mut rows, open.take(), html, at);
+        }
+        if is_row {
+            rows.push(Vec::new());
+        } else if is_cell {
+            if rows.is_empty() {
+                rows.push(Vec::new());
+            }
+            open = html[at..].find('>').map(|end| {
+                let tag = &html[at..at + end];
+                (at + end + 1, span(tag, "rowspan"), span(tag, "colspan"))
+            });
+        }
+    }
+    close_cell(&mut rows, open, html, html.len());
+    rows.retain(|row| !row.is_empty());
+    place_cells(rows)
+}
+
+/// Cell grid of a markdown pipe table given its lines; the `|---|` separator row is dropped.
+pub fn parse_pipe_table(lines: &[&str]) -> Vec<Vec<String>> {
+    let rows: Vec<Vec<String>> = lines
+        .iter()
+        .map(|line| {
+            let line = line.trim();
+            let inner = line.strip_prefix('|').unwrap_or(line);
+            let inner = inner.strip_suffix('|').unwrap_or(inner);
+            inner
+                .split('|')
+                .map(|cell| plain_text(cell.trim()))
+                .collect::<Vec<_>>()
+        })
+        .filter(|row| !is_separator_row(row))
+        .collect();
+    pad_rows(rows)
+}
+
+struct HtmlCell {
+    text: String,
+    rowspan: usize,
+    colspan: usize,
+}
+
+/// Append the cell opened at `open` (content start, rowspan, colspan) and ending at `end`.
+fn close_cell(
+    rows: &mut [Vec<HtmlCell>],
+    open: Option<(usize, usize, usize)>,
+    html: &str,
+    end: usize,
+) {
+    if let (Some((start, rowspan, colspan)), Some(row)) = (open, rows.last_mut()) {
+        row.push(HtmlCell {
+            text: plain_text(&html[start..end]).replace('\n', " "),
+            rowspan,
+            colspan,
+        });
+    }
+}
+
+/// Lay cells out row by row, skipping positions already covered by a `rowspan` from above.
+fn place_cells(rows: Vec<Vec<HtmlCell>>) -> Vec<Vec<String>> {
+    let height = rows.len();
+    let mut grid: Vec<Vec<Option<String>>> = vec![Vec::new(); height];
+    for (row_idx, cells) in rows.into_iter().enumerate() {
+        let mut col = 0;
+        for cell in cells {
+            while grid[row_idx].get(col).is_some_and(Option::is_some) {
+                col += 1;
+            }
+            let last_row = (row_idx + cell.rowspan).min(height);
+            for row in &mut grid[row_idx..last_row] {
+                if row.len() < col + cell.colspan {
+                    row.resize(col + cell.colspan, None);
+                }
+                for slot in &mut row[col..col + cell.colspan] {
+                    *slot = Some(cell.text.clone());
+                }
+            }
+            col += cell.colspan;
+        }
+    }
+    pad_rows(
+        grid.into_iter()
+            .map(|row| row.into_iter().map(Option::unwrap_or_default).collect())
+            .collect(),
+    )
+}
+
+fn pad_rows(mut rows: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    for row in &mut rows {
+        row.resize(width, String::new());
+    }
+    rows
+}
+
+fn is_separator_row(row: &[String]) -> bool {
+    row.iter()
+        .all(|cell| !cell.is_empty() && cell.chars().all(|ch| matches!(ch, '-' | ':' | ' ')))
+}
+
+/// Value of a `rowspan`/`colspan` attribute in an opening tag, 1 when absent or invalid.
+pub(crate) fn span(tag: &str, name: &str) -> usize {
+    let lower = tag.to_ascii_lowercase();
+    let Some(at) = lower.find(name) else {
+        return 1;
+    };
+    let rest = lower[at + name.len()..].trim_start();
+    let Some(value) = rest.strip_prefix('=') else {
+        return 1;
+    };
+    let digits: String = value
+        .trim_start()
+        .trim_start_matches(['"', '\''])
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().unwrap_or(1).clamp(1, MAX_SPAN)
+}
@@ -0,0 +1,553 @@
+use anyhow::{Result, bail, ensure};
+use clap::ValueEnum;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    engine::OcrEngine,
+    export::{PageLayout, plain_text},
+    grounding::{BoundingBox, strip_grounding},
+    model::FinishReason,
+    redact::Redactor,
+    task::resolve_task,
+};
+
+/// Type an extracted value is coerced to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    #[default]
+    String,
+    Number,
+    Integer,
+    Boolean,
+    /// Calendar date, normalised to `YYYY-MM-DD` when the answer is unambiguous.
+    Date,
+}
+
+impl FieldType {
+    pub fn name(self) -> &'static str {
+        match self {
+            FieldType::String => "string",
+            FieldType::Number => "number",
+            FieldType::Integer => "integer",
+            FieldType::Boolean => "boolean",
+            FieldType::Date => "date",
+        }
+    }
+}
+
+/// One field of an [`ExtractionSchema`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldSpec {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub kind: FieldType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl FieldSpec {
+    /// Parse `name` or `name:type` (e.g. `total:number`).
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (name, kind) = match spec.split_once(':') {
+            Some((name, kind)) => match FieldType::from_str(kind.trim(), true) {
+                Ok(kind) => (name, kind),
+                Err(_) => bail!(
+                    "unknown field type `{}` in `{spec}` (expected string, number, integer, boolean or date)",
+                    kind.trim()
+                ),
+            },
+            None => (spec, FieldType::String),
+        };
+        let name = name.trim();
+        ensure!(!name.is_empty(), "field name must not be empty in `{spec}`");
+        Ok(Self {
+            name: name.to_string(),
+            kind,
+            description: None,
+        })
+    }
+}
+
+/// Fields to extract from a form or invoice, in answer order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtractionSchema {
+    pub fields: Vec<FieldSpec>,
+}
+
+impl ExtractionSchema {
+    /// Build a schema from `name[:type]` specs.
+    pub fn from_fields<S: AsRef<str>>(specs: &[S]) -> Result<Self> {
+        let fields = specs
+            .iter()
+            .map(|spec| FieldSpec::parse(spec.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(fields)
+    }
+
+    /// Build a schema from a JSON Schema object (`{"properties": {"total": {"type": "number",
+    /// "description": ...}}}`; a `"format": "date"` string becomes a date) or from an array of
+    /// field objects (`[{"name": "total", "type": "number"}]`). JSON Schema properties are taken
+    /// in key order.
+    pub fn from_json(schema: &Value) -> Result<Self> {
+        if let Value::Array(items) = schema {
+            let fields = items
+                .iter()
+                .map(|item| match item {
+                    Value::String(spec) => FieldSpec::parse(spec),
+                    _ => Ok(serde_json::from_value(item.clone())?),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            return Self::new(fields);
+        }
+        let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+            bail!("schema must be a JSON Schema object with `properties` or an array of fields");
+        };
+        let fields = properties
+            .iter()
+            .map(|(name, property)| {
+                let kind = match (
+                    property.get("type").and_then(Value::as_str),
+                    property.get("format").and_then(Value::as_str),
+                ) {
+                    (Some("string") | None, Some("date" | "date-time")) => FieldType::Date,
+                    (Some("string") | None, _) => FieldType::String,
+                    (Some("number"), _) => FieldType::Number,
+                    (Some("integer"), _) => FieldType::Integer,
+                    (Some("boolean"), _) => FieldType::Boolean,
+                    (Some(other), _) => bail!("unsupported type `{other}` for field `{name}`"),
+                };
+                Ok(FieldSpec {
+                    name: name.clone(),
+                    kind,
+                    description: property
+                        .get("description")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(fields)
+    }
+
+    fn new(fields: Vec<FieldSpec>) -> Result<Self> {
+        ensure!(!fields.is_empty(), "at least one field is required");
+        for (idx, field) in fields.iter().enumerate() {
+            ensure!(
+                !fields[..idx].iter().any(|other| other.name == field.name),
+                "field `{}` is listed twice",
+                field.name
+            );
+        }
+        Ok(Self { fields })
+    }
+
+    /// Field list filled into the `extract` task's `{query}`, one `- name (type): description`
+    /// line per field.
+    pub fn query(&self) -> String {
+        self.fields
+            .iter()
+            .map(|field| {
+                let mut line = format!("- {} ({})", field.name, field.kind.name());
+                if let Some(description) = &field.description {
+                    line.push_str(": ");
+                    line.push_str(description);
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Read the fields from a model answer. The first JSON object in the answer is used when
+    /// there is one; `Key: value` lines and two-cell table rows fill the remaining fields. Keys
+    /// match field names ignoring case, spaces and punctuation. Values that cannot be coerced to
+    /// the field type become `null` but keep their text.
+    pub fn parse_answer(&self, answer: &str) -> Vec<ExtractedField> {
+        let answer = strip_grounding(answer);
+        let object = json_object(&answer).unwrap_or_default();
+        let pairs = key_value_pairs(&answer);
+        self.fields
+            .iter()
+            .map(|field| {
+                let text = object
+                    .iter()
+                    .find(|(key, _)| matches_field(key, field))
+                    .and_then(|(_, value)| value_text(value))
+                    .or_else(|| lookup_pair(&pairs, field));
+                ExtractedField::new(field, text)
+            })
+            .collect()
+    }
+
+    /// Fill fields still missing from `fields` with `Key: value` lines of plain OCR text (e.g.
+    /// the grounding pass, when the answer skipped a field).
+    pub fn fill_missing(&self, fields: &mut [ExtractedField], text: &str) {
+        let pairs = key_value_pairs(text);
+        for (field, spec) in fields.iter_mut().zip(&self.fields) {
+            if field.text.is_none() {
+                *field = ExtractedField::new(spec, lookup_pair(&pairs, spec));
+            }
+        }
+    }
+}
+
+/// A field value read from the answer.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExtractedField {
+    pub name: String,
+    /// Value coerced to the field type; `null` when missing or not coercible.
+    pub value: Value,
+    /// Answer text the value was read from.
+    pub text: Option<String>,
+    /// Supporting box in image pixels, when grounding located the text.
+    pub bbox: Option<BoundingBox>,
+}
+
+impl ExtractedField {
+    fn new(spec: &FieldSpec, text: Option<String>) -> Self {
+        let value = text
+            .as_deref()
+            .and_then(|text| coerce(text, spec.kind))
+            .unwrap_or(Value::Null);
+        Self {
+            name: spec.name.clone(),
+            value,
+            text,
+            bbox: None,
+        }
+    }
+}
+
+/// Options for [`extract_fields`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractOptions {
+    /// Run a grounding OCR pass to find the supporting box of each value (and to fill fields
+    /// the answer left out).
+    pub boxes: bool,
+    /// Token budget of the extraction answer; falls back to the `extract` task budget.
+    pub max_new_tokens: Option<usize>,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            boxes: true,
+            max_new_tokens: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Extraction {
+    pub fields: Vec<ExtractedField>,
+    /// Raw answer to the extraction prompt.
+    pub answer: String,
+    pub width: u32,
+    pub height: u32,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    /// Most severe finish reason over the extraction and grounding runs.
+    pub finish_reason: FinishReason,
+}
+
+impl Extraction {
+    /// `field name -> value` object in schema order.
+    pub fn data(&self) -> Map<String, Value> {
+        self.fields
+            .iter()
+            .map(|field| (field.name.clone(), field.value.clone()))
+            .collect()
+    }
+
+    /// Replace personal data in the answer and in each field's text and value. A number or
+    /// other non-string value holding a match becomes the redacted string.
+    pub fn redact(&mut self, redactor: &Redactor) {
+        self.answer = redactor.redact_text(&self.answer);
+        for field in &mut self.fields {
+            field.text = field.text.as_deref().map(|text| redactor.redact_text(text));
+            redact_value(&mut field.value, redactor);
+        }
+    }
+}
+
+fn redact_value(value: &mut Value, redactor: &Redactor) {
+    match value {
+        Value::String(text) => *text = redactor.redact_text(text),
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| redact_value(item, redactor)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|item| redact_value(item, redactor)),
+        Value::Number(_) | Value::Bool(_) => {
+            let text = value.to_string();
+            let redacted = redactor.redact_text(&text);
+            if redacted != text {
+                *value = Value::String(redacted);
+            }
+        }
+        Value::Null => {}
+    }
+}
+
+/// Extract `schema` from a single form or invoice image with the `extract` task, then (with
+/// [`ExtractOptions::boxes`]) locate each value in a grounding pass over the same image.
+pub fn extract_fields(
+    engine: &OcrEngine,
+    image: &DynamicImage,
+    schema: &ExtractionSchema,
+    options: &ExtractOptions,
+) -> Result<Extraction> {
+    let mut task = resolve_task("extract")?;
+    if options.max_new_tokens.is_some() {
+        task.max_new_tokens = options.max_new_tokens;
+    }
+    let answer = engine.ocr_task(image, &task, Some(&schema.query()))?;
+    let mut fields = schema.parse_answer(&answer.text);
+    let mut extraction = Extraction {
+        fields: Vec::new(),
+        width: image.width(),
+        height: image.height(),
+        prompt_tokens: answer.prompt_tokens,
+        completion_tokens: answer.completion_tokens(),
+        finish_reason: answer.finish_reason,
+        answer: answer.text,
+    };
+    if options.boxes {
+        let grounding = engine.ocr_task(image, &resolve_task("grounding")?, None)?;
+        extraction.prompt_tokens += grounding.prompt_tokens;
+        extraction.completion_tokens += grounding.completion_tokens();
+        extraction.finish_reason = extraction.finish_reason.max(grounding.finish_reason);
+        let layout =
+            PageLayout::from_output(&grounding.grounded_text(), image.width(), image.height());
+        schema.fill_missing(&mut fields, &layout.plain_text());
+        for field in &mut fields {
+            field.bbox = field
+                .text
+                .as_deref()
+                .and_then(|text| locate_value(&layout, text));
+        }
+    }
+    extraction.fields = fields;
+    Ok(extraction)
+}
+
+/// Box of `value` in `layout`: the words of the first line containing it, else the first block
+/// containing it.
+pub fn locate_value(layout: &PageLayout, value: &str) -> Option<BoundingBox> {
+    let needle: Vec<String> = value.split_whitespace().map(normalize_word).collect();
+    let needle: Vec<String> = needle.into_iter().filter(|word| !word.is_empty()).collect();
+    if needle.is_empty() {
+        return None;
+    }
+    for block in &layout.blocks {
+        for line in block.lines() {
+            let words: Vec<String> = line
+                .words
+                .iter()
+                .map(|word| normalize_word(&word.text))
+                .collect();
+            let Some(start) = words
+                .windows(needle.len())
+                .position(|window| window == needle.as_slice())
+            else {
+                continue;
+            };
+            let covered = &line.words[start..start + needle.len()];
+            return Some(BoundingBox::new(
+                covered[0].bbox.x1,
+                line.bbox.y1,
+                covered[covered.len() - 1].bbox.x2,
+                line.bbox.y2,
+            ));
+        }
+    }
+    let joined = needle.join(" ");
+    layout
+        .blocks
+        .iter()
+        .find(|block| {
+            let words: Vec<String> = block.text.split_whitespace().map(normalize_word).collect();
+            words.join(" ").contains(&joined)
+        })
+        .map(|block| block.bbox)
+}
+
+fn normalize_word(word: &str) -> String {
+    word.trim_matches(|ch: char| !ch.is_alphanumeric())
+        .to_lowercase()
+}
+
+fn normalize_key(key: &str) -> String {
+    key.chars()
+        .filter(|ch| ch.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn matches_field(key: &str, field: &FieldSpec) -> bool {
+    let key = normalize_key(key);
+    !key.is_empty()
+        && (key == normalize_key(&field.name)
+            || field
+                .description
+                .as_deref()
+                .is_some_and(|description| key == normalize_key(description)))
+}
+
+/// The first balanced `{...}` in `text` that parses as a JSON object.
+fn json_object(text: &str) -> Option<Map<String, Value>> {
+    let mut search = 0;
+    while let Some(offset) = text[search..].find('{') {
+        let start = search + offset;
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        for (idx, ch) in text[start..].char_indices() {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' if in_string => escaped = true,
+                '"' => in_string = !in_string,
+                '{' if !in_string => depth += 1,
+                '}' if !in_string => {
+                    depth -= 1;
+                    if depth == 0 {
+                        if let Ok(Value::Object(map)) =
+                            serde_json::from_str(&text[start..=start + idx])
+                        {
+                            return Some(map);
+                        }
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        search = start + 1;
+    }
+    None
+}
+
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(text) => Some(text.trim().to_string()).filter(|text| !text.is_empty()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// `Key: value` lines and two-cell table rows of `text`.
+fn key_value_pairs(text: &str) -> Vec<(String, String)> {
+    plain_text(text)
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if let Some(row) = line.strip_prefix('|') {
+                let cells: Vec<&str> = row
+                    .trim_end_matches('|')
+                    .split('|')
+                    .map(str::trim)
+                    .collect();
+                return match cells.as_slice() {
+                    [key, value] => Some((key.to_string(), value.to_string())),
+                    _ => None,
+                };
+            }
+            let (key, value) = line.split_once(':')?;
+            let value = value.trim().trim_end_matches(',').trim_matches('"');
+            let key = key.trim().trim_start_matches('-').trim().trim_matches('"');
+            (!value.is_empty()).then(|| (key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn lookup_pair(pairs: &[(String, String)], field: &FieldSpec) -> Option<String> {
+    pairs
+        .iter()
+        .find(|(key, _)| matches_field(key, field))
+        .map(|(_, value)| value.clone())
+        .filter(|value| !matches!(value.as_str(), "null" | "N/A" | "n/a" | "-"))
+}
+
+/// Coerce answer text to `kind`; `None` when it does not fit.
+pub fn coerce(text: &str, kind: FieldType) -> Option<Value> {
+    let text = text.trim();
+    match kind {
+        FieldType::String => Some(Value::String(text.to_string())),
+        FieldType::Number => {
+            parse_number(text).and_then(|n| serde_json::Number::from_f64(n).map(Value::Number))
+        }
+        FieldType::Integer => parse_number(text)
+            .filter(|n| n.fract() == 0.0 && n.abs() < 9.0e15)
+            .map(|n| Value::from(n as i64)),
+        FieldType::Boolean => match text.to_lowercase().as_str() {
+            "true" | "yes" | "y" | "x" | "[x]" | "☑" | "☒" | "checked" => {
+                Some(Value::Bool(true))
+            }
+            "false" | "no" | "n" | "[ ]" | "☐" | "unchecked" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        FieldType::Date => Some(Value::String(
+            normalize_date(text).unwrap_or_else(|| text.to_string()),
+        )),
+    }
+}
+
+/// Parse amounts such as `$1,234.50`, `1.234,50 €` or `(12.00)`.
+fn parse_number(text: &str) -> Option<f64> {
+    let negative = text.starts_with('-') || (text.starts_with('(') && text.ends_with(')'));
+    let digits: String = text
+        .chars()
+        .filter(|ch| ch.is_ascii_digit() || matches!(ch, '.' | ','))
+        .collect();
+    if !digits.chars().any(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
+    // Separators only group thousands when every group after the first has exactly three
+    // digits; otherwise, or when both kinds appear, the last one is the decimal point. A lone
+    // `.` is always a decimal point (`3.141`), a lone `,` before three digits groups (`1,234`).
+    let last = digits.rfind(['.', ',']);
+    let decimal = last.filter(|&idx| {
+        let both = digits.contains('.') && digits.contains(',');
+        let grouped = digits
+            .split(['.', ','])
+            .skip(1)
+            .all(|group| group.len() == 3);
+        let lone_point = digits.matches(['.', ',']).count() == 1 && digits[idx..].starts_with('.');
+        both || !grouped || lone_point
+    });
+    let normalized: String = digits
+        .char_indices()
+        .filter_map(|(idx, ch)| match ch {
+            '.' | ',' if Some(idx) == decimal => Some('.'),
+            '.' | ',' => None,
+            _ => Some(ch),
+        })
+        .collect();
+    let value: f64 = normalized.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// `YYYY-MM-DD` for ISO dates and `DD.MM.YYYY`/`DD/MM/YYYY` dates whose day is above 12 (or
+/// whose month is, for `MM/DD/YYYY`).
+fn normalize_date(text: &str) -> Option<String> {
+    let parts: Vec<u32> = text
+        .split(['-', '/', '.'])
+        .map(|part| part.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    let (year, month, day) = match *parts.as_slice() {
+        [year, month, day] if year >= 1000 => (year, month, day),
+        [a, b, year] if year >= 1000 => match (a > 12, b > 12) {
+            (true, false) => (year, b, a),
+            (false, true) => (year, a, b),
+            _ if a == b => (year, a, b),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    ((1..=12).contains(&month) && (1..=31).contains(&day))
+        .then(|| format!("{year:04}-{month:02}-{day:02}"))
+}
@@ -0,0 +1,204 @@
+use anyhow::Result;
+use image::DynamicImage;
+
+use crate::{
+    engine::OcrEngine,
+    grounding::{BoundingBox, GroundingSpan, parse_grounding, strip_grounding},
+    model::FinishReason,
+    roi::crop_region,
+    task::resolve_task,
+};
+
+/// Controls which grounded blocks are treated as figures and how they are described.
+#[derive(Debug, Clone)]
+pub struct FigureOptions {
+    /// Block labels extracted as figures.
+    pub labels: Vec<String>,
+    /// Pixels of context added around each box before cropping.
+    pub padding: u32,
+    /// Boxes narrower or shorter than this many pixels are skipped as stray marks.
+    pub min_side: u32,
+    /// Maximum number of figures extracted per image.
+    pub max_figures: usize,
+    /// Task preset run on each crop to describe it; `None` skips the descriptions.
+    pub describe_task: Option<String>,
+}
+
+impl Default for FigureOptions {
+    fn default() -> Self {
+        Self {
+            labels: vec![
+                "image".to_string(),
+                "figure".to_string(),
+                "chart".to_string(),
+            ],
+            padding: 4,
+            min_side: 16,
+            max_figures: 32,
+            describe_task: Some("describe".to_string()),
+        }
+    }
+}
+
+/// A figure cropped from the source image.
+#[derive(Debug, Clone)]
+pub struct Figure {
+    /// Index into the grounding spans of the page text.
+    pub index: usize,
+    pub label: String,
+    /// Cropped rectangle in source-image pixels (padding included, clamped to the image).
+    pub region: BoundingBox,
+    pub image: DynamicImage,
+    /// Model description of the crop, when a describe task ran and produced text.
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FigureResult {
+    pub figures: Vec<Figure>,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    /// Most severe finish reason over the describe runs.
+    pub finish_reason: FinishReason,
+}
+
+/// Pick the spans labelled as figures, returned as `(span index, pixel box)`.
+pub fn select_figures(
+    spans: &[GroundingSpan],
+    width: u32,
+    height: u32,
+    options: &FigureOptions,
+) -> Vec<(usize, BoundingBox)> {
+    let min_side = options.min_side as f32;
+    spans
+        .iter()
+        .enumerate()
+        .filter(|(_, span)| {
+            options
+                .labels
+                .iter()
+                .any(|label| label.eq_ignore_ascii_case(&span.label))
+        })
+        .filter_map(|(idx, span)| {
+            let pixel = span
+                .boxes
+                .iter()
+                .map(|bbox| bbox.to_pixels(width, height))
+                .reduce(|a, b| {
+                    BoundingBox::new(
+                        a.x1.min(b.x1),
+                        a.y1.min(b.y1),
+                        a.x2.max(b.x2),
+                        a.y2.max(b.y2),
+                    )
+                })?;
+            (pixel.width() >= min_side && pixel.height() >= min_side).then_some((idx, pixel))
+        })
+        .take(options.max_figures)
+        .collect()
+}
+
+/// Crop every figure of `text` (grounded output for `image`) and describe each crop with the
+/// configured task preset through the same engine.
+pub fn extract_figures(
+    engine: &OcrEngine,
+    text: &str,
+    image: &DynamicImage,
+    options: &FigureOptions,
+) -> Result<FigureResult> {
+    let task = options
+        .describe_task
+        .as_deref()
+        .map(resolve_task)
+        .transpose()?;
+    let spans = parse_grounding(text);
+    let mut result = FigureResult {
+        figures: Vec::new(),
+        prompt_tokens: 0,
+        completion_tokens: 0,
+        finish_reason: FinishReason::Stop,
+    };
+    for (index, region) in select_figures(&spans, image.width(), image.height(), options) {
+        let padding = options.padding as f32;
+        let padded = BoundingBox::new(
+            region.x1 - padding,
+            region.y1 - padding,
+            region.x2 + padding,
+            region.y2 + padding,
+        );
+        let (crop, clamped) = crop_region(image, &padded)?;
+        let description = match &task {
+            Some(task) => {
+                let ocr = engine.ocr_task(&crop, task, None)?;
+                result.prompt_tokens += ocr.prompt_tokens;
+                result.completion_tokens += ocr.completion_tokens();
+                result.finish_reason = result.finish_reason.max(ocr.finish_reason);
+                Some(strip_grounding(&ocr.grounded_text()).trim().to_string())
+                    .filter(|text| !text.is_empty())
+            }
+            None => None,
+        };
+        result.figures.push(Figure {
+            index,
+            label: spans[index].label.clone(),
+            region: clamped,
+            image: crop,
+            description,
+        });
+    }
+    Ok(result)
+}
+
+/// Markdown for one figure: an image link followed by its description as a block quote.
+pub fn figure_markdown(link: &str, alt: &str, description: Option<&str>) -> String {
+    let mut markdown = format!("![{alt}]({link})");
+    if let Some(description) = description {
+        markdown.push_str("\n\n");
+        let quoted: Vec<String> = description
+            .lines()
+            .map(|line| format!("> {line}").trim_end().to_string())
+            .collect();
+        markdown.push_str(&quoted.join("\n"));
+    }
+    markdown
+}
+
+/// Write each figure's link (`links[i]` for `figures[i]`) and description into the body of its
+/// block in `text`, which must be the text the figures were extracted from. Grounding markup is
+/// preserved; text the model read inside a figure is replaced.
+pub fn insert_figures(text: &str, figures: &[Figure], links: &[String]) -> String {
+    let spans = parse_grounding(text);
+    let mut ordered: Vec<(&Figure, &String)> = figures
+        .iter()
+        .zip(links)
+        .filter(|(figure, _)| figure.index < spans.len())
+        .collect();
+    ordered.sort_by_key(|(figure, _)| figure.index);
+    let mut output = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for (figure, link) in ordered {
+        let span = &spans[figure.index];
+        // Empty blocks get the figure on its own line right after the markup, ahead of the
+        // whitespace separating them from the next block.
+        let range = if span.content.is_empty() {
+            span.range.end..span.range.end
+        } else {
+            span.content_range.clone()
+        };
+        if range.start < cursor {
+            continue;
+        }
+        output.push_str(&text[cursor..range.start]);
+        if range.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&figure_markdown(
+            link,
+            &figure.label,
+            figure.description.as_deref(),
+        ));
+        cursor = range.end;
+    }
+    output.push_str(&text[cursor..]);
+    output
+}
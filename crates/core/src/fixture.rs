@@ -0,0 +1,276 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result, anyhow};
+use candle_core::{DType, Device, Shape, Tensor};
+use candle_nn::{Init, VarBuilder, var_builder::SimpleBackend};
+use serde_json::{Value, json};
+use tokenizers::{
+    AddedToken, Tokenizer,
+    decoders::byte_level::ByteLevel as ByteLevelDecoder,
+    models::bpe::{BPE, Vocab},
+    pre_tokenizers::byte_level::ByteLevel,
+};
+
+use crate::{config::DeepseekOcrConfig, model::DeepseekOcrModel};
+
+pub const FIXTURE_CONFIG_FILE: &str = "config.json";
+pub const FIXTURE_WEIGHTS_FILE: &str = "model.safetensors";
+pub const FIXTURE_TOKENIZER_FILE: &str = "tokenizer.json";
+
+pub const BOS_TOKEN: &str = "<｜begin▁of▁sentence｜>";
+pub const EOS_TOKEN: &str = "<｜end▁of▁sentence｜>";
+
+/// Special tokens of the real tokenizer the pipeline relies on, appended after the byte vocabulary.
+const SPECIAL_TOKENS: &[&str] = &[
+    "<image>",
+    "<|ref|>",
+    "<|/ref|>",
+    "<|det|>",
+    "<|/det|>",
+    "<|grounding|>",
+    "<｜▁pad▁｜>",
+];
+
+/// Standard deviation of the generated matrices; matches `initializer_range` of the config.
+const INIT_STD: f64 = 0.02;
+
+/// Optional tensors the checkpoint ships, probed through `contains_tensor` by the loaders.
+/// Language-model biases, `lin1`/`lin2` SAM aliases, CLIP's unused patch embedding and MoE
+/// correction biases are left out, as in the released weights.
+const OPTIONAL_SUFFIXES: &[&str] = &["pos_embed", "rel_pos_h", "fc1.weight", "fc2.weight"];
+const BIASED_PREFIXES: &[&str] = &[
+    "model.sam_model.",
+    "model.vision_model.",
+    "model.projector.",
+];
+
+/// Files of a miniature DeepSeek-OCR checkpoint written by [`write_tiny_model`].
+#[derive(Debug, Clone)]
+pub struct TinyModel {
+    pub config: PathBuf,
+    pub weights: PathBuf,
+    pub tokenizer: PathBuf,
+}
+
+/// Byte-level BPE tokenizer without merges: every byte is its own token, followed by the BOS/EOS
+/// pair at ids 0/1 and the special tokens prompts and post-processing use.
+pub fn tiny_tokenizer() -> Result<Tokenizer> {
+    let mut alphabet: Vec<char> = ByteLevel::alphabet().into_iter().collect();
+    alphabet.sort_unstable();
+    let vocab: Vocab = [BOS_TOKEN.to_string(), EOS_TOKEN.to_string()]
+        .into_iter()
+        .chain(alphabet.iter().map(char::to_string))
+        .enumerate()
+        .map(|(id, token)| (token, id as u32))
+        .collect();
+    let bpe = BPE::builder()
+        .vocab_and_merges(vocab, Vec::new())
+        .build()
+        .map_err(|err| anyhow!("failed to build fixture tokenizer: {err}"))?;
+    let mut tokenizer = Tokenizer::new(bpe);
+    tokenizer.with_pre_tokenizer(Some(ByteLevel::new(false, true, true)));
+    tokenizer.with_decoder(Some(ByteLevelDecoder::default()));
+    let specials: Vec<AddedToken> = [BOS_TOKEN, EOS_TOKEN]
+        .iter()
+        .chain(SPECIAL_TOKENS)
+        .map(|token| AddedToken::from(token.to_string(), true))
+        .collect();
+    tokenizer.add_special_tokens(&specials);
+    Ok(tokenizer)
+}
+
+/// Miniature configuration with the layout of the released `config.json`: the same SAM/CLIP
+/// geometry (patch 16, two stride-2 downsamples, CLIP fed by SAM features) and a two-layer MoE
+/// decoder, shrunk to a few hundred thousand parameters.
+pub fn tiny_config(vocab_size: usize) -> Result<DeepseekOcrConfig> {
+    serde_json::from_value(tiny_config_json(vocab_size))
+        .context("fixture config does not match DeepseekOcrConfig")
+}
+
+fn tiny_config_json(vocab_size: usize) -> Value {
+    let sam_channels = [32, 64];
+    let hidden_size = 64;
+    json!({
+        "_name_or_path": "deepseek-ocr-tiny",
+        "architectures": ["DeepseekOCRForCausalLM"],
+        "candidate_resolutions": [[1024, 1024]],
+        "global_view_pos": "head",
+        "model_type": "deepseek_vl_v2",
+        "tile_tag": "2D",
+        "torch_dtype": "float32",
+        "projector_config": {
+            "input_dim": 2 * sam_channels[1],
+            "model_type": "mlp_projector",
+            "n_embed": hidden_size,
+            "projector_type": "linear"
+        },
+        "vision_config": {
+            "image_size": 1024,
+            "model_name": "deeplip_b_l",
+            "model_type": "vision",
+            "width": {
+                "clip-l-14-224": {
+                    "heads": 4,
+                    "image_size": 224,
+                    "layers": 2,
+                    "patch_size": 14,
+                    "width": sam_channels[1]
+                },
+                "sam_vit_b": {
+                    "downsample_channels": sam_channels,
+                    "global_attn_indexes": [1],
+                    "heads": 2,
+                    "layers": 2,
+                    "width": 32
+                }
+            }
+        },
+        "language_config": {
+            "architectures": ["DeepseekV2ForCausalLM"],
+            "model_type": "deepseek_v2",
+            "torch_dtype": "float32",
+            "vocab_size": vocab_size,
+            "hidden_size": hidden_size,
+            "intermediate_size": 128,
+            "moe_intermediate_size": 32,
+            "num_hidden_layers": 2,
+            "num_attention_heads": 4,
+            "num_key_value_heads": 4,
+            "n_routed_experts": 4,
+            "n_shared_experts": 1,
+            "num_experts_per_tok": 2,
+            "first_k_dense_replace": 1,
+            "topk_method": "greedy",
+            "max_position_embeddings": 8192,
+            "bos_token_id": 0,
+            "eos_token_id": 1,
+            "use_mla": false
+        }
+    })
+}
+
+/// Random weights for every tensor `cfg` requires, keyed by checkpoint name. Values are drawn
+/// from a generator seeded per tensor name, so they only depend on `seed` and the tensor's name
+/// and shape.
+pub fn random_weights(cfg: &DeepseekOcrConfig, seed: u64) -> Result<HashMap<String, Tensor>> {
+    let backend = RandomBackend {
+        seed,
+        tensors: Mutex::new(HashMap::new()),
+    };
+    let tensors = {
+        let vb = VarBuilder::from_backend(Box::new(&backend), DType::F32, Device::Cpu);
+        DeepseekOcrModel::from_var_builder(cfg.clone(), &vb, PathBuf::new())
+            .context("fixture config does not build a model")?;
+        backend
+            .tensors
+            .lock()
+            .expect("fixture tensors lock poisoned")
+            .clone()
+    };
+    Ok(tensors)
+}
+
+/// Write a loadable miniature checkpoint (`config.json`, `model.safetensors`, `tokenizer.json`)
+/// into `dir`, for tests that run the full pipeline without the real weights.
+pub fn write_tiny_model(dir: &Path, seed: u64) -> Result<TinyModel> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let files = TinyModel {
+        config: dir.join(FIXTURE_CONFIG_FILE),
+        weights: dir.join(FIXTURE_WEIGHTS_FILE),
+        tokenizer: dir.join(FIXTURE_TOKENIZER_FILE),
+    };
+
+    let tokenizer = tiny_tokenizer()?;
+    tokenizer
+        .save(&files.tokenizer, false)
+        .map_err(|err| anyhow!("failed to write {}: {err}", files.tokenizer.display()))?;
+    let config = tiny_config_json(tokenizer.get_vocab_size(true));
+    fs::write(&files.config, serde_json::to_string_pretty(&config)?)
+        .with_context(|| format!("failed to write {}", files.config.display()))?;
+    let cfg = serde_json::from_value(config)?;
+    candle_core::safetensors::save(&random_weights(&cfg, seed)?, &files.weights)
+        .with_context(|| format!("failed to write {}", files.weights.display()))?;
+    Ok(files)
+}
+
+/// [`SimpleBackend`] that makes up every requested tensor and remembers it for saving.
+struct RandomBackend {
+    seed: u64,
+    tensors: Mutex<HashMap<String, Tensor>>,
+}
+
+impl SimpleBackend for &RandomBackend {
+    fn get(
+        &self,
+        shape: Shape,
+        name: &str,
+        _hint: Init,
+        dtype: DType,
+        dev: &Device,
+    ) -> candle_core::Result<Tensor> {
+        let mut tensors = self.tensors.lock().expect("fixture tensors lock poisoned");
+        if let Some(tensor) = tensors.get(name) {
+            return tensor.to_dtype(dtype)?.to_device(dev);
+        }
+        let leaf = name.rsplit('.').next().unwrap_or(name);
+        let tensor = if leaf == "bias" {
+            Tensor::zeros(shape, DType::F32, &Device::Cpu)?
+        } else if leaf == "weight" && shape.rank() == 1 {
+            // Layer/RMS norm scales.
+            Tensor::ones(shape, DType::F32, &Device::Cpu)?
+        } else {
+            let mut rng = SplitMix64(self.seed ^ fnv1a(name));
+            let values: Vec<f32> = (0..shape.elem_count())
+                .map(|_| (rng.next_normal() * INIT_STD) as f32)
+                .collect();
+            Tensor::from_vec(values, shape, &Device::Cpu)?
+        };
+        tensors.insert(name.to_string(), tensor.clone());
+        tensor.to_dtype(dtype)?.to_device(dev)
+    }
+
+    fn contains_tensor(&self, name: &str) -> bool {
+        if name.ends_with(".bias") {
+            return BIASED_PREFIXES
+                .iter()
+                .any(|prefix| name.starts_with(prefix));
+        }
+        OPTIONAL_SUFFIXES
+            .iter()
+            .any(|suffix| name == *suffix || name.ends_with(&format!(".{suffix}")))
+    }
+}
+
+fn fnv1a(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `(0, 1]`.
+    fn next_unit(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standard normal sample (Box–Muller).
+    fn next_normal(&mut self) -> f64 {
+        let radius = (-2.0 * self.next_unit().ln()).sqrt();
+        radius * (std::f64::consts::TAU * self.next_unit()).cos()
+    }
+}
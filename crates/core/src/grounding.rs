@@ -0,0 +1,271 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+/// Grounded coordinates emitted by the model are normalised to `0..=999` on both axes.
+pub const GROUNDING_COORD_SCALE: f32 = 999.0;
+
+const REF_OPEN: &str = "<|ref|>";
+const REF_CLOSE: &str = "<|/ref|>";
+const DET_OPEN: &str = "<|det|>";
+const DET_CLOSE: &str = "<|/det|>";
+/// Special tokens of the grounding markup.
+const GROUNDING_TOKENS: [&str; 5] = [REF_OPEN, REF_CLOSE, DET_OPEN, DET_CLOSE, "<|grounding|>"];
+
+/// Axis-aligned rectangle `(x1, y1)`–`(x2, y2)`. Depending on context the coordinates are either
+/// model-normalised (`0..=999`) or pixels in a given image frame.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x1: f32,
+    pub y1: f32,
+    pub x2: f32,
+    pub y2: f32,
+}
+
+impl BoundingBox {
+    pub fn new(x1: f32, y1: f32, x2: f32, y2: f32) -> Self {
+        Self {
+            x1: x1.min(x2),
+            y1: y1.min(y2),
+            x2: x1.max(x2),
+            y2: y1.max(y2),
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.x2 - self.x1
+    }
+
+    pub fn height(&self) -> f32 {
+        self.y2 - self.y1
+    }
+
+    pub fn area(&self) -> f32 {
+        self.width().max(0.0) * self.height().max(0.0)
+    }
+
+    /// Convert model-normalised coordinates into pixels for a `width`×`height` image.
+    pub fn to_pixels(&self, width: u32, height: u32) -> BoundingBox {
+        let sx = width as f32 / GROUNDING_COORD_SCALE;
+        let sy = height as f32 / GROUNDING_COORD_SCALE;
+        BoundingBox::new(
+            (self.x1 * sx).clamp(0.0, width as f32),
+            (self.y1 * sy).clamp(0.0, height as f32),
+            (self.x2 * sx).clamp(0.0, width as f32),
+            (self.y2 * sy).clamp(0.0, height as f32),
+        )
+    }
+
+    /// Inverse of [`BoundingBox::to_pixels`].
+    pub fn to_normalized(&self, width: u32, height: u32) -> BoundingBox {
+        let sx = GROUNDING_COORD_SCALE / width.max(1) as f32;
+        let sy = GROUNDING_COORD_SCALE / height.max(1) as f32;
+        BoundingBox::new(self.x1 * sx, self.y1 * sy, self.x2 * sx, self.y2 * sy)
+    }
+
+    /// Shift the box by `(dx, dy)`.
+    pub fn translate(&self, dx: f32, dy: f32) -> BoundingBox {
+        BoundingBox::new(self.x1 + dx, self.y1 + dy, self.x2 + dx, self.y2 + dy)
+    }
+
+    /// Scale both axes by the given factors.
+    pub fn scale(&self, sx: f32, sy: f32) -> BoundingBox {
+        BoundingBox::new(self.x1 * sx, self.y1 * sy, self.x2 * sx, self.y2 * sy)
+    }
+}
+
+/// One `<|ref|>label<|/ref|><|det|>[[..]]<|/det|>` occurrence in decoded text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroundingSpan {
+    /// Text inside the `<|ref|>` tags (block type for layout output, the needle for locate).
+    pub label: String,
+    /// Boxes inside the `<|det|>` tags, in model-normalised coordinates.
+    pub boxes: Vec<BoundingBox>,
+    /// Text following the detection up to the next grounding reference.
+    pub content: String,
+    /// Byte range of the `<|ref|>…<|/det|>` markup within the parsed text.
+    pub range: Range<usize>,
+    /// Byte range of `content` (whitespace trimmed) within the parsed text.
+    pub content_range: Range<usize>,
+}
+
+/// Extract every grounding reference from decoded model output. Malformed references are
+/// skipped.
+pub fn parse_grounding(text: &str) -> Vec<GroundingSpan> {
+    let mut spans: Vec<GroundingSpan> = Vec::new();
+    let mut cursor = 0;
+    while let Some(offset) = text[cursor..].find(REF_OPEN) {
+        let start = cursor + offset;
+        let label_start = start + REF_OPEN.len();
+        let Some(label_len) = text[label_start..].find(REF_CLOSE) else {
+            break;
+        };
+        let label_end = label_start + label_len;
+        let after_label = label_end + REF_CLOSE.len();
+        let Some(det_rel) = text[after_label..].strip_prefix(DET_OPEN) else {
+            cursor = after_label;
+            continue;
+        };
+        let det_start = after_label + DET_OPEN.len();
+        let Some(det_len) = det_rel.find(DET_CLOSE) else {
+            break;
+        };
+        let det_end = det_start + det_len;
+        let end = det_end + DET_CLOSE.len();
+        if let Some(previous) = spans.last_mut() {
+            set_content(previous, text, start);
+        }
+        spans.push(GroundingSpan {
+            label: text[label_start..label_end].trim().to_owned(),
+            boxes: parse_box_list(&text[det_start..det_end]),
+            content: String::new(),
+            range: start..end,
+            content_range: end..end,
+        });
+        cursor = end;
+    }
+    if let Some(last) = spans.last_mut() {
+        set_content(last, text, text.len());
+    }
+    spans
+}
+
+fn set_content(span: &mut GroundingSpan, text: &str, until: usize) {
+    let raw = &text[span.range.end..until];
+    let leading = raw.len() - raw.trim_start().len();
+    let trimmed = raw.trim();
+    let start = span.range.end + leading;
+    span.content = trimmed.to_owned();
+    span.content_range = start..start + trimmed.len();
+}
+
+/// Replace the content of selected spans (by index into `spans`, which must come from
+/// [`parse_grounding`] on the same `text`). Markup and surrounding whitespace are preserved.
+pub fn replace_span_contents(
+    text: &str,
+    spans: &[GroundingSpan],
+    replacements: &[(usize, String)],
+) -> String {
+    let mut ordered: Vec<&(usize, String)> = replacements
+        .iter()
+        .filter(|(idx, _)| *idx < spans.len())
+        .collect();
+    ordered.sort_by_key(|(idx, _)| *idx);
+    let mut output = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for (idx, replacement) in ordered {
+        let range = &spans[*idx].content_range;
+        if range.start < cursor {
+            continue;
+        }
+        output.push_str(&text[cursor..range.start]);
+        output.push_str(replacement.trim());
+        cursor = range.end;
+    }
+    output.push_str(&text[cursor..]);
+    output
+}
+
+/// Remove `<|det|>` payloads and unwrap `<|ref|>` labels so the text reads naturally.
+pub fn strip_grounding(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut cursor = 0;
+    while let Some(offset) = text[cursor..].find(DET_OPEN) {
+        let start = cursor + offset;
+        output.push_str(&text[cursor..start]);
+        match text[start..].find(DET_CLOSE) {
+            Some(len) => cursor = start + len + DET_CLOSE.len(),
+            None => {
+                cursor = text.len();
+            }
+        }
+    }
+    output.push_str(&text[cursor..]);
+    output
+        .replace(REF_OPEN, "")
+        .replace(REF_CLOSE, "")
+        .replace("<|grounding|>", "")
+}
+
+/// Drop the grounding special tokens but keep labels and boxes, which is what decoding with
+/// `skip_special_tokens` (and therefore streaming) produces.
+pub fn strip_grounding_tokens(text: &str) -> String {
+    GROUNDING_TOKENS
+        .iter()
+        .fold(text.to_string(), |text, token| text.replace(token, ""))
+}
+
+/// Rewrite the boxes of every `<|det|>` payload with `map`, leaving all other text untouched.
+/// Boxes are written back rounded to integers, matching the model's own formatting.
+pub fn remap_grounding(text: &str, map: impl Fn(&BoundingBox) -> BoundingBox) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut cursor = 0;
+    while let Some(offset) = text[cursor..].find(DET_OPEN) {
+        let start = cursor + offset + DET_OPEN.len();
+        let Some(len) = text[start..].find(DET_CLOSE) else {
+            break;
+        };
+        output.push_str(&text[cursor..start]);
+        let boxes: Vec<BoundingBox> = parse_box_list(&text[start..start + len])
+            .iter()
+            .map(&map)
+            .collect();
+        output.push_str(&format_box_list(&boxes));
+        cursor = start + len;
+    }
+    output.push_str(&text[cursor..]);
+    output
+}
+
+/// Format boxes as `[[x1, y1, x2, y2], ...]` with integer coordinates.
+pub fn format_box_list(boxes: &[BoundingBox]) -> String {
+    let items: Vec<String> = boxes
+        .iter()
+        .map(|b| {
+            format!(
+                "[{}, {}, {}, {}]",
+                b.x1.round() as i64,
+                b.y1.round() as i64,
+                b.x2.round() as i64,
+                b.y2.round() as i64
+            )
+        })
+        .collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Parse `[[x1, y1, x2, y2], ...]` (or a single `[x1, y1, x2, y2]`).
+fn parse_box_list(payload: &str) -> Vec<BoundingBox> {
+    let numbers: Vec<f32> = payload
+        .split(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .filter(|chunk| !chunk.is_empty())
+        .filter_map(|chunk| chunk.parse::<f32>().ok())
+        .collect();
+    numbers
+        .chunks_exact(4)
+        .map(|chunk| BoundingBox::new(chunk[0], chunk[1], chunk[2], chunk[3]))
+        .collect()
+}
+
+/// A grounded region projected into the pixel frame of the source image.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroundedBox {
+    pub label: String,
+    #[serde(rename = "box")]
+    pub bbox: BoundingBox,
+}
+
+/// Parse `text` and convert every detected box to pixels for a `width`×`height` image. A
+/// reference with several boxes yields one entry per box.
+pub fn pixel_boxes(text: &str, width: u32, height: u32) -> Vec<GroundedBox> {
+    parse_grounding(text)
+        .into_iter()
+        .flat_map(|span| {
+            let label = span.label;
+            span.boxes.into_iter().map(move |bbox| GroundedBox {
+                label: label.clone(),
+                bbox: bbox.to_pixels(width, height),
+            })
+        })
+        .collect()
+}
@@ -9,8 +9,12 @@ use crate::{
     benchmark::Timer,
     conversation::get_conv_template,
     model::{DeepseekOcrModel, OwnedVisionInput, VisionInput},
+    vision::{TilingOptions, select_tile_grid},
 };
 
+const PATCH_SIZE: u32 = 16;
+const DOWNSAMPLE_RATIO: u32 = 4;
+
 /// Render a prompt using the configured conversation template and system prompt.
 pub fn render_prompt(template: &str, system_prompt: &str, raw_prompt: &str) -> Result<String> {
     let timer = Timer::new("prompt.render");
@@ -34,18 +38,20 @@ pub fn prepare_vision_inputs(
     base_size: u32,
     image_size: u32,
     crop_mode: bool,
+    tiling: TilingOptions,
 ) -> Result<Vec<OwnedVisionInput>> {
     let timer = Timer::new("vision.prepare_inputs");
     if !images.is_empty() {
         trace!(
-            "Preparing vision input (base_size={base_size}, image_size={image_size}, crop_mode={crop_mode})"
+            "Preparing vision input (base_size={base_size}, image_size={image_size}, crop_mode={crop_mode}, tiles={}..={}, thumbnail={})",
+            tiling.min_tiles, tiling.max_tiles, tiling.use_thumbnail
         );
     }
     let result = images
         .iter()
         .map(|image| {
             model
-                .prepare_vision_input_from_image(image, base_size, image_size, crop_mode)
+                .prepare_vision_input_with_tiling(image, base_size, image_size, crop_mode, tiling)
                 .with_context(|| "failed to build vision input")
         })
         .collect::<Result<Vec<_>>>();
@@ -174,6 +180,63 @@ pub fn build_prompt_tokens(
     Ok((tokens, mask))
 }
 
+/// Prompt length for a request, computed from image dimensions instead of embeddings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTokenEstimate {
+    /// Text tokens, including the leading BOS token.
+    pub text_tokens: usize,
+    /// Placeholder tokens per image, in prompt order.
+    pub image_tokens: Vec<usize>,
+}
+
+impl PromptTokenEstimate {
+    pub fn total_image_tokens(&self) -> usize {
+        self.image_tokens.iter().sum()
+    }
+
+    /// Total prompt length, as reported by `OcrResult::prompt_tokens`.
+    pub fn prompt_tokens(&self) -> usize {
+        self.text_tokens + self.total_image_tokens()
+    }
+}
+
+/// Count the tokens `build_prompt_tokens` would produce for `prompt` with images of the given
+/// `(width, height)`, without preprocessing pixels or running the vision tower.
+pub fn estimate_prompt_tokens(
+    tokenizer: &Tokenizer,
+    prompt: &str,
+    image_sizes: &[(u32, u32)],
+    base_size: u32,
+    image_size: u32,
+    crop_mode: bool,
+    tiling: TilingOptions,
+) -> Result<PromptTokenEstimate> {
+    let segments: Vec<&str> = prompt.split("<image>").collect();
+    anyhow::ensure!(
+        segments.len() - 1 == image_sizes.len(),
+        "prompt includes {} <image> tokens but {} image sizes were provided",
+        segments.len() - 1,
+        image_sizes.len()
+    );
+    let mut text_tokens = 1; // BOS
+    for segment in segments {
+        text_tokens += tokenizer
+            .encode(segment, false)
+            .map_err(|err| anyhow!("tokenization failed: {err}"))?
+            .len();
+    }
+    let image_tokens = image_sizes
+        .iter()
+        .map(|&(width, height)| {
+            estimate_image_tokens(width, height, base_size, image_size, crop_mode, tiling)
+        })
+        .collect();
+    Ok(PromptTokenEstimate {
+        text_tokens,
+        image_tokens,
+    })
+}
+
 /// Normalise decoder output by stripping sentinel tokens and Windows line-endings.
 pub fn normalize_text(s: &str) -> String {
     s.replace("\r\n", "\n")
@@ -182,6 +245,70 @@ pub fn normalize_text(s: &str) -> String {
         .to_string()
 }
 
+/// Projected query tokens along one side of a `size`×`size` view.
+fn num_queries(size: u32) -> usize {
+    let grid = (size / PATCH_SIZE) as usize;
+    ((grid as f32) / (DOWNSAMPLE_RATIO as f32)).ceil() as usize
+}
+
+/// Number of `<image>` placeholders emitted for one vision input with the given crop grid
+/// (`(columns, rows)`), matching the layout produced by `build_image_placeholders`.
+pub fn image_token_count(
+    crop_shape: Option<(usize, usize)>,
+    thumbnail: bool,
+    base_size: u32,
+    image_size: u32,
+    crop_mode: bool,
+) -> usize {
+    // Every grid row ends with a newline token.
+    let grid = |rows: usize, cols: usize| rows * (cols + 1);
+    if !crop_mode {
+        let queries = num_queries(image_size);
+        return grid(queries, queries) + 1;
+    }
+    let global = num_queries(base_size);
+    let mut count = grid(global, global) + 1;
+    let (width_crops, height_crops) = crop_shape.unwrap_or((1, 1));
+    if width_crops > 1 || height_crops > 1 {
+        let local = num_queries(image_size);
+        count += grid(local * height_crops, local * width_crops);
+        if thumbnail {
+            count += grid(local, local);
+        }
+    }
+    count
+}
+
+/// Placeholder count for a `width`×`height` image, computed from the tiling decision alone so
+/// no model or pixel data is needed.
+pub fn estimate_image_tokens(
+    width: u32,
+    height: u32,
+    base_size: u32,
+    image_size: u32,
+    crop_mode: bool,
+    tiling: TilingOptions,
+) -> usize {
+    if !crop_mode {
+        return image_token_count(None, false, base_size, image_size, false);
+    }
+    let (cols, rows) = select_tile_grid(
+        width.max(1),
+        height.max(1),
+        tiling.min_tiles,
+        tiling.max_tiles,
+        image_size,
+    );
+    let tiled = (cols, rows) != (1, 1);
+    image_token_count(
+        Some((cols as usize, rows as usize)),
+        tiled && tiling.use_thumbnail,
+        base_size,
+        image_size,
+        true,
+    )
+}
+
 fn build_image_placeholders(
     image_token_id: i64,
     input: &OwnedVisionInput,
@@ -190,9 +317,6 @@ fn build_image_placeholders(
     image_size: u32,
     crop_mode: bool,
 ) -> Result<Vec<i64>> {
-    const PATCH_SIZE: u32 = 16;
-    const DOWNSAMPLE_RATIO: u32 = 4;
-
     let mut placeholders = Vec::new();
 
     let push_grid = |placeholders: &mut Vec<i64>, rows: usize, cols: usize, add_terminal: bool| {
@@ -206,8 +330,7 @@ fn build_image_placeholders(
     };
 
     if crop_mode {
-        let grid = (base_size / PATCH_SIZE) as usize;
-        let num_queries_global = ((grid as f32) / (DOWNSAMPLE_RATIO as f32)).ceil() as usize;
+        let num_queries_global = num_queries(base_size);
         push_grid(
             &mut placeholders,
             num_queries_global,
@@ -217,17 +340,22 @@ fn build_image_placeholders(
 
         let (width_crops, height_crops) = input.crop_shape.unwrap_or((1, 1));
         if width_crops > 1 || height_crops > 1 {
-            let local_grid = (image_size / PATCH_SIZE) as usize;
-            let num_queries_local =
-                ((local_grid as f32) / (DOWNSAMPLE_RATIO as f32)).ceil() as usize;
+            let num_queries_local = num_queries(image_size);
             let rows = num_queries_local * height_crops;
             let cols = num_queries_local * width_crops;
             push_grid(&mut placeholders, rows, cols, false);
+            if input.thumbnail {
+                push_grid(
+                    &mut placeholders,
+                    num_queries_local,
+                    num_queries_local,
+                    false,
+                );
+            }
         }
     } else {
-        let grid = (image_size / PATCH_SIZE) as usize;
-        let num_queries = ((grid as f32) / (DOWNSAMPLE_RATIO as f32)).ceil() as usize;
-        push_grid(&mut placeholders, num_queries, num_queries, true);
+        let queries = num_queries(image_size);
+        push_grid(&mut placeholders, queries, queries, true);
     }
 
     anyhow::ensure!(
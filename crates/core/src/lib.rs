@@ -1,11 +1,27 @@
+pub mod baseline;
 pub mod benchmark;
 pub mod config;
 pub mod conversation;
+pub mod engine;
+pub mod eval;
+pub mod export;
+pub mod extract;
+pub mod figures;
+pub mod grounding;
 pub mod inference;
 pub mod model;
+pub mod postprocess;
+pub mod redact;
+pub mod repetition;
+pub mod resolution;
+pub mod roi;
 pub mod runtime;
+pub mod segment;
+pub mod task;
+pub mod text;
 pub mod transformer;
 pub mod vision;
+pub mod zoom;
 
 // #[cfg(feature = "mkl")]
 // extern crate intel_mkl_src;
@@ -13,6 +29,9 @@ pub mod vision;
 #[cfg(feature = "memlog")]
 pub mod memlog;
 
+#[cfg(feature = "fixture")]
+pub mod fixture;
+
 #[cfg(feature = "dhat-heap")]
 #[global_allocator]
 static ALLOC: dhat::Alloc = dhat::Alloc;
@@ -10,17 +10,19 @@ use candle_nn::VarBuilder;
 use image::GenericImageView;
 use image::{DynamicImage, Rgb, RgbImage, imageops};
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     benchmark::Timer,
     config::{DeepseekOcrConfig, ProjectorConfig, load_ocr_config},
+    repetition::{RepetitionAction, RepetitionOptions, detect_loop},
     transformer::{
         cache::{DynamicCache, PromptCacheGuard},
         model::{DeepseekLanguageModel, LanguageModelOutput},
     },
     vision::{
-        ClipDebugTrace, ClipVisionModel, SamBackbone, SamDebugTrace, dynamic_preprocess,
-        resample::resize_bicubic,
+        ClipDebugTrace, ClipVisionModel, SamBackbone, SamDebugTrace, TilingOptions,
+        dynamic_preprocess, resample::resize_bicubic,
     },
 };
 
@@ -32,6 +34,8 @@ pub struct VisionInput<'a> {
     pub global: &'a Tensor,
     pub patches: Option<&'a Tensor>,
     pub crop_shape: Option<(usize, usize)>,
+    /// Whether `patches` ends with a thumbnail tile after the `crop_shape` grid.
+    pub thumbnail: bool,
 }
 
 /// Owned buffers backing a [`VisionInput`].
@@ -39,6 +43,7 @@ pub struct OwnedVisionInput {
     pub global: Tensor,
     pub patches: Option<Tensor>,
     pub crop_shape: Option<(usize, usize)>,
+    pub thumbnail: bool,
 }
 
 impl OwnedVisionInput {
@@ -47,6 +52,7 @@ impl OwnedVisionInput {
             global: &self.global,
             patches: self.patches.as_ref(),
             crop_shape: self.crop_shape,
+            thumbnail: self.thumbnail,
         }
     }
 }
@@ -95,6 +101,8 @@ pub struct GenerateOptions<'a> {
     pub eos_token_id: Option<i64>,
     pub progress_callback: Option<&'a dyn Fn(usize, &[i64])>,
     pub use_cache: bool,
+    /// Repetition-loop detection; off by default.
+    pub repetition: RepetitionOptions,
 }
 
 impl<'a> GenerateOptions<'a> {
@@ -109,10 +117,43 @@ impl<'a> GenerateOptions<'a> {
             eos_token_id: None,
             progress_callback: None,
             use_cache: true,
+            repetition: RepetitionOptions::default(),
         }
     }
 }
 
+/// Why generation ended. Ordered by severity, so the maximum over several runs summarises them.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum FinishReason {
+    /// End-of-sequence token.
+    #[default]
+    Stop,
+    /// `max_new_tokens` reached.
+    Length,
+    /// A repetition loop was detected (see [`RepetitionOptions`]).
+    Repetition,
+}
+
+impl FinishReason {
+    pub fn name(self) -> &'static str {
+        match self {
+            FinishReason::Stop => "stop",
+            FinishReason::Length => "length",
+            FinishReason::Repetition => "repetition",
+        }
+    }
+}
+
+/// Generated token ids (`[1, len]`, prompt excluded) and why generation ended.
+#[derive(Debug, Clone)]
+pub struct Generation {
+    pub tokens: Tensor,
+    pub finish_reason: FinishReason,
+}
+
 struct ImageProjector {
     input_dim: usize,
     hidden: usize,
@@ -404,6 +445,7 @@ impl<'a> VisionContext<'a> {
         &self,
         projected: &Tensor,
         crop_shape: (usize, usize),
+        thumbnail: bool,
         newline: &Tensor,
     ) -> Result<Tensor> {
         let (patches, seq, hidden) = projected
@@ -411,12 +453,14 @@ impl<'a> VisionContext<'a> {
             .dims3()
             .context("projected local tokens must be 3D")?;
         let (width_crops, height_crops) = crop_shape;
+        let grid_patches = width_crops * height_crops;
         ensure!(
-            patches == width_crops * height_crops,
-            "patch count {} does not match crop grid {}x{}",
+            patches == grid_patches + usize::from(thumbnail),
+            "patch count {} does not match crop grid {}x{}{}",
             patches,
             width_crops,
-            height_crops
+            height_crops,
+            if thumbnail { " plus thumbnail" } else { "" }
         );
         let side = (seq as f64).sqrt() as usize;
         ensure!(
@@ -425,12 +469,26 @@ impl<'a> VisionContext<'a> {
             seq
         );
         let grid = projected
+            .narrow(0, 0, grid_patches)?
             .reshape((height_crops, width_crops, side, side, hidden))?
             .permute((0, 2, 1, 3, 4))?
             .reshape((height_crops * side, width_crops * side, hidden))?
             .contiguous()
             .context("local grid reshape not contiguous")?;
-        self.append_row_breaks(grid, newline)
+        let tiles = self.append_row_breaks(grid, newline)?;
+        if !thumbnail {
+            return Ok(tiles);
+        }
+        let thumb = projected
+            .narrow(0, grid_patches, 1)?
+            .reshape((side, side, hidden))?
+            .contiguous()
+            .context("thumbnail grid reshape not contiguous")?;
+        let thumb = self.append_row_breaks(thumb, newline)?;
+        Tensor::cat(&[tiles, thumb], 0)
+            .context("failed to append thumbnail tokens")?
+            .contiguous()
+            .context("local tokens with thumbnail not contiguous")
     }
 
     fn process_input_full(&self, input: &VisionInput<'_>) -> Result<VisionProcessArtifacts> {
@@ -547,7 +605,7 @@ impl<'a> VisionContext<'a> {
                         .contiguous()
                         .context("local post tokens not contiguous")?;
                     let local_tokens = self
-                        .format_local_tokens(&local_post, crop_shape, newline)
+                        .format_local_tokens(&local_post, crop_shape, input.thumbnail, newline)
                         .context("format local tokens")?
                         .contiguous()
                         .context("local tokens not contiguous")?;
@@ -558,7 +616,7 @@ impl<'a> VisionContext<'a> {
                         .context("local patch tensor not contiguous")?;
                     let (local_pre, local_post) = self.process_patch_batch(&patches)?;
                     let local_tokens = self
-                        .format_local_tokens(&local_post, crop_shape, newline)
+                        .format_local_tokens(&local_post, crop_shape, input.thumbnail, newline)
                         .context("format local tokens")?
                         .contiguous()
                         .context("local tokens not contiguous")?;
@@ -651,8 +709,7 @@ impl DeepseekOcrModel {
         device: Device,
         dtype: DType,
     ) -> Result<Self> {
-        let cfg = Arc::new(load_ocr_config(config_path)?);
-        let language_cfg = Arc::new(cfg.resolved_language_config()?);
+        let cfg = load_ocr_config(config_path)?;
         let resolved_weights = weights_path
             .map(PathBuf::from)
             .unwrap_or_else(|| PathBuf::from(DEFAULT_WEIGHTS_PATH));
@@ -660,7 +717,19 @@ impl DeepseekOcrModel {
             VarBuilder::from_mmaped_safetensors(&[resolved_weights.as_path()], dtype, &device)
         }
         .with_context(|| format!("failed to mmap weights at {}", resolved_weights.display()))?;
-        let language = DeepseekLanguageModel::load(language_cfg, &vb)
+        Self::from_var_builder(cfg, &vb, resolved_weights)
+    }
+
+    /// Build the model from an already opened [`VarBuilder`]; tensors are allocated on its
+    /// device and dtype. `weights_path` is only recorded for diagnostics.
+    pub fn from_var_builder(
+        cfg: DeepseekOcrConfig,
+        vb: &VarBuilder,
+        weights_path: PathBuf,
+    ) -> Result<Self> {
+        let cfg = Arc::new(cfg);
+        let language_cfg = Arc::new(cfg.resolved_language_config()?);
+        let language = DeepseekLanguageModel::load(language_cfg, vb)
             .context("failed to load language model")?;
         let projector_cfg = Arc::new(
             cfg.resolved_projector_config()
@@ -672,7 +741,7 @@ impl DeepseekOcrModel {
             projector_cfg.n_embed,
             language.config().hidden_size
         );
-        let projector = ImageProjector::load(&vb, projector_cfg.as_ref())
+        let projector = ImageProjector::load(vb, projector_cfg.as_ref())
             .context("failed to load image projector")?;
         let sam = SamBackbone::new(cfg.as_ref(), &vb.pp("model").pp("sam_model"))
             .context("failed to load SAM backbone")?;
@@ -686,9 +755,9 @@ impl DeepseekOcrModel {
             projector_cfg,
             projector,
             vision,
-            device,
-            dtype,
-            weights_path: resolved_weights,
+            device: vb.device().clone(),
+            dtype: vb.dtype(),
+            weights_path,
         })
     }
 
@@ -727,6 +796,14 @@ impl DeepseekOcrModel {
         self.projector_cfg.as_ref()
     }
 
+    /// Learned `image_newline` and `view_separator` embeddings placed between the image tokens.
+    pub fn projector_separators(&self) -> (&Tensor, &Tensor) {
+        (
+            &self.projector.image_newline,
+            &self.projector.view_separator,
+        )
+    }
+
     /// Construct a fresh dynamic cache sized for this model.
     pub fn new_cache(&self) -> DynamicCache {
         let layers = self.language.transformer_weights().layers.len();
@@ -1179,16 +1256,43 @@ impl DeepseekOcrModel {
         image_size: u32,
         crop_mode: bool,
     ) -> Result<OwnedVisionInput> {
+        self.prepare_vision_input_with_tiling(
+            image,
+            base_size,
+            image_size,
+            crop_mode,
+            TilingOptions::default(),
+        )
+    }
+
+    /// Like [`Self::prepare_vision_input_from_image`] with explicit tiler limits. A 1x1 grid
+    /// yields no local patches, matching the placeholder layout in `inference.rs`.
+    pub fn prepare_vision_input_with_tiling(
+        &self,
+        image: &DynamicImage,
+        base_size: u32,
+        image_size: u32,
+        crop_mode: bool,
+        tiling: TilingOptions,
+    ) -> Result<OwnedVisionInput> {
+        tiling.validate()?;
         let global_view = build_global_view(image, base_size);
         let global = image_to_tensor(&global_view, self.device(), self.dtype)?
             .unsqueeze(0)?
             .contiguous()?;
 
+        let mut thumbnail = false;
         let (patches, crop_shape) = if crop_mode {
-            let preprocess = dynamic_preprocess(image, 2, 9, image_size, false);
+            let preprocess = dynamic_preprocess(
+                image,
+                tiling.min_tiles,
+                tiling.max_tiles,
+                image_size,
+                tiling.use_thumbnail,
+            );
             let crop = (preprocess.ratio.0 as usize, preprocess.ratio.1 as usize);
             let tiles = preprocess.tiles;
-            if tiles.is_empty() {
+            if tiles.is_empty() || crop == (1, 1) {
                 (None, Some(crop))
             } else {
                 tracing::info!("Preparing {} image crops for vision input", tiles.len());
@@ -1206,6 +1310,7 @@ impl DeepseekOcrModel {
                         .collect::<Result<Vec<_>>>()?
                 };
                 let stacked = Tensor::stack(&tensors, 0)?.contiguous()?;
+                thumbnail = tiling.use_thumbnail;
                 (Some(stacked), Some(crop))
             }
         } else {
@@ -1216,6 +1321,7 @@ impl DeepseekOcrModel {
             global,
             patches,
             crop_shape,
+            thumbnail,
         })
     }
 
@@ -1331,6 +1437,15 @@ impl DeepseekOcrModel {
 
     /// Greedy autoregressive generation for the multimodal model.
     pub fn generate(&self, input_ids: &Tensor, options: GenerateOptions<'_>) -> Result<Tensor> {
+        Ok(self.generate_with_reason(input_ids, options)?.tokens)
+    }
+
+    /// [`generate`](Self::generate), also reporting why decoding ended.
+    pub fn generate_with_reason(
+        &self,
+        input_ids: &Tensor,
+        options: GenerateOptions<'_>,
+    ) -> Result<Generation> {
         let total_timer = Timer::new("decode.generate");
         ensure!(
             input_ids.rank() == 2,
@@ -1356,7 +1471,7 @@ impl DeepseekOcrModel {
                 event.add_field("max_new_tokens", 0u64);
                 event.add_field("generated_tokens", 0u64);
             });
-            return self.empty_generation();
+            return self.empty_generation(FinishReason::Length);
         }
 
         let mut cache = self.new_cache();
@@ -1394,17 +1509,26 @@ impl DeepseekOcrModel {
                     event.add_field("max_new_tokens", options.max_new_tokens as u64);
                     event.add_field("terminated_on_prefill", true);
                 });
-                return self.empty_generation();
+                return self.empty_generation(FinishReason::Stop);
             }
         }
 
         let mut generated = Vec::with_capacity(options.max_new_tokens);
+        let mut finish_reason = FinishReason::Length;
         let decode_timer = Timer::new("decode.iterative");
         for step in 0..options.max_new_tokens {
             generated.push(current);
             if let Some(cb) = progress_callback {
                 cb(generated.len(), &generated);
             }
+            let banned = match loop_continuation(&generated, &options.repetition) {
+                LoopCheck::Stop => {
+                    finish_reason = FinishReason::Repetition;
+                    break;
+                }
+                LoopCheck::Ban(token) => Some(token),
+                LoopCheck::None => None,
+            };
             if step + 1 == options.max_new_tokens {
                 break;
             }
@@ -1433,9 +1557,10 @@ impl DeepseekOcrModel {
                 .context("decode logits missing batch dimension")?
                 .get(0)
                 .context("decode logits missing timestep")?;
-            current = self.select_token_id(&next_logits)?;
+            current = self.select_token_id_excluding(&next_logits, banned)?;
             if let Some(eos) = options.eos_token_id {
                 if current == eos {
+                    finish_reason = FinishReason::Stop;
                     break;
                 }
             }
@@ -1444,6 +1569,7 @@ impl DeepseekOcrModel {
         decode_timer.finish(|event| {
             event.add_field("steps", len as u64);
             event.add_field("max_new_tokens", options.max_new_tokens as u64);
+            event.add_field("finish_reason", finish_reason.name());
         });
         total_timer.finish(|event| {
             event.add_field("prompt_tokens", seq_len as u64);
@@ -1452,14 +1578,17 @@ impl DeepseekOcrModel {
             event.add_field("terminated_on_prefill", false);
             event.add_field("use_cache", true);
         });
-        Ok(Tensor::from_vec(generated, (1, len), self.device())?.to_dtype(DType::I64)?)
+        Ok(Generation {
+            tokens: Tensor::from_vec(generated, (1, len), self.device())?.to_dtype(DType::I64)?,
+            finish_reason,
+        })
     }
 
     fn generate_without_cache(
         &self,
         input_ids: &Tensor,
         options: GenerateOptions<'_>,
-    ) -> Result<Tensor> {
+    ) -> Result<Generation> {
         let total_timer = Timer::new("decode.generate_no_cache");
         ensure!(
             input_ids.rank() == 2,
@@ -1477,7 +1606,7 @@ impl DeepseekOcrModel {
                 event.add_field("max_new_tokens", 0u64);
                 event.add_field("use_cache", false);
             });
-            return self.empty_generation();
+            return self.empty_generation(FinishReason::Length);
         }
         ensure!(
             options.position_ids.is_none(),
@@ -1612,17 +1741,26 @@ impl DeepseekOcrModel {
                     event.add_field("forward_calls", forward_calls);
                     event.add_field("max_seq_len_seen", max_seq_len_seen);
                 });
-                return self.empty_generation();
+                return self.empty_generation(FinishReason::Stop);
             }
         }
 
         let progress_callback = options.progress_callback;
         let mut generated = Vec::with_capacity(options.max_new_tokens);
+        let mut finish_reason = FinishReason::Length;
         for step in 0..options.max_new_tokens {
             generated.push(current);
             if let Some(cb) = progress_callback {
                 cb(generated.len(), &generated);
             }
+            let banned = match loop_continuation(&generated, &options.repetition) {
+                LoopCheck::Stop => {
+                    finish_reason = FinishReason::Repetition;
+                    break;
+                }
+                LoopCheck::Ban(token) => Some(token),
+                LoopCheck::None => None,
+            };
             if step + 1 == options.max_new_tokens {
                 break;
             }
@@ -1665,9 +1803,10 @@ impl DeepseekOcrModel {
                 .context("decode logits missing batch dimension")?
                 .get(seq_pos)
                 .context("decode logits missing timestep")?;
-            current = self.select_token_id(&next_logits)?;
+            current = self.select_token_id_excluding(&next_logits, banned)?;
             if let Some(eos) = options.eos_token_id {
                 if current == eos {
+                    finish_reason = FinishReason::Stop;
                     break;
                 }
             }
@@ -1682,12 +1821,20 @@ impl DeepseekOcrModel {
             event.add_field("use_cache", false);
             event.add_field("forward_calls", forward_calls);
             event.add_field("max_seq_len_seen", max_seq_len_seen);
+            event.add_field("finish_reason", finish_reason.name());
         });
-        Ok(Tensor::from_vec(generated, (1, len), self.device())?.to_dtype(DType::I64)?)
+        Ok(Generation {
+            tokens: Tensor::from_vec(generated, (1, len), self.device())?.to_dtype(DType::I64)?,
+            finish_reason,
+        })
     }
 
-    fn empty_generation(&self) -> Result<Tensor> {
-        Ok(Tensor::from_vec(Vec::<i64>::new(), (1, 0), self.device())?.to_dtype(DType::I64)?)
+    fn empty_generation(&self, finish_reason: FinishReason) -> Result<Generation> {
+        Ok(Generation {
+            tokens: Tensor::from_vec(Vec::<i64>::new(), (1, 0), self.device())?
+                .to_dtype(DType::I64)?,
+            finish_reason,
+        })
     }
 
     fn select_token_id(&self, logits: &Tensor) -> Result<i64> {
@@ -1700,6 +1847,43 @@ impl DeepseekOcrModel {
         idx.to_scalar::<i64>()
             .context("failed to convert argmax index to scalar")
     }
+
+    /// Greedy pick that skips `banned`, used to steer decoding out of a repetition loop.
+    fn select_token_id_excluding(&self, logits: &Tensor, banned: Option<i64>) -> Result<i64> {
+        let Some(banned) = banned else {
+            return self.select_token_id(logits);
+        };
+        let scores = logits
+            .to_dtype(DType::F32)?
+            .to_vec1::<f32>()
+            .context("failed to read logits for repetition penalty")?;
+        let mut best: Option<(usize, f32)> = None;
+        for (idx, &score) in scores.iter().enumerate() {
+            if i64::try_from(idx).is_ok_and(|id| id == banned) {
+                continue;
+            }
+            if best.is_none_or(|(_, top)| score > top) {
+                best = Some((idx, score));
+            }
+        }
+        let (idx, _) = best.context("no candidate token left after repetition penalty")?;
+        Ok(i64::try_from(idx)?)
+    }
+}
+
+enum LoopCheck {
+    None,
+    Stop,
+    /// Token that would continue the detected loop.
+    Ban(i64),
+}
+
+fn loop_continuation(generated: &[i64], options: &RepetitionOptions) -> LoopCheck {
+    match detect_loop(generated, options) {
+        None => LoopCheck::None,
+        Some(_) if options.action == RepetitionAction::Stop => LoopCheck::Stop,
+        Some(period) => LoopCheck::Ban(generated[generated.len() - period]),
+    }
 }
 
 fn round_ties_to_even(value: f64) -> f64 {
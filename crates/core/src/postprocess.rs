@@ -0,0 +1,426 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::export::{table::span, tag_at};
+
+/// Longest run of lines (in lines) recognised as one unit of a repetition loop.
+pub const MAX_LOOP_PERIOD: usize = 8;
+/// Consecutive copies of a unit from which it counts as a loop and is collapsed to one.
+pub const MIN_LOOP_REPEATS: usize = 3;
+
+/// Optional clean-up passes applied to decoded text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostprocessOptions {
+    /// Collapse decoding loops: a line or block of lines repeated back to back.
+    pub remove_repeats: bool,
+    /// Give every row of a table the same number of cells and close truncated HTML tables.
+    pub repair_tables: bool,
+    /// Join words hyphenated across a line break (`infor-` / `mation`).
+    pub dehyphenate: bool,
+    /// Rewrite `$$ … $$` and `$ … $` math as `\[ … \]` and `\( … \)`.
+    pub normalize_latex: bool,
+}
+
+/// Individual post-processing pass, as accepted by the CLI/server `postprocess` lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PostprocessStep {
+    Repeats,
+    Tables,
+    Hyphens,
+    Latex,
+    /// Every pass above.
+    All,
+}
+
+impl PostprocessOptions {
+    /// Options with exactly `steps` enabled.
+    pub fn from_steps(steps: &[PostprocessStep]) -> Self {
+        let mut options = Self::default();
+        for step in steps {
+            match step {
+                PostprocessStep::Repeats => options.remove_repeats = true,
+                PostprocessStep::Tables => options.repair_tables = true,
+                PostprocessStep::Hyphens => options.dehyphenate = true,
+                PostprocessStep::Latex => options.normalize_latex = true,
+                PostprocessStep::All => {
+                    options = Self {
+                        remove_repeats: true,
+                        repair_tables: true,
+                        dehyphenate: true,
+                        normalize_latex: true,
+                    }
+                }
+            }
+        }
+        options
+    }
+
+    /// Whether any pass is enabled.
+    pub fn any_enabled(&self) -> bool {
+        self.remove_repeats || self.repair_tables || self.dehyphenate || self.normalize_latex
+    }
+}
+
+/// Run the enabled passes on `text` in order: repeats, tables, hyphens, LaTeX.
+pub fn postprocess(text: &str, options: &PostprocessOptions) -> String {
+    let mut text = text.to_string();
+    if options.remove_repeats {
+        text = remove_repeated_lines(&text);
+    }
+    if options.repair_tables {
+        text = repair_tables(&text);
+    }
+    if options.dehyphenate {
+        text = dehyphenate(&text);
+    }
+    if options.normalize_latex {
+        text = normalize_latex(&text);
+    }
+    text
+}
+
+/// Keep one copy of any unit of up to [`MAX_LOOP_PERIOD`] lines that repeats back to back at
+/// least [`MIN_LOOP_REPEATS`] times. Lines are compared with surrounding whitespace trimmed and
+/// runs of blank lines are left alone. Tables legitimately repeat rows (`| 0 | 0 |`, separators),
+/// so a unit with a pipe or HTML table row only collapses when it repeats up to the end of the
+/// text, where the decoder ran out of tokens.
+pub fn remove_repeated_lines(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let keys: Vec<&str> = lines.iter().map(|line| line.trim()).collect();
+    let mut kept: Vec<&str> = Vec::with_capacity(lines.len());
+    let mut idx = 0;
+    while idx < lines.len() {
+        let found = (1..=MAX_LOOP_PERIOD).find_map(|period| {
+            let unit = keys.get(idx..idx + period)?;
+            if unit.iter().all(|key| key.is_empty()) {
+                return None;
+            }
+            let repeats = 1
+                + (1..)
+                    .take_while(|rep| {
+                        keys.get(idx + rep * period..idx + (rep + 1) * period) == Some(unit)
+                    })
+                    .count();
+            let to_end = idx + period * repeats == lines.len();
+            let table = unit.iter().any(|key| is_table_row(key));
+            (repeats >= MIN_LOOP_REPEATS && (to_end || !table)).then_some((period, repeats))
+        });
+        match found {
+            Some((period, repeats)) => {
+                kept.extend(&lines[idx..idx + period]);
+                idx += period * repeats;
+            }
+            None => {
+                kept.push(lines[idx]);
+                idx += 1;
+            }
+        }
+    }
+    let mut collapsed = kept.join("\n");
+    if text.ends_with('\n') {
+        collapsed.push('\n');
+    }
+    collapsed
+}
+
+/// Whether a trimmed line is a row of a pipe or HTML table.
+fn is_table_row(line: &str) -> bool {
+    line.starts_with('|')
+        || ["<tr", "</tr", "<td", "<th"]
+            .iter()
+            .any(|tag| line.starts_with(tag))
+}
+
+/// Move the tail of a word hyphenated at the end of a line up to that line. Only lowercase
+/// continuations are joined, so compounds such as `Jean-` / `Paul` keep their hyphen.
+pub fn dehyphenate(text: &str) -> String {
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    for idx in 0..lines.len().saturating_sub(1) {
+        let line = lines[idx].trim_end();
+        let Some(stem) = line.strip_suffix('-') else {
+            continue;
+        };
+        if !stem.chars().next_back().is_some_and(char::is_alphabetic) || line.starts_with('|') {
+            continue;
+        }
+        let next = lines[idx + 1].trim_start();
+        if !next.chars().next().is_some_and(char::is_lowercase) {
+            continue;
+        }
+        let split = next
+            .find(|ch: char| !ch.is_alphanumeric())
+            .unwrap_or(next.len());
+        // Keep trailing punctuation with the word it belongs to.
+        let split = split
+            + next[split..]
+                .find(|ch: char| !matches!(ch, '.' | ',' | ';' | ':' | '!' | '?' | ')'))
+                .unwrap_or(next.len() - split);
+        let (tail, rest) = next.split_at(split);
+        let joined = format!("{stem}{tail}");
+        let rest = rest.trim_start().to_string();
+        lines[idx] = joined;
+        lines[idx + 1] = rest;
+    }
+    let mut output: Vec<String> = Vec::with_capacity(lines.len());
+    let original: Vec<&str> = text.lines().collect();
+    for (idx, line) in lines.into_iter().enumerate() {
+        // Drop lines emptied by the join, but keep blank lines that were there before.
+        if line.is_empty() && !original[idx].trim().is_empty() {
+            continue;
+        }
+        output.push(line);
+    }
+    output.join("\n")
+}
+
+/// Rewrite `$$ … $$` as `\[ … \]` and inline `$ … $` as `\( … \)`. An inline `$` only opens
+/// when followed by a non-space and only closes after a non-space when no digit follows, so
+/// amounts such as `$5 and $10` are left untouched; `\$` is never treated as a delimiter.
+pub fn normalize_latex(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut output = String::with_capacity(text.len());
+    let mut idx = 0;
+    while idx < chars.len() {
+        let ch = chars[idx];
+        if ch == '\\' && idx + 1 < chars.len() {
+            output.push(ch);
+            output.push(chars[idx + 1]);
+            idx += 2;
+            continue;
+        }
+        if ch != '$' {
+            output.push(ch);
+            idx += 1;
+            continue;
+        }
+        if chars.get(idx + 1) == Some(&'$') {
+            match find_display_close(&chars, idx + 2) {
+                Some(end) => {
+                    output.push_str("\\[");
+                    output.extend(&chars[idx + 2..end]);
+                    output.push_str("\\]");
+                    idx = end + 2;
+                }
+                None => {
+                    output.push_str("$$");
+                    idx += 2;
+                }
+            }
+            continue;
+        }
+        if let Some(end) = find_inline_close(&chars, idx + 1) {
+            output.push_str("\\(");
+            output.extend(&chars[idx + 1..end]);
+            output.push_str("\\)");
+            idx = end + 1;
+            continue;
+        }
+        output.push(ch);
+        idx += 1;
+    }
+    output
+}
+
+fn find_display_close(chars: &[char], from: usize) -> Option<usize> {
+    let mut idx = from;
+    while idx + 1 < chars.len() {
+        match chars[idx] {
+            '\\' => idx += 2,
+            '$' if chars[idx + 1] == '$' => return Some(idx),
+            _ => idx += 1,
+        }
+    }
+    None
+}
+
+fn find_inline_close(chars: &[char], from: usize) -> Option<usize> {
+    if chars.get(from).is_none_or(|ch| ch.is_whitespace()) {
+        return None;
+    }
+    let mut idx = from;
+    while idx < chars.len() {
+        match chars[idx] {
+            '\\' => idx += 2,
+            '\n' => return None,
+            '$' => {
+                let closes = !chars[idx - 1].is_whitespace()
+                    && !chars.get(idx + 1).is_some_and(char::is_ascii_digit);
+                return closes.then_some(idx);
+            }
+            _ => idx += 1,
+        }
+    }
+    None
+}
+
+/// Repair tables the decoder left unbalanced. Pipe tables get a separator row when it is
+/// missing and every row padded to the widest one; HTML tables get missing `</td>`, `</tr>` and
+/// `</table>` closers and, when they use no `rowspan`, short rows padded with empty cells.
+pub fn repair_tables(text: &str) -> String {
+    repair_html_tables(&repair_pipe_tables(text))
+}
+
+fn repair_pipe_tables(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut output: Vec<String> = Vec::with_capacity(lines.len());
+    let mut idx = 0;
+    while idx < lines.len() {
+        if !lines[idx].trim_start().starts_with('|') {
+            output.push(lines[idx].to_string());
+            idx += 1;
+            continue;
+        }
+        let start = idx;
+        while idx < lines.len() && lines[idx].trim_start().starts_with('|') {
+            idx += 1;
+        }
+        let mut rows: Vec<Vec<String>> = lines[start..idx]
+            .iter()
+            .map(|line| {
+                let line = line.trim();
+                let inner = line.strip_prefix('|').unwrap_or(line);
+                let inner = inner.strip_suffix('|').unwrap_or(inner);
+                inner
+                    .split('|')
+                    .map(|cell| cell.trim().to_string())
+                    .collect()
+            })
+            .collect();
+        let widths: Vec<usize> = rows.iter().map(Vec::len).collect();
+        let width = widths.iter().copied().max().unwrap_or(0);
+        let has_separator = rows.iter().any(|row| is_separator_row(row));
+        if rows.len() < 2 || (has_separator && widths.iter().all(|&cells| cells == width)) {
+            output.extend(lines[start..idx].iter().map(|line| line.to_string()));
+            continue;
+        }
+        if !has_separator {
+            rows.insert(1, Vec::new());
+        }
+        for row in &mut rows {
+            let filler = if row.is_empty() || is_separator_row(row) {
+                "---"
+            } else {
+                ""
+            };
+            row.resize(width, filler.to_string());
+            output.push(format!("| {} |", row.join(" | ")));
+        }
+    }
+    let mut repaired = output.join("\n");
+    if text.ends_with('\n') {
+        repaired.push('\n');
+    }
+    repaired
+}
+
+fn repair_html_tables(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut cursor = 0;
+    while let Some(offset) = find_tag(text, cursor, "table") {
+        let start = cursor + offset;
+        let (end, closed) = match find_tag(text, start + 1, "/table") {
+            Some(len) => (start + 1 + len, true),
+            None => (text.len(), false),
+        };
+        output.push_str(&text[cursor..start]);
+        output.push_str(&repair_html_table(&text[start..end]));
+        if closed {
+            cursor = end;
+        } else {
+            output.push_str("</table>");
+            cursor = text.len();
+        }
+    }
+    output.push_str(&text[cursor..]);
+    output
+}
+
+/// Repair one `<table …>` fragment without its closing `</table>` tag.
+fn repair_html_table(table: &str) -> String {
+    let lower = table.to_ascii_lowercase();
+    let row_starts: Vec<usize> = lower
+        .match_indices('<')
+        .map(|(at, _)| at)
+        .filter(|&at| tag_at(&lower, at, "tr"))
+        .collect();
+    let Some(&first_row) = row_starts.first() else {
+        return table.to_string();
+    };
+    // Per row: the markup before `</tr>` and whatever follows it up to the next row
+    // (`</thead><tbody>`, whitespace, ...).
+    let rows: Vec<(&str, &str)> = row_starts
+        .iter()
+        .enumerate()
+        .map(|(idx, &start)| {
+            let end = row_starts.get(idx + 1).copied().unwrap_or(table.len());
+            match lower[start..end].find("</tr") {
+                Some(close) => {
+                    let close = start + close;
+                    let after = lower[close..end].find('>').map_or(end, |gt| close + gt + 1);
+                    (&table[start..close], &table[after..end])
+                }
+                None => (&table[start..end], ""),
+            }
+        })
+        .collect();
+    let widths: Vec<usize> = rows.iter().map(|(cells, _)| row_width(cells)).collect();
+    let width = widths.iter().copied().max().unwrap_or(0);
+    let pad = !lower.contains("rowspan");
+
+    let mut repaired = table[..first_row].to_string();
+    for ((cells, trailer), &count) in rows.iter().zip(&widths) {
+        let mut row = cells.trim_end().to_string();
+        close_open_cell(&mut row);
+        if pad {
+            for _ in count..width {
+                row.push_str("<td></td>");
+            }
+        }
+        row.push_str("</tr>");
+        repaired.push_str(&row);
+        repaired.push_str(trailer);
+    }
+    repaired
+}
+
+fn is_separator_row(row: &[String]) -> bool {
+    row.iter()
+        .all(|cell| !cell.is_empty() && cell.chars().all(|ch| matches!(ch, '-' | ':' | ' ')))
+}
+
+/// Cells in a `<tr>` fragment, counting `colspan`.
+fn row_width(row: &str) -> usize {
+    row.match_indices('<')
+        .filter(|&(at, _)| tag_at(row, at, "td") || tag_at(row, at, "th"))
+        .map(|(at, _)| {
+            let tag = &row[at..at + row[at..].find('>').unwrap_or(row.len() - at)];
+            span(tag, "colspan")
+        })
+        .sum()
+}
+
+/// Append `</td>`/`</th>` when the fragment ends inside a cell.
+fn close_open_cell(row: &mut String) {
+    let lower = row.to_ascii_lowercase();
+    let last_open = lower
+        .match_indices('<')
+        .map(|(at, _)| at)
+        .rfind(|&at| tag_at(&lower, at, "td") || tag_at(&lower, at, "th"));
+    let Some(open) = last_open else {
+        return;
+    };
+    let name = &lower[open + 1..open + 3];
+    if !lower[open..].contains(&format!("</{name}")) {
+        row.push_str(&format!("</{name}>"));
+    }
+}
+
+/// Byte offset of the next `<name` tag at or after `from`, relative to `from`.
+fn find_tag(text: &str, from: usize, name: &str) -> Option<usize> {
+    text[from..]
+        .match_indices('<')
+        .map(|(at, _)| at)
+        .find(|&at| tag_at(text, from + at, name))
+}
@@ -0,0 +1,436 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
+use image::{DynamicImage, Rgb, RgbImage, imageops};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    export::{LayoutBlock, PageLayout},
+    grounding::{BoundingBox, parse_grounding},
+};
+
+/// Horizontal padding added around a redacted word, in line heights, to absorb the error of the
+/// estimated word boxes.
+pub const WORD_MARGIN: f32 = 0.5;
+
+/// Built-in personal-data patterns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PiiKind {
+    Email,
+    /// Phone numbers with 9 to 15 digits, optionally with a `+` country code.
+    Phone,
+    /// US social security numbers (`123-45-6789`).
+    Ssn,
+    /// Payment card numbers passing the Luhn check.
+    Card,
+    /// IBANs passing the mod-97 check.
+    Iban,
+    /// Numeric dates such as `1984-03-07` or `07/03/1984` (birth dates); not enabled by default.
+    Date,
+}
+
+impl PiiKind {
+    /// Every kind, most specific first: on an exact tie the earlier kind names the match.
+    pub const ALL: [PiiKind; 6] = [
+        PiiKind::Ssn,
+        PiiKind::Card,
+        PiiKind::Iban,
+        PiiKind::Email,
+        PiiKind::Date,
+        PiiKind::Phone,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PiiKind::Email => "email",
+            PiiKind::Phone => "phone",
+            PiiKind::Ssn => "ssn",
+            PiiKind::Card => "card",
+            PiiKind::Iban => "iban",
+            PiiKind::Date => "date",
+        }
+    }
+
+    fn pattern(self) -> &'static str {
+        match self {
+            PiiKind::Email => r"(?i)\b[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b",
+            PiiKind::Phone => {
+                r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?)?\b\d{2,4}(?:[ .-]?\d{2,4}){1,4}\b"
+            }
+            PiiKind::Ssn => r"\b\d{3}-\d{2}-\d{4}\b",
+            PiiKind::Card => r"\b\d(?:[ -]?\d){12,18}\b",
+            PiiKind::Iban => r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?\b",
+            PiiKind::Date => {
+                r"\b(?:\d{4}[-/.]\d{1,2}[-/.]\d{1,2}|\d{1,2}[-/.]\d{1,2}[-/.](?:\d{4}|\d{2}))\b"
+            }
+        }
+    }
+
+    fn accepts(self, text: &str) -> bool {
+        let digits: Vec<u32> = text.chars().filter_map(|ch| ch.to_digit(10)).collect();
+        match self {
+            PiiKind::Phone => (9..=15).contains(&digits.len()),
+            PiiKind::Card => luhn_valid(&digits),
+            PiiKind::Iban => iban_valid(text),
+            PiiKind::Email | PiiKind::Ssn | PiiKind::Date => true,
+        }
+    }
+}
+
+/// How redacted regions are painted over in page images.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactStyle {
+    /// Solid black boxes; nothing of the original survives.
+    #[default]
+    Black,
+    /// Heavy Gaussian blur; keeps the page readable at a glance.
+    Blur,
+}
+
+/// Granularity of image redaction. The model grounds whole blocks, so line and word boxes are
+/// estimates (see [`LayoutBlock::lines`]); the default block region is the safe one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactRegion {
+    /// The estimated words of each match, padded by [`WORD_MARGIN`].
+    Word,
+    /// Every estimated line containing a match.
+    Line,
+    /// Every grounded block containing a match.
+    #[default]
+    Block,
+}
+
+/// PII redaction settings (`[inference.redaction]`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactOptions {
+    /// Redact every output; the CLI `--redact` flag and the server `redact` option turn it on
+    /// per run.
+    pub enabled: bool,
+    /// Built-in patterns to apply.
+    pub kinds: Vec<PiiKind>,
+    /// Extra named regular expressions, e.g. `patient_id = "\\bP\\d{7}\\b"`.
+    pub patterns: BTreeMap<String, String>,
+    /// Text written in place of each match; `{kind}` becomes the upper-cased pattern name.
+    pub replacement: String,
+    pub style: RedactStyle,
+    pub region: RedactRegion,
+}
+
+impl Default for RedactOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            kinds: vec![
+                PiiKind::Email,
+                PiiKind::Phone,
+                PiiKind::Ssn,
+                PiiKind::Card,
+                PiiKind::Iban,
+            ],
+            patterns: BTreeMap::new(),
+            replacement: "[{kind}]".to_string(),
+            style: RedactStyle::default(),
+            region: RedactRegion::default(),
+        }
+    }
+}
+
+/// One detected piece of personal data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PiiMatch {
+    /// Built-in kind name or custom pattern name.
+    pub kind: String,
+    /// Byte range within the searched text.
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+#[derive(Debug)]
+struct Rule {
+    name: String,
+    regex: Regex,
+    kind: Option<PiiKind>,
+}
+
+/// Compiled pattern set.
+#[derive(Debug)]
+pub struct Redactor {
+    rules: Vec<Rule>,
+    replacement: String,
+    pub style: RedactStyle,
+    pub region: RedactRegion,
+}
+
+impl Redactor {
+    /// Compile the built-in and custom patterns of `options`; fails on an invalid custom regex.
+    pub fn new(options: &RedactOptions) -> Result<Self> {
+        let mut rules = Vec::with_capacity(options.kinds.len() + options.patterns.len());
+        for kind in PiiKind::ALL
+            .into_iter()
+            .filter(|kind| options.kinds.contains(kind))
+        {
+            rules.push(Rule {
+                name: kind.name().to_string(),
+                regex: Regex::new(kind.pattern()).expect("built-in PII pattern is valid"),
+                kind: Some(kind),
+            });
+        }
+        for (name, pattern) in &options.patterns {
+            rules.push(Rule {
+                name: name.clone(),
+                regex: Regex::new(pattern)
+                    .with_context(|| format!("invalid redaction pattern `{name}`"))?,
+                kind: None,
+            });
+        }
+        Ok(Self {
+            rules,
+            replacement: options.replacement.clone(),
+            style: options.style,
+            region: options.region,
+        })
+    }
+
+    /// Non-overlapping matches in `text`, in order. Where patterns overlap the earliest and then
+    /// longest match wins.
+    pub fn find(&self, text: &str) -> Vec<PiiMatch> {
+        let mut found: Vec<PiiMatch> = self
+            .rules
+            .iter()
+            .flat_map(|rule| {
+                rule.regex
+                    .find_iter(text)
+                    .filter(|m| rule.kind.is_none_or(|kind| kind.accepts(m.as_str())))
+                    .map(|m| PiiMatch {
+                        kind: rule.name.clone(),
+                        range: m.range(),
+                        text: m.as_str().to_string(),
+                    })
+            })
+            .collect();
+        found.sort_by_key(|m| (m.range.start, std::cmp::Reverse(m.range.end)));
+        let mut kept: Vec<PiiMatch> = Vec::with_capacity(found.len());
+        for m in found {
+            if kept
+                .last()
+                .is_none_or(|last| m.range.start >= last.range.end)
+            {
+                kept.push(m);
+            }
+        }
+        kept
+    }
+
+    /// Replace every match in decoded model output. Grounding markup is left intact, so box
+    /// coordinates are never mistaken for numbers to redact.
+    pub fn redact_text(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut cursor = 0;
+        let markup = parse_grounding(text).into_iter().map(|span| span.range);
+        for range in markup.chain(std::iter::once(text.len()..text.len())) {
+            output.push_str(&self.replace_matches(&text[cursor..range.start]));
+            output.push_str(&text[range.clone()]);
+            cursor = range.end;
+        }
+        output
+    }
+
+    fn replace_matches(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut cursor = 0;
+        for m in self.find(text) {
+            output.push_str(&text[cursor..m.range.start]);
+            output.push_str(
+                &self
+                    .replacement
+                    .replace("{kind}", &m.kind.to_ascii_uppercase()),
+            );
+            cursor = m.range.end;
+        }
+        output.push_str(&text[cursor..]);
+        output
+    }
+
+    /// Copy of `image` with the PII found in `text`, the grounded output for it, painted over.
+    pub fn redact_page(&self, text: &str, image: &DynamicImage) -> DynamicImage {
+        let layout = PageLayout::from_output(text, image.width(), image.height());
+        redact_image(image, &self.regions(&layout), self.style)
+    }
+
+    /// Pixel regions of `layout` holding a match, at the configured [`RedactRegion`]. Output
+    /// without grounding says nothing about where its text is, so a match there covers the page.
+    pub fn regions(&self, layout: &PageLayout) -> Vec<BoundingBox> {
+        if !layout.grounded {
+            return layout
+                .blocks
+                .iter()
+                .filter(|block| !self.find(&block.text).is_empty())
+                .map(|_| BoundingBox::new(0.0, 0.0, layout.width as f32, layout.height as f32))
+                .collect();
+        }
+        layout
+            .blocks
+            .iter()
+            .flat_map(|block| self.block_regions(block))
+            .collect()
+    }
+
+    fn block_regions(&self, block: &LayoutBlock) -> Vec<BoundingBox> {
+        if self.region == RedactRegion::Block {
+            return if self.find(&block.text).is_empty() {
+                Vec::new()
+            } else {
+                vec![block.bbox]
+            };
+        }
+        // `LayoutBlock::lines` splits the same trimmed, non-empty lines.
+        let texts = block.text.lines().map(str::trim).filter(|l| !l.is_empty());
+        let mut regions = Vec::new();
+        for (text, line) in texts.zip(block.lines()) {
+            let matches = self.find(text);
+            if matches.is_empty() {
+                continue;
+            }
+            if self.region == RedactRegion::Line {
+                regions.push(line.bbox);
+                continue;
+            }
+            let margin = line.bbox.height() * WORD_MARGIN;
+            let words = word_ranges(text);
+            for m in &matches {
+                let covered = words
+                    .iter()
+                    .zip(&line.words)
+                    .filter(|(range, _)| range.start < m.range.end && m.range.start < range.end)
+                    .map(|(_, word)| word.bbox)
+                    .reduce(|a, b| BoundingBox::new(a.x1.min(b.x1), a.y1, a.x2.max(b.x2), a.y2));
+                if let Some(bbox) = covered {
+                    regions.push(BoundingBox::new(
+                        (bbox.x1 - margin).max(line.bbox.x1),
+                        bbox.y1,
+                        (bbox.x2 + margin).min(line.bbox.x2),
+                        bbox.y2,
+                    ));
+                }
+            }
+        }
+        regions
+    }
+}
+
+/// Parse a `NAME=REGEX` custom pattern as accepted by the CLI/server `--redact-pattern` flag.
+pub fn parse_pattern(spec: &str) -> Result<(String, String)> {
+    let (name, pattern) = spec
+        .split_once('=')
+        .ok_or_else(|| anyhow!("redaction pattern `{spec}` must have the form NAME=REGEX"))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("redaction pattern `{spec}` has an empty name"));
+    }
+    Regex::new(pattern).with_context(|| format!("invalid redaction pattern `{name}`"))?;
+    Ok((name.to_string(), pattern.to_string()))
+}
+
+/// Paint over `regions` (pixels) of `image` in `style`.
+pub fn redact_image(
+    image: &DynamicImage,
+    regions: &[BoundingBox],
+    style: RedactStyle,
+) -> DynamicImage {
+    let mut rgb = image.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    for region in regions {
+        let x1 = (region.x1.floor().max(0.0) as u32).min(width);
+        let y1 = (region.y1.floor().max(0.0) as u32).min(height);
+        let x2 = (region.x2.ceil().max(0.0) as u32).min(width);
+        let y2 = (region.y2.ceil().max(0.0) as u32).min(height);
+        if x2 <= x1 || y2 <= y1 {
+            continue;
+        }
+        match style {
+            RedactStyle::Black => {
+                for y in y1..y2 {
+                    for x in x1..x2 {
+                        rgb.put_pixel(x, y, Rgb([0, 0, 0]));
+                    }
+                }
+            }
+            RedactStyle::Blur => {
+                let crop: RgbImage = imageops::crop_imm(&rgb, x1, y1, x2 - x1, y2 - y1).to_image();
+                // A sigma of half the region height smears glyphs well past legibility.
+                let sigma = ((y2 - y1) as f32 / 2.0).max(4.0);
+                let blurred = imageops::blur(&crop, sigma);
+                imageops::replace(&mut rgb, &blurred, i64::from(x1), i64::from(y1));
+            }
+        }
+    }
+    DynamicImage::ImageRgb8(rgb)
+}
+
+/// Byte ranges of the whitespace-separated words of `line`, matching [`LayoutLine::words`].
+///
+/// [`LayoutLine::words`]: crate::export::LayoutLine
+fn word_ranges(line: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for (idx, ch) in line
+        .char_indices()
+        .chain(std::iter::once((line.len(), ' ')))
+    {
+        match (ch.is_whitespace(), start) {
+            (false, None) => start = Some(idx),
+            (true, Some(from)) => {
+                ranges.push(from..idx);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    ranges
+}
+
+fn luhn_valid(digits: &[u32]) -> bool {
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(idx, &digit)| {
+            if idx % 2 == 1 {
+                let doubled = digit * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                digit
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+fn iban_valid(text: &str) -> bool {
+    let compact: String = text.chars().filter(|ch| !ch.is_whitespace()).collect();
+    if !(15..=34).contains(&compact.len()) {
+        return false;
+    }
+    let (head, tail) = compact.split_at(4);
+    let mut remainder = 0u32;
+    for ch in tail.chars().chain(head.chars()) {
+        let Some(value) = ch.to_digit(36) else {
+            return false;
+        };
+        remainder = if value >= 10 {
+            (remainder * 100 + value) % 97
+        } else {
+            (remainder * 10 + value) % 97
+        };
+    }
+    remainder == 1
+}
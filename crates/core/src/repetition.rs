@@ -0,0 +1,66 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// What greedy decoding does once the output ends in a repetition loop.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RepetitionAction {
+    /// No detection; decode until end-of-sequence or `max_new_tokens`.
+    #[default]
+    Off,
+    /// Stop decoding and report [`FinishReason::Repetition`](crate::model::FinishReason).
+    Stop,
+    /// Keep decoding but never pick the token that would continue the loop.
+    Penalize,
+}
+
+/// On-the-fly detection of decoding loops: the generated tokens ending in `threshold` or more
+/// back-to-back copies of a unit of at most `window` tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepetitionOptions {
+    pub action: RepetitionAction,
+    /// Longest repeated unit checked, in tokens.
+    pub window: usize,
+    /// Consecutive copies of a unit that count as a loop.
+    pub threshold: usize,
+    /// Fewest tokens the copies must cover, so short legitimate runs (`----`, empty table
+    /// cells) are not mistaken for a loop.
+    pub min_tokens: usize,
+}
+
+impl Default for RepetitionOptions {
+    fn default() -> Self {
+        Self {
+            action: RepetitionAction::Off,
+            window: 64,
+            threshold: 4,
+            min_tokens: 48,
+        }
+    }
+}
+
+impl RepetitionOptions {
+    pub fn enabled(&self) -> bool {
+        self.action != RepetitionAction::Off && self.window > 0
+    }
+}
+
+/// Length of the shortest unit that `tokens` ends repeating, when the tail is a loop according
+/// to `options`.
+pub fn detect_loop(tokens: &[i64], options: &RepetitionOptions) -> Option<usize> {
+    if !options.enabled() {
+        return None;
+    }
+    let threshold = options.threshold.max(2);
+    (1..=options.window).find(|&period| {
+        let needed = threshold.max(options.min_tokens.div_ceil(period));
+        if tokens.len() < needed * period {
+            return false;
+        }
+        let unit = &tokens[tokens.len() - period..];
+        tokens[tokens.len() - needed * period..]
+            .chunks_exact(period)
+            .all(|copy| copy == unit)
+    })
+}
@@ -0,0 +1,283 @@
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView, imageops::FilterType};
+use serde::{Deserialize, Serialize};
+
+use crate::{engine::VisionSettings, inference::estimate_image_tokens};
+
+/// Longest side of the thumbnail used to estimate text density.
+const DENSITY_SAMPLE_SIDE: u32 = 512;
+/// Luma step between neighbouring pixels counted as an edge.
+const DENSITY_EDGE_THRESHOLD: u8 = 48;
+
+/// Named resolution presets from the upstream model card, plus `auto`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResolutionMode {
+    Tiny,
+    Small,
+    Base,
+    Large,
+    Gundam,
+    /// Pick one of the fixed modes per request from the image itself.
+    Auto,
+}
+
+impl ResolutionMode {
+    /// Fixed modes ordered from cheapest to most expensive.
+    pub const FIXED: [ResolutionMode; 5] = [
+        ResolutionMode::Tiny,
+        ResolutionMode::Small,
+        ResolutionMode::Base,
+        ResolutionMode::Large,
+        ResolutionMode::Gundam,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ResolutionMode::Tiny => "tiny",
+            ResolutionMode::Small => "small",
+            ResolutionMode::Base => "base",
+            ResolutionMode::Large => "large",
+            ResolutionMode::Gundam => "gundam",
+            ResolutionMode::Auto => "auto",
+        }
+    }
+
+    /// `(base_size, image_size, crop_mode)` for a fixed mode; `None` for `auto`.
+    pub fn sizes(self) -> Option<(u32, u32, bool)> {
+        match self {
+            ResolutionMode::Tiny => Some((512, 512, false)),
+            ResolutionMode::Small => Some((640, 640, false)),
+            ResolutionMode::Base => Some((1024, 1024, false)),
+            ResolutionMode::Large => Some((1280, 1280, false)),
+            ResolutionMode::Gundam => Some((1024, 640, true)),
+            ResolutionMode::Auto => None,
+        }
+    }
+
+    /// Apply this mode to `vision`. Fixed modes overwrite the sizes and disable auto selection;
+    /// `auto` keeps the sizes as a fallback and enables selection capped at `max_vision_tokens`.
+    pub fn apply(self, vision: &mut VisionSettings, max_vision_tokens: Option<usize>) {
+        match self.sizes() {
+            Some((base_size, image_size, crop_mode)) => {
+                vision.base_size = base_size;
+                vision.image_size = image_size;
+                vision.crop_mode = crop_mode;
+                vision.auto_resolution = None;
+            }
+            None => {
+                vision.auto_resolution = Some(AutoResolution { max_vision_tokens });
+            }
+        }
+    }
+}
+
+/// Enables per-request resolution selection in [`crate::engine::OcrEngine::run`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AutoResolution {
+    /// Upper bound on vision placeholder tokens per image.
+    pub max_vision_tokens: Option<usize>,
+}
+
+/// Cheap image measurements the selector works from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageStats {
+    pub width: u32,
+    pub height: u32,
+    /// Fraction of neighbouring pixel pairs with a strong luma step (see [`text_density`]).
+    pub text_density: f32,
+}
+
+impl ImageStats {
+    pub fn measure(image: &DynamicImage) -> Self {
+        Self {
+            width: image.width(),
+            height: image.height(),
+            text_density: text_density(image),
+        }
+    }
+
+    fn aspect_ratio(&self) -> f32 {
+        self.width.max(self.height) as f32 / self.width.min(self.height).max(1) as f32
+    }
+}
+
+/// Outcome of automatic selection, reported alongside the OCR result.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResolutionChoice {
+    pub mode: ResolutionMode,
+    /// Concrete settings used for the run (auto selection cleared).
+    pub vision: VisionSettings,
+    /// Vision placeholder tokens across all images.
+    pub image_tokens: usize,
+    /// Highest text density among the images.
+    pub text_density: f32,
+}
+
+/// Estimate how much of the image is text-like detail: the share of horizontally or vertically
+/// adjacent pixels (on a small grayscale thumbnail) whose luma differs sharply. Blank or
+/// photographic areas score near zero; dense print scores roughly 0.05-0.2.
+pub fn text_density(image: &DynamicImage) -> f32 {
+    let (width, height) = image.dimensions();
+    if width < 2 || height < 2 {
+        return 0.0;
+    }
+    let sample = if width.max(height) > DENSITY_SAMPLE_SIDE {
+        image.resize(
+            DENSITY_SAMPLE_SIDE,
+            DENSITY_SAMPLE_SIDE,
+            FilterType::Triangle,
+        )
+    } else {
+        image.clone()
+    };
+    let luma = sample.to_luma8();
+    let (width, height) = luma.dimensions();
+    let mut edges = 0usize;
+    let mut pairs = 0usize;
+    for y in 0..height {
+        for x in 0..width {
+            let value = luma.get_pixel(x, y)[0];
+            if x + 1 < width {
+                pairs += 1;
+                edges +=
+                    (value.abs_diff(luma.get_pixel(x + 1, y)[0]) > DENSITY_EDGE_THRESHOLD) as usize;
+            }
+            if y + 1 < height {
+                pairs += 1;
+                edges +=
+                    (value.abs_diff(luma.get_pixel(x, y + 1)[0]) > DENSITY_EDGE_THRESHOLD) as usize;
+            }
+        }
+    }
+    if pairs == 0 {
+        0.0
+    } else {
+        edges as f32 / pairs as f32
+    }
+}
+
+/// Mode an image would get without a token cap.
+pub fn preferred_mode(stats: &ImageStats) -> ResolutionMode {
+    let long = stats.width.max(stats.height);
+    let sparse = stats.text_density < 0.02;
+    let dense = stats.text_density >= 0.08;
+    if stats.aspect_ratio() >= 2.0 && long > 1280 {
+        // Tall receipts and wide spreads lose too much detail in one square view.
+        return ResolutionMode::Gundam;
+    }
+    if long <= 640 {
+        return if sparse || long <= 512 {
+            ResolutionMode::Tiny
+        } else {
+            ResolutionMode::Small
+        };
+    }
+    if sparse {
+        return if long <= 1280 {
+            ResolutionMode::Small
+        } else {
+            ResolutionMode::Base
+        };
+    }
+    match (long, dense) {
+        (..=1024, _) | (1025..=1600, false) => ResolutionMode::Base,
+        (1025..=1600, true) | (_, false) => ResolutionMode::Large,
+        (_, true) => ResolutionMode::Gundam,
+    }
+}
+
+/// Settings for a fixed `mode`, keeping the tiling limits from `base`.
+fn mode_settings(mode: ResolutionMode, base: &VisionSettings) -> VisionSettings {
+    let mut vision = VisionSettings {
+        auto_resolution: None,
+        ..*base
+    };
+    mode.apply(&mut vision, None);
+    vision
+}
+
+fn total_tokens(stats: &[ImageStats], vision: &VisionSettings) -> usize {
+    stats
+        .iter()
+        .map(|image| {
+            estimate_image_tokens(
+                image.width,
+                image.height,
+                vision.base_size,
+                vision.image_size,
+                vision.crop_mode,
+                vision.tiling,
+            )
+        })
+        .sum()
+}
+
+/// Choose one resolution mode for a request. Each image votes with [`preferred_mode`] and the
+/// most demanding vote wins; with `max_vision_tokens` set (per image), cheaper modes are tried
+/// until the estimate fits, shrinking the tile limit first when gundam is selected. Falls back
+/// to `tiny` when nothing fits.
+pub fn choose_resolution(
+    stats: &[ImageStats],
+    base: &VisionSettings,
+    max_vision_tokens: Option<usize>,
+) -> ResolutionChoice {
+    let preferred = stats
+        .iter()
+        .map(preferred_mode)
+        .max()
+        .unwrap_or(ResolutionMode::Base);
+    let text_density = stats
+        .iter()
+        .map(|image| image.text_density)
+        .fold(0.0, f32::max);
+    let budget = max_vision_tokens.map(|cap| cap.saturating_mul(stats.len().max(1)));
+    let fits =
+        |vision: &VisionSettings| budget.is_none_or(|cap| total_tokens(stats, vision) <= cap);
+
+    for mode in ResolutionMode::FIXED
+        .into_iter()
+        .rev()
+        .filter(|mode| *mode <= preferred)
+    {
+        let mut vision = mode_settings(mode, base);
+        if vision.crop_mode {
+            let min_tiles = vision.tiling.min_tiles;
+            while !fits(&vision) && vision.tiling.max_tiles > min_tiles {
+                vision.tiling.max_tiles -= 1;
+            }
+        }
+        if fits(&vision) {
+            return ResolutionChoice {
+                mode,
+                vision,
+                image_tokens: total_tokens(stats, &vision),
+                text_density,
+            };
+        }
+    }
+
+    let vision = mode_settings(ResolutionMode::Tiny, base);
+    ResolutionChoice {
+        mode: ResolutionMode::Tiny,
+        vision,
+        image_tokens: total_tokens(stats, &vision),
+        text_density,
+    }
+}
+
+/// Replace `vision` with the automatically chosen settings when auto selection is enabled and
+/// there are images to inspect.
+pub fn resolve_vision(
+    vision: VisionSettings,
+    images: &[DynamicImage],
+) -> (VisionSettings, Option<ResolutionChoice>) {
+    match vision.auto_resolution {
+        Some(auto) if !images.is_empty() => {
+            let stats: Vec<ImageStats> = images.iter().map(ImageStats::measure).collect();
+            let choice = choose_resolution(&stats, &vision, auto.max_vision_tokens);
+            (choice.vision, Some(choice))
+        }
+        _ => (vision, None),
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::{Context, Result, anyhow, ensure};
+use image::DynamicImage;
+
+use crate::grounding::{BoundingBox, GroundedBox, remap_grounding};
+
+/// Parse a `x1,y1,x2,y2` pixel rectangle as accepted by the CLI `--region` flag.
+pub fn parse_region(spec: &str) -> Result<BoundingBox> {
+    let values = spec
+        .split(',')
+        .map(|part| {
+            part.trim()
+                .parse::<f32>()
+                .with_context(|| format!("invalid region coordinate `{}`", part.trim()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let [x1, y1, x2, y2] = values[..] else {
+        return Err(anyhow!(
+            "region `{spec}` must have four comma-separated values x1,y1,x2,y2"
+        ));
+    };
+    Ok(BoundingBox::new(x1, y1, x2, y2))
+}
+
+/// Snap `region` to whole pixels inside a `width`×`height` image, rejecting empty results.
+pub fn clamp_region(region: &BoundingBox, width: u32, height: u32) -> Result<BoundingBox> {
+    let x1 = region.x1.floor().clamp(0.0, width as f32);
+    let y1 = region.y1.floor().clamp(0.0, height as f32);
+    let x2 = region.x2.ceil().clamp(0.0, width as f32);
+    let y2 = region.y2.ceil().clamp(0.0, height as f32);
+    ensure!(
+        x2 > x1 && y2 > y1,
+        "region ({}, {}, {}, {}) does not overlap the {width}x{height} image",
+        region.x1,
+        region.y1,
+        region.x2,
+        region.y2
+    );
+    Ok(BoundingBox::new(x1, y1, x2, y2))
+}
+
+/// Crop `region` out of `image`. Returns the crop and the clamped region it was cut from.
+pub fn crop_region(
+    image: &DynamicImage,
+    region: &BoundingBox,
+) -> Result<(DynamicImage, BoundingBox)> {
+    let clamped = clamp_region(region, image.width(), image.height())?;
+    let crop = image.crop_imm(
+        clamped.x1 as u32,
+        clamped.y1 as u32,
+        clamped.width() as u32,
+        clamped.height() as u32,
+    );
+    Ok((crop, clamped))
+}
+
+/// Maps grounding output produced for a crop back into the frame of the original image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegionFrame {
+    /// Crop rectangle in original-image pixels.
+    pub region: BoundingBox,
+    pub image_width: u32,
+    pub image_height: u32,
+}
+
+impl RegionFrame {
+    pub fn new(region: BoundingBox, image_width: u32, image_height: u32) -> Self {
+        Self {
+            region,
+            image_width,
+            image_height,
+        }
+    }
+
+    fn crop_size(&self) -> (u32, u32) {
+        (
+            self.region.width().round().max(1.0) as u32,
+            self.region.height().round().max(1.0) as u32,
+        )
+    }
+
+    /// Normalised crop coordinates -> original-image pixels.
+    pub fn to_image_pixels(&self, bbox: &BoundingBox) -> BoundingBox {
+        let (crop_w, crop_h) = self.crop_size();
+        bbox.to_pixels(crop_w, crop_h)
+            .translate(self.region.x1, self.region.y1)
+    }
+
+    /// Normalised crop coordinates -> normalised original-image coordinates.
+    pub fn to_image_normalized(&self, bbox: &BoundingBox) -> BoundingBox {
+        self.to_image_pixels(bbox)
+            .to_normalized(self.image_width, self.image_height)
+    }
+
+    /// Rewrite every `<|det|>` payload in `text` into the original image's normalised frame so
+    /// the output can be parsed as if the full image had been processed.
+    pub fn remap_text(&self, text: &str) -> String {
+        remap_grounding(text, |bbox| self.to_image_normalized(bbox))
+    }
+
+    /// Remap already projected boxes (crop pixels) into original-image pixels.
+    pub fn remap_boxes(&self, boxes: &[GroundedBox]) -> Vec<GroundedBox> {
+        boxes
+            .iter()
+            .map(|item| GroundedBox {
+                label: item.label.clone(),
+                bbox: item.bbox.translate(self.region.x1, self.region.y1),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::{Result, ensure};
+
+use crate::{
+    engine::{OcrEngine, OcrRequest, RegionResult},
+    grounding::{BoundingBox, strip_grounding},
+    model::FinishReason,
+    text::{char_similarity, collapse_whitespace},
+};
+
+/// Controls when and how very tall or wide images are split into overlapping strips.
+#[derive(Debug, Clone)]
+pub struct SegmentOptions {
+    /// Images whose long side exceeds this multiple of the short side are segmented.
+    pub trigger_aspect_ratio: f32,
+    /// Long/short side ratio of each strip.
+    pub strip_aspect_ratio: f32,
+    /// Fraction of each strip shared with its neighbour.
+    pub overlap: f32,
+    pub max_segments: usize,
+    /// Minimum character similarity for two lines to be treated as the same overlap line.
+    pub line_similarity: f32,
+    /// Number of lines inspected on each side of a strip boundary when de-duplicating.
+    pub max_overlap_lines: usize,
+}
+
+impl Default for SegmentOptions {
+    fn default() -> Self {
+        Self {
+            trigger_aspect_ratio: 3.0,
+            strip_aspect_ratio: 1.5,
+            overlap: 0.15,
+            max_segments: 16,
+            line_similarity: 0.85,
+            max_overlap_lines: 12,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SegmentResult {
+    /// Merged text with overlap duplicates removed and grounding in the full-image frame.
+    pub text: String,
+    pub segments: Vec<RegionResult>,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    /// Most severe finish reason over the segments.
+    pub finish_reason: FinishReason,
+}
+
+pub fn needs_segmentation(width: u32, height: u32, options: &SegmentOptions) -> bool {
+    let long = width.max(height) as f32;
+    let short = width.min(height).max(1) as f32;
+    long / short > options.trigger_aspect_ratio
+}
+
+/// Split a `width`×`height` image into overlapping strips along its long side, in reading
+/// order. Images within the aspect threshold yield a single full-frame region.
+pub fn plan_segments(width: u32, height: u32, options: &SegmentOptions) -> Vec<BoundingBox> {
+    if !needs_segmentation(width, height, options) {
+        return vec![BoundingBox::new(0.0, 0.0, width as f32, height as f32)];
+    }
+    let vertical = height >= width;
+    let (long, short) = if vertical {
+        (height as f32, width as f32)
+    } else {
+        (width as f32, height as f32)
+    };
+    let overlap = options.overlap.clamp(0.0, 0.9);
+    let mut strip = (short * options.strip_aspect_ratio).min(long);
+    let mut count = ((long - strip * overlap) / (strip * (1.0 - overlap))).ceil() as usize;
+    if count > options.max_segments.max(1) {
+        count = options.max_segments.max(1);
+        strip = long / (count as f32 - (count as f32 - 1.0) * overlap);
+    }
+    let count = count.max(1);
+    let step = if count > 1 {
+        (long - strip) / (count as f32 - 1.0)
+    } else {
+        0.0
+    };
+    (0..count)
+        .map(|idx| {
+            let start = (idx as f32 * step).round();
+            let end = (start + strip).round().min(long);
+            if vertical {
+                BoundingBox::new(0.0, start, short, end)
+            } else {
+                BoundingBox::new(start, 0.0, end, short)
+            }
+        })
+        .collect()
+}
+
+/// Concatenate per-strip outputs, dropping lines repeated across each strip boundary.
+pub fn merge_segment_texts(texts: &[String], options: &SegmentOptions) -> String {
+    let mut merged: Vec<&str> = Vec::new();
+    for text in texts {
+        let lines = trim_blank_lines(text.lines().collect());
+        if lines.is_empty() {
+            continue;
+        }
+        let skip = overlap_len(&merged, &lines, options);
+        merged.extend_from_slice(&lines[skip..]);
+        merged = trim_blank_lines(merged);
+    }
+    merged.join("\n")
+}
+
+/// Largest `k` such that the last `k` lines of `previous` match the first `k` lines of `next`.
+fn overlap_len(previous: &[&str], next: &[&str], options: &SegmentOptions) -> usize {
+    let max_k = previous
+        .len()
+        .min(next.len())
+        .min(options.max_overlap_lines);
+    (1..=max_k)
+        .rev()
+        .find(|&k| {
+            let tail = &previous[previous.len() - k..];
+            tail.iter()
+                .zip(&next[..k])
+                .all(|(a, b)| lines_match(a, b, options.line_similarity))
+        })
+        .unwrap_or(0)
+}
+
+fn lines_match(a: &str, b: &str, threshold: f32) -> bool {
+    let a = collapse_whitespace(&strip_grounding(a)).to_lowercase();
+    let b = collapse_whitespace(&strip_grounding(b)).to_lowercase();
+    if a.is_empty() || b.is_empty() {
+        return a.is_empty() && b.is_empty();
+    }
+    char_similarity(&a, &b) >= threshold
+}
+
+fn trim_blank_lines(mut lines: Vec<&str>) -> Vec<&str> {
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    let leading = lines
+        .iter()
+        .take_while(|line| line.trim().is_empty())
+        .count();
+    lines.drain(..leading);
+    lines
+}
+
+/// OCR the single image of `request`, splitting it into overlapping strips first when it is
+/// too tall or wide for the tiler. Outputs are merged with overlap de-duplication.
+pub fn segment_ocr(
+    engine: &OcrEngine,
+    request: &OcrRequest<'_>,
+    options: &SegmentOptions,
+) -> Result<SegmentResult> {
+    ensure!(
+        request.images.len() == 1,
+        "segmentation expects exactly one image (got {})",
+        request.images.len()
+    );
+    let image = &request.images[0];
+    let plan = plan_segments(image.width(), image.height(), options);
+    let segments = engine.run_regions(request, &plan)?;
+    let texts: Vec<String> = segments.iter().map(|seg| seg.text.clone()).collect();
+    Ok(SegmentResult {
+        text: merge_segment_texts(&texts, options),
+        prompt_tokens: segments.iter().map(|seg| seg.ocr.prompt_tokens).sum(),
+        completion_tokens: segments.iter().map(|seg| seg.ocr.completion_tokens()).sum(),
+        finish_reason: segments
+            .iter()
+            .map(|seg| seg.ocr.finish_reason)
+            .max()
+            .unwrap_or_default(),
+        segments,
+    })
+}
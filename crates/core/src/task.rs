@@ -0,0 +1,210 @@
+use std::{
+    collections::BTreeMap,
+    sync::{RwLock, RwLockReadGuard},
+};
+
+use anyhow::{Result, anyhow, ensure};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::VisionSettings;
+
+/// Placeholder substituted with the caller-supplied query when rendering a task prompt.
+pub const QUERY_PLACEHOLDER: &str = "{query}";
+
+/// Named prompt template plus the resolution/decoding settings it works best with.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskPreset {
+    pub description: String,
+    pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crop_mode: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_new_tokens: Option<usize>,
+}
+
+impl TaskPreset {
+    fn new(description: &str, prompt: &str) -> Self {
+        Self {
+            description: description.to_owned(),
+            prompt: prompt.to_owned(),
+            ..Self::default()
+        }
+    }
+
+    fn with_vision(mut self, base_size: u32, image_size: u32, crop_mode: bool) -> Self {
+        self.base_size = Some(base_size);
+        self.image_size = Some(image_size);
+        self.crop_mode = Some(crop_mode);
+        self
+    }
+
+    fn with_max_new_tokens(mut self, max_new_tokens: usize) -> Self {
+        self.max_new_tokens = Some(max_new_tokens);
+        self
+    }
+
+    /// Whether the prompt expects a `{query}` to be supplied.
+    pub fn requires_query(&self) -> bool {
+        self.prompt.contains(QUERY_PLACEHOLDER)
+    }
+
+    /// Produce the raw (untemplated) prompt, substituting `{query}` when present.
+    pub fn render(&self, query: Option<&str>) -> Result<String> {
+        ensure!(!self.prompt.is_empty(), "task prompt is empty");
+        let query = query.map(str::trim).filter(|q| !q.is_empty());
+        if self.requires_query() {
+            let query = query.ok_or_else(|| anyhow!("task prompt requires a query"))?;
+            Ok(self.prompt.replace(QUERY_PLACEHOLDER, query))
+        } else {
+            Ok(self.prompt.clone())
+        }
+    }
+
+    /// Overlay the preset's resolution hints on top of `base`.
+    pub fn vision_settings(&self, base: VisionSettings) -> VisionSettings {
+        VisionSettings {
+            base_size: self.base_size.unwrap_or(base.base_size),
+            image_size: self.image_size.unwrap_or(base.image_size),
+            crop_mode: self.crop_mode.unwrap_or(base.crop_mode),
+            ..base
+        }
+    }
+
+    /// Fields set on `overlay` replace ours; unset fields keep the current value.
+    pub fn merge(&mut self, overlay: &TaskPreset) {
+        if !overlay.description.is_empty() {
+            self.description = overlay.description.clone();
+        }
+        if !overlay.prompt.is_empty() {
+            self.prompt = overlay.prompt.clone();
+        }
+        if overlay.base_size.is_some() {
+            self.base_size = overlay.base_size;
+        }
+        if overlay.image_size.is_some() {
+            self.image_size = overlay.image_size;
+        }
+        if overlay.crop_mode.is_some() {
+            self.crop_mode = overlay.crop_mode;
+        }
+        if overlay.max_new_tokens.is_some() {
+            self.max_new_tokens = overlay.max_new_tokens;
+        }
+    }
+}
+
+static TASKS: Lazy<RwLock<BTreeMap<String, TaskPreset>>> = Lazy::new(|| {
+    let mut map = BTreeMap::new();
+    for (name, preset) in builtin_tasks() {
+        map.insert(name.to_owned(), preset);
+    }
+    RwLock::new(map)
+});
+
+/// Register `preset` under `name`. Existing entries are merged field by field so configuration
+/// files can tweak a built-in task without restating its prompt.
+pub fn register_task(name: &str, preset: TaskPreset) -> Result<()> {
+    let mut guard = TASKS.write().expect("task registry poisoned");
+    let merged = match guard.get(name) {
+        Some(existing) => {
+            let mut merged = existing.clone();
+            merged.merge(&preset);
+            merged
+        }
+        None => preset,
+    };
+    ensure!(
+        !merged.prompt.is_empty(),
+        "task `{name}` does not define a prompt"
+    );
+    guard.insert(name.to_owned(), merged);
+    Ok(())
+}
+
+pub fn get_task(name: &str) -> Option<TaskPreset> {
+    let guard: RwLockReadGuard<_> = TASKS.read().expect("task registry poisoned");
+    guard.get(name).cloned()
+}
+
+/// Like [`get_task`] but reports the available task names on failure.
+pub fn resolve_task(name: &str) -> Result<TaskPreset> {
+    get_task(name).ok_or_else(|| {
+        anyhow!(
+            "unknown task `{name}` (available: {})",
+            task_names().join(", ")
+        )
+    })
+}
+
+pub fn task_names() -> Vec<String> {
+    let guard: RwLockReadGuard<_> = TASKS.read().expect("task registry poisoned");
+    guard.keys().cloned().collect()
+}
+
+fn builtin_tasks() -> Vec<(&'static str, TaskPreset)> {
+    vec![
+        (
+            "markdown",
+            TaskPreset::new(
+                "Convert a document page to markdown with layout grounding.",
+                "<image>\n<|grounding|>Convert the document to markdown.",
+            )
+            .with_vision(1024, 640, true)
+            .with_max_new_tokens(4096),
+        ),
+        (
+            "free-ocr",
+            TaskPreset::new("Plain text OCR without layout.", "<image>\nFree OCR.")
+                .with_vision(1024, 640, true)
+                .with_max_new_tokens(4096),
+        ),
+        (
+            "grounding",
+            TaskPreset::new(
+                "OCR arbitrary images and return text with bounding boxes.",
+                "<image>\n<|grounding|>OCR this image.",
+            )
+            .with_vision(1024, 640, true)
+            .with_max_new_tokens(4096),
+        ),
+        (
+            "figure",
+            TaskPreset::new(
+                "Parse charts, plots and figures embedded in documents.",
+                "<image>\nParse the figure.",
+            )
+            .with_vision(1024, 1024, false),
+        ),
+        (
+            "describe",
+            TaskPreset::new(
+                "Describe the image in natural language.",
+                "<image>\nDescribe this image in detail.",
+            )
+            .with_vision(1024, 1024, false),
+        ),
+        (
+            "extract",
+            TaskPreset::new(
+                "Extract the given form or invoice fields as a JSON object.",
+                "<image>\nExtract the following fields from the document. Answer with a single JSON object using exactly these keys, and null for fields that are not present:\n{query}",
+            )
+            .with_vision(1024, 640, true)
+            .with_max_new_tokens(1024),
+        ),
+        (
+            "locate",
+            TaskPreset::new(
+                "Find the given text in the image and return its boxes.",
+                "<image>\nLocate <|ref|>{query}<|/ref|> in the image.",
+            )
+            .with_vision(1024, 640, true),
+        ),
+    ]
+}
@@ -0,0 +1,580 @@
+use clap::ValueEnum;
+use image::{
+    DynamicImage, GrayImage, Rgb, RgbImage, imageops, imageops::FilterType, metadata::Orientation,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::grounding::{BoundingBox, GROUNDING_COORD_SCALE};
+
+/// Longest side of the grayscale thumbnail used for orientation and skew analysis.
+const ANALYSIS_SIDE: u32 = 1024;
+/// Largest skew (degrees) searched by [`estimate_skew`].
+const MAX_SKEW_DEGREES: f32 = 10.0;
+/// Skew below this many degrees is left alone.
+const MIN_SKEW_DEGREES: f32 = 0.3;
+/// Upper bound on ink samples fed to the skew search.
+const MAX_SKEW_SAMPLES: usize = 40_000;
+/// Longest side of the background estimate used for shadow removal.
+const SHADOW_GRID_SIDE: u32 = 48;
+
+/// Optional document cleanup applied to each image before vision preprocessing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CleanupOptions {
+    /// Detect pages turned by 90° from the direction of the text lines.
+    pub auto_rotate: bool,
+    pub deskew: bool,
+    /// Stretch the luminance range so the darkest ink is black and the paper white.
+    pub normalize_contrast: bool,
+    /// Divide out the slowly varying background (shadows, uneven lighting).
+    pub remove_shadows: bool,
+    /// 3×3 median filter against scanner speckle and JPEG noise.
+    pub denoise: bool,
+    /// Otsu threshold to pure black ink on white paper.
+    pub binarize: bool,
+}
+
+/// Individual cleanup step, as accepted by the CLI/server `cleanup` lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CleanupStep {
+    Rotate,
+    Deskew,
+    Contrast,
+    Shadows,
+    Denoise,
+    Binarize,
+    /// Every step above.
+    All,
+}
+
+impl CleanupOptions {
+    /// Options with exactly `steps` enabled.
+    pub fn from_steps(steps: &[CleanupStep]) -> Self {
+        let mut options = Self::default();
+        for step in steps {
+            match step {
+                CleanupStep::Rotate => options.auto_rotate = true,
+                CleanupStep::Deskew => options.deskew = true,
+                CleanupStep::Contrast => options.normalize_contrast = true,
+                CleanupStep::Shadows => options.remove_shadows = true,
+                CleanupStep::Denoise => options.denoise = true,
+                CleanupStep::Binarize => options.binarize = true,
+                CleanupStep::All => {
+                    options = Self {
+                        auto_rotate: true,
+                        deskew: true,
+                        normalize_contrast: true,
+                        remove_shadows: true,
+                        denoise: true,
+                        binarize: true,
+                    }
+                }
+            }
+        }
+        options
+    }
+
+    /// Whether any step is enabled.
+    pub fn any_enabled(&self) -> bool {
+        self.auto_rotate
+            || self.deskew
+            || self.normalize_contrast
+            || self.remove_shadows
+            || self.denoise
+            || self.binarize
+    }
+}
+
+/// What [`clean_image`] changed. Geometric steps (rotation, deskew) change the frame that
+/// grounding coordinates refer to; [`CleanupReport::to_input_frame`] maps them back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct CleanupReport {
+    /// Size of the image before cleanup.
+    pub width: u32,
+    pub height: u32,
+    /// EXIF orientation tag (2–8) applied when the file was decoded, before cleanup; `None` when
+    /// the file was stored upright or the tag was ignored. Filled in by [`record_orientations`].
+    pub exif_orientation: Option<u8>,
+    /// Clockwise quarter-turn applied by auto-rotation: 0, 90 or 270.
+    pub rotation_degrees: u32,
+    /// Detected skew that was corrected, in degrees (positive = lines falling to the right).
+    pub skew_degrees: f32,
+    pub contrast_normalized: bool,
+    pub shadows_removed: bool,
+    pub denoised: bool,
+    pub binarized: bool,
+}
+
+impl CleanupReport {
+    /// Whether rotation or deskew moved the page content.
+    pub fn changes_geometry(&self) -> bool {
+        self.rotation_degrees != 0 || self.skew_degrees != 0.0
+    }
+
+    /// Map a model-normalised box on the cleaned image to the normalised frame of the input
+    /// image by undoing the deskew and then the quarter turn. A box turned by the deskew is
+    /// replaced by its bounding box.
+    pub fn to_input_frame(&self, bbox: &BoundingBox) -> BoundingBox {
+        let (width, height) = (self.width as f32, self.height as f32);
+        // Deskew keeps the canvas, so the cleaned image has the quarter-turned size.
+        let (turned_width, turned_height) = match self.rotation_degrees {
+            90 | 270 => (height, width),
+            _ => (width, height),
+        };
+        let (cx, cy) = (turned_width / 2.0, turned_height / 2.0);
+        let (sin, cos) = self.skew_degrees.to_radians().sin_cos();
+        let corners = [
+            (bbox.x1, bbox.y1),
+            (bbox.x2, bbox.y1),
+            (bbox.x1, bbox.y2),
+            (bbox.x2, bbox.y2),
+        ]
+        .map(|(x, y)| {
+            let x = x / GROUNDING_COORD_SCALE * turned_width - cx;
+            let y = y / GROUNDING_COORD_SCALE * turned_height - cy;
+            // The point `rotate_image` sampled for this output pixel.
+            let (x, y) = (cx + x * cos - y * sin, cy + x * sin + y * cos);
+            let (x, y) = match self.rotation_degrees {
+                90 => (y, height - x),
+                270 => (width - y, x),
+                _ => (x, y),
+            };
+            (
+                x.clamp(0.0, width) / width.max(1.0) * GROUNDING_COORD_SCALE,
+                y.clamp(0.0, height) / height.max(1.0) * GROUNDING_COORD_SCALE,
+            )
+        });
+        let xs = corners.map(|(x, _)| x);
+        let ys = corners.map(|(_, y)| y);
+        BoundingBox::new(
+            xs.into_iter().fold(f32::MAX, f32::min),
+            ys.into_iter().fold(f32::MAX, f32::min),
+            xs.into_iter().fold(f32::MIN, f32::max),
+            ys.into_iter().fold(f32::MIN, f32::max),
+        )
+    }
+}
+
+/// Run the enabled pixel-level steps on `image`: auto-rotate, deskew, shadow removal, contrast
+/// normalisation, denoising, then binarisation.
+pub fn clean_image(
+    image: &DynamicImage,
+    options: &CleanupOptions,
+) -> (DynamicImage, CleanupReport) {
+    let mut report = CleanupReport {
+        width: image.width(),
+        height: image.height(),
+        ..CleanupReport::default()
+    };
+    if !options.any_enabled() {
+        return (image.clone(), report);
+    }
+    let mut rgb = image.to_rgb8();
+
+    if options.auto_rotate {
+        report.rotation_degrees = detect_quarter_turn(&analysis_view(&rgb));
+        rgb = match report.rotation_degrees {
+            90 => imageops::rotate90(&rgb),
+            270 => imageops::rotate270(&rgb),
+            _ => rgb,
+        };
+    }
+    if options.deskew {
+        let skew = estimate_skew(&analysis_view(&rgb), MAX_SKEW_DEGREES);
+        if skew.abs() >= MIN_SKEW_DEGREES {
+            let fill = paper_colour(&rgb);
+            rgb = rotate_image(&rgb, skew, fill);
+            report.skew_degrees = skew;
+        }
+    }
+    if options.remove_shadows {
+        rgb = remove_shadows(&rgb);
+        report.shadows_removed = true;
+    }
+    let stretched = options
+        .normalize_contrast
+        .then(|| normalize_contrast(&rgb))
+        .flatten();
+    if let Some(stretched) = stretched {
+        rgb = stretched;
+        report.contrast_normalized = true;
+    }
+    if options.denoise {
+        rgb = median_filter(&rgb);
+        report.denoised = true;
+    }
+    if options.binarize {
+        rgb = binarize(&rgb);
+        report.binarized = true;
+    }
+    (DynamicImage::ImageRgb8(rgb), report)
+}
+
+/// Add the EXIF orientation each of `images` was decoded with to the cleanup `reports` of
+/// their run: one report per image, or per region or segment of a single image. Without
+/// cleanup there are no reports, so images that were turned upright get a fresh one.
+pub fn record_orientations(
+    reports: &mut Vec<CleanupReport>,
+    images: &[DynamicImage],
+    orientations: &[Orientation],
+) {
+    let applied = |orientation: &Orientation| {
+        (*orientation != Orientation::NoTransforms).then(|| orientation.to_exif())
+    };
+    if reports.is_empty() {
+        if orientations
+            .iter()
+            .all(|orientation| applied(orientation).is_none())
+        {
+            return;
+        }
+        *reports = images
+            .iter()
+            .map(|image| CleanupReport {
+                width: image.width(),
+                height: image.height(),
+                ..CleanupReport::default()
+            })
+            .collect();
+    }
+    match orientations {
+        [orientation] => {
+            for report in reports.iter_mut() {
+                report.exif_orientation = applied(orientation);
+            }
+        }
+        _ if orientations.len() == reports.len() => {
+            for (report, orientation) in reports.iter_mut().zip(orientations) {
+                report.exif_orientation = applied(orientation);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn analysis_view(rgb: &RgbImage) -> GrayImage {
+    let gray = DynamicImage::ImageRgb8(rgb.clone());
+    let gray = if rgb.width().max(rgb.height()) > ANALYSIS_SIDE {
+        gray.resize(ANALYSIS_SIDE, ANALYSIS_SIDE, FilterType::Triangle)
+    } else {
+        gray
+    };
+    gray.to_luma8()
+}
+
+/// Otsu threshold separating ink from paper.
+pub fn otsu_threshold(gray: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in gray.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    let weighted_total: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, &count)| value as f64 * count as f64)
+        .sum();
+    let (mut background, mut weighted_background) = (0u64, 0f64);
+    let (mut best, mut best_variance) = (127u8, -1f64);
+    for (value, &count) in histogram.iter().enumerate() {
+        background += count;
+        if background == 0 || background == total {
+            continue;
+        }
+        weighted_background += value as f64 * count as f64;
+        let foreground = total - background;
+        let mean_background = weighted_background / background as f64;
+        let mean_foreground = (weighted_total - weighted_background) / foreground as f64;
+        let variance =
+            background as f64 * foreground as f64 * (mean_background - mean_foreground).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best = value as u8;
+        }
+    }
+    best
+}
+
+fn ink_mask(gray: &GrayImage) -> Vec<bool> {
+    let threshold = otsu_threshold(gray);
+    gray.pixels().map(|pixel| pixel[0] <= threshold).collect()
+}
+
+fn coefficient_of_variation(values: &[f64]) -> f64 {
+    let mean = values.iter().sum::<f64>() / values.len().max(1) as f64;
+    if mean <= 0.0 {
+        return 0.0;
+    }
+    let variance =
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len().max(1) as f64;
+    variance.sqrt() / mean
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    let mean = values.iter().sum::<f64>() / values.len().max(1) as f64;
+    (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len().max(1) as f64).sqrt()
+}
+
+/// Clockwise rotation (0, 90 or 270) that turns sideways text lines horizontal. Text lines make
+/// the ink profile across them far more uneven than along them; the turn direction comes from
+/// which end of the lines is aligned (line starts form the straight margin).
+pub fn detect_quarter_turn(gray: &GrayImage) -> u32 {
+    let (width, height) = (gray.width() as usize, gray.height() as usize);
+    if width < 8 || height < 8 {
+        return 0;
+    }
+    let ink = ink_mask(gray);
+    let mut rows = vec![0f64; height];
+    let mut cols = vec![0f64; width];
+    for y in 0..height {
+        for x in 0..width {
+            if ink[y * width + x] {
+                rows[y] += 1.0;
+                cols[x] += 1.0;
+            }
+        }
+    }
+    let total: f64 = rows.iter().sum();
+    if total < (width * height) as f64 * 0.002 {
+        return 0;
+    }
+    if coefficient_of_variation(&cols) <= coefficient_of_variation(&rows) * 1.3 {
+        return 0;
+    }
+
+    // Lines run vertically: compare how straight their top and bottom ends are.
+    let min_run = (height as f64 * 0.05).max(2.0);
+    let mut tops = Vec::new();
+    let mut bottoms = Vec::new();
+    for x in 0..width {
+        if cols[x] < min_run {
+            continue;
+        }
+        let mut column = (0..height).filter(|&y| ink[y * width + x]);
+        let top = column.next().unwrap_or(0);
+        let bottom = column.next_back().unwrap_or(top);
+        tops.push(top as f64);
+        bottoms.push(bottom as f64);
+    }
+    if tops.len() < 2 {
+        return 0;
+    }
+    // Starts aligned at the top: the page was turned clockwise, so turn it back.
+    if std_dev(&tops) <= std_dev(&bottoms) {
+        270
+    } else {
+        90
+    }
+}
+
+/// Skew of the text lines in degrees within `±max_degrees`, found by maximising the sharpness
+/// of the horizontal ink projection. Positive means lines fall towards the right.
+pub fn estimate_skew(gray: &GrayImage, max_degrees: f32) -> f32 {
+    let (width, height) = gray.dimensions();
+    let ink = ink_mask(gray);
+    let mut points: Vec<(f32, f32)> = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            if ink[(y * width + x) as usize] {
+                points.push((x as f32, y as f32));
+            }
+        }
+    }
+    if points.len() < 16 {
+        return 0.0;
+    }
+    if points.len() > MAX_SKEW_SAMPLES {
+        let stride = points.len().div_ceil(MAX_SKEW_SAMPLES);
+        points = points.into_iter().step_by(stride).collect();
+    }
+
+    let diagonal = ((width * width + height * height) as f32).sqrt();
+    let bins = (diagonal * 2.0).ceil() as usize + 2;
+    let score = |degrees: f32| -> f64 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let mut histogram = vec![0u32; bins];
+        for &(x, y) in &points {
+            let projected = y * cos - x * sin + diagonal;
+            let bin = (projected.max(0.0) as usize).min(bins - 1);
+            histogram[bin] += 1;
+        }
+        histogram.iter().map(|&count| (count as f64).powi(2)).sum()
+    };
+
+    let search = |from: f32, to: f32, step: f32| -> f32 {
+        let steps = ((to - from) / step).round() as i32;
+        (0..=steps)
+            .map(|idx| from + idx as f32 * step)
+            .map(|angle| (angle, score(angle)))
+            .fold((0.0, f64::MIN), |best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            })
+            .0
+    };
+    let coarse = search(-max_degrees, max_degrees, 0.5);
+    search(coarse - 0.5, coarse + 0.5, 0.1)
+}
+
+/// Rotate `rgb` so lines skewed by `degrees` become horizontal, keeping the canvas size and
+/// filling uncovered corners with `fill`. Uses bilinear sampling.
+pub fn rotate_image(rgb: &RgbImage, degrees: f32, fill: Rgb<u8>) -> RgbImage {
+    let (width, height) = rgb.dimensions();
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let cx = (width as f32 - 1.0) / 2.0;
+    let cy = (height as f32 - 1.0) / 2.0;
+    RgbImage::from_fn(width, height, |x, y| {
+        let dx = x as f32 - cx;
+        let dy = y as f32 - cy;
+        let sx = cx + dx * cos - dy * sin;
+        let sy = cy + dx * sin + dy * cos;
+        sample_bilinear(rgb, sx, sy).unwrap_or(fill)
+    })
+}
+
+fn sample_bilinear(rgb: &RgbImage, x: f32, y: f32) -> Option<Rgb<u8>> {
+    let (width, height) = rgb.dimensions();
+    if x < 0.0 || y < 0.0 || x > (width - 1) as f32 || y > (height - 1) as f32 {
+        return None;
+    }
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+    let mut out = [0u8; 3];
+    for (channel, value) in out.iter_mut().enumerate() {
+        let top = rgb.get_pixel(x0, y0)[channel] as f32 * (1.0 - fx)
+            + rgb.get_pixel(x1, y0)[channel] as f32 * fx;
+        let bottom = rgb.get_pixel(x0, y1)[channel] as f32 * (1.0 - fx)
+            + rgb.get_pixel(x1, y1)[channel] as f32 * fx;
+        *value = (top * (1.0 - fy) + bottom * fy).round().clamp(0.0, 255.0) as u8;
+    }
+    Some(Rgb(out))
+}
+
+/// Median colour of the image border, used as the paper colour.
+fn paper_colour(rgb: &RgbImage) -> Rgb<u8> {
+    let (width, height) = rgb.dimensions();
+    let mut border: Vec<Rgb<u8>> = Vec::new();
+    for x in 0..width {
+        border.push(*rgb.get_pixel(x, 0));
+        border.push(*rgb.get_pixel(x, height - 1));
+    }
+    for y in 0..height {
+        border.push(*rgb.get_pixel(0, y));
+        border.push(*rgb.get_pixel(width - 1, y));
+    }
+    let channel = |idx: usize| {
+        let mut values: Vec<u8> = border.iter().map(|pixel| pixel[idx]).collect();
+        values.sort_unstable();
+        values[values.len() / 2]
+    };
+    Rgb([channel(0), channel(1), channel(2)])
+}
+
+/// Flatten uneven lighting: estimate the paper brightness on a coarse grid (taking the
+/// brightest nearby cell so text does not darken it), upsample it, and divide it out.
+pub fn remove_shadows(rgb: &RgbImage) -> RgbImage {
+    let (width, height) = rgb.dimensions();
+    let luma = DynamicImage::ImageRgb8(rgb.clone()).to_luma8();
+    let coarse = DynamicImage::ImageLuma8(luma).resize(
+        SHADOW_GRID_SIDE,
+        SHADOW_GRID_SIDE,
+        FilterType::Triangle,
+    );
+    let coarse = coarse.to_luma8();
+    let (cw, ch) = coarse.dimensions();
+    let dilated = GrayImage::from_fn(cw, ch, |x, y| {
+        let mut brightest = 0u8;
+        for ny in y.saturating_sub(1)..=(y + 1).min(ch - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(cw - 1) {
+                brightest = brightest.max(coarse.get_pixel(nx, ny)[0]);
+            }
+        }
+        image::Luma([brightest])
+    });
+    let background = imageops::resize(&dilated, width, height, FilterType::Triangle);
+    RgbImage::from_fn(width, height, |x, y| {
+        let bg = background.get_pixel(x, y)[0].max(1) as f32;
+        let pixel = rgb.get_pixel(x, y);
+        Rgb(pixel
+            .0
+            .map(|value| (value as f32 * 255.0 / bg).round().min(255.0) as u8))
+    })
+}
+
+/// Stretch luminance between the 1st and 99th percentiles to the full range. Returns `None`
+/// when the image is already close to full range or nearly flat.
+pub fn normalize_contrast(rgb: &RgbImage) -> Option<RgbImage> {
+    let mut histogram = [0u64; 256];
+    for pixel in rgb.pixels() {
+        let [r, g, b] = pixel.0;
+        let luma = (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32).round() as usize;
+        histogram[luma.min(255)] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    let percentile = |fraction: f64| -> u8 {
+        let target = (total as f64 * fraction) as u64;
+        let mut seen = 0u64;
+        for (value, &count) in histogram.iter().enumerate() {
+            seen += count;
+            if seen > target {
+                return value as u8;
+            }
+        }
+        255
+    };
+    let (low, high) = (percentile(0.01), percentile(0.99));
+    if high <= low.saturating_add(16) || (low <= 8 && high >= 247) {
+        return None;
+    }
+    let scale = 255.0 / (high - low) as f32;
+    let mut out = rgb.clone();
+    for pixel in out.pixels_mut() {
+        pixel.0 = pixel.0.map(|value| {
+            ((value as f32 - low as f32) * scale)
+                .round()
+                .clamp(0.0, 255.0) as u8
+        });
+    }
+    Some(out)
+}
+
+/// 3×3 median per channel; border pixels use the neighbours inside the image.
+pub fn median_filter(rgb: &RgbImage) -> RgbImage {
+    let (width, height) = rgb.dimensions();
+    RgbImage::from_fn(width, height, |x, y| {
+        let mut window = [[0u8; 9]; 3];
+        let mut len = 0;
+        for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                let pixel = rgb.get_pixel(nx, ny);
+                for (values, &value) in window.iter_mut().zip(&pixel.0) {
+                    values[len] = value;
+                }
+                len += 1;
+            }
+        }
+        Rgb(window.map(|mut values| {
+            let values = &mut values[..len];
+            values.sort_unstable();
+            values[len / 2]
+        }))
+    })
+}
+
+/// Black ink on white paper, split at the Otsu threshold of the luminance.
+pub fn binarize(rgb: &RgbImage) -> RgbImage {
+    let luma = DynamicImage::ImageRgb8(rgb.clone()).to_luma8();
+    let threshold = otsu_threshold(&luma);
+    RgbImage::from_fn(rgb.width(), rgb.height(), |x, y| {
+        if luma.get_pixel(x, y)[0] <= threshold {
+            Rgb([0, 0, 0])
+        } else {
+            Rgb([255, 255, 255])
+        }
+    })
+}
@@ -0,0 +1,298 @@
+use std::{fmt, io::Cursor, str::FromStr};
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use image::{
+    ColorType, DynamicImage, GrayImage, ImageBuffer, ImageDecoder, ImageFormat, ImageReader, Luma,
+    Pixel, Rgb, RgbImage, metadata::Orientation,
+};
+use serde::{Deserialize, Serialize};
+use tiff::{
+    ColorType as TiffColor,
+    decoder::{Decoder, DecodingResult},
+    tags::Tag,
+};
+
+/// Upper bound on pages read from one multi-page TIFF.
+pub const MAX_TIFF_PAGES: usize = 1000;
+
+/// How input images are turned into the RGB pixels the vision pipeline expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecodeOptions {
+    /// Rotate/flip according to the EXIF orientation tag (phone photos).
+    pub exif_orientation: bool,
+    /// Colour transparent pixels are composited onto.
+    pub background: BackgroundColor,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            exif_orientation: true,
+            background: BackgroundColor::WHITE,
+        }
+    }
+}
+
+/// Opaque RGB colour written as `#rrggbb` (or `#rgb`, `white`, `black`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct BackgroundColor(pub [u8; 3]);
+
+impl BackgroundColor {
+    pub const WHITE: Self = Self([255, 255, 255]);
+    pub const BLACK: Self = Self([0, 0, 0]);
+}
+
+impl Default for BackgroundColor {
+    fn default() -> Self {
+        Self::WHITE
+    }
+}
+
+impl FromStr for BackgroundColor {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim();
+        match value.to_ascii_lowercase().as_str() {
+            "white" => return Ok(Self::WHITE),
+            "black" => return Ok(Self::BLACK),
+            _ => {}
+        }
+        let hex = value.strip_prefix('#').unwrap_or(value);
+        if !hex.is_ascii() {
+            bail!("invalid colour `{value}`; expected #rrggbb");
+        }
+        let digits: Vec<u8> = match hex.len() {
+            3 => hex
+                .chars()
+                .map(|digit| u8::from_str_radix(&digit.to_string(), 16).map(|v| v * 17))
+                .collect::<Result<_, _>>(),
+            6 => (0..6)
+                .step_by(2)
+                .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16))
+                .collect::<Result<_, _>>(),
+            _ => bail!("invalid colour `{value}`; expected #rrggbb"),
+        }
+        .map_err(|_| anyhow!("invalid colour `{value}`; expected #rrggbb"))?;
+        Ok(Self([digits[0], digits[1], digits[2]]))
+    }
+}
+
+impl TryFrom<String> for BackgroundColor {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<BackgroundColor> for String {
+    fn from(color: BackgroundColor) -> Self {
+        color.to_string()
+    }
+}
+
+impl fmt::Display for BackgroundColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+/// Image decoded by [`decode_image`], normalised to RGB8.
+#[derive(Debug, Clone)]
+pub struct DecodedImage {
+    pub image: DynamicImage,
+    /// Orientation tag that was applied; `NoTransforms` when absent or ignored.
+    pub orientation: Orientation,
+    /// Colour type stored in the file, before normalisation.
+    pub source_color: ColorType,
+}
+
+impl DecodedImage {
+    /// Whether transparent pixels were composited onto the background.
+    pub fn flattened_alpha(&self) -> bool {
+        self.source_color.has_alpha()
+    }
+}
+
+/// Decode every page of `bytes`. Multi-page TIFFs yield one image per page; every other
+/// format (including animated GIF/WebP, which keep their first frame) yields one image.
+pub fn decode_pages(bytes: &[u8], options: &DecodeOptions) -> Result<Vec<DecodedImage>> {
+    let format = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .context("failed to detect image format")?
+        .format();
+    if format != Some(ImageFormat::Tiff) {
+        return Ok(vec![decode_image(bytes, options)?]);
+    }
+    let mut decoder = Decoder::new(Cursor::new(bytes)).context("failed to read TIFF header")?;
+    if !decoder.more_images() {
+        return Ok(vec![decode_image(bytes, options)?]);
+    }
+    let mut pages = Vec::new();
+    loop {
+        ensure!(
+            pages.len() < MAX_TIFF_PAGES,
+            "TIFF has more than {MAX_TIFF_PAGES} pages"
+        );
+        let page = decode_tiff_page(&mut decoder, options)
+            .with_context(|| format!("failed to decode TIFF page {}", pages.len() + 1))?;
+        pages.push(page);
+        if !decoder.more_images() {
+            return Ok(pages);
+        }
+        decoder
+            .next_image()
+            .with_context(|| format!("failed to read TIFF page {}", pages.len() + 1))?;
+    }
+}
+
+/// Decode the current page of a multi-page TIFF the way [`decode_image`] decodes a file.
+fn decode_tiff_page(
+    decoder: &mut Decoder<Cursor<&[u8]>>,
+    options: &DecodeOptions,
+) -> Result<DecodedImage> {
+    let (width, height) = decoder.dimensions()?;
+    let color = decoder.colortype()?;
+    let orientation = if options.exif_orientation {
+        decoder
+            .find_tag_unsigned::<u8>(Tag::Orientation)?
+            .and_then(Orientation::from_exif)
+            .unwrap_or(Orientation::NoTransforms)
+    } else {
+        Orientation::NoTransforms
+    };
+    let mut image = match (color, decoder.read_image()?) {
+        (TiffColor::Gray(1), DecodingResult::U8(packed)) => {
+            let stride = (width as usize).div_ceil(8);
+            DynamicImage::ImageLuma8(GrayImage::from_fn(width, height, |x, y| {
+                let byte = packed[y as usize * stride + x as usize / 8];
+                Luma([if byte & (0x80 >> (x % 8)) != 0 {
+                    255
+                } else {
+                    0
+                }])
+            }))
+        }
+        (TiffColor::Gray(8), DecodingResult::U8(data)) => {
+            DynamicImage::ImageLuma8(buffer(width, height, data)?)
+        }
+        (TiffColor::Gray(16), DecodingResult::U16(data)) => {
+            DynamicImage::ImageLuma16(buffer(width, height, data)?)
+        }
+        (TiffColor::GrayA(8), DecodingResult::U8(data)) => {
+            DynamicImage::ImageLumaA8(buffer(width, height, data)?)
+        }
+        (TiffColor::GrayA(16), DecodingResult::U16(data)) => {
+            DynamicImage::ImageLumaA16(buffer(width, height, data)?)
+        }
+        (TiffColor::RGB(8), DecodingResult::U8(data)) => {
+            DynamicImage::ImageRgb8(buffer(width, height, data)?)
+        }
+        (TiffColor::RGB(16), DecodingResult::U16(data)) => {
+            DynamicImage::ImageRgb16(buffer(width, height, data)?)
+        }
+        (TiffColor::RGBA(8), DecodingResult::U8(data)) => {
+            DynamicImage::ImageRgba8(buffer(width, height, data)?)
+        }
+        (TiffColor::RGBA(16), DecodingResult::U16(data)) => {
+            DynamicImage::ImageRgba16(buffer(width, height, data)?)
+        }
+        (TiffColor::CMYK(8), DecodingResult::U8(data)) => {
+            let rgb = data
+                .chunks_exact(4)
+                .flat_map(|cmyk| {
+                    let k = 255 - u16::from(cmyk[3]);
+                    [0, 1, 2].map(|idx| ((255 - u16::from(cmyk[idx])) * k / 255) as u8)
+                })
+                .collect();
+            DynamicImage::ImageRgb8(buffer(width, height, rgb)?)
+        }
+        _ => bail!("unsupported TIFF colour type {color:?}"),
+    };
+    let source_color = image.color();
+    image.apply_orientation(orientation);
+    Ok(DecodedImage {
+        image: normalize_color(image, options.background),
+        orientation,
+        source_color,
+    })
+}
+
+fn buffer<P: Pixel>(
+    width: u32,
+    height: u32,
+    data: Vec<P::Subpixel>,
+) -> Result<ImageBuffer<P, Vec<P::Subpixel>>> {
+    ImageBuffer::from_raw(width, height, data)
+        .ok_or_else(|| anyhow!("TIFF page data does not match its {width}x{height} size"))
+}
+
+/// Decode `bytes`, guessing the format, then apply the EXIF orientation and normalise the
+/// pixels with [`normalize_color`].
+pub fn decode_image(bytes: &[u8], options: &DecodeOptions) -> Result<DecodedImage> {
+    let mut decoder = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .context("failed to detect image format")?
+        .into_decoder()
+        .context("failed to decode image")?;
+    let orientation = if options.exif_orientation {
+        decoder.orientation().unwrap_or(Orientation::NoTransforms)
+    } else {
+        Orientation::NoTransforms
+    };
+    let mut image = DynamicImage::from_decoder(decoder).context("failed to decode image")?;
+    let source_color = image.color();
+    image.apply_orientation(orientation);
+    Ok(DecodedImage {
+        image: normalize_color(image, options.background),
+        orientation,
+        source_color,
+    })
+}
+
+/// Convert any colour type to RGB8. Images with an alpha channel are composited onto
+/// `background` instead of having the channel dropped, which would expose whatever colour the
+/// encoder left under transparent pixels (often black).
+pub fn normalize_color(image: DynamicImage, background: BackgroundColor) -> DynamicImage {
+    if matches!(image, DynamicImage::ImageRgb8(_)) {
+        return image;
+    }
+    if !image.color().has_alpha() {
+        return DynamicImage::ImageRgb8(image.to_rgb8());
+    }
+    let rgba = image.to_rgba8();
+    let [br, bg, bb] = background.0.map(u32::from);
+    let flattened = RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0.map(u32::from);
+        let blend = |channel: u32, back: u32| ((channel * a + back * (255 - a) + 127) / 255) as u8;
+        Rgb([blend(r, br), blend(g, bg), blend(b, bb)])
+    });
+    DynamicImage::ImageRgb8(flattened)
+}
+
+/// Arrange decoded inputs (one entry per `<image>` slot, each holding its pages) into the image
+/// sets to run. Single-page inputs give one run with every image; a lone multi-page input gives
+/// one run per page, so each page goes through the regular single-image pipeline.
+pub fn page_runs<T>(inputs: Vec<Vec<T>>) -> Result<Vec<Vec<T>>> {
+    if inputs.iter().all(|pages| pages.len() == 1) {
+        return Ok(vec![inputs.into_iter().flatten().collect()]);
+    }
+    ensure!(
+        inputs.iter().all(|pages| !pages.is_empty()),
+        "input image has no pages"
+    );
+    ensure!(
+        inputs.len() == 1,
+        "a multi-page image must be the only image in the request (got {} images)",
+        inputs.len()
+    );
+    Ok(inputs
+        .into_iter()
+        .flatten()
+        .map(|page| vec![page])
+        .collect())
+}
@@ -1,8 +1,17 @@
+pub mod cleanup;
 pub mod clip;
+pub mod decode;
 pub mod preprocess;
 pub mod resample;
 pub mod sam;
 
+pub use cleanup::{CleanupOptions, CleanupReport, CleanupStep, clean_image, record_orientations};
 pub use clip::{ClipDebugTrace, ClipVisionModel, ClipVisionParams};
-pub use preprocess::{DynamicPreprocessResult, dynamic_preprocess};
+pub use decode::{
+    BackgroundColor, DecodeOptions, DecodedImage, decode_image, decode_pages, normalize_color,
+    page_runs,
+};
+pub use preprocess::{
+    DynamicPreprocessResult, MAX_TILE_LIMIT, TilingOptions, dynamic_preprocess, select_tile_grid,
+};
 pub use sam::{SamBackbone, SamBackboneParams, SamDebugTrace};
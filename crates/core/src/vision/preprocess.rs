@@ -10,15 +10,58 @@ pub struct DynamicPreprocessResult {
     pub ratio: (u32, u32),
 }
 
-pub fn dynamic_preprocess(
-    image: &DynamicImage,
+/// Upper bound accepted for `TilingOptions::max_tiles`.
+pub const MAX_TILE_LIMIT: u32 = 64;
+
+/// Limits for the dynamic tiler used in crop mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilingOptions {
+    /// Minimum number of local tiles in the chosen grid.
+    pub min_tiles: u32,
+    /// Maximum number of local tiles in the chosen grid.
+    pub max_tiles: u32,
+    /// Append a downscaled view of the whole image as an extra local tile.
+    pub use_thumbnail: bool,
+}
+
+impl Default for TilingOptions {
+    fn default() -> Self {
+        Self {
+            min_tiles: 2,
+            max_tiles: 9,
+            use_thumbnail: false,
+        }
+    }
+}
+
+impl TilingOptions {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(self.min_tiles >= 1, "min_tiles must be at least 1");
+        anyhow::ensure!(
+            self.max_tiles >= self.min_tiles,
+            "max_tiles ({}) must be >= min_tiles ({})",
+            self.max_tiles,
+            self.min_tiles
+        );
+        anyhow::ensure!(
+            self.max_tiles <= MAX_TILE_LIMIT,
+            "max_tiles ({}) must be <= {MAX_TILE_LIMIT}",
+            self.max_tiles
+        );
+        Ok(())
+    }
+}
+
+/// Pick the `(columns, rows)` tile grid whose aspect ratio best matches the image while using
+/// between `min_num` and `max_num` tiles.
+pub fn select_tile_grid(
+    width: u32,
+    height: u32,
     min_num: u32,
     max_num: u32,
     image_size: u32,
-    use_thumbnail: bool,
-) -> DynamicPreprocessResult {
-    let (orig_width, orig_height) = image.dimensions();
-    let aspect_ratio = orig_width as f64 / orig_height as f64;
+) -> (u32, u32) {
+    let aspect_ratio = width as f64 / height as f64;
 
     let mut target_ratios: BTreeSet<(u32, u32)> = BTreeSet::new();
     for n in min_num..=max_num {
@@ -33,7 +76,7 @@ pub fn dynamic_preprocess(
 
     let mut target_aspect_ratio = (1, 1);
     let mut best_ratio_diff = f64::MAX;
-    let area = (orig_width * orig_height) as f64;
+    let area = (width * height) as f64;
 
     for (w_ratio, h_ratio) in &target_ratios {
         let target_ratio = *w_ratio as f64 / *h_ratio as f64;
@@ -47,6 +90,19 @@ pub fn dynamic_preprocess(
             }
         }
     }
+    target_aspect_ratio
+}
+
+pub fn dynamic_preprocess(
+    image: &DynamicImage,
+    min_num: u32,
+    max_num: u32,
+    image_size: u32,
+    use_thumbnail: bool,
+) -> DynamicPreprocessResult {
+    let (orig_width, orig_height) = image.dimensions();
+    let target_aspect_ratio =
+        select_tile_grid(orig_width, orig_height, min_num, max_num, image_size);
 
     let target_width = image_size * target_aspect_ratio.0;
     let target_height = image_size * target_aspect_ratio.1;
@@ -0,0 +1,226 @@
+use anyhow::{Result, ensure};
+use image::DynamicImage;
+
+use crate::{
+    engine::{OcrEngine, OcrRequest, OcrResult, VisionSettings},
+    grounding::{
+        BoundingBox, GroundingSpan, parse_grounding, replace_span_contents, strip_grounding,
+    },
+    model::FinishReason,
+    roi::crop_region,
+    vision::{CleanupOptions, resample::resize_bicubic},
+};
+
+/// Prompt used to re-read each zoomed crop.
+pub const DEFAULT_ZOOM_PROMPT: &str = "<image>\nFree OCR.";
+
+/// Controls which first-pass regions are re-read and how their crops are enlarged.
+#[derive(Debug, Clone)]
+pub struct ZoomOptions {
+    /// Regions whose box is shorter than this fraction of the image height are re-read.
+    pub small_height_ratio: f32,
+    /// Also re-read regions whose first-pass text looks unreliable (empty, replacement
+    /// characters, mostly punctuation). Greedy decoding exposes no token scores, so this is the
+    /// low-confidence signal.
+    pub refine_unreliable: bool,
+    /// Pixels of context added around each box before cropping.
+    pub padding: u32,
+    /// Upscale factor applied to each crop.
+    pub scale: f32,
+    /// Upper bound on the longest side of an upscaled crop.
+    pub max_side: u32,
+    /// Maximum number of regions refined per image.
+    pub max_regions: usize,
+    /// Block labels never refined (figures carry no text to re-read).
+    pub skip_labels: Vec<String>,
+    pub prompt: String,
+    /// Token budget per refined region; falls back to the request budget.
+    pub max_new_tokens: Option<usize>,
+}
+
+impl Default for ZoomOptions {
+    fn default() -> Self {
+        Self {
+            small_height_ratio: 0.025,
+            refine_unreliable: true,
+            padding: 8,
+            scale: 3.0,
+            max_side: 2048,
+            max_regions: 32,
+            skip_labels: vec!["image".to_string()],
+            prompt: DEFAULT_ZOOM_PROMPT.to_string(),
+            max_new_tokens: Some(512),
+        }
+    }
+}
+
+/// A region that was re-read during the second pass.
+#[derive(Debug, Clone)]
+pub struct ZoomRegion {
+    /// Index into the first-pass grounding spans.
+    pub index: usize,
+    pub label: String,
+    /// Region in original-image pixels (before padding).
+    pub region: BoundingBox,
+    pub original: String,
+    pub refined: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ZoomResult {
+    /// First-pass document (grounding markup kept) with refined regions spliced in.
+    pub text: String,
+    pub first_pass: OcrResult,
+    pub refinements: Vec<ZoomRegion>,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    /// Most severe finish reason over both passes.
+    pub finish_reason: FinishReason,
+}
+
+/// Vision settings for the zoomed crops: the mode the first pass resolved to, without cleanup
+/// or automatic resolution, so crops are neither rotated nor given a different token budget.
+pub fn refinement_vision(first_pass: VisionSettings) -> VisionSettings {
+    VisionSettings {
+        auto_resolution: None,
+        cleanup: CleanupOptions::default(),
+        ..first_pass
+    }
+}
+
+/// Pick the first-pass spans worth re-reading, returned as `(span index, pixel box)`.
+pub fn select_zoom_regions(
+    spans: &[GroundingSpan],
+    width: u32,
+    height: u32,
+    options: &ZoomOptions,
+) -> Vec<(usize, BoundingBox)> {
+    let max_height = options.small_height_ratio * height as f32;
+    spans
+        .iter()
+        .enumerate()
+        .filter(|(_, span)| {
+            !options
+                .skip_labels
+                .iter()
+                .any(|label| label.eq_ignore_ascii_case(&span.label))
+        })
+        .filter_map(|(idx, span)| {
+            // Multi-box spans cover separate areas; refine them as one union box.
+            let pixel = span
+                .boxes
+                .iter()
+                .map(|bbox| bbox.to_pixels(width, height))
+                .reduce(|a, b| {
+                    BoundingBox::new(
+                        a.x1.min(b.x1),
+                        a.y1.min(b.y1),
+                        a.x2.max(b.x2),
+                        a.y2.max(b.y2),
+                    )
+                })?;
+            if pixel.area() <= 0.0 {
+                return None;
+            }
+            let small = pixel.height() < max_height;
+            let unreliable = options.refine_unreliable && looks_unreliable(&span.content);
+            (small || unreliable).then_some((idx, pixel))
+        })
+        .take(options.max_regions)
+        .collect()
+}
+
+/// Heuristic for first-pass text that probably needs another look.
+pub fn looks_unreliable(content: &str) -> bool {
+    let text = content.trim();
+    if text.is_empty() || text.contains('\u{FFFD}') {
+        return true;
+    }
+    let total = text.chars().filter(|c| !c.is_whitespace()).count();
+    let alnum = text.chars().filter(|c| c.is_alphanumeric()).count();
+    total >= 4 && alnum * 2 < total
+}
+
+/// Size of a `width`×`height` crop after applying `scale`, capped at `max_side`.
+pub fn zoomed_size(width: u32, height: u32, scale: f32, max_side: u32) -> (u32, u32) {
+    let longest = width.max(height).max(1) as f32;
+    let factor = scale.min(max_side as f32 / longest).max(1.0);
+    (
+        ((width as f32 * factor).round() as u32).max(1),
+        ((height as f32 * factor).round() as u32).max(1),
+    )
+}
+
+/// Two-pass OCR: run `request` (expected to use a grounding prompt) on its single image, then
+/// re-read small or unreliable regions as upscaled crops and splice the results back.
+pub fn zoom_ocr(
+    engine: &OcrEngine,
+    request: &OcrRequest<'_>,
+    options: &ZoomOptions,
+) -> Result<ZoomResult> {
+    ensure!(
+        request.images.len() == 1,
+        "zoom mode expects exactly one image (got {})",
+        request.images.len()
+    );
+    let image = &request.images[0];
+    let first_pass = engine.run(request)?;
+    let mut prompt_tokens = first_pass.prompt_tokens;
+    let mut completion_tokens = first_pass.completion_tokens();
+    let mut finish_reason = first_pass.finish_reason;
+
+    let grounded = first_pass.grounded_text();
+    let spans = parse_grounding(&grounded);
+    let selected = select_zoom_regions(&spans, image.width(), image.height(), options);
+    let refine_prompt = engine.render_prompt(&options.prompt)?;
+
+    let mut refinements = Vec::with_capacity(selected.len());
+    for (index, region) in selected {
+        let padded = BoundingBox::new(
+            region.x1 - options.padding as f32,
+            region.y1 - options.padding as f32,
+            region.x2 + options.padding as f32,
+            region.y2 + options.padding as f32,
+        );
+        let (crop, _) = crop_region(image, &padded)?;
+        let (width, height) =
+            zoomed_size(crop.width(), crop.height(), options.scale, options.max_side);
+        let zoomed = DynamicImage::ImageRgb8(resize_bicubic(&crop.to_rgb8(), width, height));
+
+        let mut crop_request = OcrRequest::new(&refine_prompt, std::slice::from_ref(&zoomed));
+        crop_request.vision = Some(refinement_vision(first_pass.vision));
+        crop_request.max_new_tokens = options.max_new_tokens.or(request.max_new_tokens);
+        crop_request.repetition = request.repetition;
+        let result = engine.run(&crop_request)?;
+        prompt_tokens += result.prompt_tokens;
+        completion_tokens += result.completion_tokens();
+        finish_reason = finish_reason.max(result.finish_reason);
+
+        let refined = strip_grounding(&result.grounded_text()).trim().to_string();
+        if refined.is_empty() {
+            continue;
+        }
+        refinements.push(ZoomRegion {
+            index,
+            label: spans[index].label.clone(),
+            region,
+            original: spans[index].content.clone(),
+            refined,
+        });
+    }
+
+    let replacements: Vec<(usize, String)> = refinements
+        .iter()
+        .map(|zoom| (zoom.index, zoom.refined.clone()))
+        .collect();
+    let text = replace_span_contents(&grounded, &spans, &replacements);
+
+    Ok(ZoomResult {
+        text,
+        first_pass,
+        refinements,
+        prompt_tokens,
+        completion_tokens,
+        finish_reason,
+    })
+}
@@ -0,0 +1,146 @@
+use std::{collections::BTreeMap, env, fs, process, time::Duration};
+
+use deepseek_ocr_core::benchmark::{
+    BenchEvent, BenchField, BenchValue, BenchmarkReport, RequestSample, StageStats,
+    compare_stage_totals, friendly_stage_name, load_stage_totals, percentile,
+};
+
+fn event(stage: &'static str, ms: u64, fields: &[(&'static str, u64)]) -> BenchEvent {
+    BenchEvent {
+        stage,
+        duration: Duration::from_millis(ms),
+        fields: fields
+            .iter()
+            .map(|&(key, value)| BenchField {
+                key,
+                value: BenchValue::U64(value),
+            })
+            .collect(),
+    }
+}
+
+fn sample(ms: u64, prefill_ms: u64, decode_ms: u64) -> RequestSample {
+    RequestSample {
+        duration: Duration::from_millis(ms),
+        events: vec![
+            event("vision.prepare_inputs", 5, &[]),
+            event("vision.prepare_inputs", 5, &[]),
+            event("decode.prefill", prefill_ms, &[("prompt_tokens", 100)]),
+            event("decode.iterative", decode_ms, &[("steps", 20)]),
+        ],
+    }
+}
+
+#[test]
+fn percentiles_interpolate_between_ranks() {
+    let sorted = [10.0, 20.0, 30.0, 40.0];
+    assert_eq!(percentile(&sorted, 0.0), 10.0);
+    assert_eq!(percentile(&sorted, 0.5), 25.0);
+    assert!((percentile(&sorted, 0.9) - 37.0).abs() < 1e-9);
+    assert_eq!(percentile(&sorted, 1.0), 40.0);
+    assert_eq!(percentile(&[], 0.5), 0.0);
+
+    let stats = StageStats::from_samples("request", &[30.0, 10.0, 20.0]);
+    assert_eq!((stats.count, stats.total_ms, stats.avg_ms), (3, 60.0, 20.0));
+    assert_eq!(
+        (stats.min_ms, stats.max_ms, stats.p50_ms),
+        (10.0, 30.0, Some(20.0))
+    );
+}
+
+#[test]
+fn reports_sum_events_per_request_and_derive_token_rates() {
+    let report = BenchmarkReport::from_samples(
+        BTreeMap::new(),
+        vec!["page.png".to_string()],
+        1,
+        2,
+        &[sample(400, 100, 200), sample(600, 300, 200)],
+        Some(64 * 1024 * 1024),
+    );
+    assert_eq!(report.requests.p50_ms, Some(500.0));
+    let vision = &report.stage_totals[2];
+    assert_eq!(vision.stage, "vision.prepare_inputs");
+    // Two events per request are summed into one sample.
+    assert_eq!((vision.count, vision.avg_ms), (2, 10.0));
+
+    let prefill = report.prefill.unwrap();
+    assert_eq!((prefill.tokens, prefill.tokens_per_sec), (200, 500.0));
+    assert_eq!(report.decode.unwrap().tokens_per_sec, 100.0);
+
+    let text = report.to_text();
+    assert!(text.contains("prefill: 500.00 tokens/s (200 tokens in 0.400s)"));
+    assert!(text.contains("peak memory: 64.0 MiB"));
+
+    let without_metrics = BenchmarkReport::from_samples(
+        BTreeMap::new(),
+        Vec::new(),
+        0,
+        1,
+        &[RequestSample {
+            duration: Duration::from_millis(5),
+            events: Vec::new(),
+        }],
+        None,
+    );
+    assert!(without_metrics.stage_totals.is_empty());
+    assert_eq!(without_metrics.prefill, None);
+}
+
+#[test]
+fn stage_totals_compare_across_report_formats() {
+    let dir = env::temp_dir().join(format!("deepseek-ocr-bench-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    // `--bench-output` files carry no percentiles.
+    let legacy = dir.join("python.json");
+    fs::write(
+        &legacy,
+        r#"{"events": [], "stage_totals": [
+            {"stage": "decode.prefill", "count": 1, "total_ms": 300.0, "avg_ms": 300.0},
+            {"stage": "prompt.render", "count": 1, "total_ms": 0.0, "avg_ms": 0.0}
+        ]}"#,
+    )
+    .unwrap();
+    let report = BenchmarkReport::from_samples(
+        BTreeMap::new(),
+        Vec::new(),
+        0,
+        1,
+        &[sample(400, 150, 200)],
+        None,
+    );
+    let current = dir.join("rust.json");
+    fs::write(&current, serde_json::to_string(&report).unwrap()).unwrap();
+
+    let reference = load_stage_totals(&current).unwrap();
+    let targets = vec![("python".to_string(), load_stage_totals(&legacy).unwrap())];
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(reference["decode.prefill"].p50_ms, Some(150.0));
+    assert_eq!(targets[0].1["decode.prefill"].p50_ms, None);
+
+    let table = compare_stage_totals(&reference, &targets);
+    let lines: Vec<&str> = table.lines().collect();
+    assert!(lines[0].starts_with("stage "));
+    assert!(lines[0].ends_with("| python total | python/ref"));
+    assert!(lines[1].starts_with("----"));
+    let prefill = lines
+        .iter()
+        .find(|line| line.contains("(decode.prefill)"))
+        .unwrap();
+    assert!(prefill.contains("| 150.000 "));
+    assert!(prefill.ends_with("| 300.000      | 2.00x"));
+    let render = lines
+        .iter()
+        .find(|line| line.contains("prompt.render"))
+        .unwrap();
+    assert!(render.contains("| -  "));
+
+    assert_eq!(
+        friendly_stage_name("decode.prefill"),
+        "Decode – Prompt Prefill (decode.prefill)"
+    );
+    assert_eq!(
+        friendly_stage_name("vision.sam_forward"),
+        "Vision Sam Forward (vision.sam_forward)"
+    );
+}
@@ -0,0 +1,207 @@
+use deepseek_ocr_core::{
+    grounding::{BoundingBox, GROUNDING_COORD_SCALE},
+    vision::{
+        CleanupOptions, CleanupReport, CleanupStep, clean_image,
+        cleanup::{
+            binarize, detect_quarter_turn, estimate_skew, median_filter, normalize_contrast,
+            remove_shadows, rotate_image,
+        },
+        record_orientations,
+    },
+};
+use image::{DynamicImage, GrayImage, Rgb, RgbImage, imageops, metadata::Orientation};
+
+/// White page with left-aligned black "text lines" of varying length.
+fn synthetic_page() -> RgbImage {
+    let mut page = RgbImage::from_pixel(400, 300, Rgb([255, 255, 255]));
+    for (line, y) in (30..270).step_by(20).enumerate() {
+        let end = 360 - (line as u32 * 37) % 120;
+        for yy in y..y + 6 {
+            for x in 40..end {
+                page.put_pixel(x, yy, Rgb([0, 0, 0]));
+            }
+        }
+    }
+    page
+}
+
+fn gray(rgb: &RgbImage) -> GrayImage {
+    DynamicImage::ImageRgb8(rgb.clone()).to_luma8()
+}
+
+/// Model-normalised box around the dark pixels, as grounding would report the text block.
+fn ink_box(image: &DynamicImage) -> BoundingBox {
+    let gray = image.to_luma8();
+    let (width, height) = gray.dimensions();
+    let mut bbox = BoundingBox {
+        x1: f32::MAX,
+        y1: f32::MAX,
+        x2: 0.0,
+        y2: 0.0,
+    };
+    for (x, y, pixel) in gray.enumerate_pixels() {
+        if pixel[0] < 128 {
+            bbox.x1 = bbox.x1.min(x as f32);
+            bbox.y1 = bbox.y1.min(y as f32);
+            bbox.x2 = bbox.x2.max(x as f32 + 1.0);
+            bbox.y2 = bbox.y2.max(y as f32 + 1.0);
+        }
+    }
+    bbox.scale(
+        GROUNDING_COORD_SCALE / width as f32,
+        GROUNDING_COORD_SCALE / height as f32,
+    )
+}
+
+fn assert_close(actual: BoundingBox, expected: BoundingBox, tolerance: f32) {
+    let pairs = [
+        (actual.x1, expected.x1),
+        (actual.y1, expected.y1),
+        (actual.x2, expected.x2),
+        (actual.y2, expected.y2),
+    ];
+    assert!(
+        pairs.iter().all(|(a, e)| (a - e).abs() <= tolerance),
+        "{actual:?} vs {expected:?}"
+    );
+}
+
+#[test]
+fn quarter_turns_are_detected() {
+    let page = synthetic_page();
+    assert_eq!(detect_quarter_turn(&gray(&page)), 0);
+    assert_eq!(detect_quarter_turn(&gray(&imageops::rotate90(&page))), 270);
+    assert_eq!(detect_quarter_turn(&gray(&imageops::rotate270(&page))), 90);
+}
+
+#[test]
+fn skew_is_estimated_and_corrected() {
+    let page = synthetic_page();
+    assert!(estimate_skew(&gray(&page), 10.0).abs() < 0.2);
+
+    let skewed = rotate_image(&page, 3.0, Rgb([255, 255, 255]));
+    let skew = estimate_skew(&gray(&skewed), 10.0);
+    assert!((skew + 3.0).abs() < 0.3, "estimated {skew}");
+
+    let straightened = rotate_image(&skewed, skew, Rgb([255, 255, 255]));
+    assert!(estimate_skew(&gray(&straightened), 10.0).abs() < 0.3);
+}
+
+#[test]
+fn boxes_on_cleaned_images_map_back_to_the_input() {
+    // A page photographed sideways: the text block sits on the right of the input image.
+    let sideways = DynamicImage::ImageRgb8(imageops::rotate90(&synthetic_page()));
+    let options = CleanupOptions::from_steps(&[CleanupStep::Rotate]);
+    let (cleaned, report) = clean_image(&sideways, &options);
+    assert_eq!(report.rotation_degrees, 270);
+    assert_eq!((report.width, report.height), (300, 400));
+    assert_eq!((cleaned.width(), cleaned.height()), (400, 300));
+    assert!(report.changes_geometry());
+    // What the model reads on the upright page lands on the same pixels of the input.
+    assert_close(
+        report.to_input_frame(&ink_box(&cleaned)),
+        ink_box(&sideways),
+        1.0,
+    );
+
+    // Turned and skewed: the mapped box covers the input's text block, widened only by the
+    // corners the deskew turned out of line.
+    let skewed = DynamicImage::ImageRgb8(imageops::rotate270(&rotate_image(
+        &synthetic_page(),
+        2.0,
+        Rgb([255, 255, 255]),
+    )));
+    let options = CleanupOptions::from_steps(&[CleanupStep::Rotate, CleanupStep::Deskew]);
+    let (cleaned, report) = clean_image(&skewed, &options);
+    assert_eq!(report.rotation_degrees, 90);
+    assert!(report.skew_degrees.abs() > 1.5, "{report:?}");
+    let mapped = report.to_input_frame(&ink_box(&cleaned));
+    let ink = ink_box(&skewed);
+    assert_close(mapped, ink, 25.0);
+    assert!(
+        mapped.x1 <= ink.x1 + 1.0
+            && mapped.y1 <= ink.y1 + 1.0
+            && mapped.x2 >= ink.x2 - 1.0
+            && mapped.y2 >= ink.y2 - 1.0,
+        "{mapped:?} does not cover {ink:?}"
+    );
+
+    let upright = DynamicImage::ImageRgb8(synthetic_page());
+    let (_, report) = clean_image(&upright, &options);
+    assert!(!report.changes_geometry());
+}
+
+#[test]
+fn photometric_steps_flatten_and_stretch() {
+    let washed = RgbImage::from_fn(100, 20, |x, _| {
+        let value = 100 + (x / 2) as u8;
+        Rgb([value, value, value])
+    });
+    let stretched = normalize_contrast(&washed).expect("narrow range is stretched");
+    assert!(stretched.get_pixel(0, 0)[0] < 10);
+    assert!(stretched.get_pixel(99, 0)[0] > 245);
+    assert!(normalize_contrast(&synthetic_page()).is_none());
+
+    let shadowed = RgbImage::from_fn(200, 200, |x, _| {
+        let value = 120 + (x as f32 * 0.6) as u8;
+        Rgb([value, value, value])
+    });
+    let flattened = remove_shadows(&shadowed);
+    for x in [10, 100, 190] {
+        assert!(flattened.get_pixel(x, 100)[0] > 235);
+    }
+
+    // A lone speck vanishes under the median; a solid stroke survives it.
+    let mut specked = synthetic_page();
+    specked.put_pixel(390, 290, Rgb([0, 0, 0]));
+    let denoised = median_filter(&specked);
+    assert_eq!(denoised.get_pixel(390, 290), &Rgb([255, 255, 255]));
+    assert_eq!(denoised.get_pixel(50, 50), specked.get_pixel(50, 50));
+
+    let halves = RgbImage::from_fn(100, 20, |x, _| {
+        let value = if x < 50 { 90 } else { 200 };
+        Rgb([value, value + 10, value])
+    });
+    let binary = binarize(&halves);
+    assert_eq!(binary.get_pixel(10, 10), &Rgb([0, 0, 0]));
+    assert_eq!(binary.get_pixel(90, 10), &Rgb([255, 255, 255]));
+}
+
+#[test]
+fn steps_expand_to_options() {
+    let all = CleanupOptions::from_steps(&[CleanupStep::All]);
+    assert!(all.auto_rotate && all.deskew && all.normalize_contrast && all.remove_shadows);
+    assert!(all.denoise && all.binarize);
+    let binarize = CleanupOptions::from_steps(&[CleanupStep::Binarize]);
+    assert!(binarize.any_enabled() && !binarize.denoise);
+    let deskew = CleanupOptions::from_steps(&[CleanupStep::Deskew]);
+    assert!(deskew.any_enabled() && !deskew.auto_rotate);
+    assert!(!CleanupOptions::from_steps(&[]).any_enabled());
+}
+
+#[test]
+fn applied_exif_orientations_are_reported() {
+    let images = vec![DynamicImage::ImageRgb8(RgbImage::new(40, 30)); 2];
+
+    let mut reports = Vec::new();
+    record_orientations(&mut reports, &images, &[Orientation::NoTransforms; 2]);
+    assert!(reports.is_empty());
+
+    record_orientations(
+        &mut reports,
+        &images,
+        &[Orientation::NoTransforms, Orientation::Rotate90],
+    );
+    assert_eq!(reports.len(), 2);
+    assert_eq!((reports[1].width, reports[1].height), (40, 30));
+    assert_eq!(reports[0].exif_orientation, None);
+    assert_eq!(reports[1].exif_orientation, Some(6));
+
+    let mut reports = vec![CleanupReport::default(); 3];
+    record_orientations(&mut reports, &images[..1], &[Orientation::Rotate180]);
+    assert!(
+        reports
+            .iter()
+            .all(|report| report.exif_orientation == Some(3))
+    );
+}
@@ -0,0 +1,122 @@
+use deepseek_ocr_core::vision::{
+    BackgroundColor, DecodeOptions, decode_image, decode_pages, normalize_color, page_runs,
+};
+use image::{
+    DynamicImage, GrayImage, ImageFormat, Luma, LumaA, Rgba, RgbaImage, metadata::Orientation,
+};
+
+fn png_bytes(image: &DynamicImage) -> Vec<u8> {
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+    bytes.into_inner()
+}
+
+#[test]
+fn background_colours_parse_and_round_trip() {
+    let color: BackgroundColor = "#FF8000".parse().unwrap();
+    assert_eq!(color, BackgroundColor([255, 128, 0]));
+    assert_eq!(color.to_string(), "#ff8000");
+    assert_eq!("#0f0".parse::<BackgroundColor>().unwrap().0, [0, 255, 0]);
+    assert_eq!(
+        "white".parse::<BackgroundColor>().unwrap(),
+        BackgroundColor::WHITE
+    );
+    assert!("#12345".parse::<BackgroundColor>().is_err());
+    assert!("#gg0000".parse::<BackgroundColor>().is_err());
+}
+
+#[test]
+fn transparency_is_composited_onto_background() {
+    let mut rgba = RgbaImage::from_pixel(4, 2, Rgba([0, 0, 0, 0]));
+    rgba.put_pixel(1, 0, Rgba([0, 0, 0, 255]));
+    rgba.put_pixel(2, 0, Rgba([0, 0, 255, 128]));
+    let bytes = png_bytes(&DynamicImage::ImageRgba8(rgba));
+
+    let decoded = decode_image(&bytes, &DecodeOptions::default()).unwrap();
+    assert!(decoded.flattened_alpha());
+    let rgb = decoded.image.as_rgb8().expect("normalised to rgb8");
+    assert_eq!(rgb.get_pixel(0, 0).0, [255, 255, 255]);
+    assert_eq!(rgb.get_pixel(1, 0).0, [0, 0, 0]);
+    assert_eq!(rgb.get_pixel(2, 0).0, [127, 127, 255]);
+
+    let options = DecodeOptions {
+        background: BackgroundColor::BLACK,
+        ..DecodeOptions::default()
+    };
+    let dark = normalize_color(
+        DynamicImage::ImageLumaA8(image::ImageBuffer::from_pixel(1, 1, LumaA([200, 0]))),
+        options.background,
+    );
+    assert_eq!(dark.as_rgb8().unwrap().get_pixel(0, 0).0, [0, 0, 0]);
+}
+
+#[test]
+fn opaque_images_are_converted_to_rgb() {
+    let bytes = png_bytes(&DynamicImage::ImageLuma8(GrayImage::from_pixel(
+        6,
+        4,
+        Luma([9]),
+    )));
+    let decoded = decode_image(&bytes, &DecodeOptions::default()).unwrap();
+    assert_eq!(decoded.orientation, Orientation::NoTransforms);
+    assert!(!decoded.flattened_alpha());
+    let rgb = decoded.image.as_rgb8().expect("normalised to rgb8");
+    assert_eq!(rgb.dimensions(), (6, 4));
+    assert_eq!(rgb.get_pixel(0, 0).0, [9, 9, 9]);
+}
+
+#[test]
+fn additional_formats_decode_to_rgb() {
+    let source = DynamicImage::ImageRgb8(image::RgbImage::from_fn(5, 3, |x, y| {
+        image::Rgb([(x * 40) as u8, (y * 60) as u8, 7])
+    }));
+    for format in [
+        ImageFormat::Bmp,
+        ImageFormat::Gif,
+        ImageFormat::Tiff,
+        ImageFormat::WebP,
+    ] {
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        source.write_to(&mut bytes, format).unwrap();
+        let pages = decode_pages(bytes.get_ref(), &DecodeOptions::default()).unwrap();
+        assert_eq!(pages.len(), 1, "{format:?}");
+        let rgb = pages[0].image.as_rgb8().expect("normalised to rgb8");
+        assert_eq!(rgb.dimensions(), (5, 3), "{format:?}");
+    }
+}
+
+#[test]
+fn multi_page_tiff_expands_into_pages() {
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    {
+        let mut encoder = tiff::encoder::TiffEncoder::new(&mut bytes).unwrap();
+        for (width, shade) in [(8u32, 10u8), (6, 200), (4, 90)] {
+            let data = vec![shade; (width * 3) as usize];
+            encoder
+                .write_image::<tiff::encoder::colortype::Gray8>(width, 3, &data)
+                .unwrap();
+        }
+        // Fully transparent page: composited onto the background like any other input.
+        encoder
+            .write_image::<tiff::encoder::colortype::RGBA8>(2, 3, &[0u8; 2 * 3 * 4])
+            .unwrap();
+    }
+    let pages = decode_pages(bytes.get_ref(), &DecodeOptions::default()).unwrap();
+    let summary: Vec<(u32, u8)> = pages
+        .iter()
+        .map(|page| {
+            let rgb = page.image.as_rgb8().expect("normalised to rgb8");
+            (rgb.width(), rgb.get_pixel(0, 0)[0])
+        })
+        .collect();
+    assert_eq!(summary, vec![(8, 10), (6, 200), (4, 90), (2, 255)]);
+    assert!(pages[3].flattened_alpha() && !pages[0].flattened_alpha());
+
+    let runs = page_runs(vec![vec!['a', 'b', 'c']]).unwrap();
+    assert_eq!(runs, vec![vec!['a'], vec!['b'], vec!['c']]);
+    assert_eq!(
+        page_runs(vec![vec!['a'], vec!['b']]).unwrap(),
+        vec![vec!['a', 'b']]
+    );
+    assert!(page_runs(vec![vec!['a', 'b'], vec!['c']]).is_err());
+}
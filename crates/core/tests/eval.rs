@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+
+use deepseek_ocr_core::{
+    eval::{
+        EvalReport, ReportFormat, SampleResult, TableScores, TextScores, extract_tables,
+        normalize_for_eval, table_similarity,
+    },
+    model::FinishReason,
+};
+
+fn sample(name: &str, prediction: &str, reference: &str) -> SampleResult {
+    SampleResult {
+        name: name.to_string(),
+        text: TextScores::compare(
+            &normalize_for_eval(prediction, false),
+            &normalize_for_eval(reference, false),
+        ),
+        tables: TableScores::compare(&extract_tables(prediction), &extract_tables(reference)),
+        prompt_tokens: 100,
+        completion_tokens: 10,
+        finish_reason: FinishReason::Stop,
+        seconds: 1.0,
+    }
+}
+
+fn rows(cells: &[&[&str]]) -> Vec<Vec<String>> {
+    cells
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect()
+}
+
+#[test]
+fn text_scores_count_character_and_word_edits() {
+    let scores = TextScores::compare("the cat sat", "the hat sat");
+    assert_eq!((scores.char_errors, scores.chars), (1, 11));
+    assert_eq!((scores.word_errors, scores.words), (1, 3));
+    assert!((scores.wer() - 1.0 / 3.0).abs() < 1e-9);
+    assert!((scores.normalized_edit_distance - 1.0 / 11.0).abs() < 1e-9);
+
+    let empty = TextScores::compare("", "");
+    assert_eq!((empty.cer(), empty.normalized_edit_distance), (0.0, 0.0));
+    assert_eq!(TextScores::compare("extra", "").cer(), 1.0);
+}
+
+#[test]
+fn markup_and_whitespace_are_normalised_away() {
+    let prediction =
+        "<|ref|>title<|/ref|><|det|>[[10, 10, 900, 60]]<|/det|>\n# Report\n\n**Total**   due";
+    assert_eq!(normalize_for_eval(prediction, false), "Report Total due");
+    assert_eq!(
+        normalize_for_eval("Report\nTOTAL due", true),
+        "report total due"
+    );
+}
+
+#[test]
+fn table_similarity_separates_structure_from_content() {
+    let reference = rows(&[&["Item", "Qty"], &["Apple", "3"]]);
+    assert_eq!(table_similarity(&reference, &reference, true), 1.0);
+
+    let misread = rows(&[&["Item", "Qty"], &["Apple", "8"]]);
+    assert_eq!(table_similarity(&misread, &reference, false), 1.0);
+    assert!((table_similarity(&misread, &reference, true) - 5.0 / 6.0).abs() < 1e-9);
+
+    let merged = rows(&[&["Item", "Qty"], &["Apple 3"]]);
+    assert!((table_similarity(&merged, &reference, false) - 5.0 / 6.0).abs() < 1e-9);
+
+    let table = "| A | B |\n| --- | --- |\n| 1 | 2 |";
+    let scores = TableScores::compare(&extract_tables(table), &extract_tables(table)).unwrap();
+    assert_eq!((scores.structure, scores.content), (1.0, 1.0));
+    // A missing table scores zero; no table on either side is not scored.
+    let scores = TableScores::compare(&[], &extract_tables(table)).unwrap();
+    assert_eq!((scores.predicted, scores.structure), (0, 0.0));
+    assert_eq!(TableScores::compare(&[], &[]), None);
+}
+
+#[test]
+fn reports_summarise_samples_and_compare_with_a_baseline() {
+    let settings = BTreeMap::from([("task".to_string(), "markdown".to_string())]);
+    let report = EvalReport::new(
+        settings.clone(),
+        vec![
+            sample("b.png", "hello world", "hello world"),
+            sample("a.png", "abcd", "abce"),
+        ],
+    );
+    assert_eq!(report.samples[0].name, "a.png");
+    // Micro-averaged over 15 reference characters.
+    assert!((report.summary.cer - 1.0 / 15.0).abs() < 1e-9);
+    assert!((report.summary.wer - 1.0 / 3.0).abs() < 1e-9);
+    assert_eq!(report.summary.table_structure, None);
+
+    let json = report.render(ReportFormat::Json, None).unwrap();
+    let parsed: EvalReport = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, report);
+
+    let baseline = EvalReport::new(settings, vec![sample("a.png", "abdd", "abce")]);
+    let markdown = report
+        .render(ReportFormat::Markdown, Some(&baseline))
+        .unwrap();
+    assert!(markdown.contains("| Metric | Value | Baseline | Δ |"));
+    assert!(markdown.contains("| a.png | 0.2500 | 1.0000 | 0.2500 | – | 10 | stop | -0.2500 |"));
+    assert!(markdown.contains("| b.png | 0.0000 | 0.0000 | 0.0000 | – | 10 | stop | – |"));
+}
@@ -0,0 +1,250 @@
+use deepseek_ocr_core::export::{
+    OutputFormat, PageLayout, PageOutput,
+    document::{BlockKind, Document, DocumentPage, parse_blocks},
+    escape_xml,
+    pdf::{self, ImageSource, PdfOptions, PdfPage},
+    plain_text,
+};
+use deepseek_ocr_core::vision::{DecodeOptions, decode_image};
+use image::{DynamicImage, ImageFormat, RgbImage};
+
+const GROUNDED: &str = "<|ref|>title<|/ref|><|det|>[[100, 50, 900, 150]]<|/det|>\n# Annual Report\n\n<|ref|>text<|/ref|><|det|>[[100, 200, 900, 400]]<|/det|>\nRevenue grew 5% & costs fell.\nSee <b>notes</b>.\n\n<|ref|>image<|/ref|><|det|>[[100, 500, 500, 900]]<|/det|>\n";
+
+#[test]
+fn layout_is_built_in_pixel_space() {
+    let page = PageLayout::from_output(GROUNDED, 999, 1998);
+    assert_eq!(page.blocks.len(), 3);
+    assert_eq!(page.blocks[0].text, "Annual Report");
+    assert_eq!(page.blocks[0].bbox.y2, 300.0);
+    assert!(page.blocks[2].is_picture());
+
+    let lines = page.blocks[1].lines();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].bbox.y1, 400.0);
+    assert_eq!(lines[1].bbox.y2, 800.0);
+    let words: Vec<&str> = lines[1].words.iter().map(|w| w.text.as_str()).collect();
+    assert_eq!(words, ["See", "notes."]);
+    assert!(lines[0].words[0].bbox.x2 < lines[0].words[1].bbox.x1);
+
+    let ungrounded = PageLayout::from_output("just text", 40, 30);
+    assert_eq!(ungrounded.blocks.len(), 1);
+    assert_eq!(ungrounded.blocks[0].bbox.x2, 40.0);
+}
+
+#[test]
+fn hocr_and_alto_documents() {
+    let pages = [PageOutput {
+        text: GROUNDED,
+        size: Some((999, 999)),
+    }];
+    let hocr = OutputFormat::Hocr.render(&pages).unwrap();
+    assert!(
+        hocr.contains("class=\"ocr_page\" id=\"page_1\" title=\"bbox 0 0 999 999; ppageno 0\"")
+    );
+    assert!(hocr.contains("title=\"bbox 100 50 900 150\""));
+    assert!(hocr.contains(">5%</span>"));
+    assert!(hocr.contains(">&amp;</span>"));
+    assert!(hocr.contains("class=\"ocr_photo\""));
+    assert_eq!(hocr.matches("<div").count(), hocr.matches("</div>").count());
+
+    let alto = OutputFormat::Alto.render(&pages).unwrap();
+    assert!(alto.contains("<Page ID=\"P1\" PHYSICAL_IMG_NR=\"1\" WIDTH=\"999\" HEIGHT=\"999\">"));
+    assert!(alto.contains(
+        "<TextBlock ID=\"P1_B1\" HPOS=\"100\" VPOS=\"50\" WIDTH=\"800\" HEIGHT=\"100\">"
+    ));
+    assert!(alto.contains("CONTENT=\"&amp;\""));
+    assert!(alto.contains("<Illustration ID=\"P1_B3\""));
+    assert_eq!(
+        alto.matches("<TextLine").count(),
+        alto.matches("</TextLine>").count()
+    );
+}
+
+#[test]
+fn searchable_pdf_has_invisible_text_layer() {
+    let image = DynamicImage::ImageRgb8(RgbImage::new(600, 300));
+    let layout = PageLayout::from_output(GROUNDED, 600, 300);
+    let options = PdfOptions {
+        dpi: 72.0,
+        ..PdfOptions::default()
+    };
+    let page = |source| PdfPage {
+        image: &image,
+        layout: &layout,
+        source,
+    };
+    let bytes = pdf::render(
+        &[page(ImageSource::Lossy), page(ImageSource::Lossless)],
+        &options,
+    )
+    .unwrap();
+    let text = String::from_utf8_lossy(&bytes);
+
+    assert!(bytes.starts_with(b"%PDF-1.4"));
+    assert!(text.contains("/Type /Pages /Kids [10 0 R 13 0 R] /Count 2"));
+    assert!(text.contains("/MediaBox [0 0 600 300]"));
+    assert!(text.contains("/ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode"));
+    // The black page is lossless and gray.
+    assert!(text.contains("/ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode"));
+    assert!(text.contains("/FontFile2 7 0 R"));
+    assert!(text.contains("/CIDToGIDMap 8 0 R"));
+    assert!(text.contains("/Root 1 0 R /Info 9 0 R >>"));
+    assert!(text.contains("BT 3 Tr"));
+    // "See" as UTF-16 code units.
+    assert!(text.contains("<005300650065> Tj"));
+    let page = PageOutput {
+        text: GROUNDED,
+        size: Some((600, 300)),
+    };
+    assert!(OutputFormat::Pdf.render(&[page]).is_err());
+
+    // Every xref entry points at the start of its object (offsets are in bytes).
+    let tail = String::from_utf8_lossy(&bytes[bytes.len() - 1024..]).into_owned();
+    let startxref = tail.rfind("startxref\n").unwrap() + "startxref\n".len();
+    let xref: usize = tail[startxref..].lines().next().unwrap().parse().unwrap();
+    let table = std::str::from_utf8(&bytes[xref..]).unwrap();
+    assert!(table.starts_with("xref\n0 16\n"));
+    for (id, entry) in table.lines().skip(3).take(15).enumerate() {
+        let offset: usize = entry[..10].parse().unwrap();
+        assert!(bytes[offset..].starts_with(format!("{} 0 obj", id + 1).as_bytes()));
+    }
+}
+
+#[test]
+fn unchanged_jpeg_pages_are_embedded_as_is() {
+    let mut jpeg = std::io::Cursor::new(Vec::new());
+    let photo = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 32, |x, y| {
+        image::Rgb([x as u8 * 4, y as u8 * 8, 128])
+    }));
+    photo.write_to(&mut jpeg, ImageFormat::Jpeg).unwrap();
+    let jpeg = jpeg.into_inner();
+    let decoded = decode_image(&jpeg, &DecodeOptions::default()).unwrap();
+    let layout = PageLayout::from_output(GROUNDED, 64, 32);
+
+    let source = ImageSource::new(&jpeg, &decoded, false);
+    assert_eq!(source, ImageSource::Jpeg(&jpeg));
+    assert_eq!(ImageSource::new(&jpeg, &decoded, true), ImageSource::Lossy);
+    let page = PdfPage {
+        image: &decoded.image,
+        layout: &layout,
+        source,
+    };
+    let bytes = pdf::render(&[page], &PdfOptions::default()).unwrap();
+    assert!(bytes.windows(jpeg.len()).any(|window| window == jpeg));
+
+    // A JPEG that no longer matches the page image is re-encoded.
+    let turned = decoded.image.rotate90();
+    let page = PdfPage {
+        image: &turned,
+        ..page
+    };
+    let bytes = pdf::render(&[page], &PdfOptions::default()).unwrap();
+    assert!(!bytes.windows(jpeg.len()).any(|window| window == jpeg));
+    assert!(String::from_utf8_lossy(&bytes).contains("/Width 32 /Height 64"));
+}
+
+#[test]
+fn markdown_is_parsed_into_typed_blocks() {
+    let markdown = "# Report\n\nIntro line one\ncontinues.\n\n\\[\nE = mc^2\n\\]\n\n\
+                    <table><thead><tr><th>A</th><th>B</th></tr></thead>\n<tr><td>1</td><td>x &amp; y</td></tr></table>\n\n\
+                    | k | v |\n|---|:-:|\n| a | b |\n\n- one\n- two\n1. first\n\n![Chart](img.png)";
+    let blocks = parse_blocks(markdown);
+    assert_eq!(
+        blocks,
+        [
+            BlockKind::Heading {
+                level: 1,
+                text: "Report".into()
+            },
+            BlockKind::Paragraph {
+                text: "Intro line one\ncontinues.".into()
+            },
+            BlockKind::Formula {
+                latex: "E = mc^2".into()
+            },
+            BlockKind::Table {
+                rows: vec![
+                    vec!["A".into(), "B".into()],
+                    vec!["1".into(), "x & y".into()]
+                ]
+            },
+            BlockKind::Table {
+                rows: vec![vec!["k".into(), "v".into()], vec!["a".into(), "b".into()]]
+            },
+            BlockKind::List {
+                ordered: false,
+                items: vec!["one".into(), "two".into()]
+            },
+            BlockKind::List {
+                ordered: true,
+                items: vec!["first".into()]
+            },
+            BlockKind::Figure {
+                caption: Some("Chart".into())
+            },
+        ]
+    );
+}
+
+#[test]
+fn grounded_document_keeps_boxes_and_exports() {
+    let text = "<|ref|>title<|/ref|><|det|>[[0, 0, 999, 99]]<|/det|>\nAnnual Report\n\
+                <|ref|>image<|/ref|><|det|>[[0, 100, 499, 499]]<|/det|>\n\
+                <|ref|>image_caption<|/ref|><|det|>[[0, 500, 499, 549]]<|/det|>\nFigure 1: Sales\n\
+                <|ref|>equation<|/ref|><|det|>[[0, 600, 999, 699]]<|/det|>\na^2 + b^2 = c^2\n";
+    let page = DocumentPage::from_output(text, Some((999, 1998)));
+    let kinds: Vec<&str> = page.blocks.iter().map(|block| block.kind.name()).collect();
+    assert_eq!(kinds, ["heading", "figure", "formula"]);
+    assert_eq!(
+        page.blocks[1].kind,
+        BlockKind::Figure {
+            caption: Some("Figure 1: Sales".into())
+        }
+    );
+    assert_eq!(page.blocks[1].bbox.unwrap().y2, 998.0);
+    assert!(
+        DocumentPage::from_output(text, None).blocks[0]
+            .bbox
+            .is_none()
+    );
+
+    let pages = [PageOutput {
+        text,
+        size: Some((999, 1998)),
+    }];
+    let json = OutputFormat::Json.render(&pages).unwrap();
+    assert!(json.contains("\"type\": \"heading\""));
+    let parsed: Document = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, Document::from_pages(&pages));
+
+    let html = OutputFormat::Html.render(&pages).unwrap();
+    assert!(html.contains("<h1 data-bbox=\"0 0 999 198\">Annual Report</h1>"));
+    assert!(html.contains("<figcaption>Figure 1: Sales</figcaption>"));
+    assert!(html.contains("\\[a^2 + b^2 = c^2\\]"));
+
+    let plain = OutputFormat::Plain.render(&pages).unwrap();
+    assert_eq!(plain, "Annual Report\n\nFigure 1: Sales\n\na^2 + b^2 = c^2");
+    assert!(
+        OutputFormat::Hocr
+            .render(&[PageOutput { text, size: None }])
+            .is_err()
+    );
+}
+
+#[test]
+fn markup_is_reduced_to_text() {
+    assert_eq!(
+        plain_text("<table><tr><td>a</td><td>b</td></tr><tr><td>1 &lt; 2</td></tr></table>"),
+        "a b\n1 < 2"
+    );
+    assert_eq!(plain_text("## **Bold** heading"), "Bold heading");
+    assert_eq!(plain_text("#12 and #hashtag"), "#12 and #hashtag");
+    assert_eq!(plain_text("####### seven"), "####### seven");
+    assert_eq!(plain_text("Call __init__ on a__b"), "Call __init__ on a__b");
+    assert_eq!(plain_text("__Note well__: 2 ** 3"), "Note well: 2 ** 3");
+    assert_eq!(plain_text("x < y and y > z"), "x < y and y > z");
+    assert_eq!(
+        escape_xml("a<\"b\">'&"),
+        "a&lt;&quot;b&quot;&gt;&apos;&amp;"
+    );
+}
@@ -0,0 +1,134 @@
+use deepseek_ocr_core::{
+    export::PageLayout,
+    extract::{ExtractionSchema, FieldType, coerce, locate_value},
+    grounding::BoundingBox,
+    task::resolve_task,
+};
+use serde_json::{Value, json};
+
+fn values(schema: &ExtractionSchema, answer: &str) -> Vec<Value> {
+    schema
+        .parse_answer(answer)
+        .into_iter()
+        .map(|field| field.value)
+        .collect()
+}
+
+#[test]
+fn schemas_come_from_field_lists_and_json_schema() {
+    let schema = ExtractionSchema::from_fields(&["invoice_number", "total:number"]).unwrap();
+    assert_eq!(schema.fields[1].kind, FieldType::Number);
+    assert_eq!(
+        schema.query(),
+        "- invoice_number (string)\n- total (number)"
+    );
+    assert!(ExtractionSchema::from_fields(&["total:money"]).is_err());
+    assert!(ExtractionSchema::from_fields(&["a", "a"]).is_err());
+
+    let schema = ExtractionSchema::from_json(&json!({
+        "type": "object",
+        "properties": {
+            "paid": {"type": "boolean", "description": "Marked as paid"},
+            "issued": {"type": "string", "format": "date"}
+        }
+    }))
+    .unwrap();
+    assert_eq!(
+        schema
+            .fields
+            .iter()
+            .map(|field| field.kind)
+            .collect::<Vec<_>>(),
+        [FieldType::Boolean, FieldType::Date]
+    );
+    // Properties keep their order in the schema.
+    assert!(schema.query().ends_with("- issued (date)"));
+
+    let task = resolve_task("extract").unwrap();
+    assert!(
+        task.render(Some(&schema.query()))
+            .unwrap()
+            .ends_with(":\n- paid (boolean): Marked as paid\n- issued (date)")
+    );
+}
+
+#[test]
+fn answers_are_parsed_and_coerced_into_the_schema() {
+    let schema = ExtractionSchema::from_fields(&[
+        "invoice_number",
+        "total:number",
+        "items:integer",
+        "due_date:date",
+        "paid:boolean",
+    ])
+    .unwrap();
+    let answer = "Here you go:\n```json\n{\"Invoice Number\": \"INV-7\", \"total\": \"$1,234.50\", \
+                  \"items\": 3, \"due_date\": \"31/01/2024\", \"paid\": null}\n```";
+    assert_eq!(
+        values(&schema, answer),
+        [
+            json!("INV-7"),
+            json!(1234.5),
+            json!(3),
+            json!("2024-01-31"),
+            Value::Null
+        ]
+    );
+
+    // Without JSON, `Key: value` lines and table rows are used.
+    let answer = "Invoice number: INV-8\n| Total | 1.234,50 € |\nItems: two";
+    let fields = schema.parse_answer(answer);
+    assert_eq!(fields[0].value, json!("INV-8"));
+    assert_eq!(fields[1].value, json!(1234.5));
+    // Uncoercible values keep their text.
+    assert_eq!(fields[2].value, Value::Null);
+    assert_eq!(fields[2].text.as_deref(), Some("two"));
+    assert_eq!(fields[3].text, None);
+}
+
+#[test]
+fn coercion_rules() {
+    assert_eq!(coerce("(12.00)", FieldType::Number), Some(json!(-12.0)));
+    assert_eq!(coerce("1,000", FieldType::Integer), Some(json!(1000)));
+    assert_eq!(coerce("12.5", FieldType::Integer), None);
+    // Only groups of exactly three digits are thousands.
+    assert_eq!(coerce("1.234", FieldType::Number), Some(json!(1.234)));
+    assert_eq!(coerce("1.2345", FieldType::Number), Some(json!(1.2345)));
+    assert_eq!(coerce("12,5", FieldType::Number), Some(json!(12.5)));
+    assert_eq!(
+        coerce("1.234.567", FieldType::Number),
+        Some(json!(1234567.0))
+    );
+    assert_eq!(
+        coerce("1,234,567.8", FieldType::Number),
+        Some(json!(1234567.8))
+    );
+    assert_eq!(coerce("Yes", FieldType::Boolean), Some(json!(true)));
+    assert_eq!(
+        coerce("2024-3-9", FieldType::Date),
+        Some(json!("2024-03-09"))
+    );
+    // Ambiguous day/month order is kept as written.
+    assert_eq!(
+        coerce("03/04/2024", FieldType::Date),
+        Some(json!("03/04/2024"))
+    );
+}
+
+#[test]
+fn values_are_located_in_the_grounded_layout() {
+    let text = "<|ref|>text<|/ref|><|det|>[[0, 0, 999, 199]]<|/det|>\nACME Corp\nInvoice: INV-7\n\n\
+                <|ref|>table<|/ref|><|det|>[[0, 500, 999, 599]]<|/det|>\nTotal due 1,234.50";
+    let layout = PageLayout::from_output(text, 999, 999);
+    let bbox = locate_value(&layout, "INV-7").unwrap();
+    assert_eq!((bbox.y1, bbox.y2), (99.5, 199.0));
+    assert!(bbox.x1 > 400.0 && bbox.x2 > 998.0, "{bbox:?}");
+
+    let bbox = locate_value(&layout, "1,234.50").unwrap();
+    assert_eq!(bbox.y1, 500.0);
+    assert_eq!(locate_value(&layout, "Paid"), None);
+    assert_eq!(
+        locate_value(&layout, "Corp Invoice"),
+        Some(BoundingBox::new(0.0, 0.0, 999.0, 199.0))
+    );
+}
@@ -0,0 +1,64 @@
+use deepseek_ocr_core::{
+    figures::{Figure, FigureOptions, figure_markdown, insert_figures, select_figures},
+    grounding::{BoundingBox, parse_grounding},
+};
+use image::DynamicImage;
+
+const PAGE: &str = "<|ref|>title<|/ref|><|det|>[[100, 20, 900, 80]]<|/det|>\n# Results\n\n\
+<|ref|>image<|/ref|><|det|>[[100, 100, 900, 500]]<|/det|>\n\n\
+<|ref|>image_caption<|/ref|><|det|>[[100, 510, 900, 530]]<|/det|>\nFigure 1: Revenue.\n\n\
+<|ref|>image<|/ref|><|det|>[[100, 600, 102, 602]]<|/det|>\n\n\
+<|ref|>chart<|/ref|><|det|>[[100, 700, 900, 950]]<|/det|>\nQ1 Q2 Q3\n";
+
+fn figure(index: usize, label: &str, description: Option<&str>) -> Figure {
+    Figure {
+        index,
+        label: label.to_string(),
+        region: BoundingBox::new(0.0, 0.0, 1.0, 1.0),
+        image: DynamicImage::new_rgb8(1, 1),
+        description: description.map(str::to_string),
+    }
+}
+
+#[test]
+fn figure_blocks_are_selected_by_label_and_size() {
+    let spans = parse_grounding(PAGE);
+    let selected = select_figures(&spans, 999, 999, &FigureOptions::default());
+    // The caption is not a figure and the 2×2 image is a stray mark.
+    let indices: Vec<usize> = selected.iter().map(|(idx, _)| *idx).collect();
+    assert_eq!(indices, vec![1, 4]);
+    assert_eq!(selected[0].1, BoundingBox::new(100.0, 100.0, 900.0, 500.0));
+
+    let options = FigureOptions {
+        max_figures: 1,
+        ..FigureOptions::default()
+    };
+    assert_eq!(select_figures(&spans, 999, 999, &options).len(), 1);
+}
+
+#[test]
+fn links_and_descriptions_are_written_into_figure_blocks() {
+    let figures = [
+        figure(1, "image", Some("A bar chart.\n\nRevenue grows.")),
+        figure(4, "chart", None),
+    ];
+    let links = [
+        "figures/page1_figure1.png".to_string(),
+        "f2.png".to_string(),
+    ];
+    let text = insert_figures(PAGE, &figures, &links);
+
+    let spans = parse_grounding(&text);
+    assert_eq!(spans.len(), 5);
+    assert_eq!(
+        spans[1].content,
+        "![image](figures/page1_figure1.png)\n\n> A bar chart.\n>\n> Revenue grows."
+    );
+    assert_eq!(spans[1].boxes, parse_grounding(PAGE)[1].boxes);
+    assert!(text.contains("<|/det|>\n![image]"));
+    assert_eq!(spans[2].content, "Figure 1: Revenue.");
+    // Text read inside a figure is replaced by the link.
+    assert_eq!(spans[4].content, "![chart](f2.png)");
+
+    assert_eq!(figure_markdown("a.png", "image", None), "![image](a.png)");
+}
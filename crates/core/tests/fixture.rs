@@ -0,0 +1,136 @@
+use std::{env, fs, process};
+
+use candle_core::{DType, Device};
+use deepseek_ocr_core::{
+    baseline::{CaptureOptions, capture_baseline},
+    engine::{EngineSettings, OcrEngine, OcrRequest},
+    fixture::{EOS_TOKEN, random_weights, tiny_config, tiny_tokenizer, write_tiny_model},
+    inference::{estimate_image_tokens, image_token_count, normalize_text},
+};
+use image::{DynamicImage, Rgb, RgbImage};
+
+fn page(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        if (x / 16 + y / 8) % 3 == 0 {
+            Rgb([20, 20, 20])
+        } else {
+            Rgb([240, 240, 235])
+        }
+    }))
+}
+
+#[test]
+fn tiny_tokenizer_follows_prompt_conventions() {
+    let tokenizer = tiny_tokenizer().unwrap();
+    assert_eq!(tokenizer.token_to_id(EOS_TOKEN), Some(1));
+    assert!(tokenizer.token_to_id("<image>").is_some());
+
+    let encoding = tokenizer.encode("<|grounding|>Free OCR é", false).unwrap();
+    assert_eq!(
+        tokenizer.decode(encoding.get_ids(), false).unwrap(),
+        "<|grounding|>Free OCR é"
+    );
+}
+
+#[test]
+fn random_weights_depend_only_on_the_seed() {
+    let cfg = tiny_config(tiny_tokenizer().unwrap().get_vocab_size(true)).unwrap();
+    let first = random_weights(&cfg, 7).unwrap();
+    let again = random_weights(&cfg, 7).unwrap();
+    let other = random_weights(&cfg, 8).unwrap();
+    assert!(first.contains_key("model.sam_model.blocks.0.mlp.fc1.weight"));
+    assert!(!first.contains_key("model.layers.0.self_attn.q_proj.bias"));
+
+    let name = "model.projector.layers.weight";
+    let values = |weights: &std::collections::HashMap<_, candle_core::Tensor>| {
+        weights[name]
+            .flatten_all()
+            .unwrap()
+            .to_vec1::<f32>()
+            .unwrap()
+    };
+    assert_eq!(values(&first), values(&again));
+    assert_ne!(values(&first), values(&other));
+    let norm = first["model.norm.weight"].to_vec1::<f32>().unwrap();
+    assert!(norm.iter().all(|&value| value == 1.0));
+}
+
+#[test]
+fn tiny_model_runs_the_full_pipeline() {
+    let dir = env::temp_dir().join(format!("deepseek-ocr-fixture-{}", process::id()));
+    let files = write_tiny_model(&dir, 0).unwrap();
+    let mut settings = EngineSettings::default();
+    settings.vision.base_size = 512;
+    settings.vision.image_size = 512;
+    settings.vision.crop_mode = false;
+    settings.max_new_tokens = 4;
+    let engine = OcrEngine::load(
+        &files.config,
+        &files.weights,
+        &files.tokenizer,
+        Device::Cpu,
+        DType::F32,
+        settings,
+    )
+    .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let image = page(300, 200);
+    let prompt = engine.render_prompt("<image>\nFree OCR.").unwrap();
+    let images = std::slice::from_ref(&image);
+    let request = OcrRequest::new(&prompt, images);
+    let estimate = engine.estimate_tokens(&request).unwrap();
+    let result = engine.run(&request).unwrap();
+    assert_eq!(result.prompt_tokens, estimate.prompt.prompt_tokens());
+    assert_eq!(result.image_tokens, estimate.prompt.total_image_tokens());
+    assert!((1..=4).contains(&result.completion_tokens()));
+    assert_eq!(
+        result.text,
+        normalize_text(&engine.decode_tokens(&result.tokens, true).unwrap())
+    );
+    assert_eq!(
+        result.raw_text,
+        engine.decode_tokens(&result.tokens, false).unwrap()
+    );
+    assert_eq!(engine.run(&request).unwrap().tokens, result.tokens);
+
+    // Captures carry the projector separators like the Python ones.
+    let options = CaptureOptions {
+        max_new_tokens: 2,
+        ..CaptureOptions::default()
+    };
+    let capture = capture_baseline(&engine, images, "<image>\nFree OCR.", &options).unwrap();
+    for name in ["image_newline", "view_separator"] {
+        let (_, tensor) = capture
+            .projector_outputs
+            .iter()
+            .find(|(array, _)| array == name)
+            .unwrap();
+        assert_eq!(tensor.dims(), [engine.model().projector_config().n_embed]);
+    }
+
+    // Crop mode adds local tiles for the wide page.
+    let mut vision = engine.settings().vision;
+    vision.image_size = 256;
+    vision.crop_mode = true;
+    let wide = page(768, 256);
+    let images = std::slice::from_ref(&wide);
+    let mut request = OcrRequest::new(&prompt, images);
+    request.vision = Some(vision);
+    let estimate = engine.estimate_tokens(&request).unwrap();
+    let result = engine.run(&request).unwrap();
+    assert_eq!(result.prompt_tokens, estimate.prompt.prompt_tokens());
+    assert_eq!(
+        result.image_tokens,
+        estimate_image_tokens(
+            768,
+            256,
+            vision.base_size,
+            vision.image_size,
+            true,
+            vision.tiling
+        )
+    );
+    let global_view = image_token_count(None, false, vision.base_size, vision.image_size, true);
+    assert!(result.image_tokens > global_view);
+}
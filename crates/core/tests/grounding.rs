@@ -0,0 +1,51 @@
+use deepseek_ocr_core::grounding::{
+    BoundingBox, parse_grounding, pixel_boxes, strip_grounding, strip_grounding_tokens,
+};
+
+#[test]
+fn parses_layout_spans_with_content() {
+    let text = "<|ref|>title<|/ref|><|det|>[[10, 20, 500, 60]]<|/det|>\n# Report\n\n\
+                <|ref|>text<|/ref|><|det|>[[10, 80, 990, 400]]<|/det|>\nBody paragraph.";
+    let spans = parse_grounding(text);
+    assert_eq!(spans.len(), 2);
+    assert_eq!(spans[0].label, "title");
+    assert_eq!(
+        spans[0].boxes,
+        vec![BoundingBox::new(10.0, 20.0, 500.0, 60.0)]
+    );
+    assert_eq!(spans[0].content, "# Report");
+    assert_eq!(spans[1].label, "text");
+    assert_eq!(spans[1].content, "Body paragraph.");
+    assert_eq!(&text[spans[1].range.clone()][..7], "<|ref|>");
+}
+
+#[test]
+fn locate_output_maps_to_pixels() {
+    let text = "<|ref|>Total<|/ref|><|det|>[[0, 0, 999, 999], [499, 0, 999, 333]]<|/det|>";
+    let boxes = pixel_boxes(text, 2000, 1000);
+    assert_eq!(boxes.len(), 2);
+    assert!(boxes.iter().all(|b| b.label == "Total"));
+    let full = boxes[0].bbox;
+    assert!((full.x2 - 2000.0).abs() < 1e-3 && (full.y2 - 1000.0).abs() < 1e-3);
+    let half = boxes[1].bbox;
+    assert!((half.x1 - 998.998).abs() < 1e-2);
+    let back = half.to_normalized(2000, 1000);
+    assert!((back.x1 - 499.0).abs() < 1e-2 && (back.y2 - 333.0).abs() < 1e-2);
+}
+
+#[test]
+fn malformed_references_are_skipped() {
+    let text = "<|ref|>orphan<|/ref|> text <|ref|>ok<|/ref|><|det|>[[1,2,3,4]]<|/det|> <|ref|>cut";
+    let spans = parse_grounding(text);
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0].label, "ok");
+    assert!(parse_grounding("plain text").is_empty());
+}
+
+#[test]
+fn strip_grounding_keeps_labels_and_content() {
+    let text = "<|ref|>Invoice<|/ref|><|det|>[[1, 2, 3, 4]]<|/det|> total";
+    assert_eq!(strip_grounding(text), "Invoice total");
+    // Streaming decodes without special tokens: labels and boxes stay.
+    assert_eq!(strip_grounding_tokens(text), "Invoice[[1, 2, 3, 4]] total");
+}
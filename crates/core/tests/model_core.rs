@@ -109,6 +109,7 @@ fn compute_image_embeddings_produces_tokens() -> Result<()> {
             global: &global,
             patches: None,
             crop_shape: None,
+            thumbnail: false,
         };
         let embeddings = model.compute_image_embeddings(&[Some(vision_input)])?;
         assert_eq!(embeddings.len(), 1);
@@ -148,6 +149,7 @@ fn vision_inputs_and_precomputed_embeddings_align() -> Result<()> {
             global: &global,
             patches: None,
             crop_shape: None,
+            thumbnail: false,
         };
         let vision_inputs = vec![Some(vision_spec)];
         let cached_embeddings = model.compute_image_embeddings(&vision_inputs)?;
@@ -0,0 +1,166 @@
+use std::{env, fs, process};
+
+use candle_core::{Device, Tensor};
+use deepseek_ocr_core::baseline::{
+    Baseline, BaselineMetadata, OutputTokens, PromptAssets, SCHEMA_VERSION, TokenRange, abs_error,
+    compare_baselines, token_ranges,
+};
+
+fn tensor(values: &[f32], rows: usize) -> Tensor {
+    Tensor::from_slice(values, (rows, values.len() / rows), &Device::Cpu).unwrap()
+}
+
+fn baseline(input_ids: Vec<i64>, generated: &[i64], scale: f32) -> Baseline {
+    let prefill_len = input_ids.len();
+    let mut tokens = input_ids.clone();
+    tokens.extend(generated);
+    Baseline {
+        metadata: BaselineMetadata {
+            model: "test".to_string(),
+            prompt: "<image>\nFree OCR.".to_string(),
+            image: "page.png".to_string(),
+            base_size: Some(1024),
+            image_size: Some(640),
+            crop_mode: Some(false),
+            dtype: "fp32".to_string(),
+            device: "cpu".to_string(),
+            max_new_tokens: Some(8),
+            source: Some("rust".to_string()),
+            markdown_path: None,
+            prompt_assets_path: None,
+            output_tokens_path: None,
+            vision_embeddings_path: None,
+            projector_outputs_path: None,
+            logits_path: None,
+            vision_token_total: Some(2),
+            markdown: "text".to_string(),
+        },
+        prompt: PromptAssets {
+            schema_version: SCHEMA_VERSION,
+            prompt: "<image>\nFree OCR.".to_string(),
+            rendered_prompt: "<image>\nFree OCR.".to_string(),
+            images_seq_mask: vec![0; prefill_len],
+            input_ids,
+            bos_token_id: 0,
+            image_token_id: 128815,
+            prefill_len,
+            image_token_ranges: Vec::new(),
+            image_token_counts: Vec::new(),
+            images_spatial_crop: vec![[1, 1]],
+            vision_token_counts: vec![2],
+            vision_token_total: 2,
+            image_paths: vec!["page.png".to_string()],
+            per_image_patch_counts: vec![0],
+        },
+        output: OutputTokens {
+            tokens,
+            prefill_len,
+            generated_len: generated.len(),
+            eos_token_id: Some(1),
+            decoded_markdown: Some("text".to_string()),
+        },
+        vision_embeddings: vec![(
+            "global_pre_image0".to_string(),
+            tensor(&[1.0, 2.0, 3.0, 4.0], 2),
+        )],
+        projector_outputs: vec![
+            (
+                "fused_tokens_image0".to_string(),
+                tensor(&[0.5 * scale, 1.0, -1.0, 2.0], 2),
+            ),
+            (
+                "local_post_image0".to_string(),
+                Tensor::zeros((0, 2), candle_core::DType::F32, &Device::Cpu).unwrap(),
+            ),
+        ],
+        logits: vec![
+            ("logits".to_string(), tensor(&[0.0, 1.0, 2.0 * scale], 1)),
+            (
+                "prefill_len".to_string(),
+                Tensor::new(&[prefill_len as i64], &Device::Cpu).unwrap(),
+            ),
+        ],
+    }
+}
+
+#[test]
+fn image_token_ranges_follow_the_mask() {
+    assert_eq!(
+        token_ranges(&[0, 1, 1, 0, 1, 0]),
+        [
+            TokenRange {
+                start: 1,
+                length: 2
+            },
+            TokenRange {
+                start: 4,
+                length: 1
+            }
+        ]
+    );
+    assert!(token_ranges(&[0, 0]).is_empty());
+}
+
+#[test]
+fn abs_error_reports_max_and_mean() {
+    let (max_abs, mean_abs) = abs_error(
+        &tensor(&[1.0, 2.0, 3.0, 4.0], 2),
+        &tensor(&[1.0, 2.5, 2.0, 4.0], 2),
+    )
+    .unwrap();
+    assert_eq!((max_abs, mean_abs), (1.0, 0.375));
+    assert!(abs_error(&tensor(&[1.0], 1), &tensor(&[1.0, 2.0], 1)).is_err());
+    // Same element count, transposed layout.
+    assert!(abs_error(&tensor(&[1.0, 2.0], 1), &tensor(&[1.0, 2.0], 2)).is_err());
+}
+
+#[test]
+fn captures_round_trip_and_compare_per_stage() {
+    let dir = env::temp_dir().join(format!("deepseek-ocr-parity-{}", process::id()));
+    let reference = baseline(vec![0, 5, 6], &[7, 8, 1], 1.0);
+    reference.write(&dir).unwrap();
+    let loaded = Baseline::load(&dir).unwrap();
+    assert_eq!(loaded.output.tokens, reference.output.tokens);
+    assert_eq!(loaded.projector_outputs.len(), 2);
+    assert!(
+        fs::read_to_string(dir.join("baseline.json"))
+            .unwrap()
+            .contains("\"logits_path\"")
+    );
+    fs::remove_dir_all(&dir).unwrap();
+
+    let identical = compare_baselines(&loaded, &reference).unwrap();
+    assert_eq!(identical.max_abs(), 0.0);
+    assert!(identical.failures(0.0).is_empty());
+    assert_eq!(identical.tokens.first_divergence, None);
+
+    let mut candidate = baseline(vec![0, 5, 9], &[7, 3], 2.0);
+    candidate.vision_embeddings.clear();
+    let report = compare_baselines(&reference, &candidate).unwrap();
+    assert_eq!(report.tokens.prompt_mismatches, 1);
+    assert_eq!(report.tokens.first_divergence, Some(1));
+    // Integer arrays are not compared.
+    assert_eq!(report.arrays.len(), 4);
+    let stages: Vec<_> = report
+        .stages
+        .iter()
+        .map(|stage| (stage.stage.as_str(), stage.mismatched, stage.max_abs))
+        .collect();
+    assert_eq!(
+        stages,
+        [
+            ("vision_embeddings", 1, None),
+            ("projector_outputs", 0, Some(0.5)),
+            ("logits", 0, Some(2.0))
+        ]
+    );
+    // Element-weighted: 0.5 over four projector values, the empty local tensor adds nothing.
+    assert_eq!(report.stages[1].mean_abs, Some(0.125));
+    let failures: Vec<&str> = report
+        .failures(1.0)
+        .iter()
+        .map(|diff| diff.name.as_str())
+        .collect();
+    assert_eq!(failures, ["global_pre_image0", "logits"]);
+    assert!(report.to_text().contains("global_pre_image0"));
+}
@@ -0,0 +1,84 @@
+use deepseek_ocr_core::{
+    export::table::parse_html_table,
+    postprocess::{
+        PostprocessOptions, PostprocessStep, dehyphenate, normalize_latex, postprocess,
+        remove_repeated_lines, repair_tables,
+    },
+};
+
+#[test]
+fn decoding_loops_collapse_to_one_copy() {
+    let looping = "Intro\nA\nB\nA\nB\nA\nB\nA\nEnd";
+    assert_eq!(remove_repeated_lines(looping), "Intro\nA\nB\nA\nEnd");
+
+    // Two copies are not a loop, and blank lines are never collapsed.
+    let text = "x\nx\n\n\n\ny";
+    assert_eq!(remove_repeated_lines(text), text);
+    assert_eq!(remove_repeated_lines("a\na\na\n"), "a\n");
+}
+
+#[test]
+fn repeated_table_rows_are_kept_unless_they_loop_to_the_end() {
+    let table = "| a | b |\n| --- | --- |\n| 0 | 0 |\n| 0 | 0 |\n| 0 | 0 |\n| 1 | 2 |\n";
+    assert_eq!(remove_repeated_lines(table), table);
+    let html = "<table>\n<tr><td>0</td></tr>\n<tr><td>0</td></tr>\n<tr><td>0</td></tr>\n</table>";
+    assert_eq!(remove_repeated_lines(html), html);
+
+    let looping = "| a |\n| --- |\n| x |\n| x |\n| x |\n| x |";
+    assert_eq!(remove_repeated_lines(looping), "| a |\n| --- |\n| x |");
+}
+
+#[test]
+fn hyphenated_words_are_joined_across_lines() {
+    assert_eq!(
+        dehyphenate("the infor-\nmation, as stated\nJean-\nPaul"),
+        "the information,\nas stated\nJean-\nPaul"
+    );
+    assert_eq!(dehyphenate("self-\ncontained"), "selfcontained");
+    assert_eq!(dehyphenate("| a- |\n| b |"), "| a- |\n| b |");
+}
+
+#[test]
+fn dollar_math_is_rewritten_without_touching_amounts() {
+    assert_eq!(
+        normalize_latex("Let $x^2$ be\n$$\\sum_i a_i$$"),
+        "Let \\(x^2\\) be\n\\[\\sum_i a_i\\]"
+    );
+    let prices = "It costs $5 and $10, or \\$3 $ each.";
+    assert_eq!(normalize_latex(prices), prices);
+    assert_eq!(normalize_latex("open $$ x"), "open $$ x");
+}
+
+#[test]
+fn unbalanced_tables_are_repaired() {
+    assert_eq!(
+        repair_tables("| a | b |\n| 1 |\n| 2 | 3 |"),
+        "| a | b |\n| --- | --- |\n| 1 |  |\n| 2 | 3 |"
+    );
+    let balanced = "| a | b |\n|---|---|\n| 1 | 2 |\n";
+    assert_eq!(repair_tables(balanced), balanced);
+
+    let truncated = "<table><tr><td>a</td><td>b</td></tr><tr><td>1";
+    let repaired = repair_tables(truncated);
+    assert!(repaired.ends_with("</table>"));
+    assert_eq!(
+        parse_html_table(&repaired),
+        vec![vec!["a", "b"], vec!["1", ""]]
+    );
+}
+
+#[test]
+fn steps_select_passes_and_run_in_order() {
+    let options = PostprocessOptions::from_steps(&[PostprocessStep::Hyphens]);
+    assert!(options.dehyphenate && !options.remove_repeats);
+    assert!(!PostprocessOptions::default().any_enabled());
+    let all = PostprocessOptions::from_steps(&[PostprocessStep::All]);
+    assert_eq!(
+        postprocess("x\nx\nx\ncon-\ntext $y$", &all),
+        "x\ncontext\n\\(y\\)"
+    );
+    assert_eq!(
+        postprocess("x\nx\nx", &PostprocessOptions::default()),
+        "x\nx\nx"
+    );
+}
@@ -0,0 +1,165 @@
+use deepseek_ocr_core::{
+    export::PageLayout,
+    extract::{ExtractedField, Extraction},
+    grounding::BoundingBox,
+    model::FinishReason,
+    redact::{
+        PiiKind, RedactOptions, RedactRegion, RedactStyle, Redactor, parse_pattern, redact_image,
+    },
+};
+use image::{DynamicImage, Rgb, RgbImage};
+use serde_json::json;
+
+fn kinds(text: &str, redactor: &Redactor) -> Vec<(String, String)> {
+    redactor
+        .find(text)
+        .into_iter()
+        .map(|m| (m.kind, m.text))
+        .collect()
+}
+
+#[test]
+fn builtin_patterns_are_validated() {
+    let redactor = Redactor::new(&RedactOptions::default()).unwrap();
+    let text = "Mail jane.doe@example.org or call +44 20 7946 0958. SSN 123-45-6789, \
+                card 4111 1111 1111 1111 (not 4111 1111 1111 1112), IBAN GB82 WEST 1234 5698 7654 32. \
+                Invoice 2024-01-15, total 1,250.";
+    assert_eq!(
+        kinds(text, &redactor),
+        [
+            ("email", "jane.doe@example.org"),
+            ("phone", "+44 20 7946 0958"),
+            ("ssn", "123-45-6789"),
+            ("card", "4111 1111 1111 1111"),
+            ("iban", "GB82 WEST 1234 5698 7654 32"),
+        ]
+        .map(|(kind, text)| (kind.to_string(), text.to_string()))
+    );
+
+    let options = RedactOptions {
+        kinds: vec![PiiKind::Date],
+        ..RedactOptions::default()
+    };
+    let dates = Redactor::new(&options).unwrap();
+    assert_eq!(kinds("Born 07/03/1984.", &dates).len(), 1);
+}
+
+#[test]
+fn grounding_markup_survives_text_redaction() {
+    let mut options = RedactOptions::default();
+    options
+        .patterns
+        .insert("patient_id".to_string(), r"\bP\d{7}\b".to_string());
+    let redactor = Redactor::new(&options).unwrap();
+    let text = "<|ref|>text<|/ref|><|det|>[[100, 200, 300, 400]]<|/det|>\n\
+                Patient P1234567, phone 415 555 0100";
+    assert_eq!(
+        redactor.redact_text(text),
+        "<|ref|>text<|/ref|><|det|>[[100, 200, 300, 400]]<|/det|>\n\
+         Patient [PATIENT_ID], phone [PHONE]"
+    );
+
+    assert_eq!(
+        parse_pattern("staff=S-\\d+").unwrap(),
+        ("staff".to_string(), "S-\\d+".to_string())
+    );
+    assert!(parse_pattern("staff").is_err());
+    assert!(parse_pattern("bad=(").is_err());
+}
+
+#[test]
+fn image_regions_follow_the_configured_granularity() {
+    // One block covering the top half of a 999x999 page, with two lines.
+    let text = "<|ref|>text<|/ref|><|det|>[[0, 0, 999, 499]]<|/det|>\n\
+                Dear team,\nwrite to a@b.io today";
+    let layout = PageLayout::from_output(text, 999, 999);
+    let mut options = RedactOptions::default();
+    let regions = |options: &RedactOptions| Redactor::new(options).unwrap().regions(&layout);
+    assert_eq!(options.region, RedactRegion::Block);
+
+    options.region = RedactRegion::Line;
+    let lines = regions(&options);
+    assert_eq!(lines.len(), 1);
+    assert_eq!((lines[0].x1, lines[0].y1, lines[0].y2), (0.0, 249.5, 499.0));
+
+    options.region = RedactRegion::Word;
+    let words = regions(&options);
+    assert!(words[0].x1 > 0.0 && words[0].x2 < 999.0);
+    assert_eq!(words[0].y1, 249.5);
+
+    options.region = RedactRegion::Block;
+    assert_eq!(
+        regions(&options),
+        vec![BoundingBox::new(0.0, 0.0, 999.0, 499.0)]
+    );
+}
+
+#[test]
+fn ungrounded_matches_redact_the_whole_page() {
+    let layout = PageLayout::from_output("Dear team,\nwrite to a@b.io today", 640, 480);
+    assert!(!layout.grounded);
+    let mut options = RedactOptions::default();
+    for region in [RedactRegion::Word, RedactRegion::Line, RedactRegion::Block] {
+        options.region = region;
+        assert_eq!(
+            Redactor::new(&options).unwrap().regions(&layout),
+            vec![BoundingBox::new(0.0, 0.0, 640.0, 480.0)]
+        );
+    }
+    let clean = PageLayout::from_output("Nothing to hide", 640, 480);
+    assert!(Redactor::new(&options).unwrap().regions(&clean).is_empty());
+}
+
+#[test]
+fn extractions_are_redacted_field_by_field() {
+    let redactor = Redactor::new(&RedactOptions::default()).unwrap();
+    let field = |name: &str, value, text: &str| ExtractedField {
+        name: name.to_string(),
+        value,
+        text: Some(text.to_string()),
+        bbox: None,
+    };
+    let mut extraction = Extraction {
+        fields: vec![
+            field(
+                "email",
+                json!("jane.doe@example.org"),
+                "jane.doe@example.org",
+            ),
+            field("ssn", json!(123456789), "123-45-6789"),
+            field("total", json!(1250), "1,250"),
+        ],
+        answer: "email: jane.doe@example.org\nssn: 123-45-6789\ntotal: 1,250".to_string(),
+        width: 100,
+        height: 100,
+        prompt_tokens: 0,
+        completion_tokens: 0,
+        finish_reason: FinishReason::Stop,
+    };
+    extraction.redact(&redactor);
+    assert_eq!(
+        extraction.answer,
+        "email: [EMAIL]\nssn: [SSN]\ntotal: 1,250"
+    );
+    assert_eq!(extraction.fields[0].value, json!("[EMAIL]"));
+    assert_eq!(extraction.fields[1].text.as_deref(), Some("[SSN]"));
+    assert_eq!(extraction.fields[2].value, json!(1250));
+}
+
+#[test]
+fn redacted_regions_are_painted_over() {
+    let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(20, 10, Rgb([255, 255, 255])));
+    let region = [BoundingBox::new(2.0, 2.0, 8.0, 6.0)];
+    let black = redact_image(&image, &region, RedactStyle::Black).to_rgb8();
+    assert_eq!(black.get_pixel(4, 4), &Rgb([0, 0, 0]));
+    assert_eq!(black.get_pixel(10, 4), &Rgb([255, 255, 255]));
+
+    let blurred = redact_image(&image, &region, RedactStyle::Blur);
+    assert_eq!(blurred.width(), 20);
+
+    let redactor = Redactor::new(&RedactOptions::default()).unwrap();
+    let text = "<|ref|>text<|/ref|><|det|>[[100, 200, 400, 600]]<|/det|>\nMail jane@example.org";
+    let page = redactor.redact_page(text, &image).to_rgb8();
+    assert_eq!(page.get_pixel(4, 4), &Rgb([0, 0, 0]));
+    assert_eq!(page.get_pixel(15, 8), &Rgb([255, 255, 255]));
+}
@@ -0,0 +1,69 @@
+use deepseek_ocr_core::{
+    model::FinishReason,
+    repetition::{RepetitionAction, RepetitionOptions, detect_loop},
+};
+
+fn stop(window: usize, threshold: usize, min_tokens: usize) -> RepetitionOptions {
+    RepetitionOptions {
+        action: RepetitionAction::Stop,
+        window,
+        threshold,
+        min_tokens,
+    }
+}
+
+#[test]
+fn loops_are_detected_once_the_threshold_copy_completes() {
+    let options = stop(8, 3, 0);
+    let line = [7, 8, 9];
+    let mut tokens = vec![1, 2];
+    for _ in 0..2 {
+        tokens.extend(line);
+    }
+    assert_eq!(detect_loop(&tokens, &options), None);
+    tokens.extend(line);
+    assert_eq!(detect_loop(&tokens, &options), Some(3));
+
+    // The loop must reach the end of the output.
+    tokens.push(4);
+    assert_eq!(detect_loop(&tokens, &options), None);
+}
+
+#[test]
+fn short_runs_and_long_units_are_ignored() {
+    // Four copies of a single token are only a loop once they cover `min_tokens`.
+    let dashes = vec![5; 4];
+    assert_eq!(detect_loop(&dashes, &stop(8, 4, 6)), None);
+    assert_eq!(detect_loop(&[5; 6], &stop(8, 4, 6)), Some(1));
+
+    // Units longer than the window are not checked.
+    let unit: Vec<i64> = (0..10).collect();
+    let tokens = unit.repeat(4);
+    assert_eq!(detect_loop(&tokens, &stop(8, 4, 0)), None);
+    assert_eq!(detect_loop(&tokens, &stop(10, 4, 0)), Some(10));
+}
+
+#[test]
+fn detection_is_off_by_default() {
+    let tokens = vec![1; 200];
+    assert_eq!(detect_loop(&tokens, &RepetitionOptions::default()), None);
+    let options = RepetitionOptions {
+        action: RepetitionAction::Penalize,
+        ..RepetitionOptions::default()
+    };
+    assert_eq!(detect_loop(&tokens, &options), Some(1));
+}
+
+#[test]
+fn finish_reasons_summarise_by_severity() {
+    let reasons = [
+        FinishReason::Stop,
+        FinishReason::Repetition,
+        FinishReason::Length,
+    ];
+    assert_eq!(reasons.iter().max(), Some(&FinishReason::Repetition));
+    assert_eq!(
+        serde_json::to_string(&FinishReason::Length).unwrap(),
+        "\"length\""
+    );
+}
@@ -0,0 +1,94 @@
+use deepseek_ocr_core::{
+    engine::VisionSettings,
+    inference::{estimate_image_tokens, image_token_count},
+    resolution::{ImageStats, ResolutionMode, choose_resolution, preferred_mode, text_density},
+    vision::TilingOptions,
+};
+use image::{DynamicImage, GrayImage, Luma};
+
+fn stats(width: u32, height: u32, text_density: f32) -> ImageStats {
+    ImageStats {
+        width,
+        height,
+        text_density,
+    }
+}
+
+#[test]
+fn placeholder_counts_follow_grid_layout() {
+    // Fixed modes: one newline per row plus the view separator.
+    assert_eq!(image_token_count(None, false, 512, 512, false), 73);
+    assert_eq!(image_token_count(None, false, 1024, 1024, false), 273);
+    // Gundam on a 2:1 page: 16x16 global view plus a 2x1 grid of 10x10 local tiles.
+    let tiling = TilingOptions::default();
+    assert_eq!(
+        estimate_image_tokens(1280, 640, 1024, 640, true, tiling),
+        273 + 10 * 21
+    );
+    let with_thumb = TilingOptions {
+        use_thumbnail: true,
+        ..tiling
+    };
+    assert_eq!(
+        estimate_image_tokens(1280, 640, 1024, 640, true, with_thumb),
+        273 + 10 * 21 + 10 * 11
+    );
+}
+
+#[test]
+fn density_separates_blank_and_printed_images() {
+    let blank = DynamicImage::ImageLuma8(GrayImage::from_pixel(300, 200, Luma([255])));
+    assert_eq!(text_density(&blank), 0.0);
+    let striped = DynamicImage::ImageLuma8(GrayImage::from_fn(300, 200, |x, _| {
+        Luma([if (x / 2) % 4 == 0 { 0 } else { 255 }])
+    }));
+    assert!(text_density(&striped) > 0.08);
+}
+
+#[test]
+fn preferred_mode_tracks_size_and_density() {
+    assert_eq!(preferred_mode(&stats(400, 300, 0.05)), ResolutionMode::Tiny);
+    assert_eq!(
+        preferred_mode(&stats(1200, 900, 0.01)),
+        ResolutionMode::Small
+    );
+    assert_eq!(
+        preferred_mode(&stats(1000, 800, 0.05)),
+        ResolutionMode::Base
+    );
+    assert_eq!(
+        preferred_mode(&stats(1500, 1100, 0.12)),
+        ResolutionMode::Large
+    );
+    assert_eq!(
+        preferred_mode(&stats(800, 3000, 0.05)),
+        ResolutionMode::Gundam
+    );
+}
+
+#[test]
+fn token_cap_steps_down_modes() {
+    let base = VisionSettings::default();
+    let page = [stats(1500, 1100, 0.12)];
+    let uncapped = choose_resolution(&page, &base, None);
+    assert_eq!(uncapped.mode, ResolutionMode::Large);
+    assert_eq!(uncapped.image_tokens, 421);
+    assert!(uncapped.vision.auto_resolution.is_none());
+
+    let capped = choose_resolution(&page, &base, Some(300));
+    assert_eq!(capped.mode, ResolutionMode::Base);
+    assert_eq!(
+        (capped.vision.base_size, capped.vision.crop_mode),
+        (1024, false)
+    );
+
+    // Gundam trims its tile limit before giving up on tiling.
+    let receipt = [stats(800, 3000, 0.05)];
+    let tiled = choose_resolution(&receipt, &base, Some(600));
+    assert_eq!(tiled.mode, ResolutionMode::Gundam);
+    assert_eq!(tiled.image_tokens, 273 + 20 * 11);
+    assert!(tiled.vision.tiling.max_tiles < base.tiling.max_tiles);
+
+    let tiny = choose_resolution(&page, &base, Some(10));
+    assert_eq!(tiny.mode, ResolutionMode::Tiny);
+}
@@ -0,0 +1,43 @@
+use deepseek_ocr_core::{
+    grounding::{BoundingBox, parse_grounding},
+    roi::{RegionFrame, clamp_region, crop_region, parse_region},
+};
+use image::{DynamicImage, RgbImage};
+
+#[test]
+fn parses_and_clamps_regions() {
+    let region = parse_region("300, 400, 100, 50").expect("valid region");
+    assert_eq!(region, BoundingBox::new(100.0, 50.0, 300.0, 400.0));
+    assert!(parse_region("1,2,3").is_err());
+    assert!(parse_region("a,b,c,d").is_err());
+
+    let clamped = clamp_region(&BoundingBox::new(-10.5, 20.2, 900.0, 80.7), 640, 480).unwrap();
+    assert_eq!(clamped, BoundingBox::new(0.0, 20.0, 640.0, 81.0));
+    assert!(clamp_region(&BoundingBox::new(700.0, 0.0, 800.0, 10.0), 640, 480).is_err());
+}
+
+#[test]
+fn crop_matches_clamped_region() {
+    let image = DynamicImage::ImageRgb8(RgbImage::new(200, 100));
+    let (crop, region) = crop_region(&image, &BoundingBox::new(150.0, 50.0, 400.0, 90.0)).unwrap();
+    assert_eq!((crop.width(), crop.height()), (50, 40));
+    assert_eq!(region, BoundingBox::new(150.0, 50.0, 200.0, 90.0));
+}
+
+#[test]
+fn grounding_is_mapped_back_to_full_frame() {
+    // Crop covering the right half of a 1000x500 image.
+    let frame = RegionFrame::new(BoundingBox::new(500.0, 0.0, 1000.0, 500.0), 1000, 500);
+    let text = "<|ref|>text<|/ref|><|det|>[[0, 0, 999, 999]]<|/det|>\nTotal: 42";
+
+    let remapped = frame.remap_text(text);
+    let spans = parse_grounding(&remapped);
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0].content, "Total: 42");
+    let bbox = spans[0].boxes[0];
+    assert!((bbox.x1 - 500.0).abs() <= 1.0, "{bbox:?}");
+    assert!((bbox.x2 - 999.0).abs() <= 1.0, "{bbox:?}");
+
+    let pixels = frame.to_image_pixels(&BoundingBox::new(0.0, 0.0, 999.0, 999.0));
+    assert_eq!(pixels, BoundingBox::new(500.0, 0.0, 1000.0, 500.0));
+}
@@ -0,0 +1,57 @@
+use deepseek_ocr_core::{
+    segment::{SegmentOptions, merge_segment_texts, needs_segmentation, plan_segments},
+    text::{char_similarity, edit_distance},
+};
+
+#[test]
+fn regular_pages_are_not_segmented() {
+    let options = SegmentOptions::default();
+    assert!(!needs_segmentation(2480, 3508, &options));
+    let plan = plan_segments(2480, 3508, &options);
+    assert_eq!(plan.len(), 1);
+    assert_eq!((plan[0].width(), plan[0].height()), (2480.0, 3508.0));
+}
+
+#[test]
+fn tall_images_become_overlapping_strips() {
+    let options = SegmentOptions::default();
+    let plan = plan_segments(600, 6000, &options);
+    assert!(plan.len() > 1);
+    assert_eq!(plan[0].y1, 0.0);
+    assert_eq!(plan.last().unwrap().y2, 6000.0);
+    for pair in plan.windows(2) {
+        assert!(pair[1].y1 < pair[0].y2, "strips must overlap: {pair:?}");
+        assert!(pair[1].y1 > pair[0].y1);
+        assert_eq!(pair[0].width(), 600.0);
+    }
+
+    let capped = plan_segments(100, 100_000, &options);
+    assert_eq!(capped.len(), options.max_segments);
+    assert_eq!(capped.last().unwrap().y2, 100_000.0);
+
+    let wide = plan_segments(8000, 1000, &options);
+    assert!(wide.len() > 1 && wide.iter().all(|b| b.height() == 1000.0));
+}
+
+#[test]
+fn merge_drops_overlap_duplicates() {
+    let options = SegmentOptions::default();
+    let first = "STORE #42\nMilk 1.99\nBread 2.49\nEggs 3.10\n".to_string();
+    let second = "Bread  2.49\nEggs 3.1O\nButter 4.00\nTOTAL 11.58".to_string();
+    let merged = merge_segment_texts(&[first, second], &options);
+    assert_eq!(
+        merged,
+        "STORE #42\nMilk 1.99\nBread 2.49\nEggs 3.10\nButter 4.00\nTOTAL 11.58"
+    );
+
+    let disjoint = merge_segment_texts(&["a line".into(), "\nanother".into()], &options);
+    assert_eq!(disjoint, "a line\nanother");
+}
+
+#[test]
+fn edit_distance_basics() {
+    assert_eq!(edit_distance(b"kitten", b"sitting"), 3);
+    assert_eq!(edit_distance::<u8>(b"", b"abc"), 3);
+    assert!((char_similarity("abcd", "abcd") - 1.0).abs() < f32::EPSILON);
+    assert!(char_similarity("abcd", "wxyz") < 0.01);
+}
@@ -0,0 +1,69 @@
+use deepseek_ocr_core::export::{
+    PageOutput,
+    document::Document,
+    table::{MAX_SPAN, Table, TableFormat, parse_html_table, parse_pipe_table},
+};
+
+fn grid(rows: &[&[&str]]) -> Vec<Vec<String>> {
+    rows.iter()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect()
+}
+
+#[test]
+fn spans_are_expanded_into_a_rectangular_grid() {
+    let html = "<table><tr><td rowspan=\"2\">Region</td><td colspan=2>Sales</td></tr>\
+                <tr><td>Q1</td><td>Q2</td></tr>\
+                <tr><td>North</td><td>1,200</td></tr></table>";
+    assert_eq!(
+        parse_html_table(html),
+        grid(&[
+            &["Region", "Sales", "Sales"],
+            &["Region", "Q1", "Q2"],
+            &["North", "1,200", ""],
+        ])
+    );
+
+    // Spans past the last row are truncated and absurd spans are capped.
+    let html = "<table><tr><th ROWSPAN='5'>a</th><th colspan=\"100000\">b</th></tr></table>";
+    let rows = parse_html_table(html);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].len(), 1 + MAX_SPAN);
+}
+
+#[test]
+fn pipe_tables_drop_the_separator_and_pad_rows() {
+    let rows = parse_pipe_table(&["| a | b |", "|:--|--:|", "| 1 |", "| 2 | **3** |"]);
+    assert_eq!(rows, grid(&[&["a", "b"], &["1", ""], &["2", "3"]]));
+}
+
+#[test]
+fn tables_are_extracted_per_page_and_exported() {
+    let first = "<|ref|>table<|/ref|><|det|>[[0, 0, 999, 499]]<|/det|>\n\
+                 <table><tr><td>Item</td><td>Note</td></tr><tr><td>Tea</td><td>\"green\", hot</td></tr></table>";
+    let second = "Intro\n\n| x | y |\n|---|---|\n| 1 | 2 |";
+    let pages = [
+        PageOutput {
+            text: first,
+            size: Some((999, 999)),
+        },
+        PageOutput {
+            text: second,
+            size: None,
+        },
+    ];
+    let tables = Table::extract(&Document::from_pages(&pages));
+    assert_eq!(tables.len(), 2);
+    assert_eq!(tables[0].page, 1);
+    assert_eq!(tables[0].bbox.unwrap().y2, 499.0);
+    assert_eq!((tables[1].page, tables[1].columns()), (2, 2));
+    assert!(tables[1].bbox.is_none());
+
+    assert_eq!(
+        tables[0].to_csv(),
+        "Item,Note\r\nTea,\"\"\"green\"\", hot\"\r\n"
+    );
+    let json: Vec<Vec<String>> =
+        serde_json::from_str(&tables[1].render(TableFormat::Json).unwrap()).unwrap();
+    assert_eq!(json, grid(&[&["x", "y"], &["1", "2"]]));
+}
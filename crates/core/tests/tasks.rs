@@ -0,0 +1,90 @@
+use anyhow::Result;
+use deepseek_ocr_core::{
+    engine::VisionSettings,
+    task::{TaskPreset, get_task, register_task, resolve_task, task_names},
+};
+
+#[test]
+fn builtin_tasks_are_registered() {
+    let names = task_names();
+    for expected in [
+        "describe",
+        "extract",
+        "figure",
+        "free-ocr",
+        "grounding",
+        "locate",
+        "markdown",
+    ] {
+        assert!(
+            names.iter().any(|name| name == expected),
+            "missing {expected}"
+        );
+    }
+    let markdown = get_task("markdown").expect("markdown task registered");
+    assert_eq!(
+        markdown.render(None).unwrap(),
+        "<image>\n<|grounding|>Convert the document to markdown."
+    );
+}
+
+#[test]
+fn locate_task_requires_query() -> Result<()> {
+    let locate = resolve_task("locate")?;
+    assert!(locate.requires_query());
+    assert!(locate.render(None).is_err());
+    assert!(locate.render(Some("  ")).is_err());
+    assert_eq!(
+        locate.render(Some("Total"))?,
+        "<image>\nLocate <|ref|>Total<|/ref|> in the image."
+    );
+    Ok(())
+}
+
+#[test]
+fn unknown_task_lists_available_names() {
+    let err = resolve_task("no-such-task").unwrap_err().to_string();
+    assert!(err.contains("markdown"), "{err}");
+}
+
+#[test]
+fn task_overrides_vision_defaults() -> Result<()> {
+    let figure = resolve_task("figure")?;
+    let defaults = VisionSettings::default();
+    let vision = figure.vision_settings(defaults);
+    assert!(!vision.crop_mode);
+    assert_eq!(vision.base_size, 1024);
+    assert_eq!(vision.image_size, 1024);
+    Ok(())
+}
+
+#[test]
+fn registering_partial_preset_merges_with_builtin() -> Result<()> {
+    register_task(
+        "describe",
+        TaskPreset {
+            max_new_tokens: Some(64),
+            ..TaskPreset::default()
+        },
+    )?;
+    let describe = resolve_task("describe")?;
+    assert_eq!(describe.max_new_tokens, Some(64));
+    assert!(describe.prompt.contains("Describe this image"));
+
+    assert!(register_task("empty-task", TaskPreset::default()).is_err());
+
+    register_task(
+        "invoice-total",
+        TaskPreset {
+            prompt: "<image>\nLocate <|ref|>{query}<|/ref|> in the invoice.".into(),
+            crop_mode: Some(false),
+            ..TaskPreset::default()
+        },
+    )?;
+    let custom = resolve_task("invoice-total")?;
+    assert_eq!(
+        custom.render(Some("Total"))?,
+        "<image>\nLocate <|ref|>Total<|/ref|> in the invoice."
+    );
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use deepseek_ocr_core::vision::{
+    MAX_TILE_LIMIT, TilingOptions, dynamic_preprocess, select_tile_grid,
+};
+use image::{DynamicImage, RgbImage};
+
+#[test]
+fn tile_grid_respects_limits() {
+    assert_eq!(select_tile_grid(1280, 640, 2, 9, 640), (2, 1));
+    assert_eq!(select_tile_grid(640, 1920, 2, 9, 640), (1, 3));
+    // A tight cap forces a coarser grid for a very wide page.
+    assert_eq!(select_tile_grid(6400, 640, 2, 4, 640), (4, 1));
+    // Square images may use a single tile once the minimum allows it.
+    assert_eq!(select_tile_grid(640, 640, 1, 1, 640), (1, 1));
+}
+
+#[test]
+fn thumbnail_adds_one_tile() {
+    let image = DynamicImage::ImageRgb8(RgbImage::new(1280, 640));
+    let plain = dynamic_preprocess(&image, 2, 9, 640, false);
+    let with_thumb = dynamic_preprocess(&image, 2, 9, 640, true);
+    assert_eq!(plain.ratio, (2, 1));
+    assert_eq!(plain.tiles.len(), 2);
+    assert_eq!(with_thumb.tiles.len(), 3);
+}
+
+#[test]
+fn tiling_options_validate_bounds() {
+    assert!(TilingOptions::default().validate().is_ok());
+    let zero = TilingOptions {
+        min_tiles: 0,
+        ..TilingOptions::default()
+    };
+    assert!(zero.validate().is_err());
+    let inverted = TilingOptions {
+        min_tiles: 6,
+        max_tiles: 4,
+        use_thumbnail: false,
+    };
+    assert!(inverted.validate().is_err());
+    let too_many = TilingOptions {
+        max_tiles: MAX_TILE_LIMIT + 1,
+        ..TilingOptions::default()
+    };
+    assert!(too_many.validate().is_err());
+}
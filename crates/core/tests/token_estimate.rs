@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use deepseek_ocr_core::{
+    engine::{OcrRequest, VisionSettings, estimate_tokens},
+    inference::estimate_prompt_tokens,
+    resolution::ResolutionMode,
+    vision::TilingOptions,
+};
+use image::{DynamicImage, RgbImage};
+use tokenizers::{
+    Tokenizer, models::wordlevel::WordLevel, pre_tokenizers::whitespace::WhitespaceSplit,
+};
+
+fn word_tokenizer() -> Tokenizer {
+    let vocab: HashMap<String, u32> = ["[UNK]", "<image>", "Free", "OCR."]
+        .iter()
+        .enumerate()
+        .map(|(idx, word)| (word.to_string(), idx as u32))
+        .collect();
+    let model = WordLevel::builder()
+        .vocab(vocab.into_iter().collect())
+        .unk_token("[UNK]".into())
+        .build()
+        .expect("word-level model");
+    let mut tokenizer = Tokenizer::new(model);
+    tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+    tokenizer
+}
+
+#[test]
+fn prompt_estimate_counts_text_and_placeholders() {
+    let tokenizer = word_tokenizer();
+    let estimate = estimate_prompt_tokens(
+        &tokenizer,
+        "<image>\nFree OCR.",
+        &[(1280, 640)],
+        1024,
+        640,
+        true,
+        TilingOptions::default(),
+    )
+    .unwrap();
+    // BOS plus two words after the image slot.
+    assert_eq!(estimate.text_tokens, 3);
+    assert_eq!(estimate.image_tokens, vec![273 + 10 * 21]);
+    assert_eq!(estimate.prompt_tokens(), 3 + 483);
+
+    assert!(
+        estimate_prompt_tokens(
+            &tokenizer,
+            "<image>\n<image>",
+            &[(10, 10)],
+            1024,
+            640,
+            false,
+            TilingOptions::default(),
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn request_estimate_applies_resolution_mode() {
+    let tokenizer = word_tokenizer();
+    let images = [DynamicImage::ImageRgb8(RgbImage::new(900, 700))];
+    let request = OcrRequest::new("<image>\nFree OCR.", &images);
+    let mut vision = VisionSettings::default();
+    ResolutionMode::Small.apply(&mut vision, None);
+
+    let estimate = estimate_tokens(&tokenizer, &request, vision).unwrap();
+    assert_eq!(estimate.prompt.image_tokens, vec![111]);
+    assert!(estimate.resolution.is_none());
+
+    ResolutionMode::Auto.apply(&mut vision, Some(100));
+    let estimate = estimate_tokens(&tokenizer, &request, vision).unwrap();
+    let choice = estimate.resolution.expect("auto choice");
+    assert_eq!(choice.mode, ResolutionMode::Tiny);
+    assert_eq!(estimate.prompt.image_tokens, vec![73]);
+}
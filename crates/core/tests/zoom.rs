@@ -0,0 +1,69 @@
+use deepseek_ocr_core::{
+    engine::VisionSettings,
+    grounding::{parse_grounding, replace_span_contents},
+    resolution::AutoResolution,
+    vision::{CleanupOptions, CleanupStep},
+    zoom::{ZoomOptions, looks_unreliable, refinement_vision, select_zoom_regions, zoomed_size},
+};
+
+const PAGE: &str = "<|ref|>title<|/ref|><|det|>[[100, 20, 900, 80]]<|/det|>\n# Annual Report\n\n\
+<|ref|>text<|/ref|><|det|>[[100, 900, 900, 910]]<|/det|>\nsmall print\n\n\
+<|ref|>image<|/ref|><|det|>[[100, 100, 900, 105]]<|/det|>\n\n\
+<|ref|>text<|/ref|><|det|>[[100, 200, 900, 400]]<|/det|>\n.,;:-- ..\n";
+
+#[test]
+fn selects_small_and_unreliable_regions() {
+    let spans = parse_grounding(PAGE);
+    assert_eq!(spans.len(), 4);
+    let selected = select_zoom_regions(&spans, 1000, 2000, &ZoomOptions::default());
+    let indices: Vec<usize> = selected.iter().map(|(idx, _)| *idx).collect();
+    // Span 1 is tiny, span 3 is punctuation noise; the image block is skipped.
+    assert_eq!(indices, vec![1, 3]);
+    let (_, bbox) = selected[0];
+    assert!((bbox.y1 - 1801.8).abs() < 1.0, "{bbox:?}");
+}
+
+#[test]
+fn refined_text_is_spliced_into_document() {
+    let spans = parse_grounding(PAGE);
+    let text = replace_span_contents(PAGE, &spans, &[(1, "Small print, now legible.".into())]);
+    let reparsed = parse_grounding(&text);
+    assert_eq!(reparsed.len(), 4);
+    assert_eq!(reparsed[0].content, "# Annual Report");
+    assert_eq!(reparsed[1].content, "Small print, now legible.");
+    assert_eq!(reparsed[1].boxes, spans[1].boxes);
+    assert!(text.contains("legible.\n\n<|ref|>image"));
+}
+
+#[test]
+fn unreliable_heuristic_and_zoom_size() {
+    assert!(looks_unreliable(""));
+    assert!(looks_unreliable("ab\u{FFFD}c"));
+    assert!(looks_unreliable("--..;;"));
+    assert!(!looks_unreliable("Total: $42.00"));
+
+    assert_eq!(zoomed_size(200, 20, 3.0, 2048), (600, 60));
+    assert_eq!(zoomed_size(1000, 50, 3.0, 2048), (2048, 102));
+    assert_eq!(zoomed_size(4000, 100, 3.0, 2048), (4000, 100));
+}
+
+#[test]
+fn crops_reuse_the_resolved_mode_without_cleanup() {
+    let first_pass = VisionSettings {
+        base_size: 640,
+        image_size: 640,
+        crop_mode: false,
+        auto_resolution: Some(AutoResolution {
+            max_vision_tokens: Some(256),
+        }),
+        cleanup: CleanupOptions::from_steps(&[CleanupStep::All]),
+        ..VisionSettings::default()
+    };
+    let crop = refinement_vision(first_pass);
+    assert_eq!(
+        (crop.base_size, crop.image_size, crop.crop_mode),
+        (640, 640, false)
+    );
+    assert_eq!(crop.auto_resolution, None);
+    assert!(!crop.cleanup.any_enabled());
+}
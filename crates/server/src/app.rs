@@ -1,13 +1,11 @@
-use std::sync::{Arc, Mutex};
-
-use anyhow::{Context, Result};
+use anyhow::Result;
 use deepseek_ocr_config::{AppConfig, LocalFileSystem};
 use deepseek_ocr_core::{
-    model::DeepseekOcrModel,
+    engine::{EngineSettings, OcrEngine},
+    redact::Redactor,
     runtime::{default_dtype_for_device, prepare_device_and_dtype},
 };
 use rocket::{Config, data::ToByteUnit};
-use tokenizers::Tokenizer;
 use tracing::info;
 
 use crate::{
@@ -22,6 +20,7 @@ pub async fn run(args: Args) -> Result<()> {
     let (mut app_config, descriptor) = AppConfig::load_or_init(&fs, args.config.as_deref())?;
     app_config += &args;
     app_config.normalise(&fs)?;
+    app_config.register_tasks()?;
     let resources = app_config.active_model_resources(&fs)?;
 
     info!(
@@ -38,22 +37,21 @@ pub async fn run(args: Args) -> Result<()> {
         prepare_device_and_dtype(app_config.inference.device, app_config.inference.precision)?;
     let dtype = maybe_dtype.unwrap_or_else(|| default_dtype_for_device(&device));
 
-    let model = DeepseekOcrModel::load(Some(&config_path), Some(&weights_path), device, dtype)
-        .context("failed to load DeepSeek-OCR model")?;
-    let tokenizer = Tokenizer::from_file(&tokenizer_path).map_err(|err| {
-        anyhow::anyhow!(
-            "failed to load tokenizer from {}: {err}",
-            tokenizer_path.display()
-        )
-    })?;
+    let engine = OcrEngine::load(
+        &config_path,
+        &weights_path,
+        &tokenizer_path,
+        device,
+        dtype,
+        EngineSettings::from(&app_config.inference),
+    )?;
 
     let state = AppState::new(
-        Arc::new(Mutex::new(model)),
-        Arc::new(tokenizer),
-        app_config.inference.base_size,
-        app_config.inference.image_size,
-        app_config.inference.crop_mode,
-        app_config.inference.max_new_tokens,
+        engine,
+        app_config.inference.decode,
+        app_config.inference.postprocess,
+        Redactor::new(&app_config.inference.redaction)?,
+        app_config.inference.redaction.enabled,
         app_config.server.model_id.clone(),
     );
 
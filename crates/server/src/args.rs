@@ -2,7 +2,14 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use deepseek_ocr_config::{AppConfig, ConfigOverride, ConfigOverrides};
-use deepseek_ocr_core::runtime::{DeviceKind, Precision};
+use deepseek_ocr_core::{
+    postprocess::{PostprocessOptions, PostprocessStep},
+    redact::{PiiKind, RedactRegion, RedactStyle, parse_pattern},
+    repetition::RepetitionAction,
+    resolution::ResolutionMode,
+    runtime::{DeviceKind, Precision},
+    vision::{BackgroundColor, CleanupOptions, CleanupStep},
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "DeepSeek-OCR API Server", long_about = None)]
@@ -47,6 +54,79 @@ pub struct Args {
     #[arg(long, help_heading = "Inference")]
     pub crop_mode: Option<bool>,
 
+    /// Minimum number of local tiles in crop mode.
+    #[arg(long, help_heading = "Inference")]
+    pub min_tiles: Option<u32>,
+
+    /// Maximum number of local tiles in crop mode.
+    #[arg(long, help_heading = "Inference")]
+    pub max_tiles: Option<u32>,
+
+    /// Append a whole-image thumbnail tile in crop mode.
+    #[arg(long, help_heading = "Inference")]
+    pub thumbnail: Option<bool>,
+
+    /// Resolution mode (tiny/small/base/large/gundam/auto); overrides the size flags.
+    #[arg(long, help_heading = "Inference")]
+    pub resolution: Option<ResolutionMode>,
+
+    /// Per-image vision token cap used by `--resolution auto`.
+    #[arg(long, help_heading = "Inference")]
+    pub max_vision_tokens: Option<usize>,
+
+    /// Apply the EXIF orientation tag when decoding images (true/false, defaults to true).
+    #[arg(long, help_heading = "Inference")]
+    pub exif_orientation: Option<bool>,
+
+    /// Colour transparent pixels are composited onto (#rrggbb, defaults to white).
+    #[arg(long, value_name = "COLOR", help_heading = "Inference")]
+    pub background: Option<BackgroundColor>,
+
+    /// Document cleanup steps to run before preprocessing, comma separated
+    /// (rotate,deskew,contrast,shadows,denoise,binarize or all).
+    #[arg(long, value_delimiter = ',', help_heading = "Inference")]
+    pub cleanup: Option<Vec<CleanupStep>>,
+
+    /// Text post-processing passes to run on the decoded output, comma separated
+    /// (repeats,tables,hyphens,latex or all).
+    #[arg(long, value_delimiter = ',', help_heading = "Inference")]
+    pub postprocess: Option<Vec<PostprocessStep>>,
+
+    /// What to do when decoding falls into a repetition loop: off, stop (finish reason
+    /// `repetition`) or penalize (steer away from the loop and keep going).
+    #[arg(long, value_name = "ACTION", help_heading = "Inference")]
+    pub repetition: Option<RepetitionAction>,
+
+    /// Longest repeated unit, in tokens, checked by repetition detection.
+    #[arg(long, value_name = "TOKENS", help_heading = "Inference")]
+    pub repetition_window: Option<usize>,
+
+    /// Back-to-back copies of a unit that count as a repetition loop.
+    #[arg(long, value_name = "COUNT", help_heading = "Inference")]
+    pub repetition_threshold: Option<usize>,
+
+    /// Redact personal data (e-mail addresses, phone, SSN, card and IBAN numbers by default) in
+    /// every response; without it requests can still ask for redaction.
+    #[arg(long, help_heading = "Redaction")]
+    pub redact: bool,
+
+    /// Built-in PII patterns to redact, comma separated (email,phone,ssn,card,iban,date).
+    #[arg(long, value_delimiter = ',', help_heading = "Redaction")]
+    pub redact_kinds: Option<Vec<PiiKind>>,
+
+    /// Extra named redaction pattern; repeat for several.
+    #[arg(long = "redact-pattern", value_name = "NAME=REGEX", value_parser = parse_pattern, help_heading = "Redaction")]
+    pub redact_patterns: Vec<(String, String)>,
+
+    /// How redacted image regions are painted over (black/blur).
+    #[arg(long, help_heading = "Redaction")]
+    pub redact_style: Option<RedactStyle>,
+
+    /// Granularity of image redaction (word/line/block, default block); word and line boxes are
+    /// estimated within each grounded block, and ungrounded output redacts the whole page.
+    #[arg(long, help_heading = "Redaction")]
+    pub redact_region: Option<RedactRegion>,
+
     /// Default max tokens budget per request.
     #[arg(long, help_heading = "Inference")]
     pub max_new_tokens: Option<usize>,
@@ -77,7 +157,27 @@ impl From<&Args> for ConfigOverrides {
         overrides.inference.base_size = args.base_size;
         overrides.inference.image_size = args.image_size;
         overrides.inference.crop_mode = args.crop_mode;
+        overrides.inference.min_tiles = args.min_tiles;
+        overrides.inference.max_tiles = args.max_tiles;
+        overrides.inference.use_thumbnail = args.thumbnail;
+        overrides.inference.resolution = args.resolution;
+        overrides.inference.max_vision_tokens = args.max_vision_tokens;
+        overrides.inference.exif_orientation = args.exif_orientation;
+        overrides.inference.background = args.background;
+        overrides.inference.cleanup = args.cleanup.as_deref().map(CleanupOptions::from_steps);
+        overrides.inference.postprocess = args
+            .postprocess
+            .as_deref()
+            .map(PostprocessOptions::from_steps);
         overrides.inference.max_new_tokens = args.max_new_tokens;
+        overrides.inference.redact = args.redact.then_some(true);
+        overrides.inference.redact_kinds = args.redact_kinds.clone();
+        overrides.inference.redact_patterns = args.redact_patterns.clone();
+        overrides.inference.redact_style = args.redact_style;
+        overrides.inference.redact_region = args.redact_region;
+        overrides.inference.repetition = args.repetition;
+        overrides.inference.repetition_window = args.repetition_window;
+        overrides.inference.repetition_threshold = args.repetition_threshold;
         overrides.server.host = args.host.clone();
         overrides.server.port = args.port;
         overrides.server.model_id = args.model_id.clone();
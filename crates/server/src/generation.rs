@@ -1,14 +1,25 @@
-use std::{convert::TryFrom, sync::Arc};
+use std::{io::Cursor, sync::Arc};
 
 use base64::Engine;
-use candle_core::{DType, Tensor};
 use deepseek_ocr_core::{
-    inference::{
-        build_prompt_tokens, compute_image_embeddings, normalize_text, prepare_vision_inputs,
+    engine::{LocateResult, OcrEngine, OcrRequest, VisionSettings},
+    export::{OutputFormat, PageOutput, document::Document, table::Table},
+    extract::{ExtractOptions, Extraction, ExtractionSchema, extract_fields},
+    figures::{FigureOptions, extract_figures, insert_figures},
+    grounding::{BoundingBox, strip_grounding_tokens},
+    model::FinishReason,
+    postprocess::{PostprocessOptions, postprocess},
+    redact::Redactor,
+    repetition::RepetitionOptions,
+    roi::clamp_region,
+    segment::{SegmentOptions, segment_ocr},
+    task::TaskPreset,
+    vision::{
+        CleanupReport, DecodeOptions, DecodedImage, decode_pages, page_runs, record_orientations,
     },
-    model::{DeepseekOcrModel, GenerateOptions, OwnedVisionInput},
+    zoom::{ZoomOptions, zoom_ocr},
 };
-use image::DynamicImage;
+use image::{DynamicImage, ImageFormat, metadata::Orientation};
 use reqwest::blocking::Client;
 use rocket::tokio;
 use tokenizers::Tokenizer;
@@ -16,36 +27,84 @@ use tracing::info;
 
 use crate::{
     error::ApiError,
-    models::{ApiMessage, ImagePayload, MessageContent, MessagePart},
-    state::{GenerationInputs, SharedModel},
+    models::{ApiMessage, ExtractedFigure, ImagePayload, MessageContent, MessagePart},
+    state::{GenerationInputs, SharedEngine},
     stream::{StreamContext, StreamController},
 };
 
+/// Per-request knobs resolved from the payload, task preset and server defaults.
+#[derive(Debug, Clone)]
+pub struct GenerationParams {
+    pub max_new_tokens: usize,
+    pub vision: Option<VisionSettings>,
+    /// When non-empty, OCR only these pixel rectangles of the single input image.
+    pub regions: Vec<BoundingBox>,
+    /// Run the two-pass zoom refinement on the single input image.
+    pub zoom: bool,
+    /// Split the single input image into overlapping strips when it is very tall or wide.
+    pub segment: bool,
+    /// Format of the returned text; layout formats are rendered once every page is done.
+    pub format: OutputFormat,
+    /// Extract the tables of the output into [`GenerationResult::tables`].
+    pub tables: bool,
+    /// Overrides the engine's repetition-loop detection.
+    pub repetition: Option<RepetitionOptions>,
+    /// Passes run on each page's text before it is rendered.
+    pub postprocess: PostprocessOptions,
+    /// Redact PII in each page's text with these patterns.
+    pub redactor: Option<Arc<Redactor>>,
+    /// Return the page images with the redacted regions painted over.
+    pub redact_images: bool,
+    /// Crop, describe and link the figures of each page.
+    pub figures: bool,
+}
+
 #[derive(Debug)]
 pub struct GenerationResult {
     pub text: String,
     pub prompt_tokens: usize,
     pub response_tokens: usize,
+    /// Most severe finish reason over the runs that produced `text`.
+    pub finish_reason: FinishReason,
+    pub tables: Option<Vec<Table>>,
+    /// PNG data URLs of the redacted page images.
+    pub redacted_images: Option<Vec<String>>,
+    pub figures: Option<Vec<ExtractedFigure>>,
+    /// What document cleanup changed in each image; `None` when no cleanup step ran.
+    pub cleanup: Option<Vec<CleanupReport>>,
+}
+
+/// `reports` as result metadata: omitted when cleanup was off.
+fn cleanup_metadata(reports: Vec<CleanupReport>) -> Option<Vec<CleanupReport>> {
+    (!reports.is_empty()).then_some(reports)
+}
+
+/// Add the EXIF orientations a run's images were decoded with to `result`'s cleanup metadata.
+fn add_orientations(
+    result: &mut GenerationResult,
+    images: &[DynamicImage],
+    orientations: &[Orientation],
+) {
+    let mut reports = result.cleanup.take().unwrap_or_default();
+    record_orientations(&mut reports, images, orientations);
+    result.cleanup = cleanup_metadata(reports);
 }
 
 pub async fn generate_async(
     inputs: GenerationInputs,
     prompt: String,
-    images: Vec<DynamicImage>,
-    max_new_tokens: usize,
+    runs: Vec<Vec<DecodedImage>>,
+    params: GenerationParams,
     stream: Option<StreamContext>,
 ) -> Result<GenerationResult, ApiError> {
     let stream_for_block = stream.clone();
     let join_result = tokio::task::spawn_blocking(move || {
         generate_blocking(
-            &inputs.model,
+            &inputs.engine,
             Arc::clone(&inputs.tokenizer),
             prompt,
-            images,
-            inputs.base_size,
-            inputs.image_size,
-            inputs.crop_mode,
-            max_new_tokens,
+            runs,
+            params,
             stream_for_block,
         )
     })
@@ -69,55 +128,266 @@ pub async fn generate_async(
     }
 }
 
+/// Generate for each image run in turn. A single plain-text run without figures,
+/// post-processing or redaction streams as usual; the pages of a multi-page input are generated
+/// one after another and their texts joined, like regions, and the other formats (document,
+/// hOCR/ALTO) are rendered from all pages at the end.
 fn generate_blocking(
-    model: &SharedModel,
+    engine: &SharedEngine,
     tokenizer: Arc<Tokenizer>,
     prompt: String,
-    images: Vec<DynamicImage>,
-    base_size: u32,
-    image_size: u32,
-    crop_mode: bool,
-    max_new_tokens: usize,
+    runs: Vec<Vec<DecodedImage>>,
+    params: GenerationParams,
+    stream: Option<StreamContext>,
+) -> Result<GenerationResult, ApiError> {
+    let orientations: Vec<Vec<Orientation>> = runs
+        .iter()
+        .map(|run| run.iter().map(|page| page.orientation).collect())
+        .collect();
+    let runs: Vec<Vec<DynamicImage>> = runs
+        .into_iter()
+        .map(|run| run.into_iter().map(|page| page.image).collect())
+        .collect();
+    let format = params.format;
+    if let Some(images) = runs
+        .iter()
+        .find(|images| format.is_layout() && images.len() != 1)
+    {
+        return Err(ApiError::BadRequest(format!(
+            "format `{}` needs exactly one image per page (got {})",
+            format.name(),
+            images.len()
+        )));
+    }
+    for (enabled, option) in [
+        (params.redact_images, "redact_images"),
+        (params.figures, "figures"),
+    ] {
+        if let Some(images) = runs.iter().find(|images| enabled && images.len() != 1) {
+            return Err(ApiError::BadRequest(format!(
+                "`{option}` needs exactly one image per page (got {})",
+                images.len()
+            )));
+        }
+    }
+    let streams_directly = format == OutputFormat::Text
+        && !params.tables
+        && !params.figures
+        && !params.postprocess.any_enabled()
+        && params.redactor.is_none();
+    if let ([images], true) = (runs.as_slice(), streams_directly) {
+        let mut result = generate_run(
+            engine,
+            tokenizer,
+            &prompt,
+            images,
+            &orientations[0],
+            &params,
+            stream,
+        )?;
+        result.text = strip_grounding_tokens(&result.text);
+        return Ok(result);
+    }
+
+    let mut pages = Vec::with_capacity(runs.len());
+    let mut redacted_images = Vec::new();
+    let mut figures = Vec::new();
+    for (idx, images) in runs.iter().enumerate() {
+        let mut page = generate_run(
+            engine,
+            Arc::clone(&tokenizer),
+            &prompt,
+            images,
+            &orientations[idx],
+            &params,
+            None,
+        )?;
+        info!("[generate] page {}/{} done", idx + 1, runs.len());
+        if let ([image], true) = (images.as_slice(), params.figures) {
+            let redactor = params.redactor.as_deref();
+            add_figures(engine, &mut page, image, idx + 1, redactor, &mut figures)?;
+        }
+        page.text = postprocess(&page.text, &params.postprocess);
+        if let Some(redactor) = &params.redactor {
+            if let ([image], true) = (images.as_slice(), params.redact_images) {
+                redacted_images.push(redacted_image_url(redactor, &page.text, image)?);
+            }
+            page.text = redactor.redact_text(&page.text);
+        }
+        pages.push(page);
+    }
+    let outputs: Vec<PageOutput> = pages
+        .iter()
+        .zip(&runs)
+        .map(|(page, images)| PageOutput {
+            text: &page.text,
+            size: match images.as_slice() {
+                [image] => Some((image.width(), image.height())),
+                _ => None,
+            },
+        })
+        .collect();
+    let text = format
+        .render(&outputs)
+        .map_err(|err| ApiError::BadRequest(format!("{err:#}")))?;
+    let result = GenerationResult {
+        text,
+        prompt_tokens: pages.iter().map(|page| page.prompt_tokens).sum(),
+        response_tokens: pages.iter().map(|page| page.response_tokens).sum(),
+        finish_reason: pages
+            .iter()
+            .map(|page| page.finish_reason)
+            .max()
+            .unwrap_or_default(),
+        tables: params
+            .tables
+            .then(|| Table::extract(&Document::from_pages(&outputs))),
+        redacted_images: params.redact_images.then_some(redacted_images),
+        figures: params.figures.then_some(figures),
+        cleanup: cleanup_metadata(
+            pages
+                .iter()
+                .flat_map(|page| page.cleanup.clone().unwrap_or_default())
+                .collect(),
+        ),
+    };
+    if let Some(ctx) = stream {
+        let controller = StreamController::new(tokenizer, ctx);
+        controller.send_initial();
+        controller.send_text(&result.text);
+        controller.finalize(&result.text, &result);
+    }
+    Ok(result)
+}
+
+/// Crop and describe the figures of `page`, link them from its text and append them to
+/// `figures`; the describe runs count towards the page's usage. With a `redactor` the crops
+/// come from the redacted page image and the descriptions are redacted too.
+fn add_figures(
+    engine: &SharedEngine,
+    page: &mut GenerationResult,
+    image: &DynamicImage,
+    page_number: usize,
+    redactor: Option<&Redactor>,
+    figures: &mut Vec<ExtractedFigure>,
+) -> Result<(), ApiError> {
+    let redacted = redactor.map(|redactor| redactor.redact_page(&page.text, image));
+    let image = redacted.as_ref().unwrap_or(image);
+    let guard = engine
+        .lock()
+        .map_err(|_| ApiError::Internal("model lock poisoned".into()))?;
+    let result = extract_figures(&guard, &page.text, image, &FigureOptions::default())
+        .map_err(|err| ApiError::Internal(format!("figure extraction failed: {err:#}")))?;
+    drop(guard);
+    info!(
+        "[generate] page {page_number}: extracted {} figure(s)",
+        result.figures.len()
+    );
+    page.prompt_tokens += result.prompt_tokens;
+    page.response_tokens += result.completion_tokens;
+    page.finish_reason = page.finish_reason.max(result.finish_reason);
+
+    let mut names = Vec::with_capacity(result.figures.len());
+    for (idx, figure) in result.figures.iter().enumerate() {
+        let name = format!("page{page_number}_figure{}.png", idx + 1);
+        figures.push(ExtractedFigure {
+            page: page_number,
+            name: name.clone(),
+            label: figure.label.clone(),
+            bbox: figure.region,
+            image: png_data_url(&figure.image)?,
+            description: figure
+                .description
+                .as_deref()
+                .map(|description| match redactor {
+                    Some(redactor) => redactor.redact_text(description),
+                    None => description.to_string(),
+                }),
+        });
+        names.push(name);
+    }
+    page.text = insert_figures(&page.text, &result.figures, &names);
+    Ok(())
+}
+
+/// Paint over the PII found in `text` on `image` and encode the result as a PNG data URL.
+fn redacted_image_url(
+    redactor: &Redactor,
+    text: &str,
+    image: &DynamicImage,
+) -> Result<String, ApiError> {
+    png_data_url(&redactor.redact_page(text, image))
+}
+
+fn png_data_url(image: &DynamicImage) -> Result<String, ApiError> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|err| ApiError::Internal(format!("failed to encode image: {err}")))?;
+    Ok(format!(
+        "data:image/png;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(png)
+    ))
+}
+
+/// Generate for one image run. The returned text keeps the grounding markup for the page
+/// passes; anything streamed is sent the way it streams, without the grounding tokens.
+/// `orientations` are the EXIF orientations the run's images were decoded with.
+fn generate_run(
+    engine: &SharedEngine,
+    tokenizer: Arc<Tokenizer>,
+    prompt: &str,
+    images: &[DynamicImage],
+    orientations: &[Orientation],
+    params: &GenerationParams,
     stream: Option<StreamContext>,
 ) -> Result<GenerationResult, ApiError> {
-    let guard = model
+    let image_slots = prompt.matches("<image>").count();
+    if image_slots != images.len() {
+        return Err(ApiError::BadRequest(format!(
+            "prompt formatting failed: {image_slots} <image> slots but {} images",
+            images.len()
+        )));
+    }
+
+    let modes = usize::from(!params.regions.is_empty())
+        + usize::from(params.zoom)
+        + usize::from(params.segment);
+    if modes > 1 {
+        return Err(ApiError::BadRequest(
+            "`regions`, `zoom` and `segment` cannot be combined".into(),
+        ));
+    }
+
+    let guard = engine
         .lock()
         .map_err(|_| ApiError::Internal("model lock poisoned".into()))?;
-    let tokenizer_ref = tokenizer.as_ref();
-    let stream_controller = stream.map(|ctx| StreamController::new(Arc::clone(&tokenizer), ctx));
-    let owned_inputs = prepare_inputs(&*guard, &images, base_size, image_size, crop_mode)?;
-    let embeddings = compute_image_embeddings(&*guard, &owned_inputs)
-        .map_err(|err| ApiError::Internal(format!("image embedding failed: {err:#}")))?;
-    let (input_ids_vec, mask_vec) = build_prompt_tokens(
-        tokenizer_ref,
-        &prompt,
-        &embeddings,
-        &owned_inputs,
-        base_size,
-        image_size,
-        crop_mode,
-    )
-    .map_err(|err| ApiError::BadRequest(format!("prompt formatting failed: {err:#}")))?;
-
-    let input_len = input_ids_vec.len();
-    let token_device = guard.device();
-
-    let input_ids = Tensor::from_vec(input_ids_vec.clone(), (1, input_len), token_device)
-        .map_err(|err| ApiError::Internal(format!("input tensor failed: {err}")))?
-        .to_dtype(DType::I64)
-        .map_err(|err| ApiError::Internal(format!("tensor cast failed: {err}")))?;
-
-    let mask_tensor = Tensor::from_vec(mask_vec.clone(), (1, mask_vec.len()), token_device)
-        .map_err(|err| ApiError::Internal(format!("mask tensor failed: {err}")))?
-        .to_dtype(DType::U8)
-        .map_err(|err| ApiError::Internal(format!("mask cast failed: {err}")))?;
-
-    let mut options = GenerateOptions::new(max_new_tokens);
-    options.images_seq_mask = Some(&mask_tensor);
-    if !embeddings.is_empty() {
-        options.image_embeddings = Some(embeddings.as_slice());
+    let stream_controller = stream.map(|ctx| StreamController::new(tokenizer, ctx));
+
+    let mut request = OcrRequest::new(prompt, images);
+    request.max_new_tokens = Some(params.max_new_tokens);
+    request.vision = params.vision;
+    request.repetition = params.repetition;
+
+    if modes > 0 {
+        let result = if params.zoom {
+            generate_zoom(&guard, &request)
+        } else if params.segment {
+            generate_segmented(&guard, &request)
+        } else {
+            generate_regions(&guard, &request, &params.regions)
+        };
+        drop(guard);
+        let mut result = result?;
+        add_orientations(&mut result, images, orientations);
+        if let Some(controller) = &stream_controller {
+            let text = strip_grounding_tokens(&result.text);
+            controller.send_initial();
+            controller.send_text(&text);
+            controller.finalize(&text, &result);
+        }
+        return Ok(result);
     }
-    options.eos_token_id = guard.language_model().config().eos_token_id;
 
     let mut _progress_guard: Option<Box<dyn Fn(usize, &[i64]) + Send + Sync>> = None;
     if let Some(controller) = &stream_controller {
@@ -125,38 +395,24 @@ fn generate_blocking(
         let callback = controller.callback();
         _progress_guard = Some(Box::new(callback));
         if let Some(cb) = _progress_guard.as_ref() {
-            options.progress_callback = Some(&**cb);
+            request.progress_callback = Some(&**cb);
         }
     }
 
-    let generated = guard
-        .generate(&input_ids, options)
+    let result = guard
+        .run(&request)
         .map_err(|err| ApiError::Internal(format!("generation failed: {err:#}")))?;
-    let generated_tokens = generated
-        .to_vec2::<i64>()
-        .map_err(|err| ApiError::Internal(format!("token decode failed: {err:#}")))?
-        .into_iter()
-        .next()
-        .unwrap_or_default();
-    let decoded = tokenizer_ref
-        .decode(
-            &generated_tokens
-                .iter()
-                .filter_map(|&id| u32::try_from(id).ok())
-                .collect::<Vec<_>>(),
-            true,
-        )
-        .unwrap_or_default();
-    let normalized = normalize_text(&decoded);
 
     info!(
         "[generate] decoded_raw=\"{}\" normalized=\"{}\"",
-        decoded
+        result
+            .raw_text
             .replace('\n', "\\n")
             .chars()
             .take(120)
             .collect::<String>(),
-        normalized
+        result
+            .text
             .replace('\n', "\\n")
             .chars()
             .take(120)
@@ -165,30 +421,169 @@ fn generate_blocking(
 
     drop(guard);
 
+    let mut generation = GenerationResult {
+        prompt_tokens: result.prompt_tokens,
+        response_tokens: result.completion_tokens(),
+        finish_reason: result.finish_reason,
+        text: result.grounded_text(),
+        tables: None,
+        redacted_images: None,
+        figures: None,
+        cleanup: cleanup_metadata(result.cleanup),
+    };
+    add_orientations(&mut generation, images, orientations);
     if let Some(controller) = &stream_controller {
-        controller.flush_remaining(&generated_tokens);
-        controller.finalize(&normalized, input_len, generated_tokens.len());
+        controller.flush_remaining(&result.tokens);
+        controller.finalize(&result.text, &generation);
     }
+    Ok(generation)
+}
 
+fn generate_regions(
+    engine: &OcrEngine,
+    request: &OcrRequest<'_>,
+    regions: &[BoundingBox],
+) -> Result<GenerationResult, ApiError> {
+    if request.images.len() != 1 {
+        return Err(ApiError::BadRequest(format!(
+            "regions require exactly one image (got {})",
+            request.images.len()
+        )));
+    }
+    let image = &request.images[0];
+    for region in regions {
+        clamp_region(region, image.width(), image.height())
+            .map_err(|err| ApiError::BadRequest(format!("{err:#}")))?;
+    }
+    let results = engine
+        .run_regions(request, regions)
+        .map_err(|err| ApiError::Internal(format!("generation failed: {err:#}")))?;
+    let text = results
+        .iter()
+        .map(|result| result.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
     Ok(GenerationResult {
-        text: normalized,
-        prompt_tokens: input_len,
-        response_tokens: generated_tokens.len(),
+        text,
+        prompt_tokens: results.iter().map(|r| r.ocr.prompt_tokens).sum(),
+        response_tokens: results.iter().map(|r| r.ocr.completion_tokens()).sum(),
+        finish_reason: results
+            .iter()
+            .map(|r| r.ocr.finish_reason)
+            .max()
+            .unwrap_or_default(),
+        tables: None,
+        redacted_images: None,
+        figures: None,
+        cleanup: cleanup_metadata(results.iter().flat_map(|r| r.ocr.cleanup.clone()).collect()),
     })
 }
 
-fn prepare_inputs(
-    model: &DeepseekOcrModel,
-    images: &[DynamicImage],
-    base_size: u32,
-    image_size: u32,
-    crop_mode: bool,
-) -> Result<Vec<OwnedVisionInput>, ApiError> {
-    prepare_vision_inputs(model, images, base_size, image_size, crop_mode)
-        .map_err(|err| ApiError::Internal(format!("vision input failed: {err:#}")))
+fn generate_zoom(
+    engine: &OcrEngine,
+    request: &OcrRequest<'_>,
+) -> Result<GenerationResult, ApiError> {
+    if request.images.len() != 1 {
+        return Err(ApiError::BadRequest(format!(
+            "zoom requires exactly one image (got {})",
+            request.images.len()
+        )));
+    }
+    let result = zoom_ocr(engine, request, &ZoomOptions::default())
+        .map_err(|err| ApiError::Internal(format!("generation failed: {err:#}")))?;
+    info!(
+        "[generate] zoom refined {} region(s)",
+        result.refinements.len()
+    );
+    Ok(GenerationResult {
+        text: result.text,
+        prompt_tokens: result.prompt_tokens,
+        response_tokens: result.completion_tokens,
+        finish_reason: result.finish_reason,
+        tables: None,
+        redacted_images: None,
+        figures: None,
+        cleanup: cleanup_metadata(result.first_pass.cleanup),
+    })
+}
+
+fn generate_segmented(
+    engine: &OcrEngine,
+    request: &OcrRequest<'_>,
+) -> Result<GenerationResult, ApiError> {
+    if request.images.len() != 1 {
+        return Err(ApiError::BadRequest(format!(
+            "segment requires exactly one image (got {})",
+            request.images.len()
+        )));
+    }
+    let result = segment_ocr(engine, request, &SegmentOptions::default())
+        .map_err(|err| ApiError::Internal(format!("generation failed: {err:#}")))?;
+    info!("[generate] merged {} segment(s)", result.segments.len());
+    Ok(GenerationResult {
+        text: result.text,
+        prompt_tokens: result.prompt_tokens,
+        response_tokens: result.completion_tokens,
+        finish_reason: result.finish_reason,
+        tables: None,
+        redacted_images: None,
+        figures: None,
+        cleanup: cleanup_metadata(
+            result
+                .segments
+                .iter()
+                .flat_map(|segment| segment.ocr.cleanup.clone())
+                .collect(),
+        ),
+    })
 }
 
-pub fn convert_messages(messages: &[ApiMessage]) -> Result<(String, Vec<DynamicImage>), ApiError> {
+/// Run the `locate` task for `query` on a single image off the async runtime.
+pub async fn locate_async(
+    inputs: GenerationInputs,
+    image: DynamicImage,
+    query: String,
+) -> Result<LocateResult, ApiError> {
+    tokio::task::spawn_blocking(move || {
+        let guard = inputs
+            .engine
+            .lock()
+            .map_err(|_| ApiError::Internal("model lock poisoned".into()))?;
+        guard
+            .locate(&image, &query)
+            .map_err(|err| ApiError::Internal(format!("locate failed: {err:#}")))
+    })
+    .await
+    .map_err(|err| ApiError::Internal(format!("locate task failed: {err}")))?
+}
+
+/// Extract `schema` from a single image off the async runtime.
+pub async fn extract_async(
+    inputs: GenerationInputs,
+    image: DynamicImage,
+    schema: ExtractionSchema,
+    options: ExtractOptions,
+) -> Result<Extraction, ApiError> {
+    tokio::task::spawn_blocking(move || {
+        let guard = inputs
+            .engine
+            .lock()
+            .map_err(|_| ApiError::Internal("model lock poisoned".into()))?;
+        extract_fields(&guard, &image, &schema, &options)
+            .map_err(|err| ApiError::Internal(format!("extraction failed: {err:#}")))
+    })
+    .await
+    .map_err(|err| ApiError::Internal(format!("extraction task failed: {err}")))?
+}
+
+/// Collapse the chat history into a single OCR prompt. With a task preset the user's text only
+/// fills the preset's `{query}` slot; otherwise it is used verbatim. Images come back as runs
+/// (see [`page_runs`]): one run normally, one per page for a multi-page TIFF.
+pub fn convert_messages(
+    messages: &[ApiMessage],
+    task: Option<&TaskPreset>,
+    decode: &DecodeOptions,
+) -> Result<(String, Vec<Vec<DecodedImage>>), ApiError> {
     let latest_user_idx = messages
         .iter()
         .rposition(|message| message.role.eq_ignore_ascii_case("user"))
@@ -202,7 +597,7 @@ pub fn convert_messages(messages: &[ApiMessage]) -> Result<(String, Vec<DynamicI
     // OCR模型不是为对话训练的，所以只保留一轮的prompt，留多轮连正常输出都产生不了
     for message in &messages[..latest_user_idx] {
         if message.role.eq_ignore_ascii_case("system") {
-            let (text, mut msg_images) = flatten_content(&message.content)?;
+            let (text, mut msg_images) = flatten_content(&message.content, decode)?;
             if !text.is_empty() {
                 sections.push(text);
             }
@@ -210,7 +605,7 @@ pub fn convert_messages(messages: &[ApiMessage]) -> Result<(String, Vec<DynamicI
         }
     }
 
-    let (user_text, mut user_images) = flatten_content(&messages[latest_user_idx].content)?;
+    let (user_text, mut user_images) = flatten_content(&messages[latest_user_idx].content, decode)?;
     if !user_text.is_empty() {
         sections.push(user_text);
     }
@@ -223,7 +618,14 @@ pub fn convert_messages(messages: &[ApiMessage]) -> Result<(String, Vec<DynamicI
     }
 
     let mut prompt = String::from("<|User|>\n");
-    let body = sections.join("\n\n");
+    let body = match task {
+        Some(task) => {
+            let query = sections.join("\n\n").replace("<image>", "");
+            task.render(Some(&query))
+                .map_err(|err| ApiError::BadRequest(format!("{err:#}")))?
+        }
+        None => sections.join("\n\n"),
+    };
     if !body.is_empty() {
         prompt.push_str(&body);
         if !body.ends_with('\n') {
@@ -231,10 +633,14 @@ pub fn convert_messages(messages: &[ApiMessage]) -> Result<(String, Vec<DynamicI
         }
     }
     prompt.push_str("<|Assistant|>\n");
-    Ok((prompt, all_images))
+    let runs = page_runs(all_images).map_err(|err| ApiError::BadRequest(format!("{err:#}")))?;
+    Ok((prompt, runs))
 }
 
-fn flatten_content(content: &MessageContent) -> Result<(String, Vec<DynamicImage>), ApiError> {
+fn flatten_content(
+    content: &MessageContent,
+    decode: &DecodeOptions,
+) -> Result<(String, Vec<Vec<DecodedImage>>), ApiError> {
     match content {
         MessageContent::Text(text) => Ok((text.trim().to_owned(), Vec::new())),
         MessageContent::Parts(parts) => {
@@ -244,7 +650,7 @@ fn flatten_content(content: &MessageContent) -> Result<(String, Vec<DynamicImage
                 match part {
                     MessagePart::ImageUrl { image_url } | MessagePart::InputImage { image_url } => {
                         buffer.push_str("<image>");
-                        images.push(load_image(image_url)?);
+                        images.push(load_pages(image_url, decode)?);
                     }
                     MessagePart::Text { text } | MessagePart::InputText { text } => {
                         if !buffer.is_empty() {
@@ -259,20 +665,33 @@ fn flatten_content(content: &MessageContent) -> Result<(String, Vec<DynamicImage
     }
 }
 
-fn load_image(spec: &ImagePayload) -> Result<DynamicImage, ApiError> {
+/// Fetch and decode a single-page image payload to RGB according to `decode`.
+pub fn load_image(spec: &ImagePayload, decode: &DecodeOptions) -> Result<DynamicImage, ApiError> {
+    let mut pages = load_pages(spec, decode)?;
+    if pages.len() != 1 {
+        return Err(ApiError::BadRequest(format!(
+            "expected a single-page image (got {} pages)",
+            pages.len()
+        )));
+    }
+    Ok(pages.remove(0).image)
+}
+
+/// Fetch and decode every page of an image payload to RGB according to `decode`.
+fn load_pages(spec: &ImagePayload, decode: &DecodeOptions) -> Result<Vec<DecodedImage>, ApiError> {
     let url = spec.url();
     if let Some(rest) = url.strip_prefix("data:") {
-        return load_data_url(rest);
+        return load_data_url(rest, decode);
     }
     if url.starts_with("http://") || url.starts_with("https://") {
-        return fetch_remote_image(url);
+        return fetch_remote_image(url, decode);
     }
     Err(ApiError::BadRequest(
         "only data: URIs or http(s) image URLs are supported".into(),
     ))
 }
 
-fn load_data_url(data: &str) -> Result<DynamicImage, ApiError> {
+fn load_data_url(data: &str, decode: &DecodeOptions) -> Result<Vec<DecodedImage>, ApiError> {
     let (meta, payload) = data
         .split_once(',')
         .ok_or_else(|| ApiError::BadRequest("invalid data URL".into()))?;
@@ -284,11 +703,11 @@ fn load_data_url(data: &str) -> Result<DynamicImage, ApiError> {
     let decoded = base64::engine::general_purpose::STANDARD
         .decode(payload)
         .map_err(|err| ApiError::BadRequest(format!("invalid base64 image payload: {err}")))?;
-    image::load_from_memory(&decoded)
-        .map_err(|err| ApiError::BadRequest(format!("failed to decode inline image: {err}")))
+    decode_pages(&decoded, decode)
+        .map_err(|err| ApiError::BadRequest(format!("failed to decode inline image: {err:#}")))
 }
 
-fn fetch_remote_image(url: &str) -> Result<DynamicImage, ApiError> {
+fn fetch_remote_image(url: &str, decode: &DecodeOptions) -> Result<Vec<DecodedImage>, ApiError> {
     let client = Client::new();
     let response = client
         .get(url)
@@ -299,6 +718,6 @@ fn fetch_remote_image(url: &str) -> Result<DynamicImage, ApiError> {
     let bytes = response
         .bytes()
         .map_err(|err| ApiError::BadRequest(format!("failed to read image body: {err}")))?;
-    image::load_from_memory(&bytes)
-        .map_err(|err| ApiError::BadRequest(format!("failed to decode remote image: {err}")))
+    decode_pages(&bytes, decode)
+        .map_err(|err| ApiError::BadRequest(format!("failed to decode remote image: {err:#}")))
 }
@@ -1,4 +1,15 @@
+use deepseek_ocr_core::{
+    export::{OutputFormat, table::Table},
+    extract::ExtractedField,
+    grounding::{BoundingBox, GroundedBox},
+    model::FinishReason,
+    postprocess::PostprocessStep,
+    repetition::RepetitionOptions,
+    resolution::ResolutionMode,
+    vision::{CleanupReport, CleanupStep},
+};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 #[derive(Debug, Serialize)]
 pub struct ResponsesResponse {
@@ -6,8 +17,39 @@ pub struct ResponsesResponse {
     pub object: String,
     pub created: i64,
     pub model: String,
+    /// `stop`, `length` (token budget reached) or `repetition` (decoding loop detected).
+    pub finish_reason: FinishReason,
     pub output: Vec<ResponseOutput>,
     pub usage: Usage,
+    /// Tables extracted from the output when `options.tables` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tables: Option<Vec<Table>>,
+    /// Redacted page images when `options.redact_images` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redacted_images: Option<Vec<String>>,
+    /// Figures cropped from the page images when `options.figures` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub figures: Option<Vec<ExtractedFigure>>,
+    /// What document cleanup changed in each image, when a cleanup step ran.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cleanup: Option<Vec<CleanupReport>>,
+}
+
+/// A figure cropped from a page image; the response text links to it by `name`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractedFigure {
+    /// 1-based page number.
+    pub page: usize,
+    /// File name used in the markdown link, e.g. `page1_figure1.png`.
+    pub name: String,
+    /// Grounding label of the block (`image`, `figure`, `chart`).
+    pub label: String,
+    /// Crop rectangle in pixels of the page image.
+    pub bbox: BoundingBox,
+    /// PNG data URL of the crop.
+    pub image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -41,13 +83,25 @@ pub struct ChatCompletionResponse {
     pub model: String,
     pub choices: Vec<ChatChoice>,
     pub usage: Usage,
+    /// Tables extracted from the output when `options.tables` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tables: Option<Vec<Table>>,
+    /// Redacted page images when `options.redact_images` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redacted_images: Option<Vec<String>>,
+    /// Figures cropped from the page images when `options.figures` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub figures: Option<Vec<ExtractedFigure>>,
+    /// What document cleanup changed in each image, when a cleanup step ran.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cleanup: Option<Vec<CleanupReport>>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ChatChoice {
     pub index: usize,
     pub message: ChatMessageResponse,
-    pub finish_reason: String,
+    pub finish_reason: FinishReason,
 }
 
 #[derive(Debug, Serialize)]
@@ -81,6 +135,10 @@ pub struct ResponsesRequest {
     pub max_tokens: Option<usize>,
     #[serde(default)]
     pub stream: Option<bool>,
+    #[serde(default)]
+    pub task: Option<String>,
+    #[serde(flatten)]
+    pub options: RequestOptions,
 }
 
 #[derive(Debug, Deserialize)]
@@ -92,6 +150,152 @@ pub struct ChatCompletionRequest {
     pub max_tokens: Option<usize>,
     #[serde(default)]
     pub stream: Option<bool>,
+    #[serde(default)]
+    pub task: Option<String>,
+    #[serde(flatten)]
+    pub options: RequestOptions,
+}
+
+/// Pipeline knobs shared by the generation endpoints.
+#[derive(Debug, Default, Deserialize)]
+pub struct RequestOptions {
+    /// Pixel rectangles `[x1, y1, x2, y2]` of the single input image to OCR individually.
+    #[serde(default)]
+    pub regions: Vec<[f32; 4]>,
+    /// Re-read small or unreliable grounded regions as upscaled crops (two-pass zoom).
+    #[serde(default)]
+    pub zoom: Option<bool>,
+    /// Split very tall or wide images into overlapping strips and merge the outputs.
+    #[serde(default)]
+    pub segment: Option<bool>,
+    /// Tile-count limits and thumbnail toggle for crop mode.
+    #[serde(default)]
+    pub min_tiles: Option<u32>,
+    #[serde(default)]
+    pub max_tiles: Option<u32>,
+    #[serde(default)]
+    pub thumbnail: Option<bool>,
+    /// Resolution mode (`tiny`..`gundam`, or `auto` to pick one from the image).
+    #[serde(default)]
+    pub resolution: Option<ResolutionMode>,
+    /// Per-image vision token cap honoured by `auto`.
+    #[serde(default)]
+    pub max_vision_tokens: Option<usize>,
+    /// Document cleanup steps (`rotate`, `deskew`, `contrast`, `shadows`, `denoise`,
+    /// `binarize`, `all`); the response reports what each one changed (`cleanup`).
+    #[serde(default)]
+    pub cleanup: Option<Vec<CleanupStep>>,
+    /// Text post-processing passes (`repeats`, `tables`, `hyphens`, `latex`, `all`); replaces the
+    /// server default, so `[]` disables post-processing.
+    #[serde(default)]
+    pub postprocess: Option<Vec<PostprocessStep>>,
+    /// Repetition-loop detection (`action`, `window`, `threshold`, `min_tokens`); replaces the
+    /// server default, with omitted fields taking the built-in defaults.
+    #[serde(default)]
+    pub repetition: Option<RepetitionOptions>,
+    /// Redact personal data in the returned text (always on when the server redacts by default).
+    #[serde(default)]
+    pub redact: Option<bool>,
+    /// Also return each page image with the redacted regions painted over (`redacted_images`,
+    /// PNG data URLs); implies `redact` and needs one image per page.
+    #[serde(default)]
+    pub redact_images: Option<bool>,
+    /// Response text format: `text` (default), `plain`, `html`, `json`, `hocr` or `alto` (`pdf` is
+    /// CLI only).
+    #[serde(default)]
+    pub format: Option<OutputFormat>,
+    /// Also return every table in the output as a normalised cell grid (`tables`).
+    #[serde(default)]
+    pub tables: Option<bool>,
+    /// Crop the grounded figures of each page, describe them and link them from the text
+    /// (`figures`); needs one image per page.
+    #[serde(default)]
+    pub figures: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExtractRequest {
+    pub model: String,
+    pub image: ImagePayload,
+    /// Fields as `name` or `name:type` strings.
+    #[serde(default)]
+    pub fields: Vec<String>,
+    /// JSON Schema object (or array of `{"name", "type", "description"}` fields); used instead
+    /// of `fields`.
+    #[serde(default)]
+    pub schema: Option<Value>,
+    /// Run a grounding pass to return the supporting box of each value (default `true`).
+    #[serde(default)]
+    pub boxes: Option<bool>,
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExtractResponse {
+    pub id: String,
+    pub object: String,
+    pub created: i64,
+    pub model: String,
+    /// `field name -> value`, with values coerced to the field types.
+    pub data: Map<String, Value>,
+    pub fields: Vec<ExtractedField>,
+    pub width: u32,
+    pub height: u32,
+    /// Raw model answer to the extraction prompt.
+    pub text: String,
+    pub finish_reason: FinishReason,
+    pub usage: Usage,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LocateRequest {
+    pub model: String,
+    pub image: ImagePayload,
+    pub query: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocateResponse {
+    pub id: String,
+    pub object: String,
+    pub created: i64,
+    pub model: String,
+    pub query: String,
+    pub width: u32,
+    pub height: u32,
+    pub boxes: Vec<GroundedBox>,
+    pub text: String,
+    pub usage: Usage,
+}
+
+/// Body of `/v1/tokenize`: the same prompt fields as the generation endpoints, without
+/// decoding options.
+#[derive(Debug, Deserialize)]
+pub struct TokenizeRequest {
+    pub model: String,
+    #[serde(default, alias = "input")]
+    pub messages: Vec<ApiMessage>,
+    #[serde(default)]
+    pub task: Option<String>,
+    #[serde(flatten)]
+    pub options: RequestOptions,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenizeResponse {
+    pub object: String,
+    pub model: String,
+    pub prompt_tokens: usize,
+    pub text_tokens: usize,
+    /// Vision placeholder tokens per image, in prompt order.
+    pub image_tokens: Vec<usize>,
+    pub base_size: u32,
+    pub image_size: u32,
+    pub crop_mode: bool,
+    /// Mode picked by `"resolution": "auto"`, when enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<ResolutionMode>,
 }
 
 #[derive(Debug, Deserialize)]
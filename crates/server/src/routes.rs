@@ -1,16 +1,27 @@
-use std::time::SystemTime;
+use std::{sync::Arc, time::SystemTime};
 
+use deepseek_ocr_core::{
+    engine::{OcrRequest, TokenEstimate, estimate_tokens},
+    extract::{ExtractOptions, ExtractionSchema},
+    grounding::BoundingBox,
+    postprocess::PostprocessOptions,
+    task::{TaskPreset, resolve_task},
+    vision::CleanupOptions,
+};
 use rocket::{Either, Route, State, serde::json::Json, tokio::sync::mpsc};
 use tracing::debug;
 use uuid::Uuid;
 
 use crate::{
     error::ApiError,
-    generation::{convert_messages, generate_async},
+    generation::{
+        GenerationParams, convert_messages, extract_async, generate_async, load_image, locate_async,
+    },
     models::{
-        ChatChoice, ChatCompletionRequest, ChatCompletionResponse, ChatMessageResponse, ModelInfo,
-        ModelsResponse, ResponseContent, ResponseOutput, ResponsesRequest, ResponsesResponse,
-        Usage,
+        ChatChoice, ChatCompletionRequest, ChatCompletionResponse, ChatMessageResponse,
+        ExtractRequest, ExtractResponse, LocateRequest, LocateResponse, ModelInfo, ModelsResponse,
+        RequestOptions, ResponseContent, ResponseOutput, ResponsesRequest, ResponsesResponse,
+        TokenizeRequest, TokenizeResponse, Usage,
     },
     state::{AppState, GenerationInputs},
     stream::{BoxEventStream, StreamContext, StreamKind, into_event_stream},
@@ -42,11 +53,14 @@ pub async fn responses_endpoint(
 ) -> Result<Either<Json<ResponsesResponse>, BoxEventStream>, ApiError> {
     ensure_model(&req.model, &state.model_id)?;
     let gen_inputs = GenerationInputs::from_app(state.inner());
-    let (prompt, images) = convert_messages(&req.input)?;
-    let max_tokens = req
-        .max_output_tokens
-        .or(req.max_tokens)
-        .unwrap_or(state.max_new_tokens);
+    let task = resolve_request_task(req.task.as_deref())?;
+    let params = generation_params(
+        state,
+        task.as_ref(),
+        req.max_output_tokens.or(req.max_tokens),
+        &req.options,
+    )?;
+    let (prompt, runs) = convert_messages(&req.input, task.as_ref(), &state.decode)?;
     if req.stream.unwrap_or(false) {
         let stream_inputs = gen_inputs.clone();
         let created = current_timestamp();
@@ -65,24 +79,18 @@ pub async fn responses_endpoint(
         };
         let task_context = context.clone();
         rocket::tokio::spawn(async move {
-            let _ = generate_async(
-                stream_inputs,
-                prompt,
-                images,
-                max_tokens,
-                Some(task_context),
-            )
-            .await;
+            let _ = generate_async(stream_inputs, prompt, runs, params, Some(task_context)).await;
         });
         return Ok(Either::Right(stream));
     }
-    let generation = generate_async(gen_inputs, prompt, images, max_tokens, None).await?;
+    let generation = generate_async(gen_inputs, prompt, runs, params, None).await?;
     let created = current_timestamp();
     let response = ResponsesResponse {
         id: format!("resp-{}", Uuid::new_v4()),
         object: "response".into(),
         created,
         model: req.model.clone(),
+        finish_reason: generation.finish_reason,
         output: vec![ResponseOutput {
             id: format!("msg-{}", Uuid::new_v4()),
             r#type: "message".into(),
@@ -97,6 +105,10 @@ pub async fn responses_endpoint(
             completion_tokens: generation.response_tokens,
             total_tokens: generation.prompt_tokens + generation.response_tokens,
         },
+        tables: generation.tables,
+        redacted_images: generation.redacted_images,
+        figures: generation.figures,
+        cleanup: generation.cleanup,
     };
     Ok(Either::Left(Json(response)))
 }
@@ -108,9 +120,10 @@ pub async fn chat_completions_endpoint(
 ) -> Result<Either<Json<ChatCompletionResponse>, BoxEventStream>, ApiError> {
     ensure_model(&req.model, &state.model_id)?;
     let gen_inputs = GenerationInputs::from_app(state.inner());
-    let (prompt, images) = convert_messages(&req.messages)?;
+    let task = resolve_request_task(req.task.as_deref())?;
+    let params = generation_params(state, task.as_ref(), req.max_tokens, &req.options)?;
+    let (prompt, runs) = convert_messages(&req.messages, task.as_ref(), &state.decode)?;
     debug!(prompt = %prompt, "Prepared chat prompt");
-    let max_tokens = req.max_tokens.unwrap_or(state.max_new_tokens);
     if req.stream.unwrap_or(false) {
         let stream_inputs = gen_inputs.clone();
         let created = current_timestamp();
@@ -127,18 +140,11 @@ pub async fn chat_completions_endpoint(
         };
         let task_context = context.clone();
         rocket::tokio::spawn(async move {
-            let _ = generate_async(
-                stream_inputs,
-                prompt,
-                images,
-                max_tokens,
-                Some(task_context),
-            )
-            .await;
+            let _ = generate_async(stream_inputs, prompt, runs, params, Some(task_context)).await;
         });
         return Ok(Either::Right(stream));
     }
-    let generation = generate_async(gen_inputs, prompt, images, max_tokens, None).await?;
+    let generation = generate_async(gen_inputs, prompt, runs, params, None).await?;
     let created = current_timestamp();
     let response = ChatCompletionResponse {
         id: format!("chatcmpl-{}", Uuid::new_v4()),
@@ -151,26 +157,225 @@ pub async fn chat_completions_endpoint(
                 role: "assistant".into(),
                 content: generation.text.clone(),
             },
-            finish_reason: "stop".into(),
+            finish_reason: generation.finish_reason,
         }],
         usage: Usage {
             prompt_tokens: generation.prompt_tokens,
             completion_tokens: generation.response_tokens,
             total_tokens: generation.prompt_tokens + generation.response_tokens,
         },
+        tables: generation.tables,
+        redacted_images: generation.redacted_images,
+        figures: generation.figures,
+        cleanup: generation.cleanup,
     };
     Ok(Either::Left(Json(response)))
 }
 
+#[post("/extract", format = "json", data = "<req>")]
+pub async fn extract_endpoint(
+    state: &State<AppState>,
+    req: Json<ExtractRequest>,
+) -> Result<Json<ExtractResponse>, ApiError> {
+    ensure_model(&req.model, &state.model_id)?;
+    let schema = match &req.schema {
+        Some(schema) => ExtractionSchema::from_json(schema),
+        None => ExtractionSchema::from_fields(&req.fields),
+    }
+    .map_err(|err| ApiError::BadRequest(format!("{err:#}")))?;
+    let options = ExtractOptions {
+        boxes: req.boxes.unwrap_or(true),
+        max_new_tokens: req.max_tokens,
+    };
+    let image = load_image(&req.image, &state.decode)?;
+    let inputs = GenerationInputs::from_app(state.inner());
+    let mut result = extract_async(inputs, image, schema, options).await?;
+    if state.redact_by_default {
+        result.redact(&state.redactor);
+    }
+    Ok(Json(ExtractResponse {
+        id: format!("extract-{}", Uuid::new_v4()),
+        object: "extraction".into(),
+        created: current_timestamp(),
+        model: req.model.clone(),
+        data: result.data(),
+        width: result.width,
+        height: result.height,
+        finish_reason: result.finish_reason,
+        usage: Usage {
+            prompt_tokens: result.prompt_tokens,
+            completion_tokens: result.completion_tokens,
+            total_tokens: result.prompt_tokens + result.completion_tokens,
+        },
+        fields: result.fields,
+        text: result.answer,
+    }))
+}
+
+#[post("/locate", format = "json", data = "<req>")]
+pub async fn locate_endpoint(
+    state: &State<AppState>,
+    req: Json<LocateRequest>,
+) -> Result<Json<LocateResponse>, ApiError> {
+    ensure_model(&req.model, &state.model_id)?;
+    if req.query.trim().is_empty() {
+        return Err(ApiError::BadRequest("query must not be empty".into()));
+    }
+    let image = load_image(&req.image, &state.decode)?;
+    let inputs = GenerationInputs::from_app(state.inner());
+    let mut result = locate_async(inputs, image, req.query.clone()).await?;
+    if state.redact_by_default {
+        result.redact(&state.redactor);
+    }
+    let prompt_tokens = result.ocr.prompt_tokens;
+    let completion_tokens = result.ocr.completion_tokens();
+    Ok(Json(LocateResponse {
+        id: format!("locate-{}", Uuid::new_v4()),
+        object: "locate".into(),
+        created: current_timestamp(),
+        model: req.model.clone(),
+        query: result.query,
+        width: result.width,
+        height: result.height,
+        boxes: result.boxes,
+        text: result.ocr.text,
+        usage: Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        },
+    }))
+}
+
+/// Count the prompt tokens a generation request would use without running the model.
+#[post("/tokenize", format = "json", data = "<req>")]
+pub fn tokenize_endpoint(
+    state: &State<AppState>,
+    req: Json<TokenizeRequest>,
+) -> Result<Json<TokenizeResponse>, ApiError> {
+    ensure_model(&req.model, &state.model_id)?;
+    let options = &req.options;
+    if !options.regions.is_empty()
+        || options.zoom.unwrap_or(false)
+        || options.segment.unwrap_or(false)
+    {
+        return Err(ApiError::BadRequest(
+            "`regions`, `zoom` and `segment` run several passes and cannot be estimated".into(),
+        ));
+    }
+    let task = resolve_request_task(req.task.as_deref())?;
+    let params = generation_params(state, task.as_ref(), None, options)?;
+    let (prompt, runs) = convert_messages(&req.messages, task.as_ref(), &state.decode)?;
+    let mut estimate = None::<TokenEstimate>;
+    for run in runs {
+        let images: Vec<_> = run.into_iter().map(|page| page.image).collect();
+        let mut request = OcrRequest::new(&prompt, &images);
+        request.vision = params.vision;
+        let page = estimate_tokens(&state.tokenizer, &request, state.vision)
+            .map_err(|err| ApiError::BadRequest(format!("{err:#}")))?;
+        match estimate.as_mut() {
+            Some(total) => total.add_page(page),
+            None => estimate = Some(page),
+        }
+    }
+    let estimate = estimate.ok_or_else(|| ApiError::BadRequest("nothing to estimate".into()))?;
+    Ok(Json(TokenizeResponse {
+        object: "tokenize".into(),
+        model: req.model.clone(),
+        prompt_tokens: estimate.prompt.prompt_tokens(),
+        text_tokens: estimate.prompt.text_tokens,
+        image_tokens: estimate.prompt.image_tokens,
+        base_size: estimate.vision.base_size,
+        image_size: estimate.vision.image_size,
+        crop_mode: estimate.vision.crop_mode,
+        resolution: estimate.resolution.map(|choice| choice.mode),
+    }))
+}
+
 pub fn v1_routes() -> Vec<Route> {
     routes![
         health,
         list_models,
         responses_endpoint,
-        chat_completions_endpoint
+        chat_completions_endpoint,
+        locate_endpoint,
+        extract_endpoint,
+        tokenize_endpoint
     ]
 }
 
+fn resolve_request_task(name: Option<&str>) -> Result<Option<TaskPreset>, ApiError> {
+    name.map(|name| resolve_task(name).map_err(|err| ApiError::BadRequest(format!("{err:#}"))))
+        .transpose()
+}
+
+fn generation_params(
+    state: &AppState,
+    task: Option<&TaskPreset>,
+    requested_max_tokens: Option<usize>,
+    options: &RequestOptions,
+) -> Result<GenerationParams, ApiError> {
+    let max_new_tokens = requested_max_tokens
+        .or_else(|| task.and_then(|task| task.max_new_tokens))
+        .unwrap_or(state.max_new_tokens);
+    let mut vision = task.map(|task| task.vision_settings(state.vision));
+    if options.min_tiles.is_some() || options.max_tiles.is_some() || options.thumbnail.is_some() {
+        let settings = vision.get_or_insert(state.vision);
+        let tiling = &mut settings.tiling;
+        tiling.min_tiles = options.min_tiles.unwrap_or(tiling.min_tiles);
+        tiling.max_tiles = options.max_tiles.unwrap_or(tiling.max_tiles);
+        tiling.use_thumbnail = options.thumbnail.unwrap_or(tiling.use_thumbnail);
+        tiling
+            .validate()
+            .map_err(|err| ApiError::BadRequest(format!("{err:#}")))?;
+    }
+    if let Some(mode) = options.resolution {
+        mode.apply(
+            vision.get_or_insert(state.vision),
+            options.max_vision_tokens,
+        );
+    } else if let Some(cap) = options.max_vision_tokens {
+        let settings = vision.get_or_insert(state.vision);
+        if let Some(auto) = settings.auto_resolution.as_mut() {
+            auto.max_vision_tokens = Some(cap);
+        }
+    }
+    if let Some(steps) = &options.cleanup {
+        vision.get_or_insert(state.vision).cleanup = CleanupOptions::from_steps(steps);
+    }
+    let format = options.format.unwrap_or_default();
+    if format.is_binary() {
+        return Err(ApiError::BadRequest(format!(
+            "format `{}` is only available from the CLI",
+            format.name()
+        )));
+    }
+    Ok(GenerationParams {
+        max_new_tokens,
+        vision,
+        regions: options
+            .regions
+            .iter()
+            .map(|&[x1, y1, x2, y2]| BoundingBox::new(x1, y1, x2, y2))
+            .collect(),
+        zoom: options.zoom.unwrap_or(false),
+        segment: options.segment.unwrap_or(false),
+        format,
+        tables: options.tables.unwrap_or(false),
+        figures: options.figures.unwrap_or(false),
+        repetition: options.repetition,
+        redactor: (state.redact_by_default
+            || options.redact.unwrap_or(false)
+            || options.redact_images.unwrap_or(false))
+        .then(|| Arc::clone(&state.redactor)),
+        redact_images: options.redact_images.unwrap_or(false),
+        postprocess: options
+            .postprocess
+            .as_deref()
+            .map_or(state.postprocess, PostprocessOptions::from_steps),
+    })
+}
+
 fn ensure_model(requested: &str, available: &str) -> Result<(), ApiError> {
     if requested == available {
         Ok(())
@@ -2,37 +2,51 @@ use std::sync::{Arc, Mutex};
 
 use tokenizers::Tokenizer;
 
-use deepseek_ocr_core::model::DeepseekOcrModel;
+use deepseek_ocr_core::{
+    engine::{OcrEngine, VisionSettings},
+    postprocess::PostprocessOptions,
+    redact::Redactor,
+    vision::DecodeOptions,
+};
 
-pub type SharedModel = Arc<Mutex<DeepseekOcrModel>>;
+pub type SharedEngine = Arc<Mutex<OcrEngine>>;
 
 pub struct AppState {
-    pub model: SharedModel,
+    pub engine: SharedEngine,
     pub tokenizer: Arc<Tokenizer>,
-    pub base_size: u32,
-    pub image_size: u32,
-    pub crop_mode: bool,
+    pub vision: VisionSettings,
+    /// How request images are decoded before they reach the engine.
+    pub decode: DecodeOptions,
+    /// Default text post-processing passes, overridable per request.
+    pub postprocess: PostprocessOptions,
+    /// Compiled PII patterns, used for every request when `redact_by_default` is set and
+    /// otherwise only for requests asking for redaction.
+    pub redactor: Arc<Redactor>,
+    pub redact_by_default: bool,
     pub max_new_tokens: usize,
     pub model_id: String,
 }
 
 impl AppState {
-    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        model: SharedModel,
-        tokenizer: Arc<Tokenizer>,
-        base_size: u32,
-        image_size: u32,
-        crop_mode: bool,
-        max_new_tokens: usize,
+        engine: OcrEngine,
+        decode: DecodeOptions,
+        postprocess: PostprocessOptions,
+        redactor: Redactor,
+        redact_by_default: bool,
         model_id: String,
     ) -> Self {
+        let tokenizer = Arc::clone(engine.tokenizer());
+        let vision = engine.settings().vision;
+        let max_new_tokens = engine.settings().max_new_tokens;
         Self {
-            model,
+            engine: Arc::new(Mutex::new(engine)),
             tokenizer,
-            base_size,
-            image_size,
-            crop_mode,
+            vision,
+            decode,
+            postprocess,
+            redactor: Arc::new(redactor),
+            redact_by_default,
             max_new_tokens,
             model_id,
         }
@@ -41,21 +55,15 @@ impl AppState {
 
 #[derive(Clone)]
 pub struct GenerationInputs {
-    pub model: SharedModel,
+    pub engine: SharedEngine,
     pub tokenizer: Arc<Tokenizer>,
-    pub base_size: u32,
-    pub image_size: u32,
-    pub crop_mode: bool,
 }
 
 impl GenerationInputs {
     pub fn from_app(state: &AppState) -> Self {
         Self {
-            model: Arc::clone(&state.model),
+            engine: Arc::clone(&state.engine),
             tokenizer: Arc::clone(&state.tokenizer),
-            base_size: state.base_size,
-            image_size: state.image_size,
-            crop_mode: state.crop_mode,
         }
     }
 }
@@ -12,6 +12,8 @@ use serde_json::json;
 use tokenizers::Tokenizer;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
+use crate::generation::GenerationResult;
+
 pub type BoxEventStream =
     EventStream<Pin<Box<dyn rocket::futures::stream::Stream<Item = Event> + Send>>>;
 
@@ -115,9 +117,16 @@ impl StreamController {
         self.inner.flush_remaining(tokens);
     }
 
-    pub fn finalize(&self, normalized: &str, prompt_tokens: usize, completion_tokens: usize) {
-        self.inner
-            .finalize(normalized, prompt_tokens, completion_tokens);
+    /// Emit already decoded text as a single delta (used when output is assembled from several
+    /// generation passes rather than streamed token by token).
+    pub fn send_text(&self, text: &str) {
+        self.inner.send_text(text);
+    }
+
+    /// Send the closing event with `normalized` as the full text and the usage and metadata
+    /// of `result`.
+    pub fn finalize(&self, normalized: &str, result: &GenerationResult) {
+        self.inner.finalize(normalized, result);
     }
 
     pub fn callback(&self) -> impl Fn(usize, &[i64]) + Send + Sync + 'static {
@@ -258,6 +267,21 @@ impl StreamControllerInner {
         }
     }
 
+    fn send_text(&self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let include_role = {
+            let mut state = self.runtime.lock().expect("stream state lock poisoned");
+            let include_role = matches!(self.kind, StreamKind::Chat { .. }) && !state.role_sent;
+            if include_role {
+                state.role_sent = true;
+            }
+            include_role
+        };
+        self.emit_delta(text.to_owned(), include_role);
+    }
+
     fn flush_remaining(&self, ids: &[i64]) {
         let len = ids.len();
         if len == 0 {
@@ -284,7 +308,10 @@ impl StreamControllerInner {
         }
     }
 
-    fn finalize(&self, normalized: &str, prompt_tokens: usize, completion_tokens: usize) {
+    fn finalize(&self, normalized: &str, result: &GenerationResult) {
+        let prompt_tokens = result.prompt_tokens;
+        let completion_tokens = result.response_tokens;
+        let finish_reason = result.finish_reason;
         {
             let mut state = self.runtime.lock().expect("stream state lock poisoned");
             if state.finished {
@@ -301,13 +328,14 @@ impl StreamControllerInner {
                 created,
             } => {
                 let total_tokens = prompt_tokens + completion_tokens;
-                let payload = json!({
+                let mut payload = json!({
                     "type": "response.completed",
                     "response": {
                         "id": response_id,
                         "object": "response",
                         "created": created,
                         "model": model,
+                        "finish_reason": finish_reason,
                         "output": [{
                             "id": output_id,
                             "type": "message",
@@ -324,6 +352,7 @@ impl StreamControllerInner {
                         },
                     }
                 });
+                add_metadata(&mut payload["response"], result);
                 let _ = self.sender.send(Event::json(&payload));
                 let _ = self.sender.send(Event::data("[DONE]"));
             }
@@ -332,7 +361,7 @@ impl StreamControllerInner {
                 model,
                 created,
             } => {
-                let payload = json!({
+                let mut payload = json!({
                     "id": completion_id,
                     "object": "chat.completion.chunk",
                     "created": created,
@@ -340,7 +369,7 @@ impl StreamControllerInner {
                     "choices": [{
                         "index": 0,
                         "delta": serde_json::Value::Object(serde_json::Map::new()),
-                        "finish_reason": "stop",
+                        "finish_reason": finish_reason,
                     }],
                     "usage": {
                         "prompt_tokens": prompt_tokens,
@@ -348,9 +377,27 @@ impl StreamControllerInner {
                         "total_tokens": prompt_tokens + completion_tokens,
                     }
                 });
+                add_metadata(&mut payload, result);
                 let _ = self.sender.send(Event::json(&payload));
                 let _ = self.sender.send(Event::data("[DONE]"));
             }
         }
     }
 }
+
+/// Copy the optional metadata of `result` into a closing payload, as the non-streaming
+/// responses carry it.
+fn add_metadata(payload: &mut serde_json::Value, result: &GenerationResult) {
+    if let Some(tables) = &result.tables {
+        payload["tables"] = json!(tables);
+    }
+    if let Some(images) = &result.redacted_images {
+        payload["redacted_images"] = json!(images);
+    }
+    if let Some(figures) = &result.figures {
+        payload["figures"] = json!(figures);
+    }
+    if let Some(cleanup) = &result.cleanup {
+        payload["cleanup"] = json!(cleanup);
+    }
+}
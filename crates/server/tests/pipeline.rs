@@ -0,0 +1,135 @@
+use std::{
+    env, fs,
+    io::Cursor,
+    net::TcpListener,
+    path::PathBuf,
+    process::{self, Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use base64::Engine;
+use deepseek_ocr_core::fixture::write_tiny_model;
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+use reqwest::blocking::Client;
+use serde_json::{Value, json};
+
+/// Server process on the tiny model, killed and cleaned up when dropped.
+struct Server {
+    child: Child,
+    dir: PathBuf,
+    base_url: String,
+}
+
+impl Server {
+    fn start(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("deepseek-ocr-server-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let files = write_tiny_model(&dir.join("model"), 0).unwrap();
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let child = Command::new(env!("CARGO_BIN_EXE_deepseek-ocr-server"))
+            .arg("--config")
+            .arg(dir.join("config.toml"))
+            .arg("--model-config")
+            .arg(&files.config)
+            .arg("--weights")
+            .arg(&files.weights)
+            .arg("--tokenizer")
+            .arg(&files.tokenizer)
+            .args(["--device", "cpu", "--resolution", "tiny"])
+            .args(["--max-new-tokens", "4"])
+            .args(["--host", "127.0.0.1", "--port", &port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        Self {
+            child,
+            dir,
+            base_url: format!("http://127.0.0.1:{port}/v1"),
+        }
+    }
+
+    /// Poll the health endpoint until the model is loaded and Rocket listens.
+    fn wait_ready(&mut self, client: &Client) {
+        let deadline = Instant::now() + Duration::from_secs(120);
+        while Instant::now() < deadline {
+            if let Some(status) = self.child.try_wait().unwrap() {
+                panic!("server exited early with {status}");
+            }
+            let health = client.get(format!("{}/health", self.base_url)).send();
+            if health.is_ok_and(|response| response.status().is_success()) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(200));
+        }
+        panic!("server did not become ready");
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn page_data_url() -> String {
+    let page = DynamicImage::ImageRgb8(RgbImage::from_fn(300, 200, |x, y| {
+        if (x / 16 + y / 8) % 3 == 0 {
+            Rgb([20, 20, 20])
+        } else {
+            Rgb([240, 240, 235])
+        }
+    }));
+    let mut png = Cursor::new(Vec::new());
+    page.write_to(&mut png, ImageFormat::Png).unwrap();
+    format!(
+        "data:image/png;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(png.into_inner())
+    )
+}
+
+fn get_json(client: &Client, url: &str) -> Value {
+    let response = client.get(url).send().unwrap();
+    assert!(response.status().is_success());
+    serde_json::from_str(&response.text().unwrap()).unwrap()
+}
+
+#[test]
+fn chat_completion_runs_the_tiny_model() {
+    let client = Client::new();
+    let mut server = Server::start("chat");
+    server.wait_ready(&client);
+    let models = get_json(&client, &format!("{}/models", server.base_url));
+    let model = models["data"][0]["id"].as_str().unwrap();
+
+    let request = json!({
+        "model": model,
+        "messages": [{
+            "role": "user",
+            "content": [
+                {"type": "image_url", "image_url": {"url": page_data_url()}},
+                {"type": "text", "text": "Free OCR."}
+            ]
+        }],
+        "cleanup": ["contrast"]
+    });
+    let response = client
+        .post(format!("{}/chat/completions", server.base_url))
+        .header("Content-Type", "application/json")
+        .body(request.to_string())
+        .send()
+        .unwrap();
+    assert!(response.status().is_success());
+    let body: Value = serde_json::from_str(&response.text().unwrap()).unwrap();
+    assert!(body["choices"][0]["message"]["content"].is_string());
+    let completion = body["usage"]["completion_tokens"].as_u64().unwrap();
+    assert!((1..=4).contains(&completion));
+    assert_eq!(body["cleanup"].as_array().unwrap().len(), 1);
+}